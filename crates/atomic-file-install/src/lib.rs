@@ -111,6 +111,14 @@ pub fn atomic_install(src: &Path, dst: &Path) -> io::Result<()> {
     Ok(())
 }
 
+/// Atomically install a copy of a file, keeping `src`, this atomically
+/// replace `dst` if it exists.
+///
+/// This is a blocking function, must be called in `block_in_place` mode.
+pub fn atomic_copy(src: &Path, dst: &Path) -> io::Result<()> {
+    persist(copy_to_tempfile(src, dst)?.into_temp_path(), dst)
+}
+
 /// Create a symlink at `link` to `dest`, this fails if the `link`
 /// already exists.
 ///
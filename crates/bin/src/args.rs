@@ -2,15 +2,18 @@ use std::{
     env,
     ffi::OsString,
     fmt,
-    num::{NonZeroU16, NonZeroU64, ParseIntError},
+    num::{NonZeroU16, NonZeroU64, NonZeroUsize, ParseIntError},
     path::PathBuf,
     str::FromStr,
 };
 
 use binstalk::{
-    helpers::remote,
+    helpers::remote::{self, Url},
     manifests::cargo_toml_binstall::PkgFmt,
-    ops::resolve::{CrateName, VersionReqExt},
+    ops::{
+        resolve::{CrateName, VersionReqExt},
+        RepoRewrite,
+    },
     registry::Registry,
 };
 use clap::{error::ErrorKind, CommandFactory, Parser, ValueEnum};
@@ -21,6 +24,11 @@ use semver::VersionReq;
 use strum::EnumCount;
 use strum_macros::EnumCount;
 
+use crate::{
+    install_order::{install_levels, InstallAfter},
+    install_script::ScriptKind,
+};
+
 #[derive(Debug, Parser)]
 #[clap(
     version,
@@ -36,7 +44,8 @@ pub struct Args {
     /// Syntax: `crate[@version]`
     ///
     /// Each value is either a crate name alone, or a crate name followed by @ and the version to
-    /// install. The version syntax is as with the --version option.
+    /// install. The version syntax is as with the --version option, and `latest` selects the
+    /// newest stable version even with --pre, e.g. `cargo-nextest@latest`.
     ///
     /// When multiple names are provided, the --version option and override option
     /// `--manifest-path` and `--git` are unavailable due to ambiguity.
@@ -46,10 +55,96 @@ pub struct Args {
     #[clap(
         help_heading = "Package selection",
         value_name = "crate[@version]",
-        required_unless_present_any = ["version", "help"],
+        required_unless_present_any = [
+            "version", "help", "url", "dev_tools", "from_file", "check_manifest"
+        ],
     )]
     pub(crate) crate_names: Vec<CrateName>,
 
+    /// List the targets the crates provide pre-built binaries for, instead of installing them.
+    ///
+    /// The targets specified by `--targets` are checked, or a list of common targets if not
+    /// specified. The result is printed in json if `--json-output` is specified, as an object
+    /// per crate with its name under `crate` and its targets under `targets`.
+    #[clap(help_heading = "Package selection", long)]
+    pub(crate) list_targets: bool,
+
+    /// Diagnose why the crates cannot be installed from pre-built binaries, instead of installing
+    /// them.
+    ///
+    /// For crate authors: the packages found for each target are downloaded and checked, and
+    /// a checklist of findings is printed, such as a missing `[package.metadata.binstall]`, no
+    /// release asset matching the templates, or an unsupported archive format.
+    ///
+    /// The targets are chosen like with `--list-targets`. The result is printed in json if
+    /// `--json-output` is specified.
+    #[clap(
+        help_heading = "Package selection",
+        long,
+        conflicts_with = "list_targets"
+    )]
+    pub(crate) report_unsupported: bool,
+
+    /// List the versions of the crates published on the registry, instead of installing them.
+    ///
+    /// Yanked versions are marked as such, and the newest 10 other versions are marked if they
+    /// provide pre-built binaries for the targets, which are chosen as when installing. Only the
+    /// versions matching the version requirement are listed if one is given, which can be used
+    /// to check older versions.
+    ///
+    /// The result is printed in json if `--json-output` is specified, with `prebuilt` set to
+    /// `null` for the versions not checked.
+    #[clap(
+        help_heading = "Package selection",
+        long,
+        conflicts_with_all = ["list_targets", "report_unsupported"]
+    )]
+    pub(crate) list_versions: bool,
+
+    /// Install the development tools declared by the current project.
+    ///
+    /// The tools are read from the `tools` list in `[package.metadata.binstall]` of
+    /// `./Cargo.toml`, or of the manifest specified by `--manifest-path`, and of the members
+    /// of its workspace. Each of them is installed like a crate passed on the command line, e.g.
+    /// `tools = ["cargo-nextest", "cargo-deny@0.14"]`.
+    #[clap(
+        help_heading = "Package selection",
+        long,
+        conflicts_with_all = ["version_req", "url"]
+    )]
+    pub(crate) dev_tools: bool,
+
+    /// Install the crates listed in a file, along with the ones on the command line.
+    ///
+    /// Each line of the file is a crate as on the command line, `crate[@version]`, optionally
+    /// followed by `--version <version>` instead. Blank lines and comments starting with `#` are
+    /// ignored, e.g. `cargo-nextest@0.9 # test runner`.
+    #[clap(
+        help_heading = "Package selection",
+        long,
+        value_name = "PATH",
+        conflicts_with_all = ["version_req", "url"]
+    )]
+    pub(crate) from_file: Option<PathBuf>,
+
+    /// Check the binstall metadata of a crate for mistakes, instead of installing it.
+    ///
+    /// For crate authors: the `[package.metadata.binstall]` of `./Cargo.toml`, or of the
+    /// manifest specified by `--manifest-path`, is validated without any network access, e.g.
+    /// unknown keys in templates, a `bin-dir` outside of the package or overrides of unknown
+    /// targets. If crate names are given, they are looked up in the workspace of the manifest.
+    ///
+    /// The issues are printed in json if `--json-output` is specified, and binstall exits with
+    /// an error if there is any.
+    #[clap(
+        help_heading = "Package selection",
+        long,
+        conflicts_with_all = [
+            "list_targets", "report_unsupported", "list_versions", "dev_tools", "url"
+        ]
+    )]
+    pub(crate) check_manifest: bool,
+
     /// Package version to install.
     ///
     /// Takes either an exact semver version or a semver version requirement expression, which will
@@ -64,6 +159,44 @@ pub struct Args {
     )]
     pub(crate) version_req: Option<VersionReq>,
 
+    /// Read the version of the packages to install from a file.
+    ///
+    /// Each line of the file pins the version of one package, as `name = "version"` (a flat
+    /// TOML table), `name=version` or `name version` (the format of asdf's `.tool-versions`,
+    /// where only the first version of a line is used). Blank lines and comments starting
+    /// with `#` are ignored.
+    ///
+    /// Versions are requirements as in `crate@version`, and `latest` is accepted. They only
+    /// apply to the packages which do not specify one with `crate@version` or `--version`,
+    /// packages absent from the file resolve to their latest version.
+    #[clap(help_heading = "Package selection", long, value_name = "PATH")]
+    pub(crate) version_file: Option<PathBuf>,
+
+    /// Only install the exact version given by `--version` or `crate@version`.
+    ///
+    /// A version requirement such as `1.2.3` or `^1.2.3` then only accepts `1.2.3`, and the
+    /// installation fails if this version is not published. Requirements which do not name a
+    /// complete version, such as `1.2` or `>=1.2.3`, are rejected.
+    #[clap(help_heading = "Package selection", long)]
+    pub(crate) exact: bool,
+
+    /// Allow installing pre-release versions.
+    ///
+    /// By default, pre-releases are only installed if the version requirement names one,
+    /// and a crate which has only published pre-release versions fails to resolve. With
+    /// this flag, the pre-releases of the versions matching the requirement are considered
+    /// too, and one is installed if no higher stable version matches.
+    #[clap(help_heading = "Package selection", long)]
+    pub(crate) pre: bool,
+
+    /// Do not check whether a newer version than the one matching the version
+    /// requirement is available.
+    ///
+    /// By default, when a version requirement is given, binstall informs you if the
+    /// registry has a newer version of the crate, which is usually not useful in scripts.
+    #[clap(help_heading = "Package selection", long)]
+    pub(crate) quiet_version_check: bool,
+
     /// Override binary target set.
     ///
     /// Binstall is able to look for binaries for several targets, installing the first one it finds
@@ -73,9 +206,14 @@ pub struct Args {
     /// considered.
     ///
     /// This option takes a comma-separated list of target triples, which will be tried in order.
-    /// They override the default list, which is detected automatically from the current platform.
+    /// They override the default list, which is detected automatically from the current platform,
+    /// or taken from `CARGO_BUILD_TARGET` or `build.target` in cargo's config if set.
     ///
     /// If falling back to installing from source, the first target will be used.
+    ///
+    /// The aliases `linux`, `macos` and `windows` may be given instead of a triple, and are
+    /// expanded to the common triples of that platform, the ones of the architecture of the host
+    /// first. For the platform of the host, only the triples it can run are kept.
     #[clap(
         help_heading = "Package selection",
         alias = "target",
@@ -84,6 +222,16 @@ pub struct Args {
     )]
     pub(crate) targets: Option<Vec<String>>,
 
+    /// Only look for binaries built for the exact host target.
+    ///
+    /// By default, binstall also considers the targets the host is able to run, e.g. the musl
+    /// target on a glibc Linux or `x86_64-apple-darwin` on an Apple Silicon Mac through Rosetta.
+    /// With this option, only the host triple is considered, ignoring `CARGO_BUILD_TARGET` and
+    /// `build.target` as well: if no binary is found for it, the crate is built from source, or
+    /// the installation fails if that is disabled.
+    #[clap(help_heading = "Package selection", long, conflicts_with = "targets")]
+    pub(crate) frozen_targets: bool,
+
     /// Override Cargo.toml package manifest path.
     ///
     /// This skips searching crates.io for a manifest and uses the specified path directly, useful
@@ -94,16 +242,83 @@ pub struct Args {
     #[clap(help_heading = "Overrides", long)]
     pub(crate) manifest_path: Option<PathBuf>,
 
+    /// Download the Cargo.toml of the crate from this url rather than from crates.io, e.g. the
+    /// raw file in its repository, to install crates which are not published.
+    ///
+    /// The manifest is validated like a local one, and its `repository` is used to look for
+    /// the pre-built binaries as usual.
+    ///
+    /// This option cannot be used with `--manifest-path`, `--git` or `--url`.
+    #[clap(
+        help_heading = "Overrides",
+        long,
+        value_name = "URL",
+        conflicts_with_all = ["manifest_path", "url", "dev_tools", "prefer_local_manifest"]
+    )]
+    pub(crate) manifest_url: Option<Url>,
+
     #[cfg(feature = "git")]
     /// Override how to fetch Cargo.toml package manifest.
     ///
     /// This skip searching crates.io and instead clone the repository specified and
     /// runs as if `--manifest-path $cloned_repo` is passed to binstall.
     ///
+    /// For repositories on GitHub and GitLab, the tag of the latest release is cloned. On other
+    /// hosts, or if the repository has no release, the default branch is cloned instead, and is
+    /// what gets built if falling back to building from source.
+    ///
     /// This option cannot be used with `--manifest-path`.
-    #[clap(help_heading = "Overrides", long, conflicts_with("manifest_path"))]
+    #[clap(
+        help_heading = "Overrides",
+        long,
+        conflicts_with_all = ["manifest_path", "manifest_url", "url", "dev_tools", "prefer_local_manifest"]
+    )]
     pub(crate) git: Option<binstalk::registry::GitUrl>,
 
+    /// Use the manifests of the crates in this workspace, or folder containing a crate, instead of
+    /// fetching them from crates.io.
+    ///
+    /// Unlike `--manifest-path`, the crates not found in the workspace, or whose version does not
+    /// match the version requirement, are still looked up on crates.io, which is useful in
+    /// monorepos where the local sources are authoritative.
+    #[clap(
+        help_heading = "Overrides",
+        long,
+        value_name = "DIR",
+        conflicts_with_all = ["manifest_path", "url"]
+    )]
+    pub(crate) prefer_local_manifest: Option<PathBuf>,
+
+    /// Install directly from the archive (or bare binary) at this url.
+    ///
+    /// This skips searching crates.io and all fetchers entirely: the url is
+    /// downloaded and extracted, then the binaries specified by `--bin` are
+    /// installed from it.
+    ///
+    /// If no crate name is specified, the first `--bin` is used as the name
+    /// to track the installation under. It is tracked as version 0.0.0 of the
+    /// crates.io crate of that name, unless an exact version is specified with
+    /// `crate@version` or `--version`.
+    ///
+    /// This option cannot be used with `--manifest-path`, `--git` or `--pkg-url`.
+    #[clap(
+        help_heading = "Overrides",
+        long,
+        value_name = "URL",
+        conflicts_with_all = ["manifest_path", "pkg_url"],
+        requires = "bins"
+    )]
+    pub(crate) url: Option<Url>,
+
+    /// Name of the binaries to install from `--url`.
+    #[clap(
+        help_heading = "Overrides",
+        long = "bin",
+        value_name = "NAME",
+        requires = "url"
+    )]
+    pub(crate) bins: Vec<CompactString>,
+
     /// Override Cargo.toml package manifest bin-dir.
     #[clap(help_heading = "Overrides", long)]
     pub(crate) bin_dir: Option<String>,
@@ -122,9 +337,13 @@ pub struct Args {
     ///
     /// - tzstd: Download format is TAR + Zstd
     ///
+    /// - tlz4: Download format is TAR + LZ4
+    ///
     /// - zip: Download format is Zip
     ///
     /// - bin: Download format is raw / binary
+    ///
+    /// - lz4: Download format is raw / binary compressed with LZ4
     #[clap(help_heading = "Overrides", long, value_name = "PKG_FMT")]
     pub(crate) pkg_fmt: Option<PkgFmt>,
 
@@ -132,6 +351,22 @@ pub struct Args {
     #[clap(help_heading = "Overrides", long)]
     pub(crate) pkg_url: Option<String>,
 
+    /// Read per-target overrides from a `Cross.toml`-style configuration.
+    ///
+    /// Each `[target.TARGET]` table of the file may set `pkg-url`, `pkg-fmt` and `bin-dir`,
+    /// like `[package.metadata.binstall.overrides.TARGET]` in Cargo.toml, and takes precedence
+    /// over the crate's own. Other fields, such as the ones of `cross`, are ignored.
+    #[clap(help_heading = "Overrides", long, value_name = "PATH")]
+    pub(crate) target_config: Option<PathBuf>,
+
+    /// Prefer the smallest package when several are available for a target.
+    ///
+    /// By default, the first package found is used. With this flag, the size of every
+    /// matching package is queried and the smallest one is downloaded, which is useful
+    /// for releases providing both a full and a minimal archive.
+    #[clap(help_heading = "Options", long)]
+    pub(crate) prefer_smallest: bool,
+
     /// Override the rate limit duration.
     ///
     /// By default, cargo-binstall allows one request per 10 ms.
@@ -147,19 +382,95 @@ pub struct Args {
     #[clap(help_heading = "Overrides", long, default_value_t = RateLimit::default(), env = "BINSTALL_RATE_LIMIT")]
     pub(crate) rate_limit: RateLimit,
 
+    /// Maximum number of connections to each host, for the registry and for the downloads.
+    ///
+    /// A request counts until its response has been received in full. Higher values speed up
+    /// large batch installs, at the expense of the servers. By default, it is not limited.
+    #[clap(
+        help_heading = "Overrides",
+        long,
+        value_name = "N",
+        env = "BINSTALL_CONNECTIONS"
+    )]
+    pub(crate) connections: Option<NonZeroUsize>,
+
     /// Specify the strategies to be used,
     /// binstall will run the strategies specified in order.
     ///
-    /// Default value is "crate-meta-data,quick-install,compile".
+    /// Default value is "releases-index,crate-meta-data,bitbucket,quick-install,compile",
+    /// preceded by "external" if `--external-fetcher` is specified. "gh-actions" is only used
+    /// if specified.
     #[clap(help_heading = "Overrides", long, value_delimiter(','))]
     pub(crate) strategies: Vec<Strategy>,
 
+    /// Program used by the "external" strategy to look for packages.
+    ///
+    /// It receives a JSON request with the crate name, version and target on its stdin, and
+    /// prints a JSON response with the url of the package, or the path to an already downloaded
+    /// one, on its stdout. It prints nothing if it has no package for the target.
+    ///
+    /// Since it cannot provide signatures, it is not used for crates declaring `signing` unless
+    /// `--skip-signatures` is passed. It requires the `external-fetcher` feature.
+    #[clap(help_heading = "Overrides", long, value_name = "PATH")]
+    pub(crate) external_fetcher: Option<PathBuf>,
+
     /// Disable the strategies specified.
     /// If a strategy is specified in `--strategies` and `--disable-strategies`,
     /// then it will be removed.
     #[clap(help_heading = "Overrides", long, value_delimiter(','))]
     pub(crate) disable_strategies: Vec<Strategy>,
 
+    /// Features to enable when building from source with the "compile" strategy.
+    ///
+    /// They are passed to `cargo install`, and do not affect pre-built binaries.
+    #[clap(
+        help_heading = "Overrides",
+        long,
+        value_name = "FEATURES",
+        value_delimiter(',')
+    )]
+    pub(crate) features: Vec<CompactString>,
+
+    /// Do not enable the default features when building from source.
+    #[clap(help_heading = "Overrides", long)]
+    pub(crate) no_default_features: bool,
+
+    /// Enable all the features when building from source.
+    #[clap(help_heading = "Overrides", long, conflicts_with = "features")]
+    pub(crate) all_features: bool,
+
+    /// Start from an empty set of fetchers, instead of the default ones.
+    ///
+    /// Only the fetchers specified by `--add-fetcher` are used, followed by the "compile"
+    /// strategy unless it is disabled by `--disable-strategies`.
+    #[clap(help_heading = "Overrides", long, conflicts_with = "strategies")]
+    pub(crate) no_default_fetchers: bool,
+
+    /// Add a fetcher to use after `--no-default-fetchers`, in the order specified.
+    #[clap(
+        help_heading = "Overrides",
+        long,
+        value_name = "FETCHER",
+        value_delimiter(','),
+        requires = "no_default_fetchers"
+    )]
+    pub(crate) add_fetcher: Vec<FetcherName>,
+
+    /// Install from the stages specified only, trying them in order.
+    ///
+    /// "prebuilt" is the official pre-built artifacts, found by all the strategies except
+    /// "gh-actions", "quick-install" and "compile", "quickinstall" is the third-party
+    /// QuickInstall and "source" is building from source, which must be the last stage. For
+    /// example, `--fallback prebuilt` never uses QuickInstall nor builds from source.
+    #[clap(
+        help_heading = "Overrides",
+        long,
+        value_name = "STAGE",
+        value_delimiter(','),
+        conflicts_with_all = ["strategies", "no_default_fetchers"]
+    )]
+    pub(crate) fallback: Vec<FallbackStage>,
+
     /// If `--github-token` or environment variable `GITHUB_TOKEN`/`GH_TOKEN`
     /// is not specified, then cargo-binstall will try to extract github token from
     /// `$HOME/.git-credentials` or `$HOME/.config/gh/hosts.yml` by default.
@@ -168,6 +479,14 @@ pub struct Args {
     #[clap(help_heading = "Overrides", long)]
     pub(crate) no_discover_github_token: bool,
 
+    /// Do not use the credentials of `~/.netrc`.
+    ///
+    /// By default, requests to the machines listed in `$NETRC`, or `~/.netrc` (`_netrc` on
+    /// Windows), such as a registry or a release mirror, are authenticated with their login
+    /// and password.
+    #[clap(help_heading = "Overrides", long)]
+    pub(crate) no_netrc: bool,
+
     /// This flag is now enabled by default thus a no-op.
     ///
     /// By default, Binstall will install a binary as-is in the install path.
@@ -178,10 +497,55 @@ pub struct Args {
     #[clap(help_heading = "Options", long)]
     pub(crate) dry_run: bool,
 
+    /// Download the packages and verify their checksums and signatures, without installing them.
+    ///
+    /// This implies `--dry-run`, and prints what was verified for each crate, in json if
+    /// `--json-output` is specified. Binstall exits with an error if nothing could be verified
+    /// for a crate, e.g. because its package provides no checksum or signature, or it would be
+    /// built from source, so that it can be used as a pre-flight check in CI.
+    #[clap(help_heading = "Options", long, conflicts_with = "emit_script")]
+    pub(crate) verify_only: bool,
+
+    /// Print how long each phase took for each crate: resolving the version, discovering a
+    /// package with the fetchers, downloading it and installing it.
+    ///
+    /// The package is extracted while it is downloaded, so the extraction is included in the
+    /// download time. The report is printed in json to stderr if `--json-output` is specified.
+    #[clap(help_heading = "Options", long)]
+    pub(crate) time_report: bool,
+
+    /// Print a script installing the resolved crates, instead of installing them.
+    ///
+    /// The script downloads the packages resolved, checks their sha256 digests before extracting
+    /// them and installs their binaries to the same paths, so that it can run where
+    /// cargo-binstall cannot.
+    /// Crates built from source are installed by `cargo install`. Defaults to a PowerShell
+    /// script on Windows, and a POSIX shell script otherwise.
+    ///
+    /// The script is printed to stdout, use `--log-level off` to only print the script.
+    #[clap(
+        help_heading = "Options",
+        long,
+        value_name = "KIND",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = if cfg!(windows) { "powershell" } else { "sh" },
+    )]
+    pub(crate) emit_script: Option<ScriptKind>,
+
     /// Disable interactive mode / confirmation prompts.
     #[clap(help_heading = "Options", short = 'y', long)]
     pub(crate) no_confirm: bool,
 
+    /// Ask which source to install each crate from when several have a pre-built binary.
+    ///
+    /// All the fetchers are waited for and those which found a package are listed with their
+    /// target, whether they are third-party and the url of the package, e.g. to consciously
+    /// pick the official release over a third-party rebuild. Without it, or with `--no-confirm`,
+    /// the first one in the order of the strategies and targets is used.
+    #[clap(help_heading = "Options", long)]
+    pub(crate) select_fetcher_interactive: bool,
+
     /// Do not cleanup temporary files.
     #[clap(help_heading = "Options", long)]
     pub(crate) no_cleanup: bool,
@@ -201,6 +565,22 @@ pub struct Args {
     #[clap(help_heading = "Options", long)]
     pub(crate) no_track: bool,
 
+    /// Refuse to overwrite the files which are not tracked as installed by any crate, such as
+    /// binaries placed by hand or by the package manager of the system.
+    ///
+    /// By default, binstall warns about them before asking for confirmation. `--force`
+    /// overwrites them without any warning.
+    #[clap(help_heading = "Options", long, conflicts_with = "no_track")]
+    pub(crate) refuse_untracked: bool,
+
+    /// Install binaries as `<bin>-v<version>`, alongside the other installed versions.
+    ///
+    /// The tracking manifest records each version separately, so that `cargo uninstall
+    /// <crate>@<version>` only removes that version. `<bin>` is symlinked to the newest
+    /// version installed, unless `--no-symlinks` is specified.
+    #[clap(help_heading = "Options", long)]
+    pub(crate) versioned_bin: bool,
+
     /// Install binaries in a custom location.
     ///
     /// By default, binaries are installed to the global location `$CARGO_HOME/bin`, and global
@@ -212,8 +592,10 @@ pub struct Args {
 
     /// Install binaries with a custom cargo root.
     ///
-    /// By default, we use `$CARGO_INSTALL_ROOT` or `$CARGO_HOME` as the
-    /// cargo root and global metadata files are updated with the
+    /// By default, we use `$CARGO_INSTALL_ROOT`, `install.root` in the
+    /// `.cargo/config.toml` of the current directory or of its parents, or
+    /// in `$CARGO_HOME/config.toml`, or `$CARGO_HOME` as the cargo root, like
+    /// `cargo install`, and global metadata files are updated with the
     /// package information.
     ///
     /// Specifying another path here would install the binaries and update
@@ -223,6 +605,107 @@ pub struct Args {
     #[clap(help_heading = "Options", long, alias = "roots")]
     pub(crate) root: Option<PathBuf>,
 
+    /// Stage the installation under this directory, like `make install DESTDIR=`.
+    ///
+    /// The install path and the cargo root are resolved as usual, then everything is written
+    /// under `DESTDIR` instead, e.g. `$DESTDIR/usr/local/bin` for `--root /usr/local`. Once
+    /// installed, the final paths of the pre-built binaries and symlinks are printed, in json if
+    /// `--json-output` is specified, to package the staged files.
+    ///
+    /// NOTE that on Windows, symlinks point to the staged binaries.
+    #[clap(help_heading = "Options", long, value_name = "DESTDIR")]
+    pub(crate) destdir: Option<PathBuf>,
+
+    /// Download and extract packages in a custom directory.
+    ///
+    /// By default, a temporary directory is created inside the install path, so that binaries
+    /// can be moved into place atomically. Use this if the filesystem of the install path is
+    /// full or cannot hold executables, e.g. it is mounted `noexec`.
+    #[clap(help_heading = "Options", long, value_name = "DIR")]
+    pub(crate) temp_dir: Option<PathBuf>,
+
+    /// Keep a copy of the downloaded packages in this directory.
+    ///
+    /// The packages are saved under the file name they are downloaded as, which is useful to
+    /// inspect what has been installed or to cache packages for offline reuse.
+    #[clap(help_heading = "Options", long, value_name = "DIR")]
+    pub(crate) keep_download: Option<PathBuf>,
+
+    /// Extract the packages in formats binstall does not support with this program.
+    ///
+    /// This is an escape hatch for such formats, declared with `--pkg-fmt bin`: the program is
+    /// run with the path of the downloaded package and the path of the directory to extract it
+    /// to, and the binaries are then looked up in this directory as usual. The packages in the
+    /// supported formats are still extracted by binstall. The installation is aborted if the
+    /// program exits with an error.
+    #[clap(help_heading = "Options", long, value_name = "PATH")]
+    pub(crate) extract_command: Option<PathBuf>,
+
+    /// Make installing the same crates produce the same files, e.g. for Docker layer caching.
+    ///
+    /// The modification times of the installed binaries, extra files and tracking manifests are
+    /// set to `SOURCE_DATE_EPOCH` if set, or to the Unix epoch otherwise. The tracking manifests
+    /// contain no timestamps and are always sorted by crate name, regardless of the order the
+    /// crates are given in.
+    ///
+    /// This does not apply to the crates built from source, as their build is not reproducible.
+    #[clap(help_heading = "Options", long)]
+    pub(crate) reproducible: bool,
+
+    /// Run the installed binaries with `--version` and warn if they report another version.
+    ///
+    /// This catches packages which are mislabeled or contain a stale binary. The output is
+    /// searched for versions like `1.2.3` or `v1.2`, and binaries failing to run or not
+    /// reporting any version are skipped.
+    #[clap(help_heading = "Options", long)]
+    pub(crate) check_version: bool,
+
+    /// Install the extra files declared by the packages, such as licenses, into this directory.
+    ///
+    /// They are installed into a subdirectory named after the crate, and default to
+    /// `share/doc` in the cargo root.
+    #[clap(help_heading = "Options", long, value_name = "DIR")]
+    pub(crate) extra_files_dir: Option<PathBuf>,
+
+    /// Do not install the extra files declared by the packages.
+    #[clap(help_heading = "Options", long, conflicts_with("extra_files_dir"))]
+    pub(crate) no_extra_files: bool,
+
+    /// Run this command before installing the binaries of each crate downloaded, e.g. to stop
+    /// a service using them.
+    ///
+    /// The command is run with `sh -c`, or `cmd /C` on Windows. The name and version of the crate
+    /// are passed in the environment variables `BINSTALL_CRATE_NAME` and `BINSTALL_CRATE_VERSION`,
+    /// and the paths the binaries are installed to in `BINSTALL_INSTALLED_PATHS`, separated like
+    /// in `PATH`. If it fails, the crate is not installed.
+    ///
+    /// The commands of different crates are run one at a time, and their output is printed to
+    /// stderr. It is not run for crates built from source.
+    #[clap(help_heading = "Options", long, value_name = "CMD")]
+    pub(crate) pre_install_cmd: Option<String>,
+
+    /// Run this command after installing the binaries of each crate downloaded, e.g. to restart
+    /// a service using them.
+    ///
+    /// The command is run like `--pre-install-cmd`, but only if the binaries were installed,
+    /// and only a warning is printed if it fails.
+    #[clap(help_heading = "Options", long, value_name = "CMD")]
+    pub(crate) post_install_cmd: Option<String>,
+
+    /// Maximum number of crates resolved, or installed from source, at the same time.
+    ///
+    /// By default, all crates are processed concurrently.
+    #[clap(help_heading = "Options", long, value_name = "N")]
+    pub(crate) max_concurrent_crates: Option<NonZeroUsize>,
+
+    /// Install CRATE from source only after PREREQUISITE is installed.
+    ///
+    /// For crates needing the binaries of another crate to build. Pre-built binaries are
+    /// always installed before the crates built from source, and crates without ordering
+    /// constraints are installed concurrently. Can be specified multiple times.
+    #[clap(help_heading = "Options", long, value_name = "CRATE:PREREQUISITE")]
+    pub(crate) after: Vec<InstallAfter>,
+
     /// The URL of the registry index to use.
     ///
     /// Cannot be used with `--registry`.
@@ -256,6 +739,15 @@ pub struct Args {
     #[clap(help_heading = "Options", long)]
     pub(crate) locked: bool,
 
+    /// Implies `--locked` and forbids any network access.
+    ///
+    /// The crate manifest must be provided with `--manifest-path` and pre-built binaries
+    /// cannot be downloaded, so the crate is installed by `cargo-install --frozen`, which
+    /// requires the cargo cache to already contain the crate and all its dependencies.
+    /// Fails if any of these constraints cannot be satisfied.
+    #[clap(help_heading = "Options", long)]
+    pub(crate) frozen: bool,
+
     /// Deprecated, here for back-compat only. Secure is now on by default.
     #[clap(hide(true), long)]
     pub(crate) secure: bool,
@@ -264,6 +756,30 @@ pub struct Args {
     #[clap(help_heading = "Options", long)]
     pub(crate) force: bool,
 
+    /// Abort the installation if a package to download is larger than this many bytes.
+    ///
+    /// Both the `Content-Length` reported by the server and the bytes actually
+    /// received are checked against the limit.
+    #[clap(
+        help_heading = "Options",
+        long,
+        value_name = "BYTES",
+        env = "BINSTALL_MAX_DOWNLOAD_SIZE"
+    )]
+    pub(crate) max_download_size: Option<NonZeroU64>,
+
+    /// Throttle each download of a package to at most this many bytes per second.
+    ///
+    /// Like `curl --limit-rate`, the limit applies to every connection on its own, so downloads
+    /// running in parallel may use more bandwidth in total. Downloads are not throttled by default.
+    #[clap(
+        help_heading = "Options",
+        long,
+        value_name = "BYTES_PER_SEC",
+        env = "BINSTALL_LIMIT_RATE"
+    )]
+    pub(crate) limit_rate: Option<NonZeroU64>,
+
     /// Require a minimum TLS version from remote endpoints.
     ///
     /// The default is not to require any minimum TLS version, and use the negotiated highest
@@ -271,11 +787,40 @@ pub struct Args {
     #[clap(help_heading = "Options", long, value_enum, value_name = "VERSION")]
     pub(crate) min_tls_version: Option<TLSVersion>,
 
+    /// Only use HTTP/1.1, for proxies which do not handle HTTP/2 properly.
+    ///
+    /// By default, HTTP/2 is used with the servers supporting it, such as GitHub's, sending all
+    /// the requests to a host over a single connection.
+    #[clap(help_heading = "Options", long, env = "BINSTALL_HTTP1_ONLY")]
+    pub(crate) http1_only: bool,
+
     /// Specify the root certificates to use for https connnections,
     /// in addition to default system-wide ones.
-    #[clap(help_heading = "Options", long, env = "BINSTALL_HTTPS_ROOT_CERTS")]
+    ///
+    /// This is useful behind TLS-inspecting proxies that re-sign traffic
+    /// with a custom root CA.
+    ///
+    /// The certificate specified in environment variable `SSL_CERT_FILE`
+    /// (if any) and `http.cainfo` in `.cargo/config.toml` are also loaded.
+    #[clap(
+        help_heading = "Options",
+        long,
+        alias = "cacert",
+        env = "BINSTALL_HTTPS_ROOT_CERTS"
+    )]
     pub(crate) root_certificates: Vec<PathBuf>,
 
+    /// Disable TLS certificate validation for all https connections.
+    ///
+    /// This is meant as a last resort for testing against internal mirrors
+    /// using self-signed certificates, prefer `--root-certificates` whenever
+    /// possible.
+    ///
+    /// WARNING: This makes binstall vulnerable to man-in-the-middle attacks,
+    /// never use it in production.
+    #[clap(help_heading = "Options", long, alias = "danger-accept-invalid-certs")]
+    pub(crate) insecure: bool,
+
     /// Print logs in json format to be parsable.
     #[clap(help_heading = "Options", long)]
     pub json_output: bool,
@@ -292,6 +837,25 @@ pub struct Args {
     #[clap(help_heading = "Options", long, env = "GITHUB_TOKEN")]
     pub(crate) github_token: Option<CompactString>,
 
+    /// Wait for the GitHub rate limit to be reset when it is exceeded while checking for a
+    /// package, instead of failing.
+    ///
+    /// Without a token, GitHub limits the requests to 60 per hour per IP address, and responds
+    /// with 403 once exceeded, which would otherwise not be distinguishable from the package not
+    /// being available. The time the rate limit is reset at is reported either way.
+    ///
+    /// This applies to both the GitHub API and the package urls. Each check waits at most an hour
+    /// at a time, and fails after waiting 3 times.
+    #[clap(help_heading = "Options", long, env = "BINSTALL_WAIT_RATE_LIMIT")]
+    pub(crate) wait_rate_limit: bool,
+
+    /// Provide the access token of a Bitbucket repository or workspace, to install the crates
+    /// whose repository is private.
+    ///
+    /// It is only sent to bitbucket.org, by the "bitbucket" strategy.
+    #[clap(help_heading = "Options", long, env = "BITBUCKET_TOKEN")]
+    pub(crate) bitbucket_token: Option<CompactString>,
+
     /// Only install packages that are signed
     ///
     /// The default is to verify signatures if they are available, but to allow
@@ -309,6 +873,41 @@ pub struct Args {
     #[clap(help_heading = "Options", long, conflicts_with = "only_signed")]
     pub(crate) skip_signatures: bool,
 
+    /// Only use the third-party sources specified, e.g. `QuickInstall`.
+    ///
+    /// Third-party sources not in this comma-separated list are skipped
+    /// with a warning. If not specified, all third-party sources are trusted.
+    #[clap(
+        help_heading = "Options",
+        long,
+        value_delimiter(','),
+        value_name = "SOURCE"
+    )]
+    pub(crate) trusted_host: Option<Vec<CompactString>>,
+
+    /// Abort if a package is not downloaded from the host of the crate's `repository`.
+    ///
+    /// The host of the package url must be the host of the repository, or one of its
+    /// subdomains, which catches `pkg-url` templates pointing to an unrelated domain. Crates
+    /// without a repository, and packages whose url is unknown, cannot be installed with this.
+    /// Third-party sources are not checked, use `--trusted-host` to restrict them.
+    #[clap(help_heading = "Options", long)]
+    pub(crate) strict_origin: bool,
+
+    /// Try the pre-built binaries of crates whose author disabled them.
+    ///
+    /// Crates can set `disabled = true` in their binstall metadata, e.g. if their binaries cannot
+    /// be redistributed, in which case they are built from source unless this is specified.
+    #[clap(help_heading = "Options", long)]
+    pub(crate) force_prebuilt: bool,
+
+    /// Rewrite the repository url of crates before downloading from it, e.g. to use a mirror.
+    ///
+    /// Takes `pattern=replacement`, replacing every occurrence of `pattern` in the url. Can be
+    /// specified multiple times, the rewrites are applied in order.
+    #[clap(help_heading = "Overrides", long, value_name = "PATTERN=REPLACEMENT")]
+    pub(crate) repo_rewrite: Vec<RepoRewrite>,
+
     /// Print version information
     #[clap(help_heading = "Meta", short = 'V')]
     pub version: bool,
@@ -340,7 +939,23 @@ pub struct Args {
     #[clap(help_heading = "Meta", short, long)]
     pub verbose: bool,
 
-    /// Equivalent to setting `log_level` to `off`.
+    /// Print the full chain of causes of a fatal error, e.g. the network
+    /// error under a failed download, which is useful when filing a bug report.
+    #[clap(help_heading = "Meta", long)]
+    pub verbose_errors: bool,
+
+    /// Check the fetchers for packages one at a time, in the order of the strategies and
+    /// targets, rather than all at once.
+    ///
+    /// This is slower, but makes the logs easy to follow and which fetcher is used
+    /// deterministic, which helps debugging the resolution.
+    #[clap(help_heading = "Meta", long)]
+    pub(crate) no_discovery_parallelism: bool,
+
+    /// Only print errors, suppressing all informational output and warnings.
+    ///
+    /// Equivalent to setting `log_level` to `error`, and it will also pass
+    /// `--quiet` to `cargo-install`.
     ///
     /// This would override the `log_level`.
     #[clap(help_heading = "Meta", short, long, conflicts_with("verbose"))]
@@ -414,6 +1029,73 @@ pub(crate) enum Strategy {
     QuickInstall,
     /// Build the crates from source using `cargo-build`.
     Compile,
+    /// Ask the program specified by `--external-fetcher` for the packages.
+    External,
+    /// Download the artifacts of the GitHub Actions workflows specified in `Cargo.toml`.
+    GhActions,
+    /// Download official pre-built artifacts from the Downloads of the Bitbucket repository.
+    Bitbucket,
+    /// Download official pre-built artifacts listed in the releases index specified in
+    /// `Cargo.toml`.
+    ReleasesIndex,
+}
+
+/// Fetcher which can be added by `--add-fetcher`
+#[derive(Debug, Copy, Clone, Eq, PartialEq, ValueEnum)]
+pub(crate) enum FetcherName {
+    /// Official pre-built artifacts described in `Cargo.toml`, hosted on GitHub, GitLab,
+    /// SourceForge or at `pkg-url`.
+    GhCrateMeta,
+    /// Third-party QuickInstall.
+    QuickInstall,
+    /// The program specified by `--external-fetcher`.
+    External,
+    /// Artifacts of the GitHub Actions workflows described in `Cargo.toml`.
+    GhActions,
+    /// Official pre-built artifacts in the Downloads of Bitbucket repositories.
+    BitbucketCrateMeta,
+    /// Official pre-built artifacts listed in the releases index described in `Cargo.toml`.
+    ReleasesIndex,
+}
+
+/// Stage of the installation which can be specified by `--fallback`
+#[derive(Debug, Copy, Clone, Eq, PartialEq, ValueEnum)]
+pub(crate) enum FallbackStage {
+    /// Official pre-built artifacts, from all the sources described in `Cargo.toml` and from
+    /// `--external-fetcher` if specified.
+    Prebuilt,
+    /// Third-party QuickInstall.
+    Quickinstall,
+    /// Building from source.
+    Source,
+}
+
+impl FallbackStage {
+    /// Strategies of the stage, in the default order.
+    fn strategies(self) -> &'static [Strategy] {
+        match self {
+            FallbackStage::Prebuilt => &[
+                Strategy::ReleasesIndex,
+                Strategy::CrateMetaData,
+                Strategy::Bitbucket,
+            ],
+            FallbackStage::Quickinstall => &[Strategy::QuickInstall],
+            FallbackStage::Source => &[Strategy::Compile],
+        }
+    }
+}
+
+impl From<FetcherName> for Strategy {
+    fn from(fetcher: FetcherName) -> Self {
+        match fetcher {
+            FetcherName::GhCrateMeta => Strategy::CrateMetaData,
+            FetcherName::QuickInstall => Strategy::QuickInstall,
+            FetcherName::External => Strategy::External,
+            FetcherName::GhActions => Strategy::GhActions,
+            FetcherName::BitbucketCrateMeta => Strategy::Bitbucket,
+            FetcherName::ReleasesIndex => Strategy::ReleasesIndex,
+        }
+    }
 }
 
 pub fn parse() -> Args {
@@ -439,14 +1121,14 @@ pub fn parse() -> Args {
     // Load options
     let mut opts = Args::parse_from(args);
 
-    if opts.log_level.is_none() {
+    if opts.quiet {
+        opts.log_level = Some(LevelFilter::Error);
+    } else if opts.log_level.is_none() {
         if let Some(log) = env::var("BINSTALL_LOG_LEVEL")
             .ok()
             .and_then(|s| s.parse().ok())
         {
             opts.log_level = Some(log);
-        } else if opts.quiet {
-            opts.log_level = Some(LevelFilter::Off);
         } else if opts.verbose {
             opts.log_level = Some(LevelFilter::Debug);
         }
@@ -458,8 +1140,12 @@ pub fn parse() -> Args {
     if opts.crate_names.len() > 1 {
         let option = if opts.version_req.is_some() {
             "version"
-        } else if opts.manifest_path.is_some() {
+        } else if opts.manifest_path.is_some() && !opts.dev_tools {
             "manifest-path"
+        } else if opts.url.is_some() {
+            "url"
+        } else if opts.manifest_url.is_some() {
+            "manifest-url"
         } else {
             #[cfg(not(feature = "git"))]
             {
@@ -487,11 +1173,109 @@ You cannot use --{option} and specify multiple packages at the same time. Do one
         }
     }
 
+    // Expand the platform aliases in the targets
+    if let Some(targets) = opts.targets.take() {
+        let mut expanded = Vec::with_capacity(targets.len());
+
+        for target in targets {
+            // Target triples always contain a `-`
+            if target.contains('-') {
+                expanded.push(target);
+            } else if let Some(triples) =
+                target_alias_triples(&target, env::consts::ARCH, env::consts::OS)
+            {
+                if triples.is_empty() {
+                    command
+                        .error(
+                            ErrorKind::InvalidValue,
+                            format_args!(
+                                "target alias {target} has no triple that runs on {}, \
+                                 specify the target triple instead",
+                                env::consts::ARCH
+                            ),
+                        )
+                        .exit()
+                }
+                expanded.extend(triples.into_iter().map(ToString::to_string));
+            } else {
+                command
+                    .error(
+                        ErrorKind::InvalidValue,
+                        format_args!(
+                            "unknown target alias {target}, expected a target triple \
+                             or one of linux, macos, windows"
+                        ),
+                    )
+                    .exit()
+            }
+        }
+
+        opts.targets = Some(expanded);
+    }
+
+    // Use the first bin as the crate name when installing from `--url`
+    if opts.crate_names.is_empty() && opts.url.is_some() {
+        if let Some(bin) = opts.bins.first() {
+            opts.crate_names.push(CrateName {
+                name: bin.clone(),
+                ..Default::default()
+            });
+        }
+    }
+
+    // Check that the install order constraints can be satisfied
+    if !opts.after.is_empty() {
+        let names: Vec<CompactString> = opts
+            .crate_names
+            .iter()
+            .map(|crate_name| crate_name.name.clone())
+            .collect();
+
+        if let Err(name) = install_levels(&names, &opts.after) {
+            command
+                .error(
+                    ErrorKind::ValueValidation,
+                    format_args!("--after constraints form a cycle involving {name}"),
+                )
+                .exit()
+        }
+    }
+
+    // Compose the strategies from the fetchers added
+    if opts.no_default_fetchers {
+        opts.strategies = opts
+            .add_fetcher
+            .iter()
+            .copied()
+            .map(Strategy::from)
+            .chain([Strategy::Compile])
+            .collect();
+    }
+
+    // Compose the strategies from the fallback stages
+    if !opts.fallback.is_empty() {
+        let external = opts
+            .external_fetcher
+            .is_some()
+            .then_some(Strategy::External);
+
+        opts.strategies = opts
+            .fallback
+            .iter()
+            .flat_map(|stage| {
+                let external = external.filter(|_| *stage == FallbackStage::Prebuilt);
+                external
+                    .into_iter()
+                    .chain(stage.strategies().iter().copied())
+            })
+            .collect();
+    }
+
     // Check strategies for duplicates
     let mut new_dup_strategy_err = || {
         command.error(
             ErrorKind::TooManyValues,
-            "--strategies should not contain duplicate strategy",
+            "--strategies, --add-fetcher and --fallback should not contain duplicate strategy",
         )
     };
 
@@ -516,10 +1300,35 @@ You cannot use --{option} and specify multiple packages at the same time. Do one
     // Default strategies if empty
     if opts.strategies.is_empty() {
         opts.strategies = vec![
+            Strategy::ReleasesIndex,
             Strategy::CrateMetaData,
+            Strategy::Bitbucket,
             Strategy::QuickInstall,
             Strategy::Compile,
         ];
+
+        if opts.external_fetcher.is_some() {
+            opts.strategies.insert(0, Strategy::External);
+        }
+    }
+
+    if opts.strategies.contains(&Strategy::External) && opts.external_fetcher.is_none() {
+        command
+            .error(
+                ErrorKind::MissingRequiredArgument,
+                "External strategy requires --external-fetcher",
+            )
+            .exit()
+    }
+
+    #[cfg(not(feature = "external-fetcher"))]
+    if opts.strategies.contains(&Strategy::External) {
+        command
+            .error(
+                ErrorKind::InvalidValue,
+                "External strategy requires cargo-binstall to be built with the external-fetcher feature",
+            )
+            .exit()
     }
 
     // Filter out all disabled strategies
@@ -564,6 +1373,56 @@ You cannot use --{option} and specify multiple packages at the same time. Do one
     opts
 }
 
+/// Target triples a platform alias given to `--targets` expands to, for a
+/// host of architecture `host_arch` running `host_os`, as named by
+/// [`std::env::consts`].
+///
+/// For the platform of the host, only the triples it can run are kept,
+/// the ones of its own architecture first. For other platforms, which
+/// are installed for elsewhere, they are all kept, still ranked by the
+/// architecture of the host.
+fn target_alias_triples(alias: &str, host_arch: &str, host_os: &str) -> Option<Vec<&'static str>> {
+    let triples: &[&str] = match alias {
+        "linux" => &[
+            "x86_64-unknown-linux-gnu",
+            "x86_64-unknown-linux-musl",
+            "aarch64-unknown-linux-gnu",
+            "aarch64-unknown-linux-musl",
+        ],
+        "macos" => &["x86_64-apple-darwin", "aarch64-apple-darwin"],
+        "windows" => &[
+            "x86_64-pc-windows-msvc",
+            "aarch64-pc-windows-msvc",
+            "i686-pc-windows-msvc",
+        ],
+        _ => return None,
+    };
+
+    let host_arch = match host_arch {
+        "x86" => "i686",
+        arch => arch,
+    };
+    let arch_of = |triple: &str| triple.split('-').next().unwrap_or_default().to_owned();
+    // Binaries of other architectures run through emulation on some platforms.
+    let can_run = |arch: &str| {
+        arch == host_arch
+            || matches!(
+                (host_arch, alias, arch),
+                ("aarch64", "macos" | "windows", "x86_64")
+                    | ("x86_64" | "aarch64", "windows", "i686")
+            )
+    };
+
+    let mut triples: Vec<_> = triples
+        .iter()
+        .copied()
+        .filter(|triple| alias != host_os || can_run(&arch_of(triple)))
+        .collect();
+    triples.sort_by_key(|triple| arch_of(triple) != host_arch);
+
+    Some(triples)
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -572,4 +1431,64 @@ mod test {
     fn verify_cli() {
         Args::command().debug_assert()
     }
+
+    #[test]
+    fn target_aliases_of_host_platform() {
+        assert_eq!(
+            target_alias_triples("linux", "x86_64", "linux").unwrap(),
+            ["x86_64-unknown-linux-gnu", "x86_64-unknown-linux-musl"]
+        );
+        assert_eq!(
+            target_alias_triples("linux", "aarch64", "linux").unwrap(),
+            ["aarch64-unknown-linux-gnu", "aarch64-unknown-linux-musl"]
+        );
+        // Rosetta runs x86_64 binaries on Apple silicon.
+        assert_eq!(
+            target_alias_triples("macos", "aarch64", "macos").unwrap(),
+            ["aarch64-apple-darwin", "x86_64-apple-darwin"]
+        );
+        assert_eq!(
+            target_alias_triples("macos", "x86_64", "macos").unwrap(),
+            ["x86_64-apple-darwin"]
+        );
+        assert_eq!(
+            target_alias_triples("windows", "x86_64", "windows").unwrap(),
+            ["x86_64-pc-windows-msvc", "i686-pc-windows-msvc"]
+        );
+        assert_eq!(
+            target_alias_triples("windows", "aarch64", "windows").unwrap(),
+            [
+                "aarch64-pc-windows-msvc",
+                "x86_64-pc-windows-msvc",
+                "i686-pc-windows-msvc"
+            ]
+        );
+        assert_eq!(
+            target_alias_triples("windows", "x86", "windows").unwrap(),
+            ["i686-pc-windows-msvc"]
+        );
+        assert!(target_alias_triples("linux", "riscv64", "linux")
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn target_aliases_of_other_platforms() {
+        // Installing for another platform keeps all the triples, ranked by
+        // the architecture of the host.
+        assert_eq!(
+            target_alias_triples("linux", "aarch64", "macos").unwrap(),
+            [
+                "aarch64-unknown-linux-gnu",
+                "aarch64-unknown-linux-musl",
+                "x86_64-unknown-linux-gnu",
+                "x86_64-unknown-linux-musl"
+            ]
+        );
+        assert_eq!(
+            target_alias_triples("macos", "x86_64", "linux").unwrap(),
+            ["x86_64-apple-darwin", "aarch64-apple-darwin"]
+        );
+        assert_eq!(target_alias_triples("freebsd", "x86_64", "linux"), None);
+    }
 }
@@ -1,5 +1,7 @@
 use std::{
+    error::Error,
     future::Future,
+    iter,
     process::{ExitCode, Termination},
     time::Duration,
 };
@@ -14,8 +16,9 @@ use crate::signal::cancel_on_user_sig_term;
 
 pub enum MainExit {
     Success(Option<Duration>),
-    Error(BinstallError),
-    Report(miette::Report),
+    /// The `bool` is whether to print the full chain of causes.
+    Error(BinstallError, bool),
+    Report(miette::Report, bool),
 }
 
 impl Termination for MainExit {
@@ -27,8 +30,17 @@ impl Termination for MainExit {
                 }
                 ExitCode::SUCCESS
             }
-            Self::Error(err) => err.report(),
-            Self::Report(err) => {
+            Self::Error(err, verbose_errors) => {
+                if verbose_errors {
+                    let err: &(dyn Error + 'static) = &err;
+                    report_error_chain(iter::successors(Some(err), |err| (*err).source()));
+                }
+                err.report()
+            }
+            Self::Report(err, verbose_errors) => {
+                if verbose_errors {
+                    report_error_chain(err.chain());
+                }
                 error!("Fatal error:\n{err:?}");
                 ExitCode::from(16)
             }
@@ -37,16 +49,27 @@ impl Termination for MainExit {
 }
 
 impl MainExit {
-    pub fn new(res: Result<()>, done: Duration) -> Self {
+    pub fn new(res: Result<()>, done: Duration, verbose_errors: bool) -> Self {
         res.map(|()| MainExit::Success(Some(done)))
             .unwrap_or_else(|err| {
                 err.downcast::<BinstallError>()
-                    .map(MainExit::Error)
-                    .unwrap_or_else(MainExit::Report)
+                    .map(|err| MainExit::Error(err, verbose_errors))
+                    .unwrap_or_else(|err| MainExit::Report(err, verbose_errors))
             })
     }
 }
 
+/// Print an error followed by each of its `source()`, one per line.
+fn report_error_chain<'a>(chain: impl Iterator<Item = &'a (dyn Error + 'static)>) {
+    let chain = chain
+        .enumerate()
+        .map(|(i, err)| format!("{i:>4}: {err}"))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    error!("Error chain:\n{chain}");
+}
+
 /// This function would start a tokio multithreading runtime,
 /// spawn a new task on it that runs `f()`, then `block_on` it.
 ///
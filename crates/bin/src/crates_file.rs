@@ -0,0 +1,101 @@
+//! `--from-file`: crates to install listed in a file.
+//!
+//! Each line of the file is a crate to install, as `crate[@version]` like
+//! on the command line, optionally followed by `--version <version>` as an
+//! alternative way to specify the version. Blank lines and comments starting
+//! with `#` are ignored.
+
+use std::{fs, path::Path, str::FromStr};
+
+use binstalk::ops::resolve::{CrateName, VersionReqExt};
+use miette::{miette, Result, WrapErr};
+use semver::VersionReq;
+
+/// Parse the crates listed in `content`.
+fn parse(content: &str) -> Result<Vec<CrateName>, String> {
+    let mut crate_names = Vec::new();
+
+    for (i, line) in content.lines().enumerate() {
+        let line = line
+            .split_once('#')
+            .map_or(line, |(line, _comment)| line)
+            .trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let invalid = |reason: &str| format!("line {}: {reason}: `{line}`", i + 1);
+
+        let mut words = line.split_whitespace();
+        let spec = words.next().unwrap();
+        let mut crate_name =
+            CrateName::from_str(spec).map_err(|err| invalid(&format!("invalid version: {err}")))?;
+
+        while let Some(word) = words.next() {
+            let version = match word.strip_prefix("--version") {
+                Some("") => words.next(),
+                Some(version) => version.strip_prefix('='),
+                None => None,
+            }
+            .ok_or_else(|| invalid(&format!("unexpected `{word}`, only --version is supported")))?;
+
+            if crate_name.version_req.is_some() {
+                return Err(invalid("the version is specified twice"));
+            }
+            crate_name.version_req = Some(
+                VersionReq::parse_from_cli(version)
+                    .map_err(|err| invalid(&format!("invalid version: {err}")))?,
+            );
+        }
+
+        crate_names.push(crate_name);
+    }
+
+    Ok(crate_names)
+}
+
+/// Load the crates listed in the file at `path`.
+pub(crate) fn load(path: &Path) -> Result<Vec<CrateName>> {
+    let content = fs::read_to_string(path)
+        .map_err(|err| miette!("{err}"))
+        .wrap_err_with(|| format!("Failed to read crates file {}", path.display()))?;
+
+    parse(&content).map_err(|err| miette!("Invalid crates file {}: {err}", path.display()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_crates_file() {
+        let crate_names = parse(
+            "# CI tools\n\
+             cargo-nextest@0.9.67\n\
+             \n\
+             cargo-deny --version ^0.14 # license checks\n\
+             just --version=1.23.0\n\
+             ripgrep\n",
+        )
+        .unwrap();
+
+        let crate_names: Vec<_> = crate_names
+            .iter()
+            .map(|crate_name| (&*crate_name.name, crate_name.version_req.clone()))
+            .collect();
+        assert_eq!(
+            crate_names,
+            [
+                ("cargo-nextest", Some(VersionReq::parse("=0.9.67").unwrap())),
+                ("cargo-deny", Some(VersionReq::parse("^0.14").unwrap())),
+                ("just", Some(VersionReq::parse("=1.23.0").unwrap())),
+                ("ripgrep", None),
+            ]
+        );
+
+        assert!(parse("cargo-nextest --force").is_err());
+        assert!(parse("cargo-nextest --version").is_err());
+        assert!(parse("cargo-nextest@0.9 --version 0.9").is_err());
+        assert!(parse("cargo-nextest@not-a-version").is_err());
+    }
+}
@@ -1,79 +1,210 @@
 use std::{
+    borrow::Cow,
+    collections::BTreeMap,
     env, fs,
     future::Future,
-    path::{Path, PathBuf},
+    iter,
+    num::NonZeroUsize,
+    path::{Component, Path, PathBuf},
     sync::Arc,
+    time::{Duration, Instant, SystemTime},
 };
 
 use binstalk::{
     errors::BinstallError,
-    fetchers::{Fetcher, GhCrateMeta, QuickInstall, SignaturePolicy},
-    get_desired_targets,
+    fetchers::{
+        BitbucketCrateMeta, Fetcher, GhActionsArtifact, GhCrateMeta, QuickInstall,
+        ReleasesIndexFetcher, SignaturePolicy,
+    },
+    get_desired_targets, get_host_target,
     helpers::{
         gh_api_client::GhApiClient,
         jobserver_client::LazyJobserverClient,
-        remote::{Certificate, Client},
+        pin_mtime,
+        remote::{Certificate, Client, Netrc},
         tasks::AutoAbortJoinHandle,
     },
     ops::{
         self,
-        resolve::{CrateName, Resolution, ResolutionFetch, VersionReqExt},
-        CargoTomlFetchOverride, Options, Resolver,
+        report::{CrateOutcome, InstallReport},
+        resolve::{
+            CrateName, Finding, Resolution, ResolutionFetch, TargetAvailability,
+            VersionAvailability, VersionReqExt,
+        },
+        CargoTomlFetchOverride, FetcherSelector, Options, Resolver,
     },
+    registry::Registry,
 };
 use binstalk_manifests::{
-    cargo_config::Config, cargo_toml_binstall::PkgOverride, crates_manifests::Manifests,
+    cargo_config::Config, cargo_toml_binstall::PkgOverride, crate_info::CrateInfo,
+    crates_manifests::Manifests, target_config::TargetConfig,
 };
+use compact_str::CompactString;
 use file_format::FileFormat;
-use home::cargo_home;
+use home::{cargo_home, home_dir};
 use log::LevelFilter;
 use miette::{miette, Result, WrapErr};
-use tokio::task::block_in_place;
+use tokio::{sync::Semaphore, task::block_in_place};
 use tracing::{debug, error, info, warn};
 
 use crate::{
     args::{Args, Strategy},
-    gh_token, git_credentials, install_path,
-    ui::confirm,
+    crates_file, gh_token, git_credentials,
+    install_order::install_levels,
+    install_path, install_script,
+    ui::{self, confirm},
+    version_file,
 };
 
 pub fn install_crates(
-    args: Args,
+    mut args: Args,
     jobserver_client: LazyJobserverClient,
-) -> Result<Option<impl Future<Output = Result<()>>>> {
+) -> Result<Option<impl Future<Output = Result<InstallReport>>>> {
+    if args.check_manifest {
+        check_manifest(args.manifest_path, args.crate_names, args.json_output)?;
+        return Ok(None);
+    }
+
     // Compute Resolvers
     let mut cargo_install_fallback = false;
 
-    let resolvers: Vec<_> = args
-        .strategies
-        .into_iter()
-        .filter_map(|strategy| match strategy {
-            Strategy::CrateMetaData => Some(GhCrateMeta::new as Resolver),
-            Strategy::QuickInstall => Some(QuickInstall::new as Resolver),
-            Strategy::Compile => {
-                cargo_install_fallback = true;
-                None
-            }
-        })
-        .collect();
+    let resolvers: Vec<_> = if args.url.is_some() {
+        // The url is downloaded as-is, there is nothing else to try.
+        vec![GhCrateMeta::new as Resolver]
+    } else {
+        args.strategies
+            .into_iter()
+            .filter_map(|strategy| match strategy {
+                Strategy::CrateMetaData => Some(GhCrateMeta::new as Resolver),
+                Strategy::Bitbucket => Some(BitbucketCrateMeta::new as Resolver),
+                Strategy::QuickInstall => Some(QuickInstall::new as Resolver),
+                #[cfg(feature = "external-fetcher")]
+                Strategy::External => Some(binstalk::fetchers::ExternalFetcher::new as Resolver),
+                #[cfg(not(feature = "external-fetcher"))]
+                Strategy::External => unreachable!("rejected when parsing the arguments"),
+                Strategy::GhActions => Some(GhActionsArtifact::new as Resolver),
+                Strategy::ReleasesIndex => Some(ReleasesIndexFetcher::new as Resolver),
+                Strategy::Compile => {
+                    cargo_install_fallback = true;
+                    None
+                }
+            })
+            .collect()
+    };
 
-    // Load .cargo/config.toml
+    // Load the .cargo/config.toml of the current directory and its parents,
+    // then the one of cargo home, like cargo does.
     let cargo_home = cargo_home().map_err(BinstallError::from)?;
-    let mut config = Config::load_from_path(cargo_home.join("config.toml"))?;
+    let cwd = env::current_dir().map_err(BinstallError::from)?;
+    let mut config = Config::load_hierarchy(&cwd, &cargo_home)?;
+    // Tokens of registries are usually stored in credentials.toml
+    let credentials = Config::load_from_path(cargo_home.join("credentials.toml"))?;
+
+    let pinned_mtime = if args.reproducible {
+        let secs = match env::var("SOURCE_DATE_EPOCH") {
+            Ok(secs) => secs
+                .trim()
+                .parse()
+                .map_err(|err| miette!("Invalid SOURCE_DATE_EPOCH {secs:?}: {err}"))?,
+            Err(_) => 0,
+        };
+        Some(SystemTime::UNIX_EPOCH + Duration::from_secs(secs))
+    } else {
+        None
+    };
 
     // Compute paths
-    let cargo_root = args.root;
-    let (install_path, mut manifests, temp_dir) = compute_paths_and_load_manifests(
-        cargo_root.clone(),
+    let destdir = args.destdir;
+    let (install_path, cargo_roots, track_installs, temp_dir) = compute_paths(
+        args.root.clone(),
         args.install_path,
+        args.temp_dir,
         args.no_track,
+        destdir.as_deref(),
         cargo_home,
         &mut config,
     )?;
+    // Only the manifests of the cargo root track the crates installed.
+    let manifests_root = track_installs.then(|| cargo_roots.clone());
+    let extra_files_dir = match (args.no_extra_files, args.extra_files_dir) {
+        (true, _) => None,
+        (false, Some(dir)) => Some(match &destdir {
+            Some(destdir) => stage_path(destdir, &dir)?,
+            None => dir,
+        }),
+        (false, None) => Some(cargo_roots.join("share").join("doc")),
+    };
+    // Crates built from source must be staged as well
+    let cargo_root = if destdir.is_some() {
+        Some(cargo_roots)
+    } else {
+        args.root
+    };
+
+    // Add the dev tools declared by the project, its manifest is only used
+    // to read the tools from.
+    if args.dev_tools {
+        let manifest_path = args
+            .manifest_path
+            .take()
+            .unwrap_or_else(|| PathBuf::from("Cargo.toml"));
+        args.crate_names
+            .extend(ops::resolve::load_dev_tools(&manifest_path)?);
+    }
+
+    // Add the crates listed in the file.
+    if let Some(from_file) = &args.from_file {
+        args.crate_names.extend(crates_file::load(from_file)?);
+    }
+
+    // Resolve the aliases set in the config, names which are not aliases
+    // are kept as is.
+    if let Some(aliases) = config.binstall.take().and_then(|binstall| binstall.aliases) {
+        for crate_name in &mut args.crate_names {
+            if let Some(name) = aliases.get(&crate_name.name) {
+                debug!("Resolved alias {} to crate {name}", crate_name.name);
+                crate_name.name = name.clone();
+            }
+        }
+        for constraint in &mut args.after {
+            constraint.resolve_aliases(&aliases);
+        }
+    }
+
+    // `--after` constraints only order the crates being installed.
+    for constraint in &args.after {
+        let unknown = [&constraint.name, &constraint.prerequisite]
+            .into_iter()
+            .find(|name| {
+                !args
+                    .crate_names
+                    .iter()
+                    .any(|crate_name| crate_name.name == **name)
+            });
+        if let Some(name) = unknown {
+            warn!(
+                "Ignoring `--after {}:{}`, {name} is not among the crates to install",
+                constraint.name, constraint.prerequisite
+            );
+        }
+    }
 
-    // Remove installed crates
-    let mut crate_names =
-        filter_out_installed_crates(args.crate_names, args.force, manifests.as_mut())?.peekable();
+    // Pin the versions read from the file, `--version` overrides them.
+    if let (Some(version_file), None) = (&args.version_file, &args.version_req) {
+        version_file::apply(version_file, &mut args.crate_names)?;
+    }
+
+    // Remove installed crates, unless only listing their targets or versions or diagnosing them
+    let only_inspect = args.list_targets || args.report_unsupported;
+    let installed_crates = open_manifests(manifests_root.as_deref())?
+        .map(|mut manifests| manifests.load_installed_crates())
+        .transpose()?;
+    let mut crate_names = filter_out_installed_crates(
+        args.crate_names,
+        args.force || only_inspect || args.list_versions || args.verify_only,
+        installed_crates,
+    )
+    .peekable();
 
     if crate_names.peek().is_none() {
         debug!("Nothing to do");
@@ -81,14 +212,45 @@ pub fn install_crates(
     }
 
     // Launch target detection
-    let desired_targets = get_desired_targets(args.targets);
+    let desired_targets = if args.frozen_targets {
+        get_host_target()
+    } else {
+        get_desired_targets(
+            args.targets
+                .or_else(|| {
+                    only_inspect.then(|| COMMON_TARGETS.iter().map(ToString::to_string).collect())
+                })
+                .or_else(|| {
+                    // Default to the target cargo builds for
+                    env::var("CARGO_BUILD_TARGET")
+                        .ok()
+                        .filter(|target| !target.is_empty())
+                        .map(|target| vec![target])
+                        .or_else(|| {
+                            let target = config.build.take()?.target?;
+                            Some(target.into_vec().into_iter().map(String::from).collect())
+                        })
+                }),
+        )
+    };
 
     // Computer cli_overrides
     let cli_overrides = PkgOverride {
         pkg_url: args.pkg_url,
         pkg_fmt: args.pkg_fmt,
+        mirrors: None,
         bin_dir: args.bin_dir,
         signing: None,
+        checksum: None,
+    };
+
+    let target_overrides = match &args.target_config {
+        Some(path) => {
+            TargetConfig::load_from_path(path)
+                .wrap_err_with(|| format!("Failed to load {}", path.display()))?
+                .target
+        }
+        None => Default::default(),
     };
 
     // Initialize reqwest client
@@ -96,16 +258,31 @@ pub fn install_crates(
 
     let mut http = config.http.take();
 
-    let client = Client::new(
+    if args.insecure {
+        warn!(
+            "--insecure is specified: TLS certificate validation is DISABLED for all connections!"
+        );
+        warn!("Downloads can be tampered with by anyone on the network, do NOT use this in production.");
+    }
+
+    let client = Client::builder(
         concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION")),
-        args.min_tls_version.map(|v| v.into()),
         rate_limit.duration,
         rate_limit.request_count,
-        read_root_certs(
-            args.root_certificates,
-            http.as_mut().and_then(|http| http.cainfo.take()),
-        ),
     )
+    .min_tls(args.min_tls_version.map(|v| v.into()))
+    .root_certificates(read_root_certs(
+        args.root_certificates,
+        env::var_os("SSL_CERT_FILE").map(PathBuf::from),
+        http.as_mut().and_then(|http| http.cainfo.take()),
+    ))
+    .danger_accept_invalid_certs(args.insecure)
+    .max_download_size(args.max_download_size)
+    .http1_only(args.http1_only)
+    .netrc((!args.no_netrc).then(load_netrc).flatten())
+    .limit_rate(args.limit_rate)
+    .connections(args.connections)
+    .build()
     .map_err(BinstallError::from)?;
 
     let gh_api_client = GhApiClient::new(
@@ -129,35 +306,80 @@ pub fn install_crates(
     // Create binstall_opts
     let binstall_opts = Arc::new(Options {
         no_symlinks: args.no_symlinks,
-        dry_run: args.dry_run,
+        dry_run: args.dry_run || args.verify_only,
         force: args.force,
-        quiet: args.log_level == Some(LevelFilter::Off),
-        locked: args.locked,
+        quiet: args.quiet || args.log_level == Some(LevelFilter::Off),
+        locked: args.locked || args.frozen,
+        frozen: args.frozen,
         no_track: args.no_track,
+        versioned_bin: args.versioned_bin,
 
         version_req: args.version_req,
+        exact: args.exact,
+        allow_pre_releases: args.pre,
+        version_selector: None,
+        quiet_version_check: args.quiet_version_check,
         #[cfg(feature = "git")]
-        cargo_toml_fetch_override: match (args.manifest_path, args.git) {
-            (Some(manifest_path), None) => Some(CargoTomlFetchOverride::Path(manifest_path)),
-            (None, Some(git_url)) => Some(CargoTomlFetchOverride::Git(git_url)),
-            (None, None) => None,
-            _ => unreachable!("manifest_path and git cannot be specified at the same time"),
+        cargo_toml_fetch_override: match (args.manifest_path, args.git, args.url, args.manifest_url)
+        {
+            (Some(manifest_path), None, None, None) => {
+                Some(CargoTomlFetchOverride::Path(manifest_path))
+            }
+            (None, Some(git_url), None, None) => Some(CargoTomlFetchOverride::Git(git_url)),
+            (None, None, Some(url), None) => Some(CargoTomlFetchOverride::Url {
+                url,
+                bins: args.bins,
+            }),
+            (None, None, None, Some(url)) => Some(CargoTomlFetchOverride::ManifestUrl(url)),
+            (None, None, None, None) => None,
+            _ => unreachable!(
+                "manifest_path, git, url and manifest_url cannot be specified at the same time"
+            ),
         },
 
         #[cfg(not(feature = "git"))]
-        cargo_toml_fetch_override: args.manifest_path.map(CargoTomlFetchOverride::Path),
+        cargo_toml_fetch_override: match (args.manifest_path, args.url, args.manifest_url) {
+            (Some(manifest_path), None, None) => Some(CargoTomlFetchOverride::Path(manifest_path)),
+            (None, Some(url), None) => Some(CargoTomlFetchOverride::Url {
+                url,
+                bins: args.bins,
+            }),
+            (None, None, Some(url)) => Some(CargoTomlFetchOverride::ManifestUrl(url)),
+            (None, None, None) => None,
+            _ => unreachable!(
+                "manifest_path, url and manifest_url cannot be specified at the same time"
+            ),
+        },
+        local_manifest_dir: args.prefer_local_manifest,
         cli_overrides,
+        target_overrides,
 
         desired_targets,
         resolvers,
+        select_fetcher: (args.select_fetcher_interactive && !args.no_confirm)
+            .then(|| FetcherSelector::new(ui::select_fetcher)),
+        external_fetcher: args.external_fetcher,
+        extract_command: args.extract_command,
         cargo_install_fallback,
+        features: args.features,
+        no_default_features: args.no_default_features,
+        all_features: args.all_features,
 
         temp_dir: temp_dir.path().to_owned(),
+        keep_download: args.keep_download,
         install_path,
         cargo_root,
+        extra_files_dir,
+        pre_install_cmd: args.pre_install_cmd,
+        post_install_cmd: args.post_install_cmd,
+        pinned_mtime,
+        check_version: args.check_version,
+        sequential_discovery: args.no_discovery_parallelism,
 
         client,
         gh_api_client,
+        bitbucket_token: args.bitbucket_token,
+        wait_rate_limit: args.wait_rate_limit,
         jobserver_client,
         registry: if let Some(index) = args.index {
             index
@@ -167,16 +389,20 @@ pub fn install_crates(
         {
             let registry_name_lowercase = registry_name.to_lowercase();
 
-            let v = env::vars().find_map(|(k, v)| {
-                let name_lowercase = k
-                    .strip_prefix("CARGO_REGISTRIES_")?
-                    .strip_suffix("_INDEX")?
-                    .to_lowercase();
+            let registry_env = |suffix: &str| {
+                env::vars().find_map(|(k, v)| {
+                    let name_lowercase = k
+                        .strip_prefix("CARGO_REGISTRIES_")?
+                        .strip_suffix(suffix)?
+                        .to_lowercase();
 
-                (name_lowercase == registry_name_lowercase).then_some(v)
-            });
+                    (name_lowercase == registry_name_lowercase).then_some(v)
+                })
+            };
 
-            if let Some(v) = &v {
+            let v = registry_env("_INDEX");
+
+            let registry: Registry = if let Some(v) = &v {
                 v
             } else {
                 config
@@ -184,10 +410,23 @@ pub fn install_crates(
                     .as_ref()
                     .and_then(|registries| registries.get(&registry_name))
                     .and_then(|registry| registry.index.as_deref())
-                    .ok_or_else(|| BinstallError::UnknownRegistryName(registry_name))?
+                    .ok_or_else(|| BinstallError::UnknownRegistryName(registry_name.clone()))?
             }
             .parse()
-            .map_err(BinstallError::from)?
+            .map_err(BinstallError::from)?;
+
+            let token = registry_env("_TOKEN").map(CompactString::from).or_else(|| {
+                [config.registries.as_ref(), credentials.registries.as_ref()]
+                    .into_iter()
+                    .flatten()
+                    .find_map(|registries| registries.get(&registry_name)?.token.clone())
+            });
+
+            if let Some(token) = token {
+                registry.with_auth_token(token)
+            } else {
+                registry
+            }
         } else {
             Default::default()
         },
@@ -199,31 +438,118 @@ pub fn install_crates(
         } else {
             SignaturePolicy::IfPresent
         },
+        prefer_smallest: args.prefer_smallest,
+        trusted_hosts: args.trusted_host,
+        strict_origin: args.strict_origin,
+        force_prebuilt: args.force_prebuilt,
+        repo_rewrites: args.repo_rewrite,
     });
 
     // Destruct args before any async function to reduce size of the future
-    let dry_run = args.dry_run;
+    let dry_run = args.dry_run || args.verify_only;
+    let verify_only = args.verify_only;
+    let time_report = args.time_report;
+    let json_output = args.json_output;
+
     let no_confirm = args.no_confirm;
+    let refuse_untracked = args.refuse_untracked;
     let no_cleanup = args.no_cleanup;
+    let install_after = args.after;
+    let emit_script = args.emit_script;
 
-    // Resolve crates
-    let tasks: Vec<_> = crate_names
-        .map(|(crate_name, current_version)| {
-            AutoAbortJoinHandle::spawn(ops::resolve::resolve(
-                binstall_opts.clone(),
-                crate_name,
-                current_version,
-            ))
-        })
-        .collect();
+    // Limit the number of crates resolved or built at the same time
+    let semaphore = Arc::new(Semaphore::new(
+        args.max_concurrent_crates
+            .map_or(Semaphore::MAX_PERMITS, NonZeroUsize::get),
+    ));
+
+    // Resolve crates, or only list their available targets or versions or diagnose them
+    let mut list_targets_tasks = Vec::new();
+    let mut list_versions_tasks = Vec::new();
+    let mut report_tasks = Vec::new();
+    let tasks: Vec<_> = if args.list_targets {
+        list_targets_tasks = crate_names
+            .map(|(crate_name, _)| {
+                let name = crate_name.name.clone();
+                let task = AutoAbortJoinHandle::spawn(ops::resolve::list_targets(
+                    binstall_opts.clone(),
+                    crate_name,
+                ));
+
+                (name, task)
+            })
+            .collect();
+
+        Vec::new()
+    } else if args.list_versions {
+        list_versions_tasks = crate_names
+            .map(|(crate_name, _)| {
+                let name = crate_name.name.clone();
+                let task = AutoAbortJoinHandle::spawn(ops::resolve::list_versions(
+                    binstall_opts.clone(),
+                    crate_name,
+                ));
+
+                (name, task)
+            })
+            .collect();
+
+        Vec::new()
+    } else if args.report_unsupported {
+        report_tasks = crate_names
+            .map(|(crate_name, _)| {
+                let name = crate_name.name.clone();
+                let task = AutoAbortJoinHandle::spawn(ops::resolve::report_unsupported(
+                    binstall_opts.clone(),
+                    crate_name,
+                ));
+
+                (name, task)
+            })
+            .collect();
+
+        Vec::new()
+    } else {
+        crate_names
+            .map(|(crate_name, current_version)| {
+                let name = crate_name.name.clone();
+                let resolve =
+                    ops::resolve::resolve_timed(binstall_opts.clone(), crate_name, current_version);
+                let semaphore = semaphore.clone();
+
+                let task = AutoAbortJoinHandle::spawn(async move {
+                    let _permit = semaphore.acquire_owned().await;
+                    resolve.await
+                });
+
+                (name, task)
+            })
+            .collect()
+    };
 
     Ok(Some(async move {
+        for (name, task) in list_targets_tasks {
+            print_target_availabilities(&name, task.await??, json_output);
+        }
+
+        for (name, task) in list_versions_tasks {
+            print_version_availabilities(&name, task.await??, json_output);
+        }
+
+        for (name, task) in report_tasks {
+            print_findings(&name, task.await??, json_output);
+        }
+
         // Collect results
         let mut resolution_fetchs = Vec::new();
         let mut resolution_sources = Vec::new();
+        let mut report = InstallReport::new(dry_run);
 
-        for task in tasks {
-            match task.await?? {
+        for (name, task) in tasks {
+            let (resolution, phase_times) = task.await??;
+            report.add_resolution(name, &resolution, phase_times);
+
+            match resolution {
                 Resolution::AlreadyUpToDate => {}
                 Resolution::Fetch(fetch) => {
                     fetch.print(&binstall_opts);
@@ -236,9 +562,30 @@ pub fn install_crates(
             }
         }
 
+        if verify_only {
+            report.set_dry_run();
+            return print_verifications(report, json_output);
+        }
+
         if resolution_fetchs.is_empty() && resolution_sources.is_empty() {
             debug!("Nothing to do");
-            return Ok(());
+            if time_report {
+                print_time_report(&report, json_output);
+            }
+            return Ok(report);
+        }
+
+        if let Some(kind) = emit_script {
+            let script = install_script::generate(kind, &resolution_fetchs, &resolution_sources)?;
+            print!("{script}");
+            report.set_dry_run();
+            return Ok(report);
+        }
+
+        if !binstall_opts.force {
+            if let Some(mut manifests) = open_manifests(manifests_root.as_deref())? {
+                check_untracked_files(&mut manifests, &resolution_fetchs, refuse_untracked)?;
+            }
         }
 
         // Confirm
@@ -246,44 +593,451 @@ pub fn install_crates(
             confirm().await?;
         }
 
-        do_install_fetches(
+        let has_fetches = !resolution_fetchs.is_empty();
+
+        let install_times = do_install_fetches(
             resolution_fetchs,
-            manifests,
+            manifests_root.as_deref(),
             &binstall_opts,
             dry_run,
             temp_dir,
             no_cleanup,
         )?;
+        for (name, install_time) in install_times {
+            report.set_installed(&name, install_time);
+        }
 
-        let tasks: Vec<_> = resolution_sources
-            .into_iter()
-            .map(|source| AutoAbortJoinHandle::spawn(source.install(binstall_opts.clone())))
+        // Build the crates level by level, so that each one is built after
+        // its prerequisites given by `--after`.
+        let source_names: Vec<_> = resolution_sources
+            .iter()
+            .map(|source| source.name.clone())
             .collect();
+        let levels = install_levels(&source_names, &install_after)
+            .map_err(BinstallError::InstallAfterCycle)?;
+
+        let mut sources: Vec<_> = levels.into_iter().zip(resolution_sources).collect();
+        sources.sort_by_key(|(level, _)| *level);
+
+        while let Some(&(level, _)) = sources.first() {
+            let count = sources.iter().take_while(|(l, _)| *l == level).count();
 
-        for task in tasks {
-            task.await??;
+            let tasks: Vec<_> = sources
+                .drain(..count)
+                .map(|(_, source)| {
+                    let name = source.name.clone();
+                    let install = source.install(binstall_opts.clone());
+                    let semaphore = semaphore.clone();
+
+                    let task = AutoAbortJoinHandle::spawn(async move {
+                        let _permit = semaphore.acquire_owned().await;
+                        let start = Instant::now();
+                        install.await.map(|()| start.elapsed())
+                    });
+
+                    (name, task)
+                })
+                .collect();
+
+            for (name, task) in tasks {
+                let install_time = task.await??;
+                report.set_installed(&name, install_time);
+            }
         }
 
-        Ok(())
+        if let Some(destdir) = &destdir {
+            print_relocations(destdir, &report, json_output);
+        }
+
+        // Only tell interactive users, the binaries staged in a destdir
+        // are not run from there anyway.
+        if has_fetches
+            && !dry_run
+            && !no_confirm
+            && !json_output
+            && !binstall_opts.quiet
+            && destdir.is_none()
+        {
+            warn_if_not_in_path(&binstall_opts.install_path);
+        }
+
+        if time_report {
+            print_time_report(&report, json_output);
+        }
+
+        Ok(report)
     }))
 }
 
-fn do_read_root_cert(path: &Path) -> Result<Option<Certificate>, BinstallError> {
+/// Tell the user how to add `install_path` to `PATH` if it is not in it,
+/// otherwise the binaries installed cannot be run by their name.
+fn warn_if_not_in_path(install_path: &Path) {
+    if install_path::is_in_path(install_path) {
+        return;
+    }
+
+    let hint = if cfg!(windows) {
+        "run this in PowerShell"
+    } else {
+        "add this line to your shell profile (e.g. ~/.bashrc or ~/.zshrc)"
+    };
+
+    warn!(
+        "{} is not in your PATH, so the binaries installed cannot be run by their name.\n\
+         To fix it, {hint}:\n    {}",
+        install_path.display(),
+        install_path::path_profile_line(install_path),
+    );
+}
+
+/// Targets checked by `--list-targets` if `--targets` is not specified.
+const COMMON_TARGETS: &[&str] = &[
+    "x86_64-unknown-linux-gnu",
+    "x86_64-unknown-linux-musl",
+    "aarch64-unknown-linux-gnu",
+    "aarch64-unknown-linux-musl",
+    "armv7-unknown-linux-gnueabihf",
+    "armv7-unknown-linux-musleabihf",
+    "x86_64-apple-darwin",
+    "aarch64-apple-darwin",
+    "universal-apple-darwin",
+    "x86_64-pc-windows-msvc",
+    "aarch64-pc-windows-msvc",
+    "i686-pc-windows-msvc",
+    "x86_64-unknown-freebsd",
+];
+
+/// Check the binstall metadata of `crate_names` in the manifest at
+/// `manifest_path`, or of the package it defines if none is given.
+fn check_manifest(
+    manifest_path: Option<PathBuf>,
+    crate_names: Vec<CrateName>,
+    json_output: bool,
+) -> Result<()> {
+    let manifest_path = manifest_path.unwrap_or_else(|| PathBuf::from("Cargo.toml"));
+
+    let names: Vec<Option<&str>> = if crate_names.is_empty() {
+        vec![None]
+    } else {
+        crate_names.iter().map(|name| Some(&*name.name)).collect()
+    };
+
+    let mut issues_count = 0;
+
+    for name in names {
+        let issues = ops::check_manifest::check_manifest(&manifest_path, name)?;
+        issues_count += issues.len();
+
+        let name = name
+            .map(Cow::Borrowed)
+            .unwrap_or_else(|| Cow::Owned(manifest_path.display().to_string()));
+
+        if json_output {
+            let json = serde_json::Value::Array(
+                issues
+                    .into_iter()
+                    .map(|issue| {
+                        serde_json::json!({ "field": issue.field, "message": issue.message })
+                    })
+                    .collect(),
+            );
+            println!("{}", serde_json::json!({ "crate": name, "issues": json }));
+        } else if issues.is_empty() {
+            info!("{name}: no issues found in the binstall metadata");
+        } else {
+            for issue in issues {
+                warn!("{name}: {issue}");
+            }
+        }
+    }
+
+    if issues_count != 0 {
+        Err(miette!(
+            "Found {issues_count} issue(s) in the binstall metadata"
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+/// Print the time spent in each phase for each crate in `report`.
+///
+/// The json report is printed to stderr, so that stdout is left to the
+/// other json outputs.
+fn print_time_report(report: &InstallReport, json_output: bool) {
+    if json_output {
+        let json = serde_json::Value::Array(
+            report
+                .crates
+                .iter()
+                .map(|krate| {
+                    let times = &krate.phase_times;
+                    serde_json::json!({
+                        "crate": krate.name.as_str(),
+                        "resolution": times.resolution.as_secs_f64(),
+                        "discovery": times.discovery.as_secs_f64(),
+                        "download": times.download.as_secs_f64(),
+                        "install": times.install.map(|install| install.as_secs_f64()),
+                    })
+                })
+                .collect(),
+        );
+        eprintln!("{json}");
+        return;
+    }
+
+    for krate in &report.crates {
+        let times = &krate.phase_times;
+        let install = match times.install {
+            Some(install) => format!("{install:.2?}"),
+            None => "-".to_owned(),
+        };
+        info!(
+            "{}: resolution {:.2?}, discovery {:.2?}, download {:.2?}, install {install}",
+            krate.name, times.resolution, times.discovery, times.download,
+        );
+    }
+}
+
+/// Print what was verified about the package of each crate in `report`,
+/// failing if nothing could be verified for one of them.
+fn print_verifications(report: InstallReport, json_output: bool) -> Result<InstallReport> {
+    let mut unverified = Vec::new();
+
+    if json_output {
+        let json = serde_json::Value::Array(
+            report
+                .crates
+                .iter()
+                .map(|krate| match &krate.outcome {
+                    CrateOutcome::Fetched { verifications, .. } => serde_json::json!({
+                        "crate": krate.name.as_str(),
+                        "signature": verifications.signature,
+                        "checksum": verifications.checksum.map(|algorithm| algorithm.as_str()),
+                    }),
+                    CrateOutcome::FromSource { .. } => {
+                        serde_json::json!({ "crate": krate.name.as_str(), "from-source": true })
+                    }
+                    _ => serde_json::json!({ "crate": krate.name.as_str(), "up-to-date": true }),
+                })
+                .collect(),
+        );
+        println!("{json}");
+    }
+
+    for krate in &report.crates {
+        let name = &krate.name;
+        match &krate.outcome {
+            CrateOutcome::Fetched { verifications, .. } if !verifications.is_empty() => {
+                if json_output {
+                    continue;
+                }
+                if let Some(signature) = &verifications.signature {
+                    info!("{name}: verified signature ({signature})");
+                }
+                if let Some(algorithm) = verifications.checksum {
+                    info!("{name}: verified {} checksum", algorithm.as_str());
+                }
+            }
+            CrateOutcome::Fetched { .. } => {
+                if !json_output {
+                    warn!("{name}: the package provides nothing to verify");
+                }
+                unverified.push(name.as_str());
+            }
+            CrateOutcome::FromSource { .. } => {
+                if !json_output {
+                    warn!("{name}: would be built from source, nothing to verify");
+                }
+                unverified.push(name.as_str());
+            }
+            _ => {}
+        }
+    }
+
+    if unverified.is_empty() {
+        Ok(report)
+    } else {
+        Err(miette!(
+            "Nothing could be verified for {}",
+            unverified.join(", ")
+        ))
+    }
+}
+
+fn print_target_availabilities(
+    name: &str,
+    availabilities: Vec<TargetAvailability>,
+    json_output: bool,
+) {
+    if json_output {
+        let json = serde_json::Value::Array(
+            availabilities
+                .into_iter()
+                .map(|availability| {
+                    let sources: Vec<_> = availability.sources.iter().map(|s| s.as_str()).collect();
+                    serde_json::json!({
+                        "target": availability.target,
+                        "sources": sources,
+                    })
+                })
+                .collect(),
+        );
+        println!("{}", serde_json::json!({ "crate": name, "targets": json }));
+    } else {
+        println!("{name}:");
+        for TargetAvailability { target, sources } in availabilities {
+            if sources.is_empty() {
+                println!("  {target}: no pre-built binaries");
+            } else {
+                println!("  {target}: {}", sources.join(", "));
+            }
+        }
+    }
+}
+
+fn print_version_availabilities(
+    name: &str,
+    availabilities: Vec<VersionAvailability>,
+    json_output: bool,
+) {
+    if json_output {
+        let json = serde_json::Value::Array(
+            availabilities
+                .into_iter()
+                .map(|availability| {
+                    serde_json::json!({
+                        "version": availability.version.to_string(),
+                        "yanked": availability.yanked,
+                        "prebuilt": availability.has_prebuilt,
+                        "error": availability.error.map(|err| err.to_string()),
+                    })
+                })
+                .collect(),
+        );
+        println!("{}", serde_json::json!({ "crate": name, "versions": json }));
+    } else {
+        println!("{name}:");
+        for VersionAvailability {
+            version,
+            yanked,
+            has_prebuilt,
+            error,
+        } in availabilities
+        {
+            match (yanked, has_prebuilt, error) {
+                (true, _, _) => println!("  {version} (yanked)"),
+                (false, _, Some(err)) => println!("  {version} (check failed: {err})"),
+                (false, Some(true), None) => println!("  {version} (pre-built)"),
+                (false, Some(false), None) => println!("  {version}"),
+                (false, None, None) => println!("  {version} (not checked)"),
+            }
+        }
+    }
+}
+
+fn print_findings(name: &str, findings: Vec<Finding>, json_output: bool) {
+    if json_output {
+        let json = serde_json::Value::Array(
+            findings
+                .into_iter()
+                .map(|finding| match finding {
+                    Finding::NoBinstallMetadata => serde_json::json!({ "kind": "no-metadata" }),
+                    Finding::NoRepository => serde_json::json!({ "kind": "no-repository" }),
+                    Finding::Supported { target, source } => serde_json::json!({
+                        "kind": "supported",
+                        "target": target,
+                        "source": source.as_str(),
+                    }),
+                    Finding::Unusable {
+                        target,
+                        source,
+                        reason,
+                    } => serde_json::json!({
+                        "kind": "unusable",
+                        "target": target,
+                        "source": source.as_str(),
+                        "reason": reason,
+                    }),
+                    Finding::NoPackage { target } => serde_json::json!({
+                        "kind": "no-package",
+                        "target": target,
+                    }),
+                    Finding::Failed { reason } => serde_json::json!({
+                        "kind": "failed",
+                        "reason": reason,
+                    }),
+                    _ => serde_json::json!({ "kind": "unknown" }),
+                })
+                .collect(),
+        );
+        println!("{}", serde_json::json!({ "crate": name, "findings": json }));
+    } else {
+        println!("{name}:");
+        for finding in findings {
+            match finding {
+                Finding::NoBinstallMetadata => println!(
+                    "  [ ] no `[package.metadata.binstall]`, only the default templates are tried"
+                ),
+                Finding::NoRepository => {
+                    println!("  [ ] neither `package.repository` nor `pkg-url` is set")
+                }
+                Finding::Supported { target, source } => println!("  [x] {target}: {source}"),
+                Finding::Unusable {
+                    target,
+                    source,
+                    reason,
+                } => println!("  [ ] {target}: package from {source} is unusable: {reason}"),
+                Finding::NoPackage { target } => {
+                    println!("  [ ] {target}: no release asset matches the templates")
+                }
+                Finding::Failed { reason } => println!("  [ ] failed to check the crate: {reason}"),
+                finding => println!("  [ ] {finding:?}"),
+            }
+        }
+    }
+}
+
+/// Load `$NETRC`, or `.netrc` (`_netrc` on Windows) in the home directory.
+fn load_netrc() -> Option<Netrc> {
+    let path = env::var_os("NETRC").map(PathBuf::from).or_else(|| {
+        let home = home_dir()?;
+        let path = home.join(".netrc");
+        if cfg!(windows) && !path.exists() {
+            Some(home.join("_netrc"))
+        } else {
+            Some(path)
+        }
+    })?;
+
+    match Netrc::load_from_path(&path) {
+        Ok(netrc) => netrc,
+        Err(err) => {
+            warn!("Failed to read {}: {err}", path.display());
+            None
+        }
+    }
+}
+
+fn do_read_root_cert(path: &Path) -> Result<Vec<Certificate>, BinstallError> {
     use std::io::{Read, Seek};
 
     let mut file = fs::File::open(path)?;
     let file_format = FileFormat::from_reader(&mut file)?;
 
-    let open_cert = match file_format {
-        FileFormat::PemCertificate => Certificate::from_pem,
-        FileFormat::DerCertificate => Certificate::from_der,
+    let open_certs = match file_format {
+        // A PEM file can hold a bundle of certificates, e.g. `SSL_CERT_FILE`.
+        FileFormat::PemCertificate => Certificate::from_pem_bundle,
+        FileFormat::DerCertificate => {
+            |der: &[u8]| Certificate::from_der(der).map(|cert| vec![cert])
+        }
         _ => {
             warn!(
                 "Unable to load {}: Expected pem or der ceritificate but found {file_format}",
                 path.display()
             );
 
-            return Ok(None);
+            return Ok(Vec::new());
         }
     };
 
@@ -293,36 +1047,44 @@ fn do_read_root_cert(path: &Path) -> Result<Option<Certificate>, BinstallError>
     let mut buffer = Vec::with_capacity(200);
     file.read_to_end(&mut buffer)?;
 
-    open_cert(&buffer).map_err(From::from).map(Some)
+    open_certs(&buffer).map_err(From::from)
 }
 
 fn read_root_certs(
     root_certificate_paths: Vec<PathBuf>,
+    ssl_cert_file: Option<PathBuf>,
     config_cainfo: Option<PathBuf>,
 ) -> impl Iterator<Item = Certificate> {
     root_certificate_paths
         .into_iter()
+        .chain(ssl_cert_file)
         .chain(config_cainfo)
-        .filter_map(|path| match do_read_root_cert(&path) {
-            Ok(optional_cert) => optional_cert,
+        .flat_map(|path| match do_read_root_cert(&path) {
+            Ok(certs) => certs,
             Err(err) => {
                 warn!(
                     "Failed to load root certificate at {}: {err}",
                     path.display()
                 );
-                None
+                Vec::new()
             }
         })
 }
 
-/// Return (install_path, manifests, temp_dir)
-fn compute_paths_and_load_manifests(
+/// Return (install_path, cargo_roots, track_installs, temp_dir), with
+/// install_path and cargo_roots staged under `destdir` if any.
+///
+/// The crates installed are only tracked in the manifests of cargo_roots
+/// if track_installs is `true`.
+fn compute_paths(
     roots: Option<PathBuf>,
     install_path: Option<PathBuf>,
+    temp_dir: Option<PathBuf>,
     no_track: bool,
+    destdir: Option<&Path>,
     cargo_home: PathBuf,
     config: &mut Config,
-) -> Result<(PathBuf, Option<Manifests>, tempfile::TempDir)> {
+) -> Result<(PathBuf, PathBuf, bool, tempfile::TempDir)> {
     // Compute cargo_roots
     let cargo_roots =
         install_path::get_cargo_roots_path(roots, cargo_home, config).ok_or_else(|| {
@@ -337,43 +1099,119 @@ fn compute_paths_and_load_manifests(
         error!("No viable install path found of specified, try `--install-path`");
         miette!("No install path found or specified")
     })?;
+
+    let (cargo_roots, install_path) = match destdir {
+        Some(destdir) => (
+            stage_path(destdir, &cargo_roots)?,
+            stage_path(destdir, &install_path)?,
+        ),
+        None => (cargo_roots, install_path),
+    };
+
     fs::create_dir_all(&install_path).map_err(BinstallError::Io)?;
     debug!("Using install path: {}", install_path.display());
 
-    let no_manifests = no_track || custom_install_path;
-
-    // Load manifests
-    let manifests = if !no_manifests {
-        Some(Manifests::open_exclusive(&cargo_roots)?)
-    } else {
-        None
-    };
+    let track_installs = !no_track && !custom_install_path;
 
     // Create a temporary directory for downloads etc.
     //
     // Put all binaries to a temporary directory under `dst` first, catching
     // some failure modes (e.g., out of space) before touching the existing
     // binaries. This directory will get cleaned up via RAII.
+    let temp_dir_parent = if let Some(temp_dir) = &temp_dir {
+        fs::create_dir_all(temp_dir).map_err(BinstallError::Io)?;
+        temp_dir
+    } else {
+        &install_path
+    };
     let temp_dir = tempfile::Builder::new()
         .prefix("cargo-binstall")
-        .tempdir_in(&install_path)
+        .tempdir_in(temp_dir_parent)
         .map_err(BinstallError::from)
-        .wrap_err("Creating a temporary directory failed.")?;
+        .wrap_err_with(|| {
+            format!(
+                "Creating a temporary directory in {} failed, try `--temp-dir`.",
+                temp_dir_parent.display()
+            )
+        })?;
+
+    Ok((install_path, cargo_roots, track_installs, temp_dir))
+}
 
-    Ok((install_path, manifests, temp_dir))
+/// Open the manifests tracking the crates installed in `cargo_roots`, if
+/// any.
+///
+/// They are locked until the returned value is dropped, serialising the
+/// binstall processes installing to the same cargo root, so they should
+/// only be kept open for as long as needed.
+fn open_manifests(cargo_roots: Option<&Path>) -> Result<Option<Manifests>> {
+    let Some(cargo_roots) = cargo_roots else {
+        return Ok(None);
+    };
+
+    Ok(Some(block_in_place(|| {
+        Manifests::open_exclusive(cargo_roots)
+    })?))
+}
+
+/// Return `path` staged under `destdir`, e.g. `$DESTDIR/usr/local/bin` for
+/// `/usr/local/bin`.
+fn stage_path(destdir: &Path, path: &Path) -> Result<PathBuf> {
+    let path = if path.is_absolute() {
+        Cow::Borrowed(path)
+    } else {
+        Cow::Owned(env::current_dir().map_err(BinstallError::Io)?.join(path))
+    };
+
+    Ok(destdir.join(
+        path.components()
+            .filter(|component| !matches!(component, Component::Prefix(_) | Component::RootDir))
+            .collect::<PathBuf>(),
+    ))
+}
+
+/// Print the final paths of the pre-built binaries and symlinks staged
+/// under `destdir`.
+fn print_relocations(destdir: &Path, report: &InstallReport, json_output: bool) {
+    let relocations = report
+        .crates
+        .iter()
+        .filter_map(|crate_report| match &crate_report.outcome {
+            CrateOutcome::Fetched { bins, links, .. } => Some(bins.iter().chain(links)),
+            _ => None,
+        })
+        .flatten()
+        .filter_map(|staged| {
+            let path = Path::new("/").join(staged.strip_prefix(destdir).ok()?);
+            Some((staged, path))
+        });
+
+    if json_output {
+        let json = serde_json::Value::Array(
+            relocations
+                .map(|(staged, path)| {
+                    serde_json::json!({
+                        "path": path.display().to_string(),
+                        "staged": staged.display().to_string(),
+                    })
+                })
+                .collect(),
+        );
+        println!("{json}");
+    } else {
+        for (_, path) in relocations {
+            println!("{}", path.display());
+        }
+    }
 }
 
 /// Return vec of (crate_name, current_version)
 fn filter_out_installed_crates(
     crate_names: Vec<CrateName>,
     force: bool,
-    manifests: Option<&mut Manifests>,
-) -> Result<impl Iterator<Item = (CrateName, Option<semver::Version>)> + '_> {
-    let mut installed_crates = manifests
-        .map(Manifests::load_installed_crates)
-        .transpose()?;
-
-    Ok(CrateName::dedup(crate_names)
+    mut installed_crates: Option<BTreeMap<CompactString, semver::Version>>,
+) -> impl Iterator<Item = (CrateName, Option<semver::Version>)> {
+    CrateName::dedup(crate_names)
     .filter_map(move |crate_name| {
         let name = &crate_name.name;
 
@@ -405,36 +1243,55 @@ fn filter_out_installed_crates(
 
             _ => Some((crate_name, None)),
         }
-    }))
+    })
 }
 
 #[allow(clippy::vec_box)]
 fn do_install_fetches(
     resolution_fetchs: Vec<Box<ResolutionFetch>>,
-    // Take manifests by value to drop the `FileLock`.
-    manifests: Option<Manifests>,
+    manifests_root: Option<&Path>,
     binstall_opts: &Options,
     dry_run: bool,
     temp_dir: tempfile::TempDir,
     no_cleanup: bool,
-) -> Result<()> {
+) -> Result<Vec<(CompactString, Duration)>> {
     if resolution_fetchs.is_empty() {
-        return Ok(());
+        return Ok(Vec::new());
     }
 
     if dry_run {
         info!("Dry-run: Not proceeding to install fetched binaries");
-        return Ok(());
+        return Ok(Vec::new());
     }
 
     block_in_place(|| {
+        // Lock the manifests for the whole install, so that concurrent
+        // binstall processes do not overwrite each other's binaries and
+        // records.
+        let manifests = open_manifests(manifests_root)?;
+
+        let mut install_times = Vec::with_capacity(resolution_fetchs.len());
         let metadata_vec = resolution_fetchs
             .into_iter()
-            .map(|fetch| fetch.install(binstall_opts))
+            .map(|fetch| {
+                let name = fetch.name.clone();
+                let start = Instant::now();
+                let metadata = fetch.install(binstall_opts)?;
+                install_times.push((name, start.elapsed()));
+                Ok(metadata)
+            })
             .collect::<Result<Vec<_>, BinstallError>>()?;
 
         if let Some(manifests) = manifests {
+            warn_on_changed_packages(&manifests, &metadata_vec);
+            let paths = manifests.paths();
             manifests.update(metadata_vec)?;
+
+            if let Some(mtime) = binstall_opts.pinned_mtime {
+                for path in paths {
+                    pin_mtime(&path, mtime).map_err(BinstallError::Io)?;
+                }
+            }
         }
 
         if no_cleanup {
@@ -446,6 +1303,84 @@ fn do_install_fetches(
             });
         }
 
-        Ok(())
+        Ok(install_times)
     })
 }
+
+/// Warn about the binaries which would overwrite a file not tracked as
+/// installed by any crate, such as a binary placed by hand or by the package
+/// manager of the system, and fail if `refuse_untracked` is set.
+fn check_untracked_files(
+    manifests: &mut Manifests,
+    resolution_fetchs: &[Box<ResolutionFetch>],
+    refuse_untracked: bool,
+) -> Result<()> {
+    let tracked = manifests.load_tracked_bins()?;
+    let is_tracked = |path: &Path| {
+        let Some(name) = path.file_name() else {
+            return false;
+        };
+        let name = name.to_string_lossy();
+        tracked.contains(&*name)
+            || name
+                .strip_suffix(".exe")
+                .map_or(false, |name| tracked.contains(name))
+    };
+
+    let untracked: Vec<&Path> = resolution_fetchs
+        .iter()
+        .flat_map(|fetch| &fetch.bin_files)
+        .flat_map(|bin_file| {
+            // The links to versioned binaries are not tracked themselves.
+            let link = bin_file.link.as_deref().filter(|link| !link.is_symlink());
+            iter::once(bin_file.dest.as_path()).chain(link)
+        })
+        .filter(|path| path.symlink_metadata().is_ok() && !is_tracked(path))
+        .collect();
+
+    for path in &untracked {
+        warn!(
+            "{} already exists but was not installed by cargo-binstall or cargo-install, \
+             it will be overwritten",
+            path.display()
+        );
+    }
+
+    if refuse_untracked && !untracked.is_empty() {
+        Err(miette!(
+            "Refusing to overwrite {} file(s) not installed by cargo-binstall or cargo-install, \
+             use --force to overwrite them",
+            untracked.len()
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+/// Warn if the package of a crate differs from the one recorded when the
+/// same version was installed for the same target.
+///
+/// Only the packages rebuilt by third parties have their digest recorded,
+/// a change could mean the artifact was tampered with.
+fn warn_on_changed_packages(manifests: &Manifests, metadata_vec: &[CrateInfo]) {
+    for metadata in metadata_vec {
+        let Some(previous) = manifests.get_recorded(&metadata.name) else {
+            continue;
+        };
+
+        if let (Some(previous_sha256), Some(sha256)) =
+            (&previous.package_sha256, &metadata.package_sha256)
+        {
+            if previous.current_version == metadata.current_version
+                && previous.target == metadata.target
+                && previous_sha256 != sha256
+            {
+                warn!(
+                    "The package of {} v{} for {} changed since it was last installed: \
+                     its sha256 was {previous_sha256}, it is now {sha256}",
+                    metadata.name, metadata.current_version, metadata.target,
+                );
+            }
+        }
+    }
+}
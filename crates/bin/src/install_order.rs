@@ -0,0 +1,151 @@
+//! Ordering of crates which need binaries of other crates to be installed.
+
+use std::{collections::BTreeMap, str::FromStr};
+
+use compact_str::CompactString;
+
+/// `--after <CRATE>:<PREREQUISITE>`: `name` must be installed after
+/// `prerequisite`.
+#[derive(Clone, Debug)]
+pub(crate) struct InstallAfter {
+    pub(crate) name: CompactString,
+    pub(crate) prerequisite: CompactString,
+}
+
+impl FromStr for InstallAfter {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            Some((name, prerequisite)) if !name.is_empty() && !prerequisite.is_empty() => {
+                Ok(Self {
+                    name: name.into(),
+                    prerequisite: prerequisite.into(),
+                })
+            }
+            _ => Err("expected `crate:prerequisite`"),
+        }
+    }
+}
+
+impl InstallAfter {
+    /// Replace the names which are keys of `aliases` by the crates they
+    /// map to, like for the crates to install.
+    pub(crate) fn resolve_aliases(&mut self, aliases: &BTreeMap<CompactString, CompactString>) {
+        for name in [&mut self.name, &mut self.prerequisite] {
+            if let Some(crate_name) = aliases.get(name) {
+                *name = crate_name.clone();
+            }
+        }
+    }
+}
+
+/// Assign a level to each crate of `names`, such that every crate has a
+/// higher level than its prerequisites among `names`.
+///
+/// Crates of the same level can be installed concurrently, once all crates
+/// of the lower levels are installed.
+///
+/// Return the name of a crate in a cycle if the constraints cannot be
+/// satisfied.
+pub(crate) fn install_levels(
+    names: &[CompactString],
+    constraints: &[InstallAfter],
+) -> Result<Vec<usize>, CompactString> {
+    let index_of = |name: &str| names.iter().position(|n| *n == name);
+
+    let edges: Vec<(usize, usize)> = constraints
+        .iter()
+        .filter_map(|constraint| {
+            Some((
+                index_of(&constraint.prerequisite)?,
+                index_of(&constraint.name)?,
+            ))
+        })
+        .collect();
+
+    let mut levels = vec![0; names.len()];
+
+    // Longest path from a crate without prerequisites, which has at most
+    // `names.len() - 1` edges unless there is a cycle.
+    for _ in 0..names.len() {
+        let mut changed = false;
+        for &(prerequisite, name) in &edges {
+            if levels[name] <= levels[prerequisite] {
+                levels[name] = levels[prerequisite] + 1;
+                changed = true;
+            }
+        }
+
+        if !changed {
+            return Ok(levels);
+        }
+    }
+
+    match edges
+        .iter()
+        .find(|(prerequisite, name)| levels[*name] <= levels[*prerequisite])
+    {
+        Some((_, name)) => Err(names[*name].clone()),
+        None => Ok(levels),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn levels(names: &[&str], constraints: &[&str]) -> Result<Vec<usize>, CompactString> {
+        let names: Vec<CompactString> = names.iter().copied().map(CompactString::from).collect();
+        let constraints: Vec<InstallAfter> = constraints
+            .iter()
+            .map(|constraint| constraint.parse().unwrap())
+            .collect();
+
+        install_levels(&names, &constraints)
+    }
+
+    #[test]
+    fn test_resolve_aliases() {
+        let aliases = BTreeMap::from([("rg".into(), "ripgrep".into())]);
+
+        let mut constraint: InstallAfter = "rg:cargo-make".parse().unwrap();
+        constraint.resolve_aliases(&aliases);
+        assert_eq!(constraint.name, "ripgrep");
+        assert_eq!(constraint.prerequisite, "cargo-make");
+
+        let mut constraint: InstallAfter = "cargo-make:rg".parse().unwrap();
+        constraint.resolve_aliases(&aliases);
+        assert_eq!(constraint.name, "cargo-make");
+        assert_eq!(constraint.prerequisite, "ripgrep");
+
+        // Once resolved, the constraint applies to the crate named by the alias.
+        let names = ["cargo-make".into(), "ripgrep".into()];
+        assert_eq!(install_levels(&names, &[constraint]).unwrap(), [1, 0]);
+    }
+
+    #[test]
+    fn test_install_levels() {
+        assert_eq!(levels(&["a", "b", "c"], &[]).unwrap(), [0, 0, 0]);
+        assert_eq!(
+            levels(&["a", "b", "c"], &["a:b", "b:c"]).unwrap(),
+            [2, 1, 0]
+        );
+        assert_eq!(
+            levels(&["a", "b", "c"], &["c:a", "c:b"]).unwrap(),
+            [0, 0, 1]
+        );
+
+        // Constraints on crates not installed are ignored
+        assert_eq!(levels(&["a", "b"], &["a:d", "d:b"]).unwrap(), [0, 0]);
+
+        assert!(levels(&["a", "b"], &["a:b", "b:a"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_install_after() {
+        assert!("a:b".parse::<InstallAfter>().is_ok());
+        assert!("a".parse::<InstallAfter>().is_err());
+        assert!(":b".parse::<InstallAfter>().is_err());
+    }
+}
@@ -1,5 +1,5 @@
 use std::{
-    env::var_os,
+    env::{self, var_os},
     path::{Path, PathBuf},
 };
 
@@ -54,3 +54,31 @@ pub fn get_install_path(
 
     (dir, true)
 }
+
+/// Return `true` if `install_path` is one of the directories in `PATH`.
+pub fn is_in_path(install_path: &Path) -> bool {
+    let Some(path) = var_os("PATH") else {
+        return false;
+    };
+    let install_path = install_path
+        .canonicalize()
+        .unwrap_or_else(|_| install_path.to_owned());
+
+    env::split_paths(&path).any(|dir| {
+        dir == install_path || dir.canonicalize().map_or(false, |dir| dir == install_path)
+    })
+}
+
+/// Return the line to add to the shell profile to have `install_path`
+/// in `PATH`.
+pub fn path_profile_line(install_path: &Path) -> String {
+    let install_path = install_path.display();
+
+    if cfg!(windows) {
+        format!(
+            r#"[Environment]::SetEnvironmentVariable("Path", "{install_path};" + [Environment]::GetEnvironmentVariable("Path", "User"), "User")"#
+        )
+    } else {
+        format!(r#"export PATH="{install_path}:$PATH""#)
+    }
+}
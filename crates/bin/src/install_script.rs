@@ -0,0 +1,443 @@
+//! Install script emitted by `--emit-script`, to install the resolved
+//! crates on another machine without cargo-binstall.
+
+use std::{
+    fmt::{self, Write},
+    path::Path,
+};
+
+use binstalk::{
+    manifests::cargo_toml_binstall::PkgFmt,
+    ops::resolve::{ResolutionFetch, ResolutionSource},
+};
+use clap::ValueEnum;
+use miette::{miette, Result};
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, ValueEnum)]
+pub(crate) enum ScriptKind {
+    /// POSIX shell script, using curl, tar, unzip and sha256sum or shasum.
+    Sh,
+    /// PowerShell script, using tar for the tar-based formats.
+    Powershell,
+}
+
+/// A package to download, along with its sha256 digest.
+struct ScriptPackage<'a> {
+    name: &'a str,
+    url: &'a str,
+    sha256: &'a str,
+    pkg_fmt: PkgFmt,
+}
+
+/// A binary to install from the package.
+struct ScriptBin<'a> {
+    archive_source_path: &'a Path,
+    dest: &'a Path,
+    link: Option<&'a Path>,
+}
+
+/// Generate a script downloading the packages of `fetches`, verified
+/// against the digests of the packages downloaded here before being
+/// extracted, and installing their binaries, then building `sources` with
+/// `cargo install`.
+///
+/// Must be called once the packages of `fetches` are downloaded.
+pub(crate) fn generate(
+    kind: ScriptKind,
+    fetches: &[Box<ResolutionFetch>],
+    sources: &[ResolutionSource],
+) -> Result<String> {
+    let mut script = String::new();
+
+    match kind {
+        ScriptKind::Sh => script.push_str(SH_PRELUDE),
+        ScriptKind::Powershell => script.push_str(POWERSHELL_PRELUDE),
+    }
+
+    for (index, fetch) in fetches.iter().enumerate() {
+        let url = fetch.fetcher.package_url().ok_or_else(|| {
+            miette!(
+                "The package of {} from {} is not downloaded from a url, it cannot be installed by a script",
+                fetch.name,
+                fetch.fetcher.source_name(),
+            )
+        })?;
+
+        let sha256 = fetch.fetcher.package_sha256().ok_or_else(|| {
+            miette!(
+                "The digest of the package of {} from {} is unknown, it cannot be verified by a script",
+                fetch.name,
+                fetch.fetcher.source_name(),
+            )
+        })?;
+
+        let bins: Vec<_> = fetch
+            .bin_files
+            .iter()
+            .map(|bin_file| ScriptBin {
+                archive_source_path: &bin_file.archive_source_path,
+                dest: &bin_file.dest,
+                link: bin_file.link.as_deref(),
+            })
+            .collect();
+
+        let dir = format!("pkg{index}");
+        let pkg_fmt = fetch.fetcher.pkg_fmt();
+
+        if kind == ScriptKind::Powershell && matches!(pkg_fmt, PkgFmt::Tlz4 | PkgFmt::Lz4) {
+            return Err(miette!(
+                "The package of {} is compressed with lz4, which PowerShell cannot decompress",
+                fetch.name,
+            ));
+        }
+
+        let package = ScriptPackage {
+            name: &fetch.name,
+            url: url.as_str(),
+            sha256: &sha256,
+            pkg_fmt,
+        };
+        let res = match kind {
+            ScriptKind::Sh => write_sh(&mut script, &dir, &package, &bins),
+            ScriptKind::Powershell => write_powershell(&mut script, &dir, &package, &bins),
+        };
+        res.expect("Writing to a String must not fail");
+    }
+
+    for source in sources {
+        let name = &source.name;
+        let version = format!("={}", source.version);
+        let res = match kind {
+            ScriptKind::Sh => writeln!(
+                script,
+                "\n# {name}\ncargo install --version {} {}",
+                sh_quote(&version),
+                sh_quote(name)
+            ),
+            ScriptKind::Powershell => writeln!(
+                script,
+                "\n# {name}\ncargo install --version {} {}\nif ($LASTEXITCODE -ne 0) {{ throw \"cargo install failed\" }}",
+                ps_quote(&version),
+                ps_quote(name)
+            ),
+        };
+        res.expect("Writing to a String must not fail");
+    }
+
+    if kind == ScriptKind::Powershell {
+        script.push_str("\nRemove-Item -Recurse -Force -Path $tmp\n");
+    }
+
+    Ok(script)
+}
+
+/// Quote `s` as a single argument for sh.
+fn sh_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
+
+/// Quote `s` as a single argument for PowerShell.
+fn ps_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "''"))
+}
+
+const SH_PRELUDE: &str = r#"#!/bin/sh
+# Generated by cargo-binstall --emit-script
+set -eu
+
+tmp="$(mktemp -d)"
+trap 'rm -rf "$tmp"' EXIT
+
+verify() {
+    if command -v sha256sum >/dev/null 2>&1; then
+        echo "$1  $2" | sha256sum -c -
+    else
+        echo "$1  $2" | shasum -a 256 -c -
+    fi
+}
+"#;
+
+fn write_sh(
+    script: &mut String,
+    dir: &str,
+    package: &ScriptPackage<'_>,
+    bins: &[ScriptBin<'_>],
+) -> fmt::Result {
+    let dir = format!("\"$tmp\"/{dir}");
+
+    writeln!(script, "\n# {}", package.name)?;
+    writeln!(script, "mkdir {dir}")?;
+    writeln!(
+        script,
+        "curl -fsSL {} -o {dir}/package",
+        sh_quote(package.url)
+    )?;
+    writeln!(script, "verify {} {dir}/package", package.sha256)?;
+
+    match package.pkg_fmt {
+        PkgFmt::Tar => writeln!(script, "tar -xf {dir}/package -C {dir}")?,
+        PkgFmt::Tbz2 => writeln!(script, "tar -xjf {dir}/package -C {dir}")?,
+        PkgFmt::Tgz => writeln!(script, "tar -xzf {dir}/package -C {dir}")?,
+        PkgFmt::Txz => writeln!(script, "tar -xJf {dir}/package -C {dir}")?,
+        PkgFmt::Tzstd => writeln!(script, "tar --zstd -xf {dir}/package -C {dir}")?,
+        PkgFmt::Tlz4 => writeln!(script, "tar -I lz4 -xf {dir}/package -C {dir}")?,
+        PkgFmt::Zip => writeln!(script, "unzip -q {dir}/package -d {dir}")?,
+        PkgFmt::Bin => {
+            // The package is the binary itself
+            writeln!(
+                script,
+                "mv {dir}/package {dir}/{}",
+                sh_quote(&bin_name(bins))
+            )?
+        }
+        PkgFmt::Lz4 => {
+            // The package is the binary itself, compressed
+            writeln!(
+                script,
+                "lz4 -dc {dir}/package > {dir}/{}",
+                sh_quote(&bin_name(bins))
+            )?
+        }
+    }
+
+    write_sh_bins(script, &dir, bins)
+}
+
+/// Name of the binary in the package for the formats where the package is
+/// the binary itself.
+fn bin_name(bins: &[ScriptBin<'_>]) -> String {
+    bins.first()
+        .map_or("bin".into(), |bin| {
+            bin.archive_source_path.to_string_lossy()
+        })
+        .into_owned()
+}
+
+fn write_sh_bins(script: &mut String, dir: &str, bins: &[ScriptBin<'_>]) -> fmt::Result {
+    for bin in bins {
+        let source = format!(
+            "{dir}/{}",
+            sh_quote(&bin.archive_source_path.to_string_lossy())
+        );
+        let dest = sh_quote(&bin.dest.to_string_lossy());
+
+        writeln!(script, "mkdir -p \"$(dirname {dest})\"")?;
+        writeln!(script, "install -m 755 {source} {dest}")?;
+
+        if let Some(link) = bin.link {
+            let target = bin.dest.file_name().unwrap_or_default().to_string_lossy();
+            writeln!(
+                script,
+                "ln -sf {} {}",
+                sh_quote(&target),
+                sh_quote(&link.to_string_lossy())
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+const POWERSHELL_PRELUDE: &str = r#"# Generated by cargo-binstall --emit-script
+$ErrorActionPreference = 'Stop'
+$ProgressPreference = 'SilentlyContinue'
+
+$tmp = Join-Path ([System.IO.Path]::GetTempPath()) ([System.IO.Path]::GetRandomFileName())
+New-Item -ItemType Directory -Path $tmp | Out-Null
+
+function Verify($Hash, $Path) {
+    if ((Get-FileHash -Algorithm SHA256 -Path $Path).Hash -ne $Hash) {
+        throw "Checksum mismatch for $Path"
+    }
+}
+"#;
+
+fn write_powershell(
+    script: &mut String,
+    dir: &str,
+    package: &ScriptPackage<'_>,
+    bins: &[ScriptBin<'_>],
+) -> fmt::Result {
+    let dir = format!("(Join-Path $tmp {dir})");
+
+    writeln!(script, "\n# {}", package.name)?;
+    writeln!(
+        script,
+        "New-Item -ItemType Directory -Path {dir} | Out-Null"
+    )?;
+    writeln!(
+        script,
+        "Invoke-WebRequest -Uri {} -OutFile (Join-Path {dir} 'package')",
+        ps_quote(package.url)
+    )?;
+    writeln!(
+        script,
+        "Verify '{}' (Join-Path {dir} 'package')",
+        package.sha256.to_uppercase()
+    )?;
+
+    match package.pkg_fmt {
+        PkgFmt::Bin => writeln!(
+            script,
+            "Move-Item -Path (Join-Path {dir} 'package') -Destination (Join-Path {dir} {})",
+            ps_quote(&bin_name(bins))
+        )?,
+        PkgFmt::Zip => writeln!(
+            script,
+            "Expand-Archive -Path (Join-Path {dir} 'package') -DestinationPath {dir}"
+        )?,
+        _ => writeln!(
+            script,
+            "tar -xf (Join-Path {dir} 'package') -C {dir}\nif ($LASTEXITCODE -ne 0) {{ throw \"tar failed\" }}"
+        )?,
+    }
+
+    for bin in bins {
+        let source = format!(
+            "(Join-Path {dir} {})",
+            ps_quote(&bin.archive_source_path.to_string_lossy())
+        );
+        let dest = ps_quote(&bin.dest.to_string_lossy());
+
+        writeln!(
+            script,
+            "New-Item -ItemType Directory -Force -Path (Split-Path {dest}) | Out-Null"
+        )?;
+        writeln!(
+            script,
+            "Copy-Item -Force -Path {source} -Destination {dest}"
+        )?;
+
+        if let Some(link) = bin.link {
+            writeln!(
+                script,
+                "New-Item -ItemType SymbolicLink -Force -Path {} -Target {dest} | Out-Null",
+                ps_quote(&link.to_string_lossy())
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SHA256: &str = "9a271f2a916b0b6ee6cecb2426f0b3206ef074578be55d9bc94f6f3fe3ab86aa";
+
+    fn package(pkg_fmt: PkgFmt) -> ScriptPackage<'static> {
+        ScriptPackage {
+            name: "krate",
+            url: "https://example.com/krate's.tgz",
+            sha256: SHA256,
+            pkg_fmt,
+        }
+    }
+
+    fn bins() -> [ScriptBin<'static>; 1] {
+        [ScriptBin {
+            archive_source_path: Path::new("dir/krate"),
+            dest: Path::new("/home/user/.cargo/bin/krate-v1.0.0"),
+            link: Some(Path::new("/home/user/.cargo/bin/krate")),
+        }]
+    }
+
+    fn sh(pkg_fmt: PkgFmt) -> String {
+        let mut script = String::new();
+        write_sh(&mut script, "pkg0", &package(pkg_fmt), &bins()).unwrap();
+        script
+    }
+
+    fn powershell(pkg_fmt: PkgFmt) -> String {
+        let mut script = String::new();
+        write_powershell(&mut script, "pkg0", &package(pkg_fmt), &bins()).unwrap();
+        script
+    }
+
+    /// Return the index of the first line of `script` starting with `prefix`.
+    fn line(script: &str, prefix: &str) -> usize {
+        script
+            .lines()
+            .position(|line| line.starts_with(prefix))
+            .unwrap_or_else(|| panic!("no line starting with {prefix:?} in:\n{script}"))
+    }
+
+    #[test]
+    fn quote() {
+        assert_eq!(sh_quote("a b"), "'a b'");
+        assert_eq!(sh_quote("it's"), r"'it'\''s'");
+        assert_eq!(ps_quote("it's"), "'it''s'");
+    }
+
+    #[test]
+    fn sh_verifies_package_before_extracting() {
+        let script = sh(PkgFmt::Tgz);
+
+        assert!(script
+            .contains("curl -fsSL 'https://example.com/krate'\\''s.tgz' -o \"$tmp\"/pkg0/package"));
+        assert!(line(&script, "curl") < line(&script, "verify"));
+        assert!(line(&script, &format!("verify {SHA256} ")) < line(&script, "tar -xzf"));
+        assert!(line(&script, "tar -xzf") < line(&script, "install -m 755"));
+        assert!(script.contains(
+            "install -m 755 \"$tmp\"/pkg0/'dir/krate' '/home/user/.cargo/bin/krate-v1.0.0'\n"
+        ));
+        assert!(script.contains("ln -sf 'krate-v1.0.0' '/home/user/.cargo/bin/krate'\n"));
+    }
+
+    #[test]
+    fn sh_bare_binaries() {
+        assert!(sh(PkgFmt::Bin).contains("mv \"$tmp\"/pkg0/package \"$tmp\"/pkg0/'dir/krate'\n"));
+        assert!(
+            sh(PkgFmt::Lz4).contains("lz4 -dc \"$tmp\"/pkg0/package > \"$tmp\"/pkg0/'dir/krate'\n")
+        );
+    }
+
+    #[test]
+    fn powershell_verifies_package_before_extracting() {
+        let script = powershell(PkgFmt::Zip);
+
+        let verify = line(&script, &format!("Verify '{}' ", SHA256.to_uppercase()));
+        assert!(line(&script, "Invoke-WebRequest") < verify);
+        assert!(verify < line(&script, "Expand-Archive"));
+        assert!(line(&script, "Expand-Archive") < line(&script, "Copy-Item"));
+        assert!(script.contains(
+            "New-Item -ItemType SymbolicLink -Force -Path '/home/user/.cargo/bin/krate' -Target '/home/user/.cargo/bin/krate-v1.0.0'"
+        ));
+    }
+
+    #[test]
+    fn sources_are_built() {
+        let sources = [ResolutionSource {
+            name: "krate".into(),
+            version: "1.0.0".into(),
+            git_tag: None,
+        }];
+
+        let script = generate(ScriptKind::Sh, &[], &sources).unwrap();
+        assert!(script.starts_with(SH_PRELUDE));
+        assert!(script.ends_with("\n# krate\ncargo install --version '=1.0.0' 'krate'\n"));
+
+        let script = generate(ScriptKind::Powershell, &[], &sources).unwrap();
+        assert!(script.starts_with(POWERSHELL_PRELUDE));
+        assert!(script.contains("cargo install --version '=1.0.0' 'krate'\n"));
+        assert!(script.ends_with("Remove-Item -Recurse -Force -Path $tmp\n"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn sh_syntax() {
+        use std::process::Command;
+
+        let mut script = SH_PRELUDE.to_owned();
+        for pkg_fmt in [PkgFmt::Tgz, PkgFmt::Zip, PkgFmt::Bin, PkgFmt::Lz4] {
+            script.push_str(&sh(pkg_fmt));
+        }
+
+        let status = Command::new("sh")
+            .args(["-n", "-c", &script])
+            .status()
+            .unwrap();
+        assert!(status.success(), "{script}");
+    }
+}
@@ -2,13 +2,17 @@
 
 mod args;
 mod bin_util;
+mod crates_file;
 mod entry;
 mod gh_token;
 mod git_credentials;
+mod install_order;
 mod install_path;
+mod install_script;
 mod logging;
 mod main_impl;
 mod signal;
 mod ui;
+mod version_file;
 
 pub use main_impl::do_main;
@@ -52,13 +52,21 @@ rustc-llvm-version: {rustc_llvm_version}"#
             args.json_output,
         );
 
+        let verbose_errors = args.verbose_errors;
         let start = Instant::now();
 
-        let result = run_tokio_main(|| entry::install_crates(args, jobserver_client));
+        let result = run_tokio_main(|| {
+            entry::install_crates(args, jobserver_client).map(|fut| {
+                fut.map(|fut| async move {
+                    fut.await
+                        .map(|report| debug!(?report, "Installation report"))
+                })
+            })
+        });
 
         let done = start.elapsed();
         debug!("run time: {done:?}");
 
-        MainExit::new(result, done)
+        MainExit::new(result, done, verbose_errors)
     }
 }
@@ -3,7 +3,7 @@ use std::{
     thread,
 };
 
-use binstalk::errors::BinstallError;
+use binstalk::{errors::BinstallError, ops::resolve::Candidate};
 use tokio::sync::oneshot;
 
 fn ask_for_confirm(stdin: &mut StdinLock, input: &mut String) -> io::Result<()> {
@@ -19,6 +19,67 @@ fn ask_for_confirm(stdin: &mut StdinLock, input: &mut String) -> io::Result<()>
     Ok(())
 }
 
+fn ask_for_fetcher(
+    stdin: &mut StdinLock,
+    input: &mut String,
+    name: &str,
+    candidates: &[Candidate],
+) -> io::Result<usize> {
+    {
+        let mut stdout = io::stdout().lock();
+
+        writeln!(&mut stdout, "Pre-built binaries of {name} were found in:")?;
+        for (i, candidate) in candidates.iter().enumerate() {
+            write!(
+                &mut stdout,
+                "  {}) {} ({}, {}",
+                i + 1,
+                candidate.source_name,
+                candidate.fetcher_name,
+                candidate.target
+            )?;
+            if candidate.is_third_party {
+                write!(&mut stdout, ", third-party")?;
+            }
+            write!(&mut stdout, ")")?;
+            if let Some(url) = &candidate.url {
+                write!(&mut stdout, ": {url}")?;
+            }
+            writeln!(&mut stdout)?;
+        }
+        write!(&mut stdout, "Which one do you wish to install? [1]\n? ")?;
+        stdout.flush()?;
+    }
+
+    stdin.read_line(input)
+}
+
+/// Ask which of `candidates` to install `name` from, returning its index.
+///
+/// This is a blocking function.
+pub fn select_fetcher(name: &str, candidates: &[Candidate]) -> Result<usize, BinstallError> {
+    // Hold the lock for the whole prompt, so that the prompts of the crates
+    // resolved concurrently are not mixed up.
+    let mut stdin = io::stdin().lock();
+    let mut input = String::with_capacity(16);
+
+    loop {
+        input.clear();
+        match ask_for_fetcher(&mut stdin, &mut input, name, candidates) {
+            Ok(0) | Err(_) => break Err(BinstallError::UserAbort),
+            Ok(_) => (),
+        }
+
+        match input.trim() {
+            "" => break Ok(0),
+            choice => match choice.parse::<usize>() {
+                Ok(n) if (1..=candidates.len()).contains(&n) => break Ok(n - 1),
+                _ => continue,
+            },
+        }
+    }
+}
+
 pub async fn confirm() -> Result<(), BinstallError> {
     let (tx, rx) = oneshot::channel();
 
@@ -0,0 +1,182 @@
+//! `--version-file`: versions of the crates pinned in an external file.
+//!
+//! Each line of the file pins the version of one crate, as either
+//! `name = "version"` (a flat TOML table), `name=version` or `name version`
+//! (the format of asdf's `.tool-versions`, where only the first version of
+//! a line is used). Blank lines and comments starting with `#` are ignored.
+//!
+//! The version is a requirement as in `crate@version`, so a bare version
+//! only matches itself and `latest` is the newest stable version. A
+//! requirement made of several comparators, such as `">=14, <15"`, has to be
+//! quoted.
+
+use std::{collections::BTreeMap, fs, path::Path};
+
+use binstalk::ops::resolve::CrateName;
+use compact_str::CompactString;
+use miette::{miette, Result, WrapErr};
+use tracing::warn;
+
+/// Parse the versions pinned in `content`, keyed by the name of the crate.
+fn parse(content: &str) -> Result<BTreeMap<CompactString, CrateName>, String> {
+    let mut versions = BTreeMap::new();
+
+    for (i, line) in content.lines().enumerate() {
+        let line = line
+            .split_once('#')
+            .map_or(line, |(line, _comment)| line)
+            .trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let invalid = |reason: &str| format!("line {}: {reason}: `{line}`", i + 1);
+        let expected = || invalid("expected `name = version` or `name version`");
+
+        let name_len = line
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '-' || c == '_'))
+            .unwrap_or(line.len());
+        let (name, rest) = line.split_at(name_len);
+        if name.is_empty() {
+            return Err(expected());
+        }
+
+        // The `=` of `name = version`, not to be confused with the one of
+        // a requirement such as `name >=14`.
+        let rest = rest.trim_start();
+        let rest = rest.strip_prefix('=').unwrap_or(rest).trim_start();
+
+        let version = match rest.chars().next() {
+            Some(quote @ ('"' | '\'')) => rest[1..]
+                .split_once(quote)
+                .map(|(version, _)| version.trim().to_owned())
+                .ok_or_else(|| invalid("unterminated quote"))?,
+            _ => first_version(rest),
+        };
+        if version.is_empty() {
+            return Err(expected());
+        }
+
+        let crate_name = format!("{name}@{version}")
+            .parse()
+            .map_err(|err| invalid(&format!("invalid version: {err}")))?;
+
+        versions.insert(name.into(), crate_name);
+    }
+
+    Ok(versions)
+}
+
+/// Return the first of the whitespace separated versions of `s`, the
+/// fallbacks of asdf's `.tool-versions` being ignored.
+///
+/// An operator separated from its version, as in `>= 14`, is kept with it.
+fn first_version(s: &str) -> String {
+    let (operator, rest) = s.split_at(
+        s.find(|c: char| !matches!(c, '=' | '<' | '>' | '~' | '^'))
+            .unwrap_or(s.len()),
+    );
+    let version = rest.split_whitespace().next().unwrap_or_default();
+
+    format!("{operator}{version}")
+}
+
+/// Apply the versions pinned in the file at `path` to the `crate_names`
+/// which do not specify one.
+///
+/// The crates pinned in the file which are not among `crate_names` are
+/// reported, since they are likely misspelt.
+pub(crate) fn apply(path: &Path, crate_names: &mut [CrateName]) -> Result<()> {
+    let content = fs::read_to_string(path)
+        .map_err(|err| miette!("{err}"))
+        .wrap_err_with(|| format!("Failed to read version file {}", path.display()))?;
+    let mut versions =
+        parse(&content).map_err(|err| miette!("Invalid version file {}: {err}", path.display()))?;
+
+    for crate_name in crate_names {
+        let pinned = versions.remove(&crate_name.name);
+        if crate_name.version_req.is_none() {
+            if let Some(pinned) = pinned {
+                *crate_name = pinned;
+            }
+        }
+    }
+
+    for name in versions.keys() {
+        warn!(
+            "Ignoring the version of {name} in {}, it is not among the crates to install",
+            path.display()
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use semver::VersionReq;
+
+    fn version_req(versions: &BTreeMap<CompactString, CrateName>, name: &str) -> Option<String> {
+        Some(versions.get(name)?.version_req.as_ref()?.to_string())
+    }
+
+    #[test]
+    fn parse_version_file() {
+        let versions = parse(
+            "# pinned tools\n\
+             cargo-nextest = \"0.9.67\"\n\
+             ripgrep=^14\n\
+             \n\
+             just 1.23.0 1.22.0 # asdf keeps fallback versions\n\
+             cargo-deny = latest\n\
+             fd-find >=9\n\
+             bat >= 0.24 0.23\n\
+             hyperfine = \">=1.18, <2\"\n\
+             tokei =12.1.2\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            version_req(&versions, "cargo-nextest").as_deref(),
+            Some("=0.9.67")
+        );
+        assert_eq!(version_req(&versions, "ripgrep").as_deref(), Some("^14"));
+        assert_eq!(version_req(&versions, "just").as_deref(), Some("=1.23.0"));
+        assert_eq!(version_req(&versions, "fd-find").as_deref(), Some(">=9"));
+        assert_eq!(version_req(&versions, "bat").as_deref(), Some(">=0.24"));
+        assert_eq!(
+            version_req(&versions, "hyperfine").as_deref(),
+            Some(">=1.18, <2")
+        );
+        assert_eq!(version_req(&versions, "tokei").as_deref(), Some("=12.1.2"));
+
+        let cargo_deny = &versions["cargo-deny"];
+        assert_eq!(cargo_deny.version_req, Some(VersionReq::STAR));
+        assert!(cargo_deny.stable_only);
+
+        assert_eq!(versions.len(), 8);
+
+        assert!(parse("cargo-nextest").is_err());
+        assert!(parse("cargo-nextest = not-a-version").is_err());
+        assert!(parse("cargo-nextest = \"0.9").is_err());
+        assert!(parse("= 0.9").is_err());
+    }
+
+    #[test]
+    fn apply_version_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(".tool-versions");
+        fs::write(&path, "ripgrep >=14\njust 1.23.0\nnodejs 20.11.0\n").unwrap();
+
+        let mut crate_names: Vec<CrateName> = ["ripgrep", "just@1.22.0", "cargo-deny"]
+            .into_iter()
+            .map(|name| name.parse().unwrap())
+            .collect();
+        apply(&path, &mut crate_names).unwrap();
+
+        let crate_names: Vec<_> = crate_names.iter().map(ToString::to_string).collect();
+        assert_eq!(crate_names, ["ripgrep@>=14", "just@=1.22.0", "cargo-deny"]);
+    }
+}
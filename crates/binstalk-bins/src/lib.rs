@@ -1,11 +1,13 @@
 use std::{
     borrow::Cow,
-    fmt, io,
+    collections::BTreeSet,
+    fmt, fs, io, iter,
     path::{self, Component, Path, PathBuf},
 };
 
 use atomic_file_install::{
-    atomic_install, atomic_install_noclobber, atomic_symlink_file, atomic_symlink_file_noclobber,
+    atomic_copy, atomic_install, atomic_install_noclobber, atomic_symlink_file,
+    atomic_symlink_file_noclobber,
 };
 use binstalk_types::cargo_toml_binstall::{PkgFmt, PkgMeta};
 use compact_str::{format_compact, CompactString};
@@ -28,10 +30,28 @@ pub enum Error {
     #[error("bin-dir configuration provided generates empty source path")]
     EmptySourceFilePath,
 
+    /// Name of the binary generates destination or symlink path outside
+    /// of the install path.
+    #[error(
+        "binary name generates destination path outside of the install path: {}", .0.display()
+    )]
+    InvalidDestFilePath(Box<Path>),
+
     /// Bin file is not found.
     #[error("bin file {} not found", .0.display())]
     BinFileNotFound(Box<Path>),
 
+    /// Several extra files of the package have the same file name, so they
+    /// would be installed to the same path.
+    #[error("several extra files would be installed to {}", .0.display())]
+    ExtraFileCollision(Box<Path>),
+
+    /// Bin file cannot be replaced because it is currently running,
+    /// which is only an issue on Windows.
+    #[error("bin file {} is in use", .0.display())]
+    #[diagnostic(help("Close the running program and try again."))]
+    BinFileInUse(Box<Path>),
+
     #[error(transparent)]
     Io(#[from] io::Error),
 
@@ -52,6 +72,21 @@ fn is_valid_path(path: &Path) -> bool {
     )
 }
 
+/// Return true if `path` is a file directly inside `dir`, once both are
+/// normalized, i.e. it does not escape `dir` through `..` or by being an
+/// absolute path.
+fn is_file_in_dir(path: &Path, dir: &Path) -> bool {
+    let path = path.normalize();
+    path.file_name().is_some() && path.parent() == Some(&*dir.normalize())
+}
+
+/// Return `path` with an `.exe` suffix appended to its file name.
+fn with_exe_ext(path: &Path) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(".exe");
+    path.into()
+}
+
 /// Must be called after the archive is downloaded and extracted.
 /// This function might uses blocking I/O.
 pub fn infer_bin_dir_template(
@@ -115,18 +150,28 @@ impl BinFile {
             ""
         };
 
+        // Name of the binary in the package, which may differ from the
+        // name it is installed under.
+        let source_name = data
+            .meta
+            .bin_source_names
+            .get(base_name)
+            .map(String::as_str)
+            .unwrap_or(base_name);
+
         let ctx = Context {
             name: data.name,
             repo: data.repo,
             target: data.target,
             version: data.version,
-            bin: base_name,
+            bin: source_name,
             binary_ext,
 
             target_related_info: data.target_related_info,
         };
 
-        let (source, archive_source_path) = if data.meta.pkg_fmt == Some(PkgFmt::Bin) {
+        let is_bare_binary = data.meta.pkg_fmt.map_or(false, PkgFmt::is_bare_binary);
+        let (source, archive_source_path) = if is_bare_binary {
             (
                 data.bin_path.to_path_buf(),
                 data.bin_path.file_name().unwrap().into(),
@@ -150,7 +195,7 @@ impl BinFile {
         };
 
         // Destination at install dir + base-name{.extension}
-        let mut dest = data.install_path.join(ctx.bin);
+        let mut dest = data.install_path.join(base_name);
         if !binary_ext.is_empty() {
             let binary_ext = binary_ext.strip_prefix('.').unwrap();
 
@@ -160,16 +205,23 @@ impl BinFile {
             assert!(dest.set_extension(binary_ext));
         }
 
-        let (dest, link) = if no_symlinks {
+        let (dest, link) = if no_symlinks && !data.versioned {
             (dest, None)
         } else {
             // Destination path is the install dir + base-name-version{.extension}
-            let dest_file_path_with_ver = format!("{}-v{}{}", ctx.bin, ctx.version, ctx.binary_ext);
+            let dest_file_path_with_ver =
+                format!("{}-v{}{}", base_name, ctx.version, ctx.binary_ext);
             let dest_with_ver = data.install_path.join(dest_file_path_with_ver);
 
-            (dest_with_ver, Some(dest))
+            (dest_with_ver, (!no_symlinks).then_some(dest))
         };
 
+        for path in iter::once(&dest).chain(&link) {
+            if !is_file_in_dir(path, data.install_path) {
+                return Err(Error::InvalidDestFilePath(path.as_path().into()));
+            }
+        }
+
         Ok(Self {
             base_name: format_compact!("{base_name}{binary_ext}"),
             source,
@@ -179,6 +231,19 @@ impl BinFile {
         })
     }
 
+    /// Return the same binary, installed in `install_path` instead.
+    pub fn with_install_path(&self, install_path: &Path) -> Self {
+        let rebase = |path: &Path| install_path.join(path.file_name().unwrap());
+
+        Self {
+            base_name: self.base_name.clone(),
+            source: self.source.clone(),
+            archive_source_path: self.archive_source_path.clone(),
+            dest: rebase(&self.dest),
+            link: self.link.as_deref().map(rebase),
+        }
+    }
+
     pub fn preview_bin(&self) -> impl fmt::Display + '_ {
         LazyFormat {
             base_name: &self.base_name,
@@ -195,15 +260,43 @@ impl BinFile {
         }))
     }
 
+    /// Return the path of the source in the archive with an `.exe` suffix,
+    /// if the binary is for Windows and the path lacks it, e.g. because the
+    /// `bin-dir` template does not include `{ binary-ext }`.
+    pub fn exe_archive_source_path(&self) -> Option<PathBuf> {
+        let is_windows = self.dest.extension() == Some("exe".as_ref());
+        let has_exe_ext = self
+            .archive_source_path
+            .extension()
+            .map_or(false, |ext| ext.eq_ignore_ascii_case("exe"));
+
+        (is_windows && !has_exe_ext).then(|| with_exe_ext(&self.archive_source_path))
+    }
+
     /// Return `Ok` if the source exists, otherwise `Err`.
+    ///
+    /// For Windows binaries, the source with an `.exe` suffix is used
+    /// instead if only it exists.
     pub fn check_source_exists(
-        &self,
+        &mut self,
         has_file: &mut dyn FnMut(&Path) -> bool,
     ) -> Result<(), Error> {
         if has_file(&self.archive_source_path) {
-            Ok(())
-        } else {
-            Err(Error::BinFileNotFound((&*self.source).into()))
+            return Ok(());
+        }
+
+        match self.exe_archive_source_path() {
+            Some(exe_archive_source_path) if has_file(&exe_archive_source_path) => {
+                debug!(
+                    "Using '{}' as the source of {}",
+                    exe_archive_source_path.display(),
+                    self.base_name
+                );
+                self.source = with_exe_ext(&self.source);
+                self.archive_source_path = exe_archive_source_path;
+                Ok(())
+            }
+            _ => Err(Error::BinFileNotFound((&*self.source).into())),
         }
     }
 
@@ -211,6 +304,7 @@ impl BinFile {
         if !self.source.try_exists()? {
             return Err(Error::BinFileNotFound((&*self.source).into()));
         }
+        copy_symlink_target(&self.source)?;
 
         #[cfg(unix)]
         std::fs::set_permissions(
@@ -230,7 +324,24 @@ impl BinFile {
             self.dest.display()
         );
 
-        atomic_install(&self.source, &self.dest)?;
+        atomic_install(&self.source, &self.dest)
+            .map_err(|err| map_install_error(err, &self.dest))?;
+
+        Ok(())
+    }
+
+    /// Same as [`BinFile::install_bin`], but keeps the source so that it
+    /// can be installed again.
+    pub fn install_bin_copy(&self) -> Result<(), Error> {
+        self.pre_install_bin()?;
+
+        debug!(
+            "Atomically install a copy of file '{}' to '{}'",
+            self.source.display(),
+            self.dest.display()
+        );
+
+        atomic_copy(&self.source, &self.dest).map_err(|err| map_install_error(err, &self.dest))?;
 
         Ok(())
     }
@@ -249,15 +360,34 @@ impl BinFile {
         Ok(())
     }
 
+    /// Check that `link` and the binary it points to are in the same
+    /// directory, once symlinks are resolved.
+    ///
+    /// Must be called after the binary is installed.
+    fn check_link(&self, link: &Path) -> Result<(), Error> {
+        let canonical_dir = |path: &Path| match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir.canonicalize(),
+            _ => Path::new(".").canonicalize(),
+        };
+
+        if canonical_dir(link)? == canonical_dir(&self.dest)? {
+            Ok(())
+        } else {
+            Err(Error::InvalidDestFilePath(link.into()))
+        }
+    }
+
     pub fn install_link(&self) -> Result<(), Error> {
         if let Some(link) = &self.link {
+            self.check_link(link)?;
+
             let dest = self.link_dest();
             debug!(
                 "Create link '{}' pointing to '{}'",
                 link.display(),
                 dest.display()
             );
-            atomic_symlink_file(dest, link)?;
+            atomic_symlink_file(dest, link).map_err(|err| map_install_error(err, link))?;
         }
 
         Ok(())
@@ -265,6 +395,8 @@ impl BinFile {
 
     pub fn install_link_noclobber(&self) -> Result<(), Error> {
         if let Some(link) = &self.link {
+            self.check_link(link)?;
+
             let dest = self.link_dest();
             debug!(
                 "Create link '{}' pointing to '{}' only if dst not exists",
@@ -286,6 +418,142 @@ impl BinFile {
     }
 }
 
+/// A file of the package installed alongside the binaries, such as a
+/// license, which is neither made executable nor symlinked.
+pub struct ExtraFile {
+    pub source: PathBuf,
+    pub archive_source_path: PathBuf,
+    pub dest: PathBuf,
+}
+
+impl ExtraFile {
+    /// * `tt` - template of the path of the file in the package
+    /// * `extra_files_dir` - the file is installed in its subdirectory named
+    ///   after the crate
+    pub fn new(data: &Data<'_>, tt: &Template<'_>, extra_files_dir: &Path) -> Result<Self, Error> {
+        let ctx = Context {
+            name: data.name,
+            repo: data.repo,
+            target: data.target,
+            version: data.version,
+            bin: "",
+            binary_ext: if data.target.contains("windows") {
+                ".exe"
+            } else {
+                ""
+            },
+
+            target_related_info: data.target_related_info,
+        };
+
+        let path = tt.render(&ctx)?;
+        let path_normalized = Path::new(&path).normalize();
+
+        if !is_valid_path(&path_normalized) {
+            return Err(Error::InvalidSourceFilePath(path_normalized.into()));
+        }
+        let Some(file_name) = path_normalized.file_name() else {
+            return Err(Error::EmptySourceFilePath);
+        };
+
+        Ok(Self {
+            source: data.bin_path.join(&path_normalized),
+            dest: extra_files_dir.join(data.name).join(file_name),
+            archive_source_path: path_normalized,
+        })
+    }
+
+    pub fn preview(&self) -> impl fmt::Display + '_ {
+        LazyFormat {
+            base_name: self.dest.file_name().unwrap().to_str().unwrap_or_default(),
+            source: self.archive_source_path.display(),
+            dest: self.dest.display(),
+        }
+    }
+
+    /// Return `Ok` if the source exists, otherwise `Err`.
+    pub fn check_source_exists(
+        &self,
+        has_file: &mut dyn FnMut(&Path) -> bool,
+    ) -> Result<(), Error> {
+        if has_file(&self.archive_source_path) {
+            Ok(())
+        } else {
+            Err(Error::BinFileNotFound((&*self.source).into()))
+        }
+    }
+
+    /// Return `Err` if several of `extra_files` are installed to the same
+    /// path, which would overwrite each other.
+    pub fn check_collisions(extra_files: &[Self]) -> Result<(), Error> {
+        let mut dests = BTreeSet::new();
+        for extra_file in extra_files {
+            if !dests.insert(&*extra_file.dest) {
+                return Err(Error::ExtraFileCollision((&*extra_file.dest).into()));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn install(&self) -> Result<(), Error> {
+        if let Some(dir) = self.dest.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        copy_symlink_target(&self.source)?;
+
+        debug!(
+            "Atomically install file from '{}' to '{}'",
+            self.source.display(),
+            self.dest.display()
+        );
+
+        atomic_install(&self.source, &self.dest).map_err(|err| map_install_error(err, &self.dest))
+    }
+}
+
+/// Replace `source` with a copy of the file it points to if it is a
+/// symlink of the package: its target is usually relative, so it would
+/// dangle once moved to the install path.
+fn copy_symlink_target(source: &Path) -> io::Result<()> {
+    if source.symlink_metadata()?.file_type().is_symlink() {
+        let target = source.canonicalize()?;
+        debug!(
+            "Replace symlink '{}' with a copy of '{}'",
+            source.display(),
+            target.display()
+        );
+        atomic_copy(&target, source)?;
+    }
+
+    Ok(())
+}
+
+/// Windows does not allow replacing an executable while it is running,
+/// which fails with a rather cryptic error.
+///
+/// Detect that by opening `dst` for writing: a running executable is
+/// opened without write sharing, so it fails with a sharing violation.
+#[cfg_attr(not(windows), allow(unused_variables))]
+fn map_install_error(err: io::Error, dst: &Path) -> Error {
+    #[cfg(windows)]
+    {
+        const ERROR_SHARING_VIOLATION: i32 = 32;
+
+        let in_use = std::fs::OpenOptions::new()
+            .write(true)
+            .open(dst)
+            .err()
+            .and_then(|err| err.raw_os_error())
+            == Some(ERROR_SHARING_VIOLATION);
+
+        if in_use {
+            return Error::BinFileInUse(dst.into());
+        }
+    }
+
+    Error::Io(err)
+}
+
 /// Data required to get bin paths
 pub struct Data<'a> {
     pub name: &'a str,
@@ -295,6 +563,8 @@ pub struct Data<'a> {
     pub meta: PkgMeta,
     pub bin_path: &'a Path,
     pub install_path: &'a Path,
+    /// Always install binaries under versioned names, even without symlinks.
+    pub versioned: bool,
     /// More target related info, it's recommend to provide the following keys:
     ///  - target_family,
     ///  - target_arch
@@ -357,3 +627,255 @@ impl fmt::Display for OptionalLazyFormat<'_> {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn bin_file(base_name: &str, install_path: &str, no_symlinks: bool) -> Result<BinFile, Error> {
+        let target_related_info = leon::vals(|_| None);
+        let data = Data {
+            name: "cargo-binstall",
+            target: "x86_64-unknown-linux-gnu",
+            version: "1.0.0",
+            repo: None,
+            meta: PkgMeta {
+                pkg_fmt: Some(PkgFmt::Bin),
+                ..Default::default()
+            },
+            bin_path: Path::new("/tmp/bin-cargo-binstall"),
+            install_path: Path::new(install_path),
+            versioned: false,
+            target_related_info: &target_related_info,
+        };
+
+        BinFile::new(
+            &data,
+            base_name,
+            &Template::parse("{ bin }").unwrap(),
+            no_symlinks,
+        )
+    }
+
+    #[test]
+    fn test_bin_source_names() {
+        let target_related_info = leon::vals(|_| None);
+        let bin_file = |target| {
+            let data = Data {
+                name: "tool",
+                target,
+                version: "1.0.0",
+                repo: None,
+                meta: PkgMeta {
+                    pkg_fmt: Some(PkgFmt::Tgz),
+                    bin_source_names: [("tool-bin".to_string(), "tool".to_string())].into(),
+                    ..Default::default()
+                },
+                bin_path: Path::new("/tmp/bin-tool"),
+                install_path: Path::new("/home/user/.cargo/bin"),
+                versioned: false,
+                target_related_info: &target_related_info,
+            };
+
+            BinFile::new(
+                &data,
+                "tool-bin",
+                &Template::parse("{ bin }{ binary-ext }").unwrap(),
+                true,
+            )
+            .unwrap()
+        };
+
+        // The binary is looked up under its name in the package, and
+        // installed under the name of the product.
+        let mut bin = bin_file("x86_64-unknown-linux-gnu");
+        assert_eq!(bin.archive_source_path, Path::new("tool"));
+        assert_eq!(bin.source, Path::new("/tmp/bin-tool/tool"));
+        assert_eq!(bin.dest, Path::new("/home/user/.cargo/bin/tool-bin"));
+        assert_eq!(bin.base_name, "tool-bin");
+        bin.check_source_exists(&mut |path| path == Path::new("tool"))
+            .unwrap();
+        assert!(bin
+            .check_source_exists(&mut |path| path == Path::new("tool-bin"))
+            .is_err());
+
+        let bin = bin_file("x86_64-pc-windows-msvc");
+        assert_eq!(bin.archive_source_path, Path::new("tool.exe"));
+        assert_eq!(bin.dest, Path::new("/home/user/.cargo/bin/tool-bin.exe"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_install_symlinked_bin() {
+        let dir = tempfile::tempdir().unwrap();
+        let bin_path = dir.path().join("bin-cargo-binstall");
+        let install_path = dir.path().join("install");
+        fs::create_dir_all(bin_path.join("libexec")).unwrap();
+        fs::create_dir_all(&install_path).unwrap();
+
+        fs::write(bin_path.join("libexec/cargo-binstall"), "binary").unwrap();
+        std::os::unix::fs::symlink("libexec/cargo-binstall", bin_path.join("cargo-binstall"))
+            .unwrap();
+
+        let target_related_info = leon::vals(|_| None);
+        let data = Data {
+            name: "cargo-binstall",
+            target: "x86_64-unknown-linux-gnu",
+            version: "1.0.0",
+            repo: None,
+            meta: PkgMeta::default(),
+            bin_path: &bin_path,
+            install_path: &install_path,
+            versioned: false,
+            target_related_info: &target_related_info,
+        };
+        let bin = BinFile::new(
+            &data,
+            "cargo-binstall",
+            &Template::parse("{ bin }").unwrap(),
+            true,
+        )
+        .unwrap();
+
+        bin.install_bin().unwrap();
+
+        let dest = install_path.join("cargo-binstall");
+        assert!(!dest.symlink_metadata().unwrap().file_type().is_symlink());
+        assert_eq!(fs::read_to_string(dest).unwrap(), "binary");
+        // The file the symlink pointed to is left in the package.
+        assert!(bin_path.join("libexec/cargo-binstall").is_file());
+    }
+
+    fn extra_file(path: &str) -> ExtraFile {
+        let target_related_info = leon::vals(|_| None);
+        let data = Data {
+            name: "cargo-binstall",
+            target: "x86_64-unknown-linux-gnu",
+            version: "1.0.0",
+            repo: None,
+            meta: PkgMeta::default(),
+            bin_path: Path::new("/tmp/bin-cargo-binstall"),
+            install_path: Path::new("/home/user/.cargo/bin"),
+            versioned: false,
+            target_related_info: &target_related_info,
+        };
+
+        ExtraFile::new(
+            &data,
+            &Template::parse(path).unwrap(),
+            Path::new("/home/user/.local/share/doc"),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_extra_file_dest() {
+        let extra_file = extra_file("cargo-binstall-{ target }/doc/LICENSE");
+        assert_eq!(
+            extra_file.source,
+            Path::new(
+                "/tmp/bin-cargo-binstall/cargo-binstall-x86_64-unknown-linux-gnu/doc/LICENSE"
+            )
+        );
+        assert_eq!(
+            extra_file.dest,
+            Path::new("/home/user/.local/share/doc/cargo-binstall/LICENSE")
+        );
+    }
+
+    #[test]
+    fn test_extra_file_collisions() {
+        let extra_files = [extra_file("LICENSE-MIT"), extra_file("doc/LICENSE")];
+        ExtraFile::check_collisions(&extra_files).unwrap();
+
+        // Both are installed as `LICENSE`, only keeping their file name.
+        let extra_files = [extra_file("LICENSE"), extra_file("doc/LICENSE")];
+        assert!(matches!(
+            ExtraFile::check_collisions(&extra_files),
+            Err(Error::ExtraFileCollision(path))
+                if *path == *Path::new("/home/user/.local/share/doc/cargo-binstall/LICENSE")
+        ));
+    }
+
+    #[test]
+    fn test_dest_in_install_path() {
+        let bin = bin_file("cargo-binstall", "/home/user/.cargo/bin", false).unwrap();
+        assert_eq!(
+            bin.dest,
+            Path::new("/home/user/.cargo/bin/cargo-binstall-v1.0.0")
+        );
+        assert_eq!(
+            bin.link.as_deref(),
+            Some(Path::new("/home/user/.cargo/bin/cargo-binstall"))
+        );
+
+        // Redundant components are fine as long as the path stays inside
+        assert!(bin_file("cargo-binstall", "/home/user/.cargo/./bin/", true).is_ok());
+    }
+
+    #[test]
+    fn test_dest_outside_install_path() {
+        for base_name in [
+            "../cargo-binstall",
+            "../../../etc/cron.d/evil",
+            "sub/../../evil",
+            "sub/cargo-binstall",
+            "/etc/passwd",
+            "..",
+            ".",
+        ] {
+            for no_symlinks in [false, true] {
+                assert!(
+                    matches!(
+                        bin_file(base_name, "/home/user/.cargo/bin", no_symlinks),
+                        Err(Error::InvalidDestFilePath(_))
+                    ),
+                    "{base_name} must be rejected"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_exe_source_inferred() {
+        let target_related_info = leon::vals(|_| None);
+        let data = Data {
+            name: "cargo-binstall",
+            target: "x86_64-pc-windows-msvc",
+            version: "1.0.0",
+            repo: None,
+            meta: PkgMeta {
+                pkg_fmt: Some(PkgFmt::Zip),
+                ..Default::default()
+            },
+            bin_path: Path::new("/tmp/bin-cargo-binstall"),
+            install_path: Path::new("/home/user/.cargo/bin"),
+            versioned: false,
+            target_related_info: &target_related_info,
+        };
+        let mut bin = BinFile::new(
+            &data,
+            "cargo-binstall",
+            &Template::parse("bin/{ bin }").unwrap(),
+            true,
+        )
+        .unwrap();
+
+        assert_eq!(
+            bin.exe_archive_source_path().as_deref(),
+            Some(Path::new("bin/cargo-binstall.exe"))
+        );
+        bin.check_source_exists(&mut |path| path == Path::new("bin/cargo-binstall.exe"))
+            .unwrap();
+        assert_eq!(bin.archive_source_path, Path::new("bin/cargo-binstall.exe"));
+        assert_eq!(
+            bin.source,
+            Path::new("/tmp/bin-cargo-binstall/bin/cargo-binstall.exe")
+        );
+        assert_eq!(bin.exe_archive_source_path(), None);
+
+        // Not on other targets
+        let bin = bin_file("cargo-binstall", "/home/user/.cargo/bin", true).unwrap();
+        assert_eq!(bin.exe_archive_source_path(), None);
+    }
+}
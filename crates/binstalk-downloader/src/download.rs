@@ -1,14 +1,28 @@
-use std::{fmt, io, marker::PhantomData, path::Path};
+use std::{
+    collections::HashSet,
+    fmt, io,
+    marker::PhantomData,
+    num::NonZeroU64,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
 
 use binstalk_types::cargo_toml_binstall::PkgFmtDecomposed;
-use bytes::Bytes;
-use futures_util::{stream::FusedStream, Stream, StreamExt};
+use bytes::{Bytes, BytesMut};
+use futures_util::{pin_mut, stream::FusedStream, Stream, StreamExt};
+use tempfile::{NamedTempFile, TempPath};
 use thiserror::Error as ThisError;
+use tokio::{
+    io::AsyncWriteExt,
+    sync::mpsc,
+    task::{spawn_blocking, JoinHandle},
+};
+use tokio_util::io::ReaderStream;
 use tracing::{debug, error, instrument};
 
 pub use binstalk_types::cargo_toml_binstall::{PkgFmt, TarBasedFmt};
 
-use crate::remote::{Client, Error as RemoteError, Url};
+use crate::remote::{Client, Error as RemoteError, RequestBuilder, Url};
 
 mod async_extracter;
 use async_extracter::*;
@@ -34,6 +48,34 @@ pub enum DownloadError {
     #[error("Failed to download from remote: {0}")]
     Remote(#[from] RemoteError),
 
+    #[error("Download exceeds the maximum size of {max_size} bytes")]
+    TooLarge { max_size: u64 },
+
+    #[error("Download did not complete within {timeout:?}")]
+    Timeout { timeout: Duration },
+
+    /// The archive ends unexpectedly or fails its integrity check, e.g.
+    /// because the download has been truncated.
+    #[error("The {fmt} archive is truncated or corrupted: {source}")]
+    CorruptArchive {
+        fmt: PkgFmt,
+        #[source]
+        source: io::Error,
+    },
+
+    /// A symlink of the archive is rejected, e.g. because it points outside
+    /// of the archive, which could be used to overwrite any file.
+    #[error(
+        "Symlink {} -> {} of the archive is rejected: {reason}",
+        path.display(),
+        target.display()
+    )]
+    InvalidSymlink {
+        path: PathBuf,
+        target: PathBuf,
+        reason: &'static str,
+    },
+
     /// A generic I/O error.
     ///
     /// - Code: `binstall::io`
@@ -94,6 +136,8 @@ pub struct Download<'a> {
     client: Client,
     url: Url,
     data_verifier: Option<&'a mut dyn DataVerifier>,
+    auth_token: Option<Box<str>>,
+    copy: Option<mpsc::Sender<Bytes>>,
 }
 
 impl fmt::Debug for Download<'_> {
@@ -123,6 +167,8 @@ impl Download<'static> {
             client,
             url,
             data_verifier: None,
+            auth_token: None,
+            copy: None,
         }
     }
 }
@@ -137,6 +183,43 @@ impl<'a> Download<'a> {
             client,
             url,
             data_verifier: Some(data_verifier),
+            auth_token: None,
+            copy: None,
+        }
+    }
+
+    /// Send `token` in the `Authorization` header, as expected by
+    /// registries requiring authentication.
+    pub fn with_auth_token(self, token: &str) -> Self {
+        Self {
+            auth_token: Some(token.into()),
+            ..self
+        }
+    }
+
+    /// Write a copy of the downloaded data to a temporary file next to
+    /// `path` as it is received, e.g. to keep the archive around after
+    /// extracting it.
+    ///
+    /// The copy is only moved to `path` by [`DownloadCopy::persist`].
+    pub fn with_copy_to(self, path: PathBuf) -> (Self, DownloadCopy) {
+        let (tx, rx) = mpsc::channel(16);
+        let writer = tokio::spawn(write_copy(path.clone(), rx));
+
+        (
+            Self {
+                copy: Some(tx),
+                ..self
+            },
+            DownloadCopy { path, writer },
+        )
+    }
+
+    fn request(&self) -> RequestBuilder {
+        let request = self.client.get(self.url.clone());
+        match &self.auth_token {
+            Some(token) => request.header("Authorization", token),
+            None => request,
         }
     }
 
@@ -146,26 +229,182 @@ impl<'a> Download<'a> {
         impl Stream<Item = Result<Bytes, DownloadError>> + FusedStream + Send + Sync + Unpin + 'a,
         DownloadError,
     > {
+        let max_size = self.client.max_download_size().map(NonZeroU64::get);
+        let limit_rate = self.client.limit_rate().map(NonZeroU64::get);
+
+        let response = self.request().send(true).await?;
         let mut data_verifier = self.data_verifier;
-        Ok(self
-            .client
-            .get_stream(self.url)
-            .await?
+        let copy = self.copy;
+        if let (Some(max_size), Some(len)) = (max_size, response.content_length()) {
+            if len > max_size {
+                return Err(DownloadError::TooLarge { max_size });
+            }
+        }
+
+        // The server might not report the length, or lie about it.
+        let mut downloaded = 0_u64;
+
+        let start = Instant::now();
+        let mut received = 0_u64;
+
+        let stream = response
+            .bytes_stream()
             .map(move |res| {
                 let bytes = res?;
 
+                downloaded += bytes.len() as u64;
+                if let Some(max_size) = max_size {
+                    if downloaded > max_size {
+                        return Err(DownloadError::TooLarge { max_size });
+                    }
+                }
+
                 if let Some(data_verifier) = &mut data_verifier {
                     data_verifier.update(&bytes);
                 }
 
                 Ok(bytes)
             })
+            .then(move |res| {
+                // Sleep until receiving this many bytes fits in the rate limit.
+                let delay = limit_rate.zip(res.as_ref().ok()).and_then(|(rate, bytes)| {
+                    received += bytes.len() as u64;
+                    Duration::from_secs_f64(received as f64 / rate as f64)
+                        .checked_sub(start.elapsed())
+                });
+                let copy = copy.clone();
+
+                async move {
+                    if let (Some(copy), Ok(bytes)) = (copy, &res) {
+                        // The error, if any, is reported by the writer.
+                        copy.send(bytes.clone()).await.ok();
+                    }
+                    if let Some(delay) = delay {
+                        tokio::time::sleep(delay).await;
+                    }
+                    res
+                }
+            })
             // Call `fuse` at the end to make sure `data_verifier` is only
             // called when the stream still has elements left.
-            .fuse())
+            .fuse();
+
+        // The throttling future is not `Unpin`.
+        Ok(Box::pin(stream))
     }
 }
 
+/// A copy of a [`Download`], see [`Download::with_copy_to`].
+///
+/// The temporary file is removed if the copy is dropped without being
+/// persisted, e.g. because the data failed verification.
+#[derive(Debug)]
+pub struct DownloadCopy {
+    path: PathBuf,
+    writer: JoinHandle<io::Result<TempPath>>,
+}
+
+impl DownloadCopy {
+    /// Wait for the whole download to be written and move the copy to
+    /// its final path.
+    ///
+    /// Must only be called once the [`Download`] is consumed.
+    pub async fn persist(self) -> Result<(), DownloadError> {
+        let temp_path = self.writer.await.map_err(io::Error::from)??;
+        let path = self.path;
+
+        debug!("Keeping a copy of the download at '{}'", path.display());
+        spawn_blocking(move || temp_path.persist(path))
+            .await
+            .map_err(io::Error::from)?
+            .map_err(|err| err.error)?;
+
+        Ok(())
+    }
+}
+
+async fn write_copy(path: PathBuf, mut rx: mpsc::Receiver<Bytes>) -> io::Result<TempPath> {
+    let dir = path
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or(Path::new("."))
+        .to_owned();
+
+    tokio::fs::create_dir_all(&dir).await?;
+    let (file, temp_path) = spawn_blocking(move || NamedTempFile::new_in(dir))
+        .await??
+        .into_parts();
+
+    let mut file = tokio::fs::File::from_std(file);
+    while let Some(bytes) = rx.recv().await {
+        file.write_all(&bytes).await?;
+    }
+    file.flush().await?;
+
+    Ok(temp_path)
+}
+
+async fn extract_stream<S>(
+    stream: &mut S,
+    fmt: PkgFmt,
+    path: &Path,
+    members: Option<HashSet<PathBuf>>,
+) -> Result<ExtractedFiles, DownloadError>
+where
+    S: Stream<Item = Result<Bytes, DownloadError>> + Send + Sync + Unpin,
+{
+    match fmt.decompose() {
+        PkgFmtDecomposed::Tar(tar_fmt) => extract_tar_based_stream(stream, path, tar_fmt, members)
+            .await
+            .map_err(|err| corrupt_archive_error(fmt, err)),
+        PkgFmtDecomposed::Bin => extract_bin(stream, path).await,
+        PkgFmtDecomposed::Lz4Bin => extract_lz4_bin(stream, path)
+            .await
+            .map_err(|err| corrupt_archive_error(fmt, err)),
+        PkgFmtDecomposed::Zip => extract_zip(stream, path, members.as_ref()).await,
+    }
+}
+
+/// Report the I/O errors of decoding a package in `fmt` as a corrupt archive.
+fn corrupt_archive_error(fmt: PkgFmt, err: DownloadError) -> DownloadError {
+    match err {
+        // Decoders report truncated streams as unexpected EOF, and
+        // checksum or header mismatches as invalid data.
+        DownloadError::Io(source)
+            if matches!(
+                source.kind(),
+                io::ErrorKind::UnexpectedEof | io::ErrorKind::InvalidData
+            ) =>
+        {
+            DownloadError::CorruptArchive { fmt, source }
+        }
+        err => err,
+    }
+}
+
+/// Extract the package at `src` on the local filesystem to `path`, like
+/// [`Download::and_extract_members`] does for a remote package.
+#[instrument(skip(members))]
+pub async fn extract_local_members(
+    src: &Path,
+    fmt: PkgFmt,
+    path: &Path,
+    members: Option<&[PathBuf]>,
+) -> Result<ExtractedFiles, DownloadError> {
+    let file = tokio::fs::File::open(src).await?;
+    let mut stream = ReaderStream::new(file).map(|res| res.map_err(DownloadError::from));
+
+    debug!("Extracting '{}' to: '{}'", src.display(), path.display());
+
+    extract_stream(
+        &mut stream,
+        fmt,
+        path,
+        members.map(|members| members.iter().cloned().collect()),
+    )
+    .await
+}
+
 /// Make sure `stream` is an alias instead of taking the value to avoid
 /// exploding size of the future generated.
 ///
@@ -223,26 +462,41 @@ impl Download<'_> {
         self,
         fmt: PkgFmt,
         path: impl AsRef<Path>,
+    ) -> Result<ExtractedFiles, DownloadError> {
+        self.and_extract_members(fmt, path, None).await
+    }
+
+    /// Download a file from the provided URL and extract it to the provided path.
+    ///
+    /// The download is streamed into the extraction as it arrives and paused
+    /// whenever the extraction falls behind, so the archive is never buffered
+    /// in memory as a whole.
+    ///
+    ///  * `members` - if `Some`, then only regular files whose normalized
+    ///    path inside the archive is in `members` are extracted, everything
+    ///    else (including directories) is skipped.
+    ///    It is ignored for [`PkgFmt::Bin`] and [`PkgFmt::Lz4`].
+    ///
+    /// NOTE that this will only extract directory and regular files.
+    #[instrument(skip(path, members))]
+    pub async fn and_extract_members(
+        self,
+        fmt: PkgFmt,
+        path: impl AsRef<Path>,
+        members: Option<&[PathBuf]>,
     ) -> Result<ExtractedFiles, DownloadError> {
         async fn inner(
             this: Download<'_>,
             fmt: PkgFmt,
             path: &Path,
+            members: Option<HashSet<PathBuf>>,
         ) -> Result<ExtractedFiles, DownloadError> {
             let has_data_verifier = this.data_verifier.is_some();
             let mut stream = this.get_stream().await?;
 
             debug!("Downloading and extracting to: '{}'", path.display());
 
-            let res = match fmt.decompose() {
-                PkgFmtDecomposed::Tar(fmt) => {
-                    extract_tar_based_stream(&mut stream, path, fmt).await
-                }
-                PkgFmtDecomposed::Bin => extract_bin(&mut stream, path).await,
-                PkgFmtDecomposed::Zip => extract_zip(&mut stream, path).await,
-            };
-
-            match res {
+            match extract_stream(&mut stream, fmt, path, members).await {
                 Ok(extracted_files) => {
                     debug!("Download OK, extracted to: '{}'", path.display());
                     Ok(extracted_files)
@@ -256,15 +510,71 @@ impl Download<'_> {
             }
         }
 
-        inner(self, fmt, path.as_ref()).await
+        inner(
+            self,
+            fmt,
+            path.as_ref(),
+            members.map(|members| members.iter().cloned().collect()),
+        )
+        .await
     }
 
     #[instrument]
     pub async fn into_bytes(self) -> Result<Bytes, DownloadError> {
-        let bytes = self.client.get(self.url).send(true).await?.bytes().await?;
+        let bytes = self.request().send(true).await?.bytes().await?;
+        if let Some(verifier) = self.data_verifier {
+            verifier.update(&bytes);
+        }
+        if let Some(copy) = self.copy {
+            copy.send(bytes.clone()).await.ok();
+        }
+        Ok(bytes)
+    }
+
+    /// Download a small file, such as a signature or a checksum, into memory.
+    ///
+    /// Unlike [`Download::into_bytes`], the download is aborted if it does
+    /// not complete within `timeout` or is larger than `max_size` bytes,
+    /// regardless of the limits of the [`Client`].
+    #[instrument]
+    pub async fn into_bytes_bounded(
+        self,
+        max_size: u64,
+        timeout: Duration,
+    ) -> Result<Bytes, DownloadError> {
+        let download = async {
+            let response = self.request().send(true).await?;
+            if let Some(len) = response.content_length() {
+                if len > max_size {
+                    return Err(DownloadError::TooLarge { max_size });
+                }
+            }
+
+            // The server might not report the length, or lie about it.
+            let stream = response.bytes_stream();
+            pin_mut!(stream);
+
+            let mut bytes = BytesMut::new();
+            while let Some(chunk) = stream.next().await {
+                let chunk = chunk?;
+                if (bytes.len() + chunk.len()) as u64 > max_size {
+                    return Err(DownloadError::TooLarge { max_size });
+                }
+                bytes.extend_from_slice(&chunk);
+            }
+
+            Ok(bytes.freeze())
+        };
+
+        let bytes = tokio::time::timeout(timeout, download)
+            .await
+            .map_err(|_| DownloadError::Timeout { timeout })??;
         if let Some(verifier) = self.data_verifier {
             verifier.update(&bytes);
         }
+        if let Some(copy) = self.copy {
+            copy.send(bytes.clone()).await.ok();
+        }
         Ok(bytes)
     }
 }
@@ -276,6 +586,7 @@ mod test {
     use std::{
         collections::{HashMap, HashSet},
         ffi::OsStr,
+        fs,
         num::NonZeroU16,
     };
     use tempfile::tempdir;
@@ -396,4 +707,109 @@ mod test {
             );
         }
     }
+
+    #[tokio::test]
+    async fn test_extract_lz4() {
+        use lz4_flex::frame::FrameEncoder;
+        use std::io::Write;
+
+        fn lz4(data: &[u8]) -> Vec<u8> {
+            let mut encoder = FrameEncoder::new(Vec::new());
+            encoder.write_all(data).unwrap();
+            encoder.finish().unwrap()
+        }
+
+        /// Stream `data` in small chunks, to decode it across them.
+        fn stream(data: &[u8]) -> impl Stream<Item = Result<Bytes, DownloadError>> + Unpin {
+            let chunks: Vec<_> = data
+                .chunks(7)
+                .map(|chunk| Ok(Bytes::copy_from_slice(chunk)))
+                .collect();
+            futures_util::stream::iter(chunks)
+        }
+
+        let binary = b"#!/bin/sh\necho hello\n".repeat(100);
+        let dir = tempdir().unwrap();
+
+        // Bare binary
+        let path = dir.path().join("tool");
+        let extracted_files = extract_stream(&mut stream(&lz4(&binary)), PkgFmt::Lz4, &path, None)
+            .await
+            .unwrap();
+        assert_eq!(fs::read(&path).unwrap(), binary);
+        assert!(extracted_files.has_file(Path::new("tool")));
+
+        // Tar archive
+        let mut builder = tar::Builder::new(Vec::new());
+        let mut header = tar::Header::new_gnu();
+        header.set_size(binary.len() as u64);
+        header.set_mode(0o755);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, "bin/tool", &binary[..])
+            .unwrap();
+        let archive = builder.into_inner().unwrap();
+
+        let path = dir.path().join("archive");
+        let extracted_files =
+            extract_stream(&mut stream(&lz4(&archive)), PkgFmt::Tlz4, &path, None)
+                .await
+                .unwrap();
+        assert_eq!(fs::read(path.join("bin/tool")).unwrap(), binary);
+        assert!(extracted_files.has_file(Path::new("bin/tool")));
+
+        // Truncated package
+        let compressed = lz4(&binary);
+        let err = extract_stream(
+            &mut stream(&compressed[..compressed.len() / 2]),
+            PkgFmt::Lz4,
+            &dir.path().join("truncated"),
+            None,
+        )
+        .await
+        .unwrap_err();
+        assert!(
+            matches!(
+                err,
+                DownloadError::CorruptArchive {
+                    fmt: PkgFmt::Lz4,
+                    ..
+                }
+            ),
+            "{err:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_download_copy() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("kept").join("package.tgz");
+
+        let spawn_copy = || {
+            let (tx, rx) = mpsc::channel(16);
+            let copy = DownloadCopy {
+                path: path.clone(),
+                writer: tokio::spawn(write_copy(path.clone(), rx)),
+            };
+            (tx, copy)
+        };
+
+        // A copy that is not persisted is removed.
+        let (tx, copy) = spawn_copy();
+        tx.send(Bytes::from_static(b"unverified")).await.unwrap();
+        drop(tx);
+        let temp_path = copy.writer.await.unwrap().unwrap();
+        assert!(temp_path.exists());
+        drop(temp_path);
+        assert_eq!(fs::read_dir(path.parent().unwrap()).unwrap().count(), 0);
+
+        let (tx, copy) = spawn_copy();
+        tx.send(Bytes::from_static(b"hello ")).await.unwrap();
+        tx.send(Bytes::from_static(b"world")).await.unwrap();
+        drop(tx);
+        assert!(!path.exists());
+        copy.persist().await.unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"hello world");
+        assert_eq!(fs::read_dir(path.parent().unwrap()).unwrap().count(), 1);
+    }
 }
@@ -1,5 +1,6 @@
 use std::{
     borrow::Cow,
+    collections::HashSet,
     fs,
     future::Future,
     io::{self, Write},
@@ -9,6 +10,7 @@ use std::{
 use async_zip::base::read::stream::ZipFileReader;
 use bytes::{Bytes, BytesMut};
 use futures_util::Stream;
+use lz4_flex::frame::FrameDecoder as Lz4Decoder;
 use tokio::sync::mpsc;
 use tokio_util::io::StreamReader;
 use tracing::debug;
@@ -43,7 +45,33 @@ where
     Ok(extracted_files)
 }
 
-pub async fn extract_zip<S>(stream: S, path: &Path) -> Result<ExtractedFiles, DownloadError>
+pub async fn extract_lz4_bin<S>(stream: S, path: &Path) -> Result<ExtractedFiles, DownloadError>
+where
+    S: Stream<Item = Result<Bytes, DownloadError>> + Send + Sync + Unpin,
+{
+    debug!("Decompressing from lz4 to `{}`", path.display());
+
+    extract_with_blocking_decoder(stream, path, |rx, path| {
+        let mut file = fs::File::create(path)?;
+
+        io::copy(&mut Lz4Decoder::new(StreamReadable::new(rx)), &mut file)?;
+
+        file.flush()
+    })
+    .await?;
+
+    let mut extracted_files = ExtractedFiles::new();
+
+    extracted_files.add_file(Path::new(path.file_name().unwrap()));
+
+    Ok(extracted_files)
+}
+
+pub async fn extract_zip<S>(
+    stream: S,
+    path: &Path,
+    members: Option<&HashSet<PathBuf>>,
+) -> Result<ExtractedFiles, DownloadError>
 where
     S: Stream<Item = Result<Bytes, DownloadError>> + Unpin + Send + Sync,
 {
@@ -60,6 +88,7 @@ where
             path,
             &mut buf,
             &mut extracted_files,
+            members,
         )
         .await?;
 
@@ -77,6 +106,7 @@ pub async fn extract_tar_based_stream<S>(
     stream: S,
     dst: &Path,
     fmt: TarBasedFmt,
+    mut members: Option<HashSet<PathBuf>>,
 ) -> Result<ExtractedFiles, DownloadError>
 where
     S: Stream<Item = Result<Bytes, DownloadError>> + Send + Sync + Unpin,
@@ -110,41 +140,109 @@ where
         // extraction.
         let mut directories = Vec::new();
 
+        // Symlinks are created once their targets are extracted.
+        let mut symlinks = Vec::new();
+        // Regular files skipped as they are not members.
+        let mut skipped = HashSet::new();
+
         while let Some(mut entry) = entries.next().transpose()? {
             match entry.header().entry_type() {
                 tar::EntryType::Regular => {
+                    let normalized_path = normalize_tar_entry_path(&entry.path()?);
+
+                    if let Some(members) = &members {
+                        if !members.contains(&normalized_path) {
+                            skipped.insert(normalized_path);
+                            continue;
+                        }
+                    }
+
                     // unpack_in returns false if the path contains ".."
                     // and is skipped.
                     if entry.unpack_in(dst)? {
-                        let path = entry.path()?;
-
-                        // create normalized_path in the same way
-                        // tar::Entry::unpack_in would normalize the path.
-                        let mut normalized_path = PathBuf::new();
-
-                        for part in path.components() {
-                            match part {
-                                Component::Prefix(..) | Component::RootDir | Component::CurDir => {
-                                    continue
+                        extracted_files.add_file(&normalized_path);
+                    }
+                }
+                tar::EntryType::Symlink => {
+                    let normalized_path = normalize_tar_entry_path(&entry.path()?);
+                    let Some(target) = entry.link_name()? else {
+                        continue;
+                    };
+                    let target = target.into_owned();
+
+                    let invalid_symlink = |reason| DownloadError::InvalidSymlink {
+                        path: normalized_path.clone(),
+                        target: target.clone(),
+                        reason,
+                    };
+
+                    let resolved = resolve_symlink_target(
+                        normalized_path.parent().unwrap_or(Path::new("")),
+                        &target,
+                    )
+                    .ok_or_else(|| invalid_symlink("it points outside of the archive"))?;
+
+                    // The members under the symlink are extracted from where it points to.
+                    if let Some(members) = &mut members {
+                        let redirected: Vec<_> = members
+                            .iter()
+                            .filter_map(|member| member.strip_prefix(&normalized_path).ok())
+                            .map(|rest| {
+                                if rest.as_os_str().is_empty() {
+                                    resolved.clone()
+                                } else {
+                                    resolved.join(rest)
                                 }
+                            })
+                            .collect();
 
-                                // unpack_in would return false if this happens.
-                                Component::ParentDir => unreachable!(),
-
-                                Component::Normal(part) => normalized_path.push(part),
-                            }
+                        if redirected.is_empty() {
+                            continue;
                         }
-
-                        extracted_files.add_file(&normalized_path);
+                        if redirected.iter().any(|path| skipped.contains(path)) {
+                            return Err(invalid_symlink(
+                                "it points to a file preceding it in the archive",
+                            )
+                            .into());
+                        }
+                        members.extend(redirected);
                     }
+
+                    symlinks.push((normalized_path, target, resolved));
                 }
-                tar::EntryType::Directory => {
+                // Directories of the members are created when unpacking them.
+                tar::EntryType::Directory if members.is_none() => {
                     directories.push(entry);
                 }
                 _ => (),
             }
         }
 
+        for (path, target, resolved) in symlinks {
+            let link = dst.join(&path);
+            let parent = link
+                .parent()
+                .expect("symlinks are never the root of the archive");
+            fs::create_dir_all(parent)?;
+
+            // The parent could be reached through another symlink, so check
+            // where the symlink actually points to.
+            let parent = parent.canonicalize()?;
+            let points_inside = resolve_symlink_target(&parent, &target)
+                .map_or(false, |physical| physical.starts_with(dst));
+            if !parent.starts_with(dst) || !points_inside {
+                return Err(DownloadError::InvalidSymlink {
+                    path,
+                    target,
+                    reason: "it points outside of the archive",
+                }
+                .into());
+            }
+
+            create_symlink(&target, &link)?;
+            extracted_files.add_symlink(&path, &resolved);
+        }
+
         for mut dir in directories {
             if dir.unpack_in(dst)? {
                 extracted_files.add_dir(&dir.path()?);
@@ -156,6 +254,72 @@ where
     .await
 }
 
+/// Resolve the `target` of a symlink in the directory `base`, return `None`
+/// if it points above `base` or to its root.
+///
+/// `..` is only accepted at the start of `target`: after another component,
+/// which could be a symlink, it could lead anywhere.
+fn resolve_symlink_target(base: &Path, target: &Path) -> Option<PathBuf> {
+    let mut resolved = base.to_owned();
+    let mut leading = true;
+
+    for part in target.components() {
+        match part {
+            Component::Prefix(..) | Component::RootDir => return None,
+            Component::CurDir => (),
+            Component::ParentDir if leading => {
+                if !resolved.pop() {
+                    return None;
+                }
+            }
+            Component::ParentDir => return None,
+            Component::Normal(part) => {
+                leading = false;
+                resolved.push(part);
+            }
+        }
+    }
+
+    (!leading).then_some(resolved)
+}
+
+#[cfg(unix)]
+fn create_symlink(target: &Path, link: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(target, link)
+}
+
+/// Creating symlinks requires extra privileges on Windows, so the file the
+/// symlink points to is copied instead.
+#[cfg(not(unix))]
+fn create_symlink(target: &Path, link: &Path) -> io::Result<()> {
+    let target = link
+        .parent()
+        .map_or_else(|| target.to_owned(), |dir| dir.join(target));
+    if target.is_file() {
+        fs::copy(target, link)?;
+    }
+    Ok(())
+}
+
+/// Create normalized path in the same way `tar::Entry::unpack_in` would
+/// normalize the path.
+///
+/// `unpack_in` skips any path containing "..", so it is kept as-is here
+/// to make sure it never matches any normalized path.
+fn normalize_tar_entry_path(path: &Path) -> PathBuf {
+    let mut normalized_path = PathBuf::new();
+
+    for part in path.components() {
+        match part {
+            Component::Prefix(..) | Component::RootDir | Component::CurDir => continue,
+            Component::ParentDir => normalized_path.push(".."),
+            Component::Normal(part) => normalized_path.push(part),
+        }
+    }
+
+    normalized_path
+}
+
 fn extract_with_blocking_decoder<S, F, T>(
     stream: S,
     path: &Path,
@@ -176,3 +340,28 @@ where
         f(rx, &path)
     })
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_resolve_symlink_target() {
+        let resolve =
+            |base: &str, target: &str| resolve_symlink_target(Path::new(base), Path::new(target));
+
+        assert_eq!(resolve("", "tool-1.2.3"), Some(PathBuf::from("tool-1.2.3")));
+        assert_eq!(
+            resolve("bin", "../tool/bin"),
+            Some(PathBuf::from("tool/bin"))
+        );
+        assert_eq!(resolve("a/b", "./../c"), Some(PathBuf::from("a/c")));
+
+        assert_eq!(resolve("", "/usr/bin/tool"), None);
+        assert_eq!(resolve("", "../tool"), None);
+        assert_eq!(resolve("bin", "../../tool"), None);
+        assert_eq!(resolve("bin", "tool/../../.."), None);
+        assert_eq!(resolve("bin", ".."), None);
+        assert_eq!(resolve("bin", "."), None);
+    }
+}
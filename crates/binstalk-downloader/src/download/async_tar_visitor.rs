@@ -1,9 +1,19 @@
-use std::{borrow::Cow, fmt::Debug, io, path::Path, pin::Pin};
+use std::{
+    borrow::Cow,
+    fmt::Debug,
+    io::{self, Read},
+    path::Path,
+    pin::Pin,
+};
 
 use async_compression::tokio::bufread;
-use bytes::Bytes;
-use futures_util::{Stream, StreamExt};
-use tokio::io::{copy, sink, AsyncRead};
+use bytes::{Bytes, BytesMut};
+use futures_util::{stream, Stream, StreamExt};
+use lz4_flex::frame::FrameDecoder as Lz4Decoder;
+use tokio::{
+    io::{copy, sink, AsyncRead},
+    sync::mpsc,
+};
 use tokio_tar::{Archive, Entry, EntryType};
 use tokio_util::io::StreamReader;
 use tracing::debug;
@@ -12,6 +22,7 @@ use super::{
     DownloadError,
     TarBasedFmt::{self, *},
 };
+use crate::utils::{extract_with_blocking_task, StreamReadable, MAX_PENDING_CHUNKS};
 
 pub trait TarEntry: AsyncRead + Send + Sync + Unpin + Debug {
     /// Returns the path name for this entry.
@@ -105,8 +116,58 @@ where
         Tgz => Box::pin(bufread::GzipDecoder::new(reader)),
         Txz => Box::pin(bufread::XzDecoder::new(reader)),
         Tzstd => Box::pin(bufread::ZstdDecoder::new(reader)),
+        Tlz4 => return extract_tlz4_stream_and_visit(reader.into_inner(), visitor).await,
     };
 
+    visit_tar_entries(decoder, visitor).await
+}
+
+/// async-compression has no lz4 decoder, so the lz4 frames are decoded by
+/// lz4_flex on a blocking task fed with `stream`, while the decoded archive
+/// is visited.
+async fn extract_tlz4_stream_and_visit<S>(
+    stream: S,
+    visitor: &mut dyn TarEntriesVisitor,
+) -> Result<(), DownloadError>
+where
+    S: Stream<Item = Result<Bytes, DownloadError>> + Send + Sync,
+{
+    let (tx, mut rx) = mpsc::channel(MAX_PENDING_CHUNKS);
+
+    let decode = extract_with_blocking_task::<DownloadError, _, _, _, _>(
+        Box::pin(stream),
+        move |compressed| {
+            let mut decoder = Lz4Decoder::new(StreamReadable::new(compressed));
+
+            loop {
+                let mut buf = BytesMut::zeroed(4 * 4096);
+                let n = decoder.read(&mut buf)?;
+                if n == 0 {
+                    return Ok(());
+                }
+                buf.truncate(n);
+
+                if tx.blocking_send(buf.freeze()).is_err() {
+                    // The visit ended early, its result is returned instead.
+                    return Ok(());
+                }
+            }
+        },
+    );
+
+    let decoded =
+        stream::poll_fn(move |cx| rx.poll_recv(cx).map(|bytes| bytes.map(io::Result::Ok)));
+    let visit = visit_tar_entries(Box::pin(StreamReader::new(decoded)), visitor);
+
+    let (decoded, visited) = tokio::join!(decode, visit);
+    decoded?;
+    visited
+}
+
+async fn visit_tar_entries(
+    decoder: Pin<Box<dyn AsyncRead + Send + Sync + '_>>,
+    visitor: &mut dyn TarEntriesVisitor,
+) -> Result<(), DownloadError> {
     let mut tar = Archive::new(decoder);
     let mut entries = tar.entries()?;
 
@@ -1,6 +1,7 @@
 use std::{
     collections::{hash_map::Entry as HashMapEntry, HashMap, HashSet},
     ffi::OsStr,
+    fs, io,
     path::Path,
 };
 
@@ -29,6 +30,35 @@ impl ExtractedFiles {
         Self(Default::default())
     }
 
+    /// Collect the files and directories under `dir`, for packages
+    /// extracted by other means, e.g. by an external program.
+    ///
+    /// Symlinks are not followed, they are skipped.
+    ///
+    /// This is a blocking function.
+    pub fn from_dir(dir: &Path) -> io::Result<Self> {
+        fn visit(extracted_files: &mut ExtractedFiles, dir: &Path, rel: &Path) -> io::Result<()> {
+            for entry in fs::read_dir(dir)? {
+                let entry = entry?;
+                let path = rel.join(entry.file_name());
+                let file_type = entry.file_type()?;
+
+                if file_type.is_dir() {
+                    extracted_files.add_dir(&path);
+                    visit(extracted_files, &entry.path(), &path)?;
+                } else if file_type.is_file() {
+                    extracted_files.add_file(&path);
+                }
+            }
+
+            Ok(())
+        }
+
+        let mut extracted_files = Self::new();
+        visit(&mut extracted_files, dir, Path::new(""))?;
+        Ok(extracted_files)
+    }
+
     /// * `path` - must be canonical and must not be empty
     ///
     /// NOTE that if the entry for the `path` is previously set to a dir,
@@ -79,6 +109,38 @@ impl ExtractedFiles {
         }
     }
 
+    /// Add the entries extracted at `target` at `path` as well, for `path`
+    /// being a symlink to `target`.
+    ///
+    /// * `path` and `target` - must be canonical and must not be empty
+    pub(super) fn add_symlink(&mut self, path: &Path, target: &Path) {
+        let (mut dirs, mut files) = (Vec::new(), Vec::new());
+
+        for (entry_path, entry) in &self.0 {
+            let Ok(rest) = entry_path.strip_prefix(target) else {
+                continue;
+            };
+            let entry_path = if rest.as_os_str().is_empty() {
+                path.to_owned()
+            } else {
+                path.join(rest)
+            };
+
+            match entry {
+                ExtractedFilesEntry::Dir(_) => dirs.push(entry_path),
+                ExtractedFilesEntry::File => files.push(entry_path),
+            }
+        }
+
+        // Dirs first, as adding a dir empties any existing one.
+        for dir in dirs {
+            self.add_dir(&dir);
+        }
+        for file in files {
+            self.add_file(&file);
+        }
+    }
+
     /// * `path` - must be a relative path without `.`, `..`, `/`, `prefix:/`
     ///            and must not be empty, for these values it is guaranteed to
     ///            return `None`.
@@ -105,4 +167,46 @@ impl ExtractedFiles {
     pub fn has_file(&self, path: &Path) -> bool {
         matches!(self.get_entry(path), Some(ExtractedFilesEntry::File))
     }
+
+    /// Return the paths of the files named `file_name`, in any directory.
+    pub fn find_files<'a>(&'a self, file_name: &'a OsStr) -> impl Iterator<Item = &'a Path> + 'a {
+        self.0.iter().filter_map(move |(path, entry)| {
+            (matches!(entry, ExtractedFilesEntry::File) && path.file_name() == Some(file_name))
+                .then_some(&**path)
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_from_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("a/b")).unwrap();
+        fs::write(dir.path().join("a/b/file"), "").unwrap();
+        fs::write(dir.path().join("top"), "").unwrap();
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(dir.path().join("top"), dir.path().join("file-link"))
+                .unwrap();
+            std::os::unix::fs::symlink(dir.path().join("a"), dir.path().join("dir-link")).unwrap();
+        }
+
+        let files = ExtractedFiles::from_dir(dir.path()).unwrap();
+
+        assert!(files.has_file(Path::new("top")));
+        assert!(files.has_file(Path::new("a/b/file")));
+        assert_eq!(
+            files.get_dir(Path::new(".")).unwrap(),
+            &HashSet::from([OsStr::new("a").into(), OsStr::new("top").into()])
+        );
+        assert_eq!(
+            files.get_dir(Path::new("a")).unwrap(),
+            &HashSet::from([OsStr::new("b").into()])
+        );
+        assert_eq!(files.get_entry(Path::new("file-link")), None);
+        assert_eq!(files.get_entry(Path::new("dir-link")), None);
+    }
 }
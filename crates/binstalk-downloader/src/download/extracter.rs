@@ -2,6 +2,7 @@ use std::io::{self, BufRead, Read};
 
 use bzip2::bufread::BzDecoder;
 use flate2::bufread::GzDecoder;
+use lz4_flex::frame::FrameDecoder as Lz4Decoder;
 use tar::Archive;
 use xz2::bufread::XzDecoder;
 use zstd::stream::Decoder as ZstdDecoder;
@@ -25,6 +26,7 @@ pub fn create_tar_decoder(
             // should not return any error.
             Box::new(ZstdDecoder::with_buffer(dat)?)
         }
+        Tlz4 => Box::new(Lz4Decoder::new(dat)),
     };
 
     Ok(Archive::new(r))
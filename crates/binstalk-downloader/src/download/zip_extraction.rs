@@ -1,5 +1,6 @@
 use std::{
     borrow::Cow,
+    collections::HashSet,
     io::Write,
     path::{Component, Path, PathBuf},
 };
@@ -10,16 +11,16 @@ use async_zip::{
 };
 use bytes::{Bytes, BytesMut};
 use futures_util::future::try_join;
-use futures_util::io::Take;
+use futures_util::io::AsyncBufRead;
 use thiserror::Error as ThisError;
 use tokio::{
-    io::{AsyncRead, AsyncReadExt},
+    io::{copy, sink, AsyncRead, AsyncReadExt},
     sync::mpsc,
 };
-use tokio_util::compat::{Compat, FuturesAsyncReadCompatExt};
+use tokio_util::compat::FuturesAsyncReadCompatExt;
 
 use super::{DownloadError, ExtractedFiles};
-use crate::utils::asyncify;
+use crate::utils::{asyncify, MAX_PENDING_CHUNKS};
 
 #[derive(Debug, ThisError)]
 enum ZipErrorInner {
@@ -28,6 +29,9 @@ enum ZipErrorInner {
 
     #[error("Invalid file path: {0}")]
     InvalidFilePath(Box<str>),
+
+    #[error("Unsupported compression method of zip entry: {0}")]
+    UnsupportedCompression(String),
 }
 
 #[derive(Debug, ThisError)]
@@ -36,23 +40,64 @@ pub struct ZipError(#[from] ZipErrorInner);
 
 impl ZipError {
     pub(super) fn from_inner(err: async_zip::error::ZipError) -> Self {
-        Self(ZipErrorInner::Inner(err))
+        match err {
+            async_zip::error::ZipError::CompressionNotSupported(method) => Self(
+                ZipErrorInner::UnsupportedCompression(compression_method_name(method)),
+            ),
+            err => Self(ZipErrorInner::Inner(err)),
+        }
     }
 }
 
+/// Return the name of the zip compression `method`, as defined in the
+/// section 4.4.5 of the zip specification.
+fn compression_method_name(method: u16) -> String {
+    let name = match method {
+        1 => "Shrink",
+        2..=5 => "Reduce",
+        6 => "Implode",
+        9 => "Deflate64",
+        10 => "PKWARE DCL Implode",
+        12 => "bzip2",
+        14 => "LZMA",
+        18 => "IBM TERSE",
+        19 => "IBM LZ77 z",
+        93 => "Zstandard",
+        94 => "MP3",
+        95 => "xz",
+        96 => "JPEG",
+        97 => "WavPack",
+        98 => "PPMd",
+        99 => "AE-x encryption",
+        method => return format!("unknown method {method}"),
+    };
+
+    format!("{name} (method {method})")
+}
+
 pub(super) async fn extract_zip_entry<R>(
-    zip_reader: &mut ZipEntryReader<'_, Take<Compat<R>>, WithEntry<'_>>,
+    zip_reader: &mut ZipEntryReader<'_, R, WithEntry<'_>>,
     path: &Path,
     buf: &mut BytesMut,
     extracted_files: &mut ExtractedFiles,
+    members: Option<&HashSet<PathBuf>>,
 ) -> Result<(), DownloadError>
 where
-    R: AsyncRead + Unpin + Send + Sync,
+    R: AsyncBufRead + Unpin + Send + Sync,
 {
     // Sanitize filename
     let raw_filename = zip_reader.entry().filename();
     let (filename, is_dir) = check_filename_and_normalize(raw_filename)?;
 
+    if let Some(members) = members {
+        if is_dir || !members.contains(&filename) {
+            // Skip this entry, but still read it until eof so that the
+            // next entry can be read.
+            copy(&mut zip_reader.compat(), &mut sink()).await?;
+            return Ok(());
+        }
+    }
+
     // Calculates the outpath
     let outpath = path.join(&filename);
 
@@ -92,8 +137,7 @@ where
     } else {
         extracted_files.add_file(&filename);
 
-        // Use channel size = 5 to minimize the waiting time in the extraction task
-        let (tx, mut rx) = mpsc::channel::<Bytes>(5);
+        let (tx, mut rx) = mpsc::channel::<Bytes>(MAX_PENDING_CHUNKS);
 
         // This entry is a file.
 
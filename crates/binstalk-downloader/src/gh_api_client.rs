@@ -17,7 +17,10 @@ use tokio::sync::OnceCell;
 use crate::remote;
 
 mod request;
-pub use request::{GhApiContextError, GhApiError, GhGraphQLErrors};
+pub use request::{
+    check_rate_limit, GhApiContextError, GhApiError, GhGraphQLErrors, GhWorkflowArtifact,
+    GhWorkflowArtifactQuery,
+};
 
 /// default retry duration if x-ratelimit-reset is not found in response header
 const DEFAULT_RETRY_DURATION: Duration = Duration::from_secs(10 * 60);
@@ -240,6 +243,37 @@ impl GhApiClient {
             Err(Error::Error(err)) => Err(err),
         }
     }
+
+    /// Return the auth token, unless GitHub rejected it.
+    pub fn auth_token(&self) -> Option<&str> {
+        self.0
+            .is_auth_token_valid
+            .load(Relaxed)
+            .then_some(self.0.auth_token.as_deref())
+            .flatten()
+    }
+
+    /// Find the artifact described by `query` uploaded by the latest
+    /// successful run of its workflow in the repository `owner/repo`.
+    ///
+    /// Only the runs triggered by a push of the commit of the tag to the
+    /// branch are considered, so that the artifact is built from the version
+    /// by the repository itself rather than by a pull request.
+    ///
+    /// The GitHub API requires authentication to download artifacts, so
+    /// a valid auth token is required.
+    ///
+    /// Return `None` if there isn't any.
+    pub async fn fetch_workflow_artifact(
+        &self,
+        owner: &str,
+        repo: &str,
+        query: &GhWorkflowArtifactQuery<'_>,
+    ) -> Result<Option<GhWorkflowArtifact>, GhApiError> {
+        let auth_token = self.auth_token().ok_or(GhApiError::MissingAuthToken)?;
+
+        request::fetch_workflow_artifact(&self.0.client, owner, repo, query, auth_token).await
+    }
 }
 
 #[derive(Eq, PartialEq, Copy, Clone, Debug)]
@@ -361,6 +395,64 @@ mod test {
         }
     }
 
+    #[test]
+    fn rate_limit_is_distinguished() {
+        use remote::{
+            header::{HeaderMap, HeaderValue},
+            StatusCode,
+        };
+        use std::time::{Duration, SystemTime};
+
+        let mut headers = HeaderMap::new();
+        assert_eq!(check_rate_limit(StatusCode::FORBIDDEN, &headers), None);
+
+        headers.insert("x-ratelimit-remaining", HeaderValue::from_static("0"));
+        assert_eq!(check_rate_limit(StatusCode::NOT_FOUND, &headers), None);
+        assert_eq!(
+            check_rate_limit(StatusCode::FORBIDDEN, &headers),
+            Some(None)
+        );
+
+        headers.insert("x-ratelimit-reset", HeaderValue::from_static("1700000000"));
+        assert_eq!(
+            check_rate_limit(StatusCode::FORBIDDEN, &headers),
+            Some(Some(
+                SystemTime::UNIX_EPOCH + Duration::from_secs(1700000000)
+            ))
+        );
+    }
+
+    #[test]
+    fn workflow_runs_are_pushes_of_the_version() {
+        let url = request::workflow_runs_url(
+            "https://api.github.com/repos/owner/repo",
+            "nightly.yml",
+            "main",
+            "0123abcd",
+        )
+        .unwrap();
+
+        assert_eq!(
+            url.path(),
+            "/repos/owner/repo/actions/workflows/nightly.yml/runs"
+        );
+
+        let query: Vec<_> = url.query_pairs().collect();
+        for pair in [
+            ("event", "push"),
+            ("status", "success"),
+            ("branch", "main"),
+            ("head_sha", "0123abcd"),
+        ] {
+            assert!(
+                query
+                    .iter()
+                    .any(|(key, value)| (key.as_ref(), value.as_ref()) == pair),
+                "{pair:?} missing from {url}"
+            );
+        }
+    }
+
     /// Mark this as an async fn so that you won't accidentally use it in
     /// sync context.
     async fn create_client() -> Vec<GhApiClient> {
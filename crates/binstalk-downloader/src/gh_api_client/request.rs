@@ -5,7 +5,7 @@ use std::{
     hash::{Hash, Hasher},
     io,
     sync::OnceLock,
-    time::Duration,
+    time::{Duration, SystemTime},
 };
 
 use compact_str::{CompactString, ToCompactString};
@@ -44,6 +44,9 @@ pub enum GhApiError {
 
     #[error("Remote failed to process GraphQL query: {0}")]
     GraphQLErrors(#[from] GhGraphQLErrors),
+
+    #[error("A GitHub token is required, e.g. in the GITHUB_TOKEN environment variable")]
+    MissingAuthToken,
 }
 
 impl GhApiError {
@@ -110,22 +113,39 @@ pub(super) enum FetchReleaseRet {
     Unauthorized,
 }
 
+/// Return `Some(reset)` if `status` and `headers` are from GitHub's 403
+/// response for an exhausted rate limit, `reset` being the time the rate
+/// limit is reset at if it is reported.
+///
+/// This is different from a 403 for lacking permissions or a 404.
+pub fn check_rate_limit(status: StatusCode, headers: &HeaderMap) -> Option<Option<SystemTime>> {
+    let exhausted = status == StatusCode::FORBIDDEN
+        && headers
+            .get("x-ratelimit-remaining")
+            .map(|val| val == "0")
+            .unwrap_or(false);
+
+    // x-ratelimit-reset is in UTC epoch seconds
+    exhausted.then(|| {
+        let secs = headers
+            .get("x-ratelimit-reset")?
+            .to_str()
+            .ok()?
+            .parse()
+            .ok()?;
+        SystemTime::UNIX_EPOCH.checked_add(Duration::from_secs(secs))
+    })
+}
+
 fn check_for_status(status: StatusCode, headers: &HeaderMap) -> Option<FetchReleaseRet> {
-    match status {
-        remote::StatusCode::FORBIDDEN
-            if headers
-                .get("x-ratelimit-remaining")
-                .map(|val| val == "0")
-                .unwrap_or(false) =>
-        {
-            Some(FetchReleaseRet::ReachedRateLimit {
-                retry_after: headers.get("x-ratelimit-reset").and_then(|value| {
-                    let secs = value.to_str().ok()?.parse().ok()?;
-                    Some(Duration::from_secs(secs))
-                }),
-            })
-        }
+    if let Some(reset) = check_rate_limit(status, headers) {
+        return Some(FetchReleaseRet::ReachedRateLimit {
+            retry_after: reset
+                .map(|reset| reset.duration_since(SystemTime::now()).unwrap_or_default()),
+        });
+    }
 
+    match status {
         remote::StatusCode::UNAUTHORIZED => Some(FetchReleaseRet::Unauthorized),
         remote::StatusCode::NOT_FOUND => Some(FetchReleaseRet::ReleaseNotFound),
 
@@ -161,6 +181,178 @@ async fn fetch_release_artifacts_restful_api(
     }
 }
 
+/// An artifact uploaded by a run of a GitHub Actions workflow.
+#[derive(Clone, Debug, Deserialize)]
+pub struct GhWorkflowArtifact {
+    pub id: u64,
+    pub name: CompactString,
+    pub size_in_bytes: u64,
+    /// Artifacts are deleted after their retention period, an expired one
+    /// cannot be downloaded anymore.
+    pub expired: bool,
+    /// Url to download the artifact as a zip archive, which requires
+    /// authentication.
+    pub archive_download_url: Url,
+}
+
+/// Which artifact of a GitHub Actions workflow to look for.
+#[derive(Clone, Copy, Debug)]
+pub struct GhWorkflowArtifactQuery<'a> {
+    /// File name or id of the workflow.
+    pub workflow: &'a str,
+    /// Branch or tag whose pushes trigger the workflow, the default branch
+    /// of the repository if `None`.
+    pub branch: Option<&'a str>,
+    /// Tag of the version, only the runs on its commit are considered.
+    pub tag: &'a str,
+    pub artifact_name: &'a str,
+}
+
+#[derive(Deserialize)]
+struct GhWorkflowRuns {
+    workflow_runs: Vec<GhWorkflowRun>,
+}
+
+#[derive(Deserialize)]
+struct GhWorkflowRun {
+    id: u64,
+}
+
+#[derive(Deserialize)]
+struct GhWorkflowArtifacts {
+    artifacts: Vec<GhWorkflowArtifact>,
+}
+
+#[derive(Deserialize)]
+struct GhCommit {
+    sha: CompactString,
+}
+
+#[derive(Deserialize)]
+struct GhRepository {
+    default_branch: CompactString,
+}
+
+/// Send an authenticated GET request to the GitHub API, returning `None`
+/// if the resource is not found.
+async fn get_json<T: serde::de::DeserializeOwned>(
+    client: &remote::Client,
+    url: Url,
+    auth_token: &str,
+) -> Result<Option<T>, GhApiError> {
+    let response = client
+        .get(url)
+        .header("Accept", "application/vnd.github+json")
+        .header("X-GitHub-Api-Version", "2022-11-28")
+        .bearer_auth(&auth_token)
+        .send(false)
+        .await?;
+
+    // The commits endpoint returns 422 for a ref which does not exist.
+    if matches!(
+        response.status(),
+        remote::StatusCode::NOT_FOUND | remote::StatusCode::UNPROCESSABLE_ENTITY
+    ) {
+        return Ok(None);
+    }
+
+    Ok(Some(response.error_for_status()?.json().await?))
+}
+
+/// Url listing the latest successful run of `workflow` triggered by a push
+/// of `head_sha` to `branch`.
+///
+/// Only the runs triggered by a push are considered: those of pull requests
+/// run the code of whoever opened them, including from forks.
+pub(super) fn workflow_runs_url(
+    repo_url: &str,
+    workflow: &str,
+    branch: &str,
+    head_sha: &str,
+) -> Result<Url, GhApiError> {
+    let mut runs_url = Url::parse(&format!(
+        "{repo_url}/actions/workflows/{workflow}/runs",
+        workflow = percent_encode_http_url_path(workflow),
+    ))?;
+    runs_url
+        .query_pairs_mut()
+        .append_pair("event", "push")
+        .append_pair("status", "success")
+        .append_pair("branch", branch)
+        .append_pair("head_sha", head_sha)
+        .append_pair("per_page", "1");
+
+    Ok(runs_url)
+}
+
+pub(super) async fn fetch_workflow_artifact(
+    client: &remote::Client,
+    owner: &str,
+    repo: &str,
+    query: &GhWorkflowArtifactQuery<'_>,
+    auth_token: &str,
+) -> Result<Option<GhWorkflowArtifact>, GhApiError> {
+    let GhWorkflowArtifactQuery {
+        workflow,
+        branch,
+        tag,
+        artifact_name,
+    } = *query;
+
+    let repo_url = format!(
+        "https://api.github.com/repos/{owner}/{repo}",
+        owner = percent_encode_http_url_path(owner),
+        repo = percent_encode_http_url_path(repo),
+    );
+
+    // The artifact must be built from the commit of the version.
+    let commit_url = Url::parse(&format!(
+        "{repo_url}/commits/{tag}",
+        tag = percent_encode_http_url_path(tag),
+    ))?;
+    let Some(GhCommit { sha }) = get_json(client, commit_url, auth_token).await? else {
+        debug!("Tag {tag} not found in {owner}/{repo}");
+        return Ok(None);
+    };
+
+    let branch = match branch {
+        Some(branch) => CompactString::from(branch),
+        None => {
+            let Some(GhRepository { default_branch }) =
+                get_json(client, Url::parse(&repo_url)?, auth_token).await?
+            else {
+                return Ok(None);
+            };
+            default_branch
+        }
+    };
+
+    let runs_url = workflow_runs_url(&repo_url, workflow, &branch, &sha)?;
+    let Some(GhWorkflowRuns { workflow_runs }) = get_json(client, runs_url, auth_token).await?
+    else {
+        return Ok(None);
+    };
+    let Some(run) = workflow_runs.into_iter().next() else {
+        debug!("No successful run of {workflow} on {branch} at {sha}");
+        return Ok(None);
+    };
+
+    let mut artifacts_url = Url::parse(&format!("{repo_url}/actions/runs/{}/artifacts", run.id))?;
+    artifacts_url
+        .query_pairs_mut()
+        .append_pair("name", artifact_name);
+
+    let artifacts: Option<GhWorkflowArtifacts> =
+        get_json(client, artifacts_url, auth_token).await?;
+
+    Ok(artifacts.and_then(|artifacts| {
+        artifacts
+            .artifacts
+            .into_iter()
+            .find(|artifact| artifact.name == artifact_name)
+    }))
+}
+
 #[derive(Deserialize)]
 enum GraphQLResponse {
     #[serde(rename = "data")]
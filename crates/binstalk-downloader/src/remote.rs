@@ -1,11 +1,13 @@
 use std::{
-    num::{NonZeroU16, NonZeroU64, NonZeroU8},
+    collections::HashMap,
+    num::{NonZeroU16, NonZeroU64, NonZeroU8, NonZeroUsize},
     ops::ControlFlow,
-    sync::Arc,
+    sync::{Arc, Mutex},
     time::{Duration, SystemTime},
 };
 
 use bytes::Bytes;
+use compact_str::CompactString;
 use futures_util::Stream;
 use httpdate::parse_http_date;
 use reqwest::{
@@ -13,9 +15,10 @@ use reqwest::{
     Request,
 };
 use thiserror::Error as ThisError;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
 use tracing::{debug, info, instrument};
 
-pub use reqwest::{header, Error as ReqwestError, Method, StatusCode};
+pub use reqwest::{header, Client as ReqwestClient, Error as ReqwestError, Method, StatusCode};
 pub use url::Url;
 
 mod delay_request;
@@ -24,12 +27,18 @@ use delay_request::DelayRequest;
 mod certificate;
 pub use certificate::Certificate;
 
+mod client_builder;
+pub use client_builder::ClientBuilder;
+
 mod request_builder;
 pub use request_builder::{Body, RequestBuilder, Response};
 
 mod tls_version;
 pub use tls_version::TLSVersion;
 
+mod netrc;
+pub use netrc::{Netrc, NetrcCredentials};
+
 #[cfg(feature = "hickory-dns")]
 mod resolver;
 #[cfg(feature = "hickory-dns")]
@@ -42,6 +51,7 @@ const MAX_RETRY_DURATION: Duration = Duration::from_secs(120);
 const MAX_RETRY_COUNT: u8 = 3;
 const DEFAULT_RETRY_DURATION_FOR_RATE_LIMIT: Duration = Duration::from_millis(200);
 const RETRY_DURATION_FOR_TIMEOUT: Duration = Duration::from_millis(200);
+const POOL_IDLE_TIMEOUT: Duration = Duration::from_secs(120);
 #[allow(dead_code)]
 const DEFAULT_MIN_TLS: TLSVersion = TLSVersion::TLS_1_2;
 
@@ -79,12 +89,16 @@ impl HttpError {
 struct Inner {
     client: reqwest::Client,
     service: DelayRequest,
+    max_download_size: Option<NonZeroU64>,
+    limit_rate: Option<NonZeroU64>,
+    connections: Option<NonZeroUsize>,
+    host_permits: Mutex<HashMap<CompactString, Arc<Semaphore>>>,
+    netrc: Option<Netrc>,
 }
 
 #[derive(Clone, Debug)]
 pub struct Client(Arc<Inner>);
 
-#[cfg_attr(not(feature = "__tls"), allow(unused_variables, unused_mut))]
 impl Client {
     /// * `per_millis` - The duration (in millisecond) for which at most
     ///   `num_request` can be sent, itcould be increased if rate-limit
@@ -93,6 +107,8 @@ impl Client {
     ///   each `per` duration.
     ///
     /// The Client created would use at least tls 1.2
+    ///
+    /// Use [`Client::builder`] to configure the other options.
     pub fn new(
         user_agent: impl AsRef<str>,
         min_tls: Option<TLSVersion>,
@@ -100,55 +116,75 @@ impl Client {
         num_request: NonZeroU64,
         certificates: impl IntoIterator<Item = Certificate>,
     ) -> Result<Self, Error> {
-        fn inner(
-            user_agent: &str,
-            min_tls: Option<TLSVersion>,
-            per_millis: NonZeroU16,
-            num_request: NonZeroU64,
-            certificates: &mut dyn Iterator<Item = Certificate>,
-        ) -> Result<Client, Error> {
-            let mut builder = reqwest::ClientBuilder::new()
-                .user_agent(user_agent)
-                .https_only(true)
-                .tcp_nodelay(false);
-
-            #[cfg(feature = "hickory-dns")]
-            {
-                builder = builder.dns_resolver(Arc::new(TrustDnsResolver::default()));
-            }
-
-            #[cfg(feature = "__tls")]
-            {
-                let tls_ver = min_tls
-                    .map(|tls| tls.max(DEFAULT_MIN_TLS))
-                    .unwrap_or(DEFAULT_MIN_TLS);
+        Self::builder(user_agent.as_ref(), per_millis, num_request)
+            .min_tls(min_tls)
+            .root_certificates(certificates)
+            .build()
+    }
 
-                builder = builder.min_tls_version(tls_ver.into());
+    /// See [`ClientBuilder::new`].
+    pub fn builder(
+        user_agent: impl Into<CompactString>,
+        per_millis: NonZeroU16,
+        num_request: NonZeroU64,
+    ) -> ClientBuilder {
+        ClientBuilder::new(user_agent, per_millis, num_request)
+    }
 
-                for certificate in certificates {
-                    builder = builder.add_root_certificate(certificate.0);
-                }
-            }
+    /// Create a client from an already configured reqwest client, to share
+    /// its proxy, timeouts and connection pool.
+    ///
+    /// Unlike [`Client::new`], no user agent, https-only policy, TLS settings or
+    /// certificates are applied: they are up to `client`.
+    ///
+    /// See [`Client::new`] for the other arguments, and
+    /// [`ClientBuilder::build_from_reqwest`] to configure the other options.
+    pub fn from_reqwest(
+        client: reqwest::Client,
+        per_millis: NonZeroU16,
+        num_request: NonZeroU64,
+    ) -> Self {
+        ClientBuilder::new("", per_millis, num_request).build_from_reqwest(client)
+    }
 
-            let client = builder.build()?;
+    /// Wait until a request can be sent to the host of `url`, if the number
+    /// of connections is limited.
+    async fn acquire_connection(&self, url: &Url) -> Option<OwnedSemaphorePermit> {
+        let connections = self.0.connections?;
+        let host = url.host_str()?;
+
+        let semaphore = self
+            .0
+            .host_permits
+            .lock()
+            .unwrap()
+            .entry(host.into())
+            .or_insert_with(|| Arc::new(Semaphore::new(connections.get())))
+            .clone();
+
+        semaphore.acquire_owned().await.ok()
+    }
 
-            Ok(Client(Arc::new(Inner {
-                client: client.clone(),
-                service: DelayRequest::new(
-                    num_request,
-                    Duration::from_millis(per_millis.get() as u64),
-                    client,
-                ),
-            })))
+    /// Add the credentials of `netrc` to `request`, if any.
+    fn authenticate(&self, request: Request) -> Result<Request, ReqwestError> {
+        let Some(netrc) = &self.0.netrc else {
+            return Ok(request);
+        };
+        if request.headers().contains_key(header::AUTHORIZATION) {
+            return Ok(request);
         }
+        let Some(credentials) = request
+            .url()
+            .host_str()
+            .and_then(|host| netrc.credentials(host))
+        else {
+            return Ok(request);
+        };
 
-        inner(
-            user_agent.as_ref(),
-            min_tls,
-            per_millis,
-            num_request,
-            &mut certificates.into_iter(),
-        )
+        debug!("Using credentials from netrc for {}", request.url());
+        reqwest::RequestBuilder::from_parts(self.0.client.clone(), request)
+            .basic_auth(&credentials.login, Some(&credentials.password))
+            .build()
     }
 
     /// Return inner reqwest client.
@@ -156,6 +192,16 @@ impl Client {
         &self.0.client
     }
 
+    /// Return the maximum number of bytes allowed to be downloaded.
+    pub fn max_download_size(&self) -> Option<NonZeroU64> {
+        self.0.max_download_size
+    }
+
+    /// Return the maximum number of bytes per second a download may receive.
+    pub fn limit_rate(&self) -> Option<NonZeroU64> {
+        self.0.limit_rate
+    }
+
     /// Return `Err(_)` for fatal error tht cannot be retried.
     ///
     /// Return `Ok(ControlFlow::Continue(res))` for retryable error, `res`
@@ -250,6 +296,8 @@ impl Client {
     ) -> Result<reqwest::Response, Error> {
         debug!("Downloading from: '{}'", request.url());
 
+        let request = self.authenticate(request)?;
+
         self.send_request_inner(&request)
             .await
             .and_then(|response| {
@@ -320,6 +368,24 @@ impl Client {
             .map(|response| response.url().clone())
     }
 
+    /// Attempt to get the size of the file at `url` from the `Content-Length`
+    /// header using `Method::HEAD` or fallback to `Method::GET`.
+    ///
+    /// Return `None` if the server does not report it.
+    pub async fn get_content_length(&self, url: Url) -> Result<Option<u64>, Error> {
+        self.head_or_fallback_to_get(url, true)
+            .await
+            .map(|response| {
+                response
+                    .headers()
+                    .get(header::CONTENT_LENGTH)?
+                    .to_str()
+                    .ok()?
+                    .parse()
+                    .ok()
+            })
+    }
+
     /// Create `GET` request to `url` and return a stream of the response data.
     /// On status code other than 200, it will return an error.
     pub async fn get_stream(
@@ -29,4 +29,70 @@ impl Certificate {
             .map(Self)
             .map_err(Error::from)
     }
+
+    /// Create Certificates from every PEM encoded certificate of `pem`,
+    /// e.g. a bundle of trusted root certificates like `SSL_CERT_FILE`.
+    pub fn from_pem_bundle(pem: impl AsRef<[u8]>) -> Result<Vec<Self>, Error> {
+        let pem = pem.as_ref();
+        let blocks: Vec<_> = pem_blocks(pem).collect();
+
+        if blocks.is_empty() {
+            // Let the parser report what is wrong with it.
+            Self::from_pem(pem).map(|cert| vec![cert])
+        } else {
+            blocks.into_iter().map(Self::from_pem).collect()
+        }
+    }
+}
+
+/// Split `pem` into its certificates, from their `BEGIN` to their `END`
+/// line, skipping anything in between such as comments.
+fn pem_blocks(mut pem: &[u8]) -> impl Iterator<Item = &[u8]> {
+    const BEGIN: &[u8] = b"-----BEGIN CERTIFICATE-----";
+    const END: &[u8] = b"-----END CERTIFICATE-----";
+
+    let find = |haystack: &[u8], needle: &[u8]| {
+        haystack
+            .windows(needle.len())
+            .position(|window| window == needle)
+    };
+
+    std::iter::from_fn(move || {
+        let start = find(pem, BEGIN)?;
+        let end = start + find(&pem[start..], END)? + END.len();
+        let block = &pem[start..end];
+        pem = &pem[end..];
+        Some(block)
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::pem_blocks;
+
+    #[test]
+    fn test_pem_blocks() {
+        let bundle = b"# Root CA 1
+-----BEGIN CERTIFICATE-----
+MIIB1
+-----END CERTIFICATE-----
+
+# Root CA 2
+-----BEGIN CERTIFICATE-----
+MIIB2
+-----END CERTIFICATE-----
+-----BEGIN CERTIFICATE-----
+truncated";
+
+        let blocks: Vec<_> = pem_blocks(bundle).collect();
+        assert_eq!(
+            blocks,
+            [
+                &b"-----BEGIN CERTIFICATE-----\nMIIB1\n-----END CERTIFICATE-----"[..],
+                &b"-----BEGIN CERTIFICATE-----\nMIIB2\n-----END CERTIFICATE-----"[..],
+            ]
+        );
+
+        assert_eq!(pem_blocks(b"no certificate").count(), 0);
+    }
 }
@@ -0,0 +1,202 @@
+use std::{
+    num::{NonZeroU16, NonZeroU64, NonZeroUsize},
+    sync::Arc,
+    time::Duration,
+};
+
+use compact_str::CompactString;
+
+use super::{
+    Certificate, Client, DelayRequest, Error, Inner, Netrc, TLSVersion, POOL_IDLE_TIMEOUT,
+};
+
+#[cfg(feature = "__tls")]
+use super::DEFAULT_MIN_TLS;
+
+#[cfg(feature = "hickory-dns")]
+use super::TrustDnsResolver;
+
+/// Builder of a [`Client`], for the options not taken by [`Client::new`].
+#[derive(Clone, Debug)]
+#[must_use]
+#[cfg_attr(not(feature = "__tls"), allow(dead_code))]
+pub struct ClientBuilder {
+    user_agent: CompactString,
+    min_tls: Option<TLSVersion>,
+    per_millis: NonZeroU16,
+    num_request: NonZeroU64,
+    certificates: Vec<Certificate>,
+    danger_accept_invalid_certs: bool,
+    max_download_size: Option<NonZeroU64>,
+    http1_only: bool,
+    limit_rate: Option<NonZeroU64>,
+    connections: Option<NonZeroUsize>,
+    netrc: Option<Netrc>,
+}
+
+#[cfg_attr(not(feature = "__tls"), allow(unused_variables, unused_mut))]
+impl ClientBuilder {
+    /// * `per_millis` - The duration (in millisecond) for which at most
+    ///   `num_request` can be sent, itcould be increased if rate-limit
+    ///   happens.
+    /// * `num_request` - maximum number of requests to be processed for
+    ///   each `per` duration.
+    pub fn new(
+        user_agent: impl Into<CompactString>,
+        per_millis: NonZeroU16,
+        num_request: NonZeroU64,
+    ) -> Self {
+        Self {
+            user_agent: user_agent.into(),
+            min_tls: None,
+            per_millis,
+            num_request,
+            certificates: Vec::new(),
+            danger_accept_invalid_certs: false,
+            max_download_size: None,
+            http1_only: false,
+            limit_rate: None,
+            connections: None,
+            netrc: None,
+        }
+    }
+
+    /// Use at least `min_tls`, the Client created would use at least tls 1.2
+    /// anyway.
+    pub fn min_tls(mut self, min_tls: Option<TLSVersion>) -> Self {
+        self.min_tls = min_tls;
+        self
+    }
+
+    /// Trust `certificates` in addition to the system ones.
+    pub fn root_certificates(
+        mut self,
+        certificates: impl IntoIterator<Item = Certificate>,
+    ) -> Self {
+        self.certificates.extend(certificates);
+        self
+    }
+
+    /// Disable certificate validation entirely, which makes the connection
+    /// vulnerable to MITM attacks.
+    ///
+    /// This should only ever be used for testing against mirrors with
+    /// self-signed certificates.
+    pub fn danger_accept_invalid_certs(mut self, accept_invalid_certs: bool) -> Self {
+        self.danger_accept_invalid_certs = accept_invalid_certs;
+        self
+    }
+
+    /// Maximum number of bytes [`Download`] is allowed to download, exceeding
+    /// it fails the download.
+    ///
+    /// [`Download`]: crate::download::Download
+    pub fn max_download_size(mut self, max_download_size: Option<NonZeroU64>) -> Self {
+        self.max_download_size = max_download_size;
+        self
+    }
+
+    /// Only use HTTP/1.1, e.g. for proxies mishandling HTTP/2, which is
+    /// otherwise negotiated with the servers supporting it to send the
+    /// requests to a host over a single connection.
+    pub fn http1_only(mut self, http1_only: bool) -> Self {
+        self.http1_only = http1_only;
+        self
+    }
+
+    /// Throttle each [`Download`] to at most `limit_rate` bytes per second.
+    ///
+    /// [`Download`]: crate::download::Download
+    pub fn limit_rate(mut self, limit_rate: Option<NonZeroU64>) -> Self {
+        self.limit_rate = limit_rate;
+        self
+    }
+
+    /// Send at most `connections` requests to each host at a time, which
+    /// bounds the number of connections opened to it.
+    ///
+    /// A request sent by [`RequestBuilder::send`] counts until its response
+    /// is dropped, e.g. once its body has been downloaded.
+    ///
+    /// [`RequestBuilder::send`]: super::RequestBuilder::send
+    pub fn connections(mut self, connections: Option<NonZeroUsize>) -> Self {
+        self.connections = connections;
+        self
+    }
+
+    /// Authenticate the requests to the machines of `netrc`, unless they
+    /// already have an `Authorization` header.
+    pub fn netrc(mut self, netrc: Option<Netrc>) -> Self {
+        self.netrc = netrc;
+        self
+    }
+
+    pub fn build(self) -> Result<Client, Error> {
+        let mut builder = reqwest::ClientBuilder::new()
+            .user_agent(self.user_agent.as_str())
+            .https_only(true)
+            .tcp_nodelay(false)
+            // Keep the idle connections longer than reqwest's 90s default,
+            // to reuse them between the registry lookup, the probes and the
+            // downloads.
+            .pool_idle_timeout(POOL_IDLE_TIMEOUT);
+
+        builder = if self.http1_only {
+            builder.http1_only()
+        } else {
+            // Grow the flow control window of HTTP/2 with the bandwidth,
+            // the default one slows down large downloads.
+            builder.http2_adaptive_window(true)
+        };
+
+        #[cfg(feature = "hickory-dns")]
+        {
+            builder = builder.dns_resolver(Arc::new(TrustDnsResolver::default()));
+        }
+
+        #[cfg(feature = "__tls")]
+        {
+            let tls_ver = self
+                .min_tls
+                .map(|tls| tls.max(DEFAULT_MIN_TLS))
+                .unwrap_or(DEFAULT_MIN_TLS);
+
+            builder = builder.min_tls_version(tls_ver.into());
+
+            for certificate in &self.certificates {
+                builder = builder.add_root_certificate(certificate.0.clone());
+            }
+
+            if self.danger_accept_invalid_certs {
+                builder = builder.danger_accept_invalid_certs(true);
+            }
+        }
+
+        let client = builder.build()?;
+
+        Ok(self.build_from_reqwest(client))
+    }
+
+    /// Create the client from an already configured reqwest client, to share
+    /// its proxy, timeouts and connection pool.
+    ///
+    /// The user agent, [`ClientBuilder::min_tls`],
+    /// [`ClientBuilder::root_certificates`],
+    /// [`ClientBuilder::danger_accept_invalid_certs`] and
+    /// [`ClientBuilder::http1_only`] are ignored: they are up to `client`.
+    pub fn build_from_reqwest(self, client: reqwest::Client) -> Client {
+        Client(Arc::new(Inner {
+            client: client.clone(),
+            service: DelayRequest::new(
+                self.num_request,
+                Duration::from_millis(self.per_millis.get() as u64),
+                client,
+            ),
+            max_download_size: self.max_download_size,
+            limit_rate: self.limit_rate,
+            connections: self.connections,
+            host_permits: Default::default(),
+            netrc: self.netrc,
+        }))
+    }
+}
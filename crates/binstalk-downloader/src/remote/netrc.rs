@@ -0,0 +1,173 @@
+use std::{fmt, fs, io, path::Path};
+
+/// Credentials of a machine in a `.netrc` file.
+#[derive(Clone, Eq, PartialEq)]
+pub struct NetrcCredentials {
+    pub login: String,
+    pub password: String,
+}
+
+impl fmt::Debug for NetrcCredentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NetrcCredentials")
+            .field("login", &self.login)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Credentials parsed from a `.netrc` file, used for the requests to the
+/// machines listed in it which do not already have an `Authorization`.
+///
+/// Only `machine` entries are used: the `default` entry would send its
+/// credentials to every host, so it is ignored like `account` and `macdef`.
+#[derive(Clone, Debug, Default)]
+pub struct Netrc {
+    machines: Vec<(String, NetrcCredentials)>,
+}
+
+impl Netrc {
+    /// Return `Ok(None)` if the file does not exist.
+    pub fn load_from_path(path: &Path) -> io::Result<Option<Self>> {
+        match fs::read_to_string(path) {
+            Ok(content) => Ok(Some(Self::parse(&content))),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    pub fn parse(content: &str) -> Self {
+        let mut netrc = Self::default();
+        // Entry being parsed, its machine is `None` for `default`.
+        let mut entry: Option<Entry> = None;
+
+        let mut lines = content.lines();
+        while let Some(line) = lines.next() {
+            let mut tokens = line.split_whitespace();
+
+            while let Some(token) = tokens.next() {
+                match token {
+                    "machine" => {
+                        netrc.add(entry.take());
+                        entry = Some(Entry {
+                            machine: tokens.next().map(String::from),
+                            ..Default::default()
+                        });
+                    }
+                    "default" => {
+                        netrc.add(entry.take());
+                        entry = Some(Entry::default());
+                    }
+                    "login" => {
+                        if let Some(entry) = &mut entry {
+                            entry.login = tokens.next().map(String::from);
+                        }
+                    }
+                    "password" => {
+                        if let Some(entry) = &mut entry {
+                            entry.password = tokens.next().map(String::from);
+                        }
+                    }
+                    "account" => {
+                        tokens.next();
+                    }
+                    "macdef" => {
+                        // The macro lasts until an empty line
+                        for line in lines.by_ref() {
+                            if line.trim().is_empty() {
+                                break;
+                            }
+                        }
+                        break;
+                    }
+                    // Comments, which are not standard but commonly supported
+                    token if token.starts_with('#') => break,
+                    _ => (),
+                }
+            }
+        }
+        netrc.add(entry);
+
+        netrc
+    }
+
+    fn add(&mut self, entry: Option<Entry>) {
+        if let Some(Entry {
+            machine: Some(machine),
+            login: Some(login),
+            password,
+        }) = entry
+        {
+            self.machines.push((
+                machine,
+                NetrcCredentials {
+                    login,
+                    password: password.unwrap_or_default(),
+                },
+            ));
+        }
+    }
+
+    /// Return the credentials of the first entry for `host`.
+    pub fn credentials(&self, host: &str) -> Option<&NetrcCredentials> {
+        self.machines
+            .iter()
+            .find(|(machine, _)| machine.eq_ignore_ascii_case(host))
+            .map(|(_, credentials)| credentials)
+    }
+}
+
+#[derive(Default)]
+struct Entry {
+    machine: Option<String>,
+    login: Option<String>,
+    password: Option<String>,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let netrc = Netrc::parse(
+            "# Mirror of the releases
+machine releases.example.com login ci password s3cret
+
+machine api.example.com
+    login bot
+    account ignored
+    password hunter2
+
+macdef init
+machine evil.example.com login evil password evil
+
+default login anonymous password guest
+machine example.com login first password first
+machine example.com login second password second
+",
+        );
+
+        let credentials = |login: &str, password: &str| NetrcCredentials {
+            login: login.into(),
+            password: password.into(),
+        };
+
+        assert_eq!(
+            netrc.credentials("releases.example.com"),
+            Some(&credentials("ci", "s3cret"))
+        );
+        assert_eq!(
+            netrc.credentials("API.example.com"),
+            Some(&credentials("bot", "hunter2"))
+        );
+        assert_eq!(
+            netrc.credentials("example.com"),
+            Some(&credentials("first", "first"))
+        );
+
+        // Inside of a macro
+        assert_eq!(netrc.credentials("evil.example.com"), None);
+        // Only covered by default
+        assert_eq!(netrc.credentials("github.com"), None);
+    }
+}
@@ -3,6 +3,7 @@ use std::fmt;
 use bytes::Bytes;
 use futures_util::{Stream, StreamExt};
 use reqwest::Method;
+use tokio::sync::OwnedSemaphorePermit;
 
 use super::{header, Client, Error, HttpError, StatusCode, Url};
 
@@ -42,9 +43,11 @@ impl RequestBuilder {
     pub async fn send(self, error_for_status: bool) -> Result<Response, Error> {
         let request = self.inner.build()?;
         let method = request.method().clone();
+        let permit = self.client.acquire_connection(request.url()).await;
         Ok(Response {
             inner: self.client.send_request(request, error_for_status).await?,
             method,
+            permit,
         })
     }
 }
@@ -53,6 +56,8 @@ impl RequestBuilder {
 pub struct Response {
     inner: reqwest::Response,
     method: Method,
+    /// Held until the response is dropped, to limit the connections.
+    permit: Option<OwnedSemaphorePermit>,
 }
 
 impl Response {
@@ -63,8 +68,11 @@ impl Response {
     pub fn bytes_stream(self) -> impl Stream<Item = Result<Bytes, Error>> {
         let url = Box::new(self.inner.url().clone());
         let method = self.method;
+        let permit = self.permit;
 
         self.inner.bytes_stream().map(move |res| {
+            // Keep the connection counted until the body is downloaded.
+            let _ = &permit;
             res.map_err(|err| {
                 Error::Http(Box::new(HttpError {
                     method: method.clone(),
@@ -105,6 +113,11 @@ impl Response {
         }
     }
 
+    /// Return the `Content-Length` of the response body, if known.
+    pub fn content_length(&self) -> Option<u64> {
+        self.inner.content_length()
+    }
+
     pub fn headers(&self) -> &header::HeaderMap {
         self.inner.headers()
     }
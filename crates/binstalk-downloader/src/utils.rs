@@ -7,6 +7,15 @@ use bytes::{Buf, Bytes};
 use futures_util::{FutureExt, Stream, StreamExt};
 use tokio::{sync::mpsc, task};
 
+/// Maximum number of chunks buffered between the download and the blocking
+/// task extracting it.
+///
+/// Once full, the download is paused until the extraction catches up, so peak
+/// memory usage is bounded regardless of the size of the archive.
+pub(super) const MAX_PENDING_CHUNKS: usize = 5;
+
+/// Stream `stream` into `f` running in a blocking task, through a bounded
+/// channel of [`MAX_PENDING_CHUNKS`].
 pub(super) fn extract_with_blocking_task<E, StreamError, S, F, T>(
     stream: S,
     f: F,
@@ -83,8 +92,7 @@ where
         }
     }
 
-    // Use channel size = 5 to minimize the waiting time in the extraction task
-    let (tx, rx) = mpsc::channel(5);
+    let (tx, rx) = mpsc::channel(MAX_PENDING_CHUNKS);
 
     let task = asyncify(move || f(rx));
 
@@ -170,3 +178,54 @@ impl BufRead for StreamReadable {
         self.bytes.advance(amt);
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering::Relaxed},
+            Arc,
+        },
+        thread::sleep,
+        time::Duration,
+    };
+
+    use futures_util::stream;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_extract_with_blocking_task_bounded_memory() {
+        const CHUNKS: usize = 64;
+        const CHUNK_SIZE: usize = 1024 * 1024;
+
+        // Bytes downloaded but not yet consumed by the extraction.
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let stream = stream::iter(0..CHUNKS).map({
+            let in_flight = in_flight.clone();
+            move |_| {
+                in_flight.fetch_add(CHUNK_SIZE, Relaxed);
+                Ok::<_, io::Error>(Bytes::from(vec![0; CHUNK_SIZE]))
+            }
+        });
+
+        let max_in_flight =
+            extract_with_blocking_task::<io::Error, _, _, _, _>(stream, move |mut rx| {
+                let mut max_in_flight = 0;
+                while let Some(bytes) = rx.blocking_recv() {
+                    // A slow extraction, to let the download run ahead of it.
+                    sleep(Duration::from_millis(5));
+
+                    max_in_flight = max_in_flight.max(in_flight.load(Relaxed));
+                    in_flight.fetch_sub(bytes.len(), Relaxed);
+                }
+                Ok(max_in_flight)
+            })
+            .await
+            .unwrap();
+
+        // Pending chunks, plus the one received and the one blocked on
+        // sending, out of the 64 MiB downloaded.
+        assert!(max_in_flight <= (MAX_PENDING_CHUNKS + 2) * CHUNK_SIZE);
+    }
+}
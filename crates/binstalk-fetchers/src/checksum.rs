@@ -0,0 +1,225 @@
+use binstalk_downloader::download::DataVerifier;
+use binstalk_types::cargo_toml_binstall::ChecksumAlgorithm;
+use bytes::Bytes;
+use sha2::{Digest, Sha256, Sha512};
+use tracing::{debug, error};
+
+use crate::FetchError;
+
+enum Hasher {
+    Sha256(Sha256),
+    Sha512(Sha512),
+    Blake3(Box<blake3::Hasher>),
+}
+
+impl Hasher {
+    fn new(algorithm: ChecksumAlgorithm) -> Result<Self, FetchError> {
+        match algorithm {
+            ChecksumAlgorithm::Sha256 => Ok(Self::Sha256(Sha256::new())),
+            ChecksumAlgorithm::Sha512 => Ok(Self::Sha512(Sha512::new())),
+            ChecksumAlgorithm::Blake3 => Ok(Self::Blake3(Box::default())),
+            algorithm => Err(FetchError::UnsupportedChecksumAlgorithm(algorithm)),
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        match self {
+            Self::Sha256(hasher) => hasher.update(data),
+            Self::Sha512(hasher) => hasher.update(data),
+            Self::Blake3(hasher) => {
+                hasher.update(data);
+            }
+        }
+    }
+
+    /// Return the digest of the data received so far, encoded in base16.
+    fn digest(&self) -> String {
+        match self {
+            Self::Sha256(hasher) => format!("{:x}", hasher.clone().finalize()),
+            Self::Sha512(hasher) => format!("{:x}", hasher.clone().finalize()),
+            Self::Blake3(hasher) => hasher.finalize().to_hex().to_string(),
+        }
+    }
+}
+
+/// Expected checksum of a package.
+pub(crate) struct Checksum {
+    algorithm: ChecksumAlgorithm,
+    /// Digest encoded in base16.
+    digest: String,
+}
+
+impl Checksum {
+    ///  * `digest` - digest of the package, encoded in base16.
+    pub(crate) fn new(algorithm: ChecksumAlgorithm, digest: &str) -> Self {
+        Self {
+            algorithm,
+            digest: digest.to_ascii_lowercase(),
+        }
+    }
+
+    ///  * `checksum_file` - content of the checksum file, either only the
+    ///    digest, or lines of `<digest> <filename>` as generated by
+    ///    `sha256sum` and friends.
+    ///  * `filename` - name of the file downloaded, to find its digest in
+    ///    `checksum_file`.
+    pub(crate) fn parse(
+        algorithm: ChecksumAlgorithm,
+        checksum_file: &[u8],
+        filename: &str,
+    ) -> Result<Self, FetchError> {
+        let checksum_file = std::str::from_utf8(checksum_file).map_err(|err| {
+            error!("Checksum file is not UTF-8! {err}");
+            FetchError::InvalidChecksum
+        })?;
+
+        let digest = find_digest(checksum_file, filename).ok_or_else(|| {
+            error!("Checksum file does not contain a digest for {filename}");
+            FetchError::InvalidChecksum
+        })?;
+
+        Ok(Self::new(algorithm, digest))
+    }
+}
+
+/// Wraps another [`DataVerifier`] and computes the checksum of the data
+/// at the same time, if there is any checksum to verify.
+pub(crate) struct ChecksumDataVerifier<'a> {
+    inner: &'a mut dyn DataVerifier,
+    checksum: Option<(Checksum, Hasher)>,
+}
+
+impl<'a> ChecksumDataVerifier<'a> {
+    pub(crate) fn new(
+        inner: &'a mut dyn DataVerifier,
+        checksum: Option<Checksum>,
+    ) -> Result<Self, FetchError> {
+        let checksum = match checksum {
+            Some(checksum) => {
+                let hasher = Hasher::new(checksum.algorithm)?;
+                Some((checksum, hasher))
+            }
+            None => None,
+        };
+
+        Ok(Self { inner, checksum })
+    }
+
+    /// Return `Ok` if the data received matches the checksum, or if
+    /// there is no checksum.
+    pub(crate) fn verify(&self) -> Result<(), FetchError> {
+        let Some((checksum, hasher)) = &self.checksum else {
+            return Ok(());
+        };
+        let algorithm = checksum.algorithm.as_str();
+        let digest = hasher.digest();
+
+        if digest == checksum.digest {
+            debug!("{algorithm} checksum matches: {digest}");
+            Ok(())
+        } else {
+            error!(
+                "{algorithm} checksum mismatch: expected {}, got {digest}",
+                checksum.digest
+            );
+            Err(FetchError::InvalidChecksum)
+        }
+    }
+}
+
+impl DataVerifier for ChecksumDataVerifier<'_> {
+    fn update(&mut self, data: &Bytes) {
+        if let Some((_, hasher)) = &mut self.checksum {
+            hasher.update(data);
+        }
+        self.inner.update(data);
+    }
+
+    fn validate(&mut self) -> bool {
+        self.inner.validate()
+    }
+}
+
+/// Find the digest for `filename` in `checksum_file`.
+fn find_digest<'a>(checksum_file: &'a str, filename: &str) -> Option<&'a str> {
+    let checksum_file = checksum_file.trim();
+
+    // A checksum file with only the digest
+    if !checksum_file.is_empty() && !checksum_file.contains(char::is_whitespace) {
+        return Some(checksum_file);
+    }
+
+    checksum_file.lines().find_map(|line| {
+        let (digest, name) = line.trim().split_once(char::is_whitespace)?;
+        // `*` marks binary mode in the output of `sha256sum`
+        let name = name.trim_start().trim_start_matches('*');
+        let name = name.rsplit('/').next().unwrap_or(name);
+        (name == filename).then_some(digest)
+    })
+}
+
+/// Wraps another [`DataVerifier`] and computes the sha256 digest
+/// of the data at the same time.
+pub(crate) struct Sha256DataVerifier<'a> {
+    inner: &'a mut dyn DataVerifier,
+    hasher: Sha256,
+}
+
+impl<'a> Sha256DataVerifier<'a> {
+    pub(crate) fn new(inner: &'a mut dyn DataVerifier) -> Self {
+        Self {
+            inner,
+            hasher: Sha256::new(),
+        }
+    }
+
+    /// Return sha256 digest of the data received so far, encoded in base16.
+    pub(crate) fn digest(&self) -> String {
+        format!("{:x}", self.hasher.clone().finalize())
+    }
+}
+
+impl DataVerifier for Sha256DataVerifier<'_> {
+    fn update(&mut self, data: &Bytes) {
+        self.hasher.update(data);
+        self.inner.update(data);
+    }
+
+    fn validate(&mut self) -> bool {
+        self.inner.validate()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_find_digest() {
+        assert_eq!(find_digest("abcd\n", "a.tgz"), Some("abcd"));
+        assert_eq!(find_digest("abcd  a.tgz\n", "a.tgz"), Some("abcd"));
+        assert_eq!(
+            find_digest("1234 *dist/b.tgz\nabcd *dist/a.tgz\n", "a.tgz"),
+            Some("abcd")
+        );
+        assert_eq!(find_digest("1234  b.tgz\n", "a.tgz"), None);
+        assert_eq!(find_digest("", "a.tgz"), None);
+    }
+
+    #[test]
+    fn test_digest() {
+        let mut hasher = Hasher::new(ChecksumAlgorithm::Sha256).unwrap();
+        hasher.update(b"abc");
+        assert_eq!(
+            hasher.digest(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+
+        let mut hasher = Hasher::new(ChecksumAlgorithm::Blake3).unwrap();
+        hasher.update(b"abc");
+        assert_eq!(
+            hasher.digest(),
+            "6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85"
+        );
+    }
+}
@@ -1,27 +1,56 @@
-use std::sync::{
-    atomic::{AtomicBool, Ordering::Relaxed},
-    Once,
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    process::Stdio,
+    sync::{
+        atomic::{AtomicBool, Ordering::Relaxed},
+        Once,
+    },
+    time::{Duration, Instant, SystemTime},
 };
 
-use binstalk_downloader::gh_api_client::{GhReleaseArtifact, HasReleaseArtifact};
+use binstalk_downloader::gh_api_client::{check_rate_limit, GhReleaseArtifact, HasReleaseArtifact};
 pub(super) use binstalk_downloader::{
-    download::{Download, ExtractedFiles},
+    download::{Download, DownloadCopy, DownloadError, ExtractedFiles},
     gh_api_client::GhApiClient,
     remote::{Client, Url},
 };
 pub(super) use binstalk_types::cargo_toml_binstall::{PkgFmt, PkgMeta};
 pub(super) use compact_str::CompactString;
+use tokio::process::Command;
 pub(super) use tokio::task::JoinHandle;
 pub(super) use tracing::{debug, instrument, warn};
 
 use crate::FetchError;
 
+/// Time waited for the rate limit to be reset if GitHub does not report it.
+const DEFAULT_RATE_LIMIT_WAIT: Duration = Duration::from_secs(60);
+
+/// Longest time waited for the rate limit to be reset, GitHub resets it
+/// every hour.
+const MAX_RATE_LIMIT_WAIT: Duration = Duration::from_secs(60 * 60);
+
+/// Number of times the rate limit is waited for while checking a url
+/// before giving up.
+const MAX_RATE_LIMIT_WAITS: u32 = 3;
+
+/// Signatures and checksums are tiny, so their downloads get much tighter
+/// limits than the package itself to not hold up the installation.
+pub(super) const SIDECAR_MAX_SIZE: u64 = 64 * 1024;
+pub(super) const SIDECAR_TIMEOUT: Duration = Duration::from_secs(30);
+
 /// This function returns a future where its size should be at most size of
 /// 2-4 pointers.
+///
+/// A 403 for the exhausted GitHub rate limit is not taken as the package
+/// being absent, it fails with [`FetchError::GhRateLimit`] unless
+/// `wait_rate_limit` is set, in which case it is retried once reset, up to
+/// [`MAX_RATE_LIMIT_WAITS`] times.
 pub(super) async fn does_url_exist(
     client: Client,
     gh_api_client: GhApiClient,
     url: &Url,
+    wait_rate_limit: bool,
 ) -> Result<bool, FetchError> {
     static GH_API_CLIENT_FAILED: AtomicBool = AtomicBool::new(false);
     static WARN_RATE_LIMIT_ONCE: Once = Once::new();
@@ -29,32 +58,285 @@ pub(super) async fn does_url_exist(
 
     debug!("Checking for package at: '{url}'");
 
+    let mut waits = 0;
+
     if !GH_API_CLIENT_FAILED.load(Relaxed) {
         if let Some(artifact) = GhReleaseArtifact::try_extract_from_url(url) {
             debug!("Using GitHub API to check for existence of artifact, which will also cache the API response");
 
-            // The future returned has the same size as a pointer
-            match gh_api_client.has_release_artifact(artifact).await? {
-                HasReleaseArtifact::Yes => return Ok(true),
-                HasReleaseArtifact::No | HasReleaseArtifact::NoSuchRelease => return Ok(false),
+            loop {
+                // The future returned has the same size as a pointer
+                match gh_api_client.has_release_artifact(artifact.clone()).await? {
+                    HasReleaseArtifact::Yes => return Ok(true),
+                    HasReleaseArtifact::No | HasReleaseArtifact::NoSuchRelease => return Ok(false),
 
-                HasReleaseArtifact::RateLimit { retry_after } => {
-                    WARN_RATE_LIMIT_ONCE.call_once(|| {
-                        warn!("Your GitHub API token (if any) has reached its rate limit and cannot be used again until {retry_after:?}, so we will fallback to HEAD/GET on the url.");
-                        warn!("If you did not supply a github token, consider doing so: GitHub limits unauthorized users to 60 requests per hour per origin IP address.");
-                    });
-                }
-                HasReleaseArtifact::Unauthorized => {
-                    WARN_UNAUTHORIZED_ONCE.call_once(|| {
-                        warn!("GitHub API somehow requires a token for the API access, so we will fallback to HEAD/GET on the url.");
-                        warn!("Please consider supplying a token to cargo-binstall to speedup resolution.");
-                    });
+                    HasReleaseArtifact::RateLimit { retry_after } => {
+                        let duration = retry_after.saturating_duration_since(Instant::now());
+                        if wait_rate_limit && wait_for_rate_limit(url, duration, &mut waits).await {
+                            continue;
+                        }
+
+                        WARN_RATE_LIMIT_ONCE.call_once(|| {
+                            warn!("Your GitHub API token (if any) has reached its rate limit and cannot be used again until {retry_after:?}, so we will fallback to HEAD/GET on the url.");
+                            warn!("If you did not supply a github token, consider doing so: GitHub limits unauthorized users to 60 requests per hour per origin IP address.");
+                        });
+                    }
+                    HasReleaseArtifact::Unauthorized => {
+                        WARN_UNAUTHORIZED_ONCE.call_once(|| {
+                            warn!("GitHub API somehow requires a token for the API access, so we will fallback to HEAD/GET on the url.");
+                            warn!("Please consider supplying a token to cargo-binstall to speedup resolution.");
+                        });
+                    }
                 }
+
+                break;
             }
 
             GH_API_CLIENT_FAILED.store(true, Relaxed);
         }
     }
 
-    Ok(Box::pin(client.remote_gettable(url.clone())).await?)
+    loop {
+        let response = Box::pin(client.get(url.clone()).send(false)).await?;
+
+        let Some(reset) = check_rate_limit(response.status(), response.headers()) else {
+            return Ok(response.status().is_success());
+        };
+
+        let duration = reset
+            .and_then(|reset| reset.duration_since(SystemTime::now()).ok())
+            .unwrap_or(DEFAULT_RATE_LIMIT_WAIT);
+        if !wait_rate_limit || !wait_for_rate_limit(url, duration, &mut waits).await {
+            return Err(FetchError::GhRateLimit {
+                url: url.as_str().into(),
+                reset,
+            });
+        }
+    }
+}
+
+/// Wait for `duration`, at most [`MAX_RATE_LIMIT_WAIT`], for the GitHub rate
+/// limit to be reset, returns `false` without waiting if it has already been
+/// waited for [`MAX_RATE_LIMIT_WAITS`] times.
+async fn wait_for_rate_limit(url: &Url, duration: Duration, waits: &mut u32) -> bool {
+    if *waits >= MAX_RATE_LIMIT_WAITS {
+        return false;
+    }
+    *waits += 1;
+
+    let duration = duration.min(MAX_RATE_LIMIT_WAIT);
+    warn!("GitHub rate limit exceeded while checking {url}, waiting {duration:?} for it to reset");
+    tokio::time::sleep(duration).await;
+
+    true
+}
+
+/// Like [`does_url_exist`] for urls requiring the `Authorization` header
+/// `auth`, e.g. in private repositories.
+pub(super) async fn does_authed_url_exist(
+    client: Client,
+    url: &Url,
+    auth: &str,
+) -> Result<bool, FetchError> {
+    debug!("Checking for package at: '{url}' with authentication");
+
+    let request = client.get(url.clone()).header("Authorization", auth);
+    Ok(Box::pin(request.send(false)).await?.status().is_success())
+}
+
+/// Like [`does_url_exist`] for urls on a mirror, but fail on server errors
+/// so that the next mirror can be tried instead, a mirror not having the
+/// package is still reported as `Ok(false)`.
+pub(super) async fn does_mirror_url_exist(client: Client, url: &Url) -> Result<bool, FetchError> {
+    debug!("Checking for package at mirror: '{url}'");
+
+    let status = Box::pin(client.get(url.clone()).send(false))
+        .await?
+        .status();
+    if status.is_server_error() {
+        Err(FetchError::MirrorUnavailable(status))
+    } else {
+        Ok(status.is_success())
+    }
+}
+
+/// Keep a copy of the package downloaded by `download` from `url` in
+/// `keep_download_dir`, under the file name of the package, if specified.
+///
+/// The copy is written to a temporary file and only kept once
+/// [`persist_download`] is called, after the package is verified.
+pub(super) fn keep_download<'a>(
+    download: Download<'a>,
+    url: &Url,
+    keep_download_dir: Option<&Path>,
+) -> (Download<'a>, Option<DownloadCopy>) {
+    let file_name = url
+        .path_segments()
+        .and_then(|mut segments| segments.next_back())
+        .filter(|file_name| !file_name.is_empty())
+        .unwrap_or("package");
+
+    keep_download_as(download, file_name, keep_download_dir)
+}
+
+/// Like [`keep_download`], for packages whose url does not end with
+/// their file name.
+pub(super) fn keep_download_as<'a>(
+    download: Download<'a>,
+    file_name: &str,
+    keep_download_dir: Option<&Path>,
+) -> (Download<'a>, Option<DownloadCopy>) {
+    match keep_download_dir {
+        Some(keep_download_dir) => {
+            let (download, copy) = download.with_copy_to(keep_download_dir.join(file_name));
+            (download, Some(copy))
+        }
+        None => (download, None),
+    }
+}
+
+/// Keep the copy of a verified package made by [`keep_download`], if any.
+pub(super) async fn persist_download(copy: Option<DownloadCopy>) -> Result<(), FetchError> {
+    if let Some(copy) = copy {
+        copy.persist().await?;
+    }
+    Ok(())
+}
+
+/// Return the `extract_command` to use for a package in `pkg_fmt`.
+///
+/// The formats binstall supports are always extracted natively, so the
+/// command only handles the packages that would otherwise be installed as
+/// is, i.e. archives in other formats declared as `bin`.
+pub(super) fn extract_command_for(
+    pkg_fmt: PkgFmt,
+    extract_command: Option<&Path>,
+) -> Option<&Path> {
+    extract_command.filter(|_| pkg_fmt == PkgFmt::Bin)
+}
+
+/// Extract the package downloaded by `download` to `dst`.
+///
+/// If [`extract_command_for`] returns a command, the package is downloaded
+/// as is next to `dst` and extracted by [`run_extract_command`] instead.
+pub(super) async fn extract_download(
+    download: Download<'_>,
+    pkg_fmt: PkgFmt,
+    dst: &Path,
+    members: Option<&[PathBuf]>,
+    extract_command: Option<&Path>,
+) -> Result<ExtractedFiles, FetchError> {
+    let Some(extract_command) = extract_command_for(pkg_fmt, extract_command) else {
+        return Ok(download.and_extract_members(pkg_fmt, dst, members).await?);
+    };
+
+    let mut package = dst.as_os_str().to_owned();
+    package.push(".package");
+    let package = PathBuf::from(package);
+
+    download.and_extract(PkgFmt::Bin, &package).await?;
+    run_extract_command(extract_command, &package, dst).await
+}
+
+/// Extract the package at `src` to the directory `dst` by running
+/// `extract_command src dst`, every file it extracts is collected.
+pub(super) async fn run_extract_command(
+    extract_command: &Path,
+    src: &Path,
+    dst: &Path,
+) -> Result<ExtractedFiles, FetchError> {
+    fs::create_dir_all(dst).map_err(DownloadError::from)?;
+
+    debug!(
+        "Running '{}' to extract '{}' to '{}'",
+        extract_command.display(),
+        src.display(),
+        dst.display()
+    );
+    let status = Command::new(extract_command)
+        .arg(src)
+        .arg(dst)
+        .stdin(Stdio::null())
+        .kill_on_drop(true)
+        .status()
+        .await
+        .map_err(DownloadError::from)?;
+    if !status.success() {
+        return Err(FetchError::ExtractCommand {
+            command: extract_command.to_owned(),
+            status,
+        });
+    }
+
+    let dst = dst.to_owned();
+    let extracted_files = tokio::task::spawn_blocking(move || ExtractedFiles::from_dir(&dst))
+        .await
+        .map_err(io::Error::from)
+        .and_then(|res| res)
+        .map_err(DownloadError::from)?;
+
+    Ok(extracted_files)
+}
+
+#[cfg(all(test, unix))]
+mod test {
+    use std::os::unix::fs::PermissionsExt;
+
+    use super::*;
+
+    fn extract_command(dir: &Path, script: &str) -> PathBuf {
+        let path = dir.join("extract");
+        fs::write(&path, format!("#!/bin/sh\n{script}\n")).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_extract_command_for() {
+        let command = Path::new("extract");
+
+        assert_eq!(
+            extract_command_for(PkgFmt::Bin, Some(command)),
+            Some(command)
+        );
+        for pkg_fmt in [PkgFmt::Tgz, PkgFmt::Zip, PkgFmt::Lz4] {
+            assert_eq!(extract_command_for(pkg_fmt, Some(command)), None);
+        }
+        assert_eq!(extract_command_for(PkgFmt::Bin, None), None);
+    }
+
+    #[tokio::test]
+    async fn test_run_extract_command() {
+        let dir = tempfile::tempdir().unwrap();
+        let command = extract_command(
+            dir.path(),
+            r#"mkdir "$2/bin" && cp "$1" "$2/bin/tool" && ln -s /bin/sh "$2/sh""#,
+        );
+        let src = dir.path().join("package");
+        fs::write(&src, "package").unwrap();
+        let dst = dir.path().join("dst");
+
+        let files = run_extract_command(&command, &src, &dst).await.unwrap();
+
+        assert_eq!(fs::read(dst.join("bin/tool")).unwrap(), b"package");
+        assert!(files.has_file(Path::new("bin/tool")));
+        assert!(files.get_entry(Path::new("sh")).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_run_extract_command_failure() {
+        let dir = tempfile::tempdir().unwrap();
+        let command = extract_command(dir.path(), "exit 3");
+        let src = dir.path().join("package");
+        fs::write(&src, "package").unwrap();
+
+        let err = run_extract_command(&command, &src, &dir.path().join("dst"))
+            .await
+            .unwrap_err();
+
+        assert!(
+            matches!(&err, FetchError::ExtractCommand { command: c, status } if *c == command && status.code() == Some(3)),
+            "{err:?}"
+        );
+    }
 }
@@ -0,0 +1,464 @@
+//! Fetcher delegating the lookup of packages to an external program.
+//!
+//! The program is run once per target, with a JSON request on its stdin:
+//!
+//! ```json
+//! { "protocol": 1, "crate": "cargo-binstall", "version": "1.4.4", "target": "x86_64-unknown-linux-gnu" }
+//! ```
+//!
+//! It must exit with status 0 and either print nothing to its stdout if it has
+//! no package for this target, or a JSON response with exactly one of `url`
+//! (download url of the package) or `path` (path to an already downloaded
+//! package):
+//!
+//! ```json
+//! { "url": "https://example.com/cargo-binstall-x86_64-unknown-linux-gnu.tgz", "pkg-fmt": "tgz" }
+//! ```
+//!
+//! `pkg-fmt` is optional, it is otherwise guessed from the url or path, then
+//! taken from the crate's metadata.
+//!
+//! Any other exit status or malformed response is a fatal error. The stderr
+//! of the program is inherited, so it can report progress and errors there.
+//!
+//! The protocol has no way to provide a signature, so the packages of crates
+//! declaring `signing` are rejected unless signatures are ignored.
+
+use std::{
+    path::{Path, PathBuf},
+    process::Stdio,
+    sync::Arc,
+};
+
+use binstalk_downloader::download::extract_local_members;
+use serde::{Deserialize, Serialize};
+use tokio::{io::AsyncWriteExt, process::Command, sync::OnceCell};
+use tracing::trace;
+
+use crate::{
+    checksum::Sha256DataVerifier, common::*, Data, FetchError, SignaturePolicy, TargetDataErased,
+};
+
+/// Version of the protocol, sent in every request.
+const PROTOCOL_VERSION: u32 = 1;
+
+#[derive(Serialize)]
+struct Request<'a> {
+    protocol: u32,
+    #[serde(rename = "crate")]
+    name: &'a str,
+    version: &'a str,
+    target: &'a str,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct Response {
+    url: Option<Url>,
+    path: Option<PathBuf>,
+    pkg_fmt: Option<PkgFmt>,
+}
+
+#[derive(Debug)]
+enum PackageLocation {
+    Url(Url),
+    Path(PathBuf),
+}
+
+#[derive(Debug)]
+struct Resolved {
+    location: PackageLocation,
+    pkg_fmt: PkgFmt,
+}
+
+pub struct ExternalFetcher {
+    client: Client,
+    data: Arc<Data>,
+    target_data: Arc<TargetDataErased>,
+    signature_policy: SignaturePolicy,
+    resolution: OnceCell<Option<Resolved>>,
+    package_sha256: OnceCell<CompactString>,
+}
+
+impl ExternalFetcher {
+    fn program(&self) -> Result<&Path, FetchError> {
+        self.data
+            .external_fetcher
+            .as_deref()
+            .ok_or_else(|| FetchError::ExternalFetcher("no external fetcher is configured".into()))
+    }
+
+    async fn run(&self) -> Result<Option<Resolved>, FetchError> {
+        let program = self.program()?;
+        let failed = |reason: String| {
+            FetchError::ExternalFetcher(format!("{}: {reason}", program.display()).into())
+        };
+
+        let request = serde_json::to_vec(&Request {
+            protocol: PROTOCOL_VERSION,
+            name: &self.data.name,
+            version: &self.data.version,
+            target: &self.target_data.target,
+        })
+        .expect("Serializing the request must not fail");
+
+        debug!("Running external fetcher {}", program.display());
+        let mut child = Command::new(program)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|err| failed(format!("failed to spawn: {err}")))?;
+
+        let mut stdin = child.stdin.take().expect("stdin is piped");
+        stdin
+            .write_all(&request)
+            .await
+            .map_err(|err| failed(format!("failed to write the request: {err}")))?;
+        // Close stdin so that the program sees the end of the request.
+        drop(stdin);
+
+        let output = child
+            .wait_with_output()
+            .await
+            .map_err(|err| failed(format!("failed to wait for it: {err}")))?;
+        if !output.status.success() {
+            return Err(failed(format!("exited with {}", output.status)));
+        }
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        trace!(%stdout, "got external fetcher response");
+        if stdout.trim().is_empty() {
+            return Ok(None);
+        }
+
+        let response: Response = serde_json::from_str(&stdout)
+            .map_err(|err| failed(format!("invalid response: {err}")))?;
+
+        let location = match (response.url, response.path) {
+            (Some(url), None) => PackageLocation::Url(url),
+            (None, Some(path)) => PackageLocation::Path(path),
+            _ => {
+                return Err(failed(
+                    "response must contain exactly one of `url` or `path`".into(),
+                ))
+            }
+        };
+
+        let pkg_fmt = response
+            .pkg_fmt
+            .or_else(|| match &location {
+                PackageLocation::Url(url) => PkgFmt::guess_pkg_format(url.path()),
+                PackageLocation::Path(path) => PkgFmt::guess_pkg_format(&path.to_string_lossy()),
+            })
+            .or(self.target_data.meta.pkg_fmt)
+            .ok_or_else(|| failed("cannot determine the format of the package".into()))?;
+
+        Ok(Some(Resolved { location, pkg_fmt }))
+    }
+
+    fn resolved(&self) -> &Resolved {
+        self.resolution
+            .get()
+            .and_then(Option::as_ref)
+            .expect("find() is called first")
+    }
+}
+
+#[async_trait::async_trait]
+impl super::Fetcher for ExternalFetcher {
+    fn new(
+        client: Client,
+        _gh_api_client: GhApiClient,
+        data: Arc<Data>,
+        target_data: Arc<TargetDataErased>,
+        signature_policy: SignaturePolicy,
+    ) -> Arc<dyn super::Fetcher> {
+        Arc::new(Self {
+            client,
+            data,
+            target_data,
+            signature_policy,
+            resolution: OnceCell::new(),
+            package_sha256: OnceCell::new(),
+        })
+    }
+
+    fn find(self: Arc<Self>) -> JoinHandle<Result<bool, FetchError>> {
+        tokio::spawn(async move {
+            match (self.signature_policy, &self.target_data.meta.signing) {
+                (SignaturePolicy::Ignore, _) | (SignaturePolicy::IfPresent, None) => (),
+                // The protocol has no way to provide a signature
+                (SignaturePolicy::IfPresent, Some(_)) | (SignaturePolicy::Require, _) => {
+                    return Err(FetchError::MissingSignature)
+                }
+            }
+
+            let resolution = self.resolution.get_or_try_init(|| self.run()).await?;
+            Ok(resolution.is_some())
+        })
+    }
+
+    async fn fetch_and_extract(
+        &self,
+        dst: &Path,
+        members: Option<&[PathBuf]>,
+    ) -> Result<ExtractedFiles, FetchError> {
+        let resolved = self.resolved();
+        let extract_command = self.data.extract_command.as_deref();
+
+        Ok(match &resolved.location {
+            PackageLocation::Url(url) => {
+                debug!(%url, "Downloading package");
+                let mut data_verifier = ();
+                let mut data_verifier = Sha256DataVerifier::new(&mut data_verifier);
+                let (download, copy) = keep_download(
+                    Download::new_with_data_verifier(
+                        self.client.clone(),
+                        url.clone(),
+                        &mut data_verifier,
+                    ),
+                    url,
+                    self.data.keep_download.as_deref(),
+                );
+                let files =
+                    extract_download(download, resolved.pkg_fmt, dst, members, extract_command)
+                        .await?;
+                self.package_sha256.set(data_verifier.digest().into()).ok();
+                persist_download(copy).await?;
+                files
+            }
+            PackageLocation::Path(path) => {
+                match extract_command_for(resolved.pkg_fmt, extract_command) {
+                    Some(extract_command) => {
+                        run_extract_command(extract_command, path, dst).await?
+                    }
+                    None => extract_local_members(path, resolved.pkg_fmt, dst, members).await?,
+                }
+            }
+        })
+    }
+
+    fn pkg_fmt(&self) -> PkgFmt {
+        self.resolved().pkg_fmt
+    }
+
+    fn package_url(&self) -> Option<Url> {
+        match &self.resolved().location {
+            PackageLocation::Url(url) => Some(url.clone()),
+            PackageLocation::Path(_) => None,
+        }
+    }
+
+    fn package_sha256(&self) -> Option<CompactString> {
+        self.package_sha256.get().cloned()
+    }
+
+    fn target_meta(&self) -> PkgMeta {
+        let mut meta = self.target_data.meta.clone();
+        meta.pkg_fmt = Some(self.pkg_fmt());
+        meta
+    }
+
+    fn source_name(&self) -> CompactString {
+        self.data
+            .external_fetcher
+            .as_deref()
+            .and_then(Path::file_stem)
+            .map(|name| name.to_string_lossy().into())
+            .unwrap_or_else(|| CompactString::from("External"))
+    }
+
+    fn fetcher_name(&self) -> &'static str {
+        "ExternalFetcher"
+    }
+
+    fn is_third_party(&self) -> bool {
+        true
+    }
+
+    fn target(&self) -> &str {
+        &self.target_data.target
+    }
+
+    fn target_data(&self) -> &Arc<TargetDataErased> {
+        &self.target_data
+    }
+}
+
+#[cfg(all(test, unix))]
+mod test {
+    use std::{fs, num::NonZeroU16, os::unix::fs::PermissionsExt};
+
+    use binstalk_types::cargo_toml_binstall::{PkgSigning, SigningAlgorithm};
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::{Fetcher, TargetData};
+
+    /// Write an external fetcher saving its request into `request` and
+    /// running `script`.
+    fn external_fetcher(dir: &TempDir, script: &str) -> PathBuf {
+        let path = dir.path().join("fetcher");
+        fs::write(
+            &path,
+            format!(
+                "#!/bin/sh\ncat > '{}'\n{script}\n",
+                dir.path().join("request").display()
+            ),
+        )
+        .unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+        path
+    }
+
+    fn fetcher(
+        program: PathBuf,
+        signature_policy: SignaturePolicy,
+        signing: Option<PkgSigning>,
+    ) -> Arc<dyn Fetcher> {
+        let client = Client::new(
+            concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION")),
+            None,
+            NonZeroU16::new(10).unwrap(),
+            1.try_into().unwrap(),
+            [],
+        )
+        .unwrap();
+
+        ExternalFetcher::new(
+            client.clone(),
+            GhApiClient::new(client, None),
+            Arc::new(
+                Data::new("crate".into(), "1.2.3".into(), None)
+                    .with_external_fetcher(Some(program)),
+            ),
+            Arc::new(TargetData {
+                target: "x86_64-unknown-linux-gnu".into(),
+                meta: PkgMeta {
+                    signing,
+                    ..Default::default()
+                },
+                target_related_info: Vec::<(String, String)>::new(),
+            }),
+            signature_policy,
+        )
+    }
+
+    fn signing() -> Option<PkgSigning> {
+        Some(PkgSigning {
+            algorithm: SigningAlgorithm::Minisign,
+            pubkey: "RWRnmBcLmQbXVcEPWo2OOKMI36kki4GiI7gcBgIaPLwvxe14Wtxm9acX".into(),
+            file: None,
+        })
+    }
+
+    #[tokio::test]
+    async fn test_request() {
+        let dir = tempfile::tempdir().unwrap();
+        let program = external_fetcher(&dir, "");
+
+        let found = fetcher(program, SignaturePolicy::IfPresent, None)
+            .find()
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(!found);
+
+        let request: serde_json::Value =
+            serde_json::from_slice(&fs::read(dir.path().join("request")).unwrap()).unwrap();
+        assert_eq!(
+            request,
+            serde_json::json!({
+                "protocol": 1,
+                "crate": "crate",
+                "version": "1.2.3",
+                "target": "x86_64-unknown-linux-gnu",
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_url_response() {
+        let dir = tempfile::tempdir().unwrap();
+        let program = external_fetcher(
+            &dir,
+            r#"echo '{ "url": "https://example.com/crate-x86_64-unknown-linux-gnu.tar.gz" }'"#,
+        );
+
+        let fetcher = fetcher(program, SignaturePolicy::IfPresent, None);
+        assert!(fetcher.clone().find().await.unwrap().unwrap());
+        assert_eq!(fetcher.pkg_fmt(), PkgFmt::Tgz);
+        assert_eq!(
+            fetcher.package_url().unwrap().as_str(),
+            "https://example.com/crate-x86_64-unknown-linux-gnu.tar.gz"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_path_response() {
+        let dir = tempfile::tempdir().unwrap();
+        let package = dir.path().join("crate");
+        fs::write(&package, "binary").unwrap();
+        let program = external_fetcher(
+            &dir,
+            &format!(
+                r#"echo '{{ "path": "{}", "pkg-fmt": "bin" }}'"#,
+                package.display()
+            ),
+        );
+
+        let fetcher = fetcher(program, SignaturePolicy::IfPresent, None);
+        assert!(fetcher.clone().find().await.unwrap().unwrap());
+        assert_eq!(fetcher.pkg_fmt(), PkgFmt::Bin);
+        assert_eq!(fetcher.package_url(), None);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_responses() {
+        for script in [
+            "exit 1",
+            "echo 'not json'",
+            r#"echo '{ "url": "https://example.com/crate.tgz", "path": "/tmp/crate.tgz" }'"#,
+            r#"echo '{ "url": "https://example.com/crate.tgz", "unknown": 1 }'"#,
+            r#"echo '{ "pkg-fmt": "tgz" }'"#,
+        ] {
+            let dir = tempfile::tempdir().unwrap();
+            let program = external_fetcher(&dir, script);
+
+            let res = fetcher(program, SignaturePolicy::IfPresent, None)
+                .find()
+                .await
+                .unwrap();
+            assert!(
+                matches!(res, Err(FetchError::ExternalFetcher(_))),
+                "{script}: {res:?}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_signing() {
+        let dir = tempfile::tempdir().unwrap();
+        let program =
+            external_fetcher(&dir, r#"echo '{ "url": "https://example.com/crate.tgz" }'"#);
+
+        for (signature_policy, signing, missing_signature) in [
+            (SignaturePolicy::Ignore, signing(), false),
+            (SignaturePolicy::IfPresent, None, false),
+            (SignaturePolicy::IfPresent, signing(), true),
+            (SignaturePolicy::Require, None, true),
+        ] {
+            let res = fetcher(program.clone(), signature_policy, signing)
+                .find()
+                .await
+                .unwrap();
+            if missing_signature {
+                assert!(matches!(res, Err(FetchError::MissingSignature)));
+            } else {
+                assert!(res.unwrap());
+            }
+        }
+    }
+}
@@ -3,7 +3,7 @@ use tokio::sync::mpsc;
 
 /// Given multiple futures with output = `Result<Option<T>, E>`,
 /// returns the the first one that returns either `Err(_)` or
-/// `Ok(Some(_))`.
+/// `Ok(Some(_))`, or all of them with [`FuturesResolver::resolve_all`].
 pub struct FuturesResolver<T, E> {
     rx: mpsc::Receiver<Result<T, E>>,
     tx: mpsc::Sender<Result<T, E>>,
@@ -45,14 +45,10 @@ impl<T: Send + 'static, E: Send + 'static> FuturesResolver<T, E> {
         };
 
         if let Some(res) = res.transpose() {
-            // try_send can only fail due to being full or being closed.
-            //
-            // In both cases, this could means some other future has
-            // completed first.
-            //
-            // For closed, it could additionally means that the task
-            // is cancelled.
-            tx.try_send(res).ok();
+            // send can only fail due to the receiver being closed,
+            // which means some other future has completed first and
+            // `resolve` has returned, or that the task is cancelled.
+            tx.send(res).await.ok();
         }
     }
 
@@ -73,4 +69,18 @@ impl<T: Send + 'static, E: Send + 'static> FuturesResolver<T, E> {
 
         async move { rx.recv().await.transpose() }
     }
+
+    /// Return all the resolutions, or the first error.
+    pub fn resolve_all(self) -> impl Future<Output = Result<Vec<T>, E>> {
+        let mut rx = self.rx;
+        drop(self.tx);
+
+        async move {
+            let mut resolutions = Vec::new();
+            while let Some(res) = rx.recv().await {
+                resolutions.push(res?);
+            }
+            Ok(resolutions)
+        }
+    }
 }
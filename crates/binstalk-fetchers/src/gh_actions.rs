@@ -0,0 +1,342 @@
+//! Fetcher downloading the artifacts uploaded by GitHub Actions workflows.
+//!
+//! It is configured in the crate's metadata:
+//!
+//! ```toml
+//! [package.metadata.binstall.github-actions]
+//! workflow = "nightly.yml"
+//! artifact = "{ name }-{ target }"
+//! ```
+//!
+//! The artifact uploaded by the latest successful run of the workflow
+//! triggered by a push of the commit of the version's tag is downloaded,
+//! which requires a GitHub token even for public repositories.
+
+use std::{
+    borrow::Cow,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use binstalk_downloader::gh_api_client::{GhWorkflowArtifact, GhWorkflowArtifactQuery};
+use binstalk_types::cargo_toml_binstall::PkgGhActions;
+use leon::Template;
+use tokio::sync::OnceCell;
+
+use crate::{
+    common::*, gh_crate_meta::hosting::RepositoryHost, Data, FetchError, SignaturePolicy,
+    TargetDataErased,
+};
+
+/// Tag of the version if the crate does not specify it.
+const DEFAULT_TAG: &str = "v{ version }";
+
+pub struct GhActionsArtifact {
+    client: Client,
+    gh_api_client: GhApiClient,
+    data: Arc<Data>,
+    target_data: Arc<TargetDataErased>,
+    signature_policy: SignaturePolicy,
+    artifact: OnceCell<Option<GhWorkflowArtifact>>,
+}
+
+struct Context<'a> {
+    name: &'a str,
+    version: &'a str,
+    target: &'a str,
+    target_related_info: &'a dyn leon::Values,
+}
+
+impl leon::Values for Context<'_> {
+    fn get_value<'s>(&'s self, key: &str) -> Option<Cow<'s, str>> {
+        match key {
+            "name" => Some(Cow::Borrowed(self.name)),
+            "version" => Some(Cow::Borrowed(self.version)),
+            "target" => Some(Cow::Borrowed(self.target)),
+            key => self.target_related_info.get_value(key),
+        }
+    }
+}
+
+impl GhActionsArtifact {
+    /// Return the owner and name of the repository running the workflow.
+    async fn repo(&self, config: &PkgGhActions) -> Result<(String, String), FetchError> {
+        let invalid = |repo: &str| {
+            FetchError::GhActionsArtifact(
+                format!("repository `{repo}` is not of the form `owner/repo`").into(),
+            )
+        };
+
+        if let Some(repo) = &config.repo {
+            return match repo.split_once('/') {
+                Some((owner, name)) if !owner.is_empty() && !name.is_empty() => {
+                    Ok((owner.to_owned(), name.to_owned()))
+                }
+                _ => Err(invalid(repo)),
+            };
+        }
+
+        let repo_info = self
+            .data
+            .get_repo_info(&self.client)
+            .await?
+            .as_ref()
+            .filter(|repo_info| repo_info.repository_host == RepositoryHost::GitHub)
+            .ok_or_else(|| {
+                FetchError::GhActionsArtifact(
+                    "the crate is not hosted on GitHub, `repo` must be specified".into(),
+                )
+            })?;
+
+        let mut segments = repo_info
+            .repo
+            .path_segments()
+            .ok_or_else(|| invalid(repo_info.repo.as_str()))?;
+        match (segments.next(), segments.next()) {
+            (Some(owner), Some(name)) if !owner.is_empty() && !name.is_empty() => {
+                Ok((owner.to_owned(), name.to_owned()))
+            }
+            _ => Err(invalid(repo_info.repo.as_str())),
+        }
+    }
+
+    async fn find_artifact(&self) -> Result<Option<GhWorkflowArtifact>, FetchError> {
+        let Some(config) = &self.target_data.meta.github_actions else {
+            return Ok(None);
+        };
+
+        let (owner, repo) = self.repo(config).await?;
+
+        let (artifact_name, branch, tag) = {
+            let ctx = Context {
+                name: &self.data.name,
+                version: &self.data.version,
+                target: &self.target_data.target,
+                target_related_info: &self.target_data.target_related_info,
+            };
+            let render = |template: &str| -> Result<String, FetchError> {
+                Ok(Template::parse(template)?.render(&ctx)?)
+            };
+
+            (
+                render(&config.artifact)?,
+                config.branch.as_deref().map(render).transpose()?,
+                render(config.tag.as_deref().unwrap_or(DEFAULT_TAG))?,
+            )
+        };
+
+        debug!(
+            "Looking for artifact {artifact_name} of workflow {} in {owner}/{repo} at tag {tag}",
+            config.workflow
+        );
+        let artifact = self
+            .gh_api_client
+            .fetch_workflow_artifact(
+                &owner,
+                &repo,
+                &GhWorkflowArtifactQuery {
+                    workflow: &config.workflow,
+                    branch: branch.as_deref(),
+                    tag: &tag,
+                    artifact_name: &artifact_name,
+                },
+            )
+            .await?;
+
+        match artifact {
+            Some(artifact) if artifact.expired => Err(FetchError::GhActionsArtifactExpired {
+                artifact: artifact.name,
+                workflow: config.workflow.as_str().into(),
+            }),
+            artifact => Ok(artifact),
+        }
+    }
+
+    fn artifact(&self) -> &GhWorkflowArtifact {
+        self.artifact
+            .get()
+            .and_then(Option::as_ref)
+            .expect("find() is called first")
+    }
+}
+
+#[async_trait::async_trait]
+impl super::Fetcher for GhActionsArtifact {
+    fn new(
+        client: Client,
+        gh_api_client: GhApiClient,
+        data: Arc<Data>,
+        target_data: Arc<TargetDataErased>,
+        signature_policy: SignaturePolicy,
+    ) -> Arc<dyn super::Fetcher> {
+        Arc::new(Self {
+            client,
+            gh_api_client,
+            data,
+            target_data,
+            signature_policy,
+            artifact: OnceCell::new(),
+        })
+    }
+
+    fn find(self: Arc<Self>) -> JoinHandle<Result<bool, FetchError>> {
+        tokio::spawn(async move {
+            if self.target_data.meta.github_actions.is_none() {
+                return Ok(false);
+            }
+
+            if self.signature_policy == SignaturePolicy::Require {
+                // There is nowhere to publish the signature of an artifact
+                return Err(FetchError::MissingSignature);
+            }
+
+            if self.gh_api_client.auth_token().is_none() {
+                return Err(FetchError::GhActionsArtifact(
+                    "downloading artifacts requires a GitHub token, e.g. in the \
+                     GITHUB_TOKEN environment variable"
+                        .into(),
+                ));
+            }
+
+            let artifact = self
+                .artifact
+                .get_or_try_init(|| self.find_artifact())
+                .await?;
+            Ok(artifact.is_some())
+        })
+    }
+
+    async fn fetch_and_extract(
+        &self,
+        dst: &Path,
+        members: Option<&[PathBuf]>,
+    ) -> Result<ExtractedFiles, FetchError> {
+        let artifact = self.artifact();
+        let auth_token = self.gh_api_client.auth_token().ok_or_else(|| {
+            FetchError::GhActionsArtifact("the GitHub token has been rejected".into())
+        })?;
+
+        debug!(url = %artifact.archive_download_url, "Downloading artifact");
+        let download = Download::new(self.client.clone(), artifact.archive_download_url.clone())
+            .with_auth_token(&format!("Bearer {auth_token}"));
+
+        let (download, copy) = keep_download_as(
+            download,
+            &format!("{}.zip", artifact.name),
+            self.data.keep_download.as_deref(),
+        );
+
+        let files = extract_download(
+            download,
+            PkgFmt::Zip,
+            dst,
+            members,
+            self.data.extract_command.as_deref(),
+        )
+        .await?;
+        persist_download(copy).await?;
+
+        Ok(files)
+    }
+
+    fn pkg_fmt(&self) -> PkgFmt {
+        PkgFmt::Zip
+    }
+
+    fn package_url(&self) -> Option<Url> {
+        self.artifact
+            .get()
+            .and_then(Option::as_ref)
+            .map(|artifact| artifact.archive_download_url.clone())
+    }
+
+    fn target_meta(&self) -> PkgMeta {
+        let mut meta = self.target_data.meta.clone();
+        meta.pkg_fmt = Some(self.pkg_fmt());
+        meta
+    }
+
+    fn source_name(&self) -> CompactString {
+        CompactString::from("GitHub Actions")
+    }
+
+    fn fetcher_name(&self) -> &'static str {
+        "GhActionsArtifact"
+    }
+
+    fn is_third_party(&self) -> bool {
+        // The workflow of another repository is not run by the crate's authors.
+        self.target_data
+            .meta
+            .github_actions
+            .as_ref()
+            .map_or(false, |config| config.repo.is_some())
+    }
+
+    fn target(&self) -> &str {
+        &self.target_data.target
+    }
+
+    fn target_data(&self) -> &Arc<TargetDataErased> {
+        &self.target_data
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::num::NonZeroU16;
+
+    use binstalk_types::cargo_toml_binstall::PkgGhActions;
+
+    use super::*;
+    use crate::{Fetcher, TargetData};
+
+    fn fetcher(repo: Option<&str>) -> Arc<dyn Fetcher> {
+        let client = Client::new(
+            concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION")),
+            None,
+            NonZeroU16::new(10).unwrap(),
+            1.try_into().unwrap(),
+            [],
+        )
+        .unwrap();
+
+        GhActionsArtifact::new(
+            client.clone(),
+            GhApiClient::new(client, None),
+            Arc::new(Data::new("crate".into(), "1.2.3".into(), None)),
+            Arc::new(TargetData {
+                target: "x86_64-unknown-linux-gnu".into(),
+                meta: PkgMeta {
+                    github_actions: Some(PkgGhActions {
+                        repo: repo.map(String::from),
+                        workflow: "nightly.yml".into(),
+                        artifact: "{ name }-{ target }".into(),
+                        branch: None,
+                        tag: None,
+                    }),
+                    ..Default::default()
+                },
+                target_related_info: Vec::<(String, String)>::new(),
+            }),
+            SignaturePolicy::IfPresent,
+        )
+    }
+
+    #[test]
+    fn other_repo_is_third_party() {
+        assert!(!fetcher(None).is_third_party());
+        assert!(fetcher(Some("someone/else")).is_third_party());
+    }
+
+    #[test]
+    fn package_url_is_unknown_until_found() {
+        assert_eq!(fetcher(None).package_url(), None);
+    }
+
+    #[tokio::test]
+    async fn token_is_required() {
+        let err = fetcher(None).find().await.unwrap().unwrap_err();
+        assert!(matches!(err, FetchError::GhActionsArtifact(_)), "{err}");
+    }
+}
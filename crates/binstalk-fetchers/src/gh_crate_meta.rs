@@ -1,19 +1,31 @@
-use std::{borrow::Cow, fmt, iter, path::Path, sync::Arc};
+use std::{
+    borrow::Cow,
+    env, fmt, iter,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
+use binstalk_downloader::download::DataVerifier;
 use compact_str::{CompactString, ToCompactString};
 use either::Either;
 use leon::Template;
 use once_cell::sync::OnceCell;
 use strum::IntoEnumIterator;
-use tracing::{debug, info, trace, warn};
+use tracing::{debug, error, info, trace, warn};
 use url::Url;
 
 use crate::{
-    common::*, futures_resolver::FuturesResolver, Data, FetchError, InvalidPkgFmtError, RepoInfo,
-    SignaturePolicy, SignatureVerifier, TargetDataErased,
+    checksum::{Checksum, ChecksumDataVerifier, Sha256DataVerifier},
+    common::*,
+    futures_resolver::FuturesResolver,
+    Data, FetchError, InvalidPkgFmtError, RepoInfo, SignaturePolicy, SignatureVerifier,
+    TargetDataErased, Verifications,
 };
 
 pub(crate) mod hosting;
+use hosting::RepositoryHost;
+
+mod target_aliases;
 
 pub struct GhCrateMeta {
     client: Client,
@@ -22,6 +34,90 @@ pub struct GhCrateMeta {
     target_data: Arc<TargetDataErased>,
     signature_policy: SignaturePolicy,
     resolution: OnceCell<Resolved>,
+    verifications: OnceCell<Verifications>,
+    package_sha256: OnceCell<CompactString>,
+    /// Whether this is the [`BitbucketCrateMeta`] fetcher.
+    bitbucket: bool,
+}
+
+/// Fetcher for the crates hosted on Bitbucket, looking for their packages
+/// in the Downloads of the repository like [`GhCrateMeta`] does.
+///
+/// Unlike [`GhCrateMeta`], the requests to Bitbucket are authenticated with
+/// the access token set with [`Data::with_bitbucket_token`], so that the
+/// packages of private repositories are found too.
+pub struct BitbucketCrateMeta(Arc<GhCrateMeta>);
+
+#[async_trait::async_trait]
+impl super::Fetcher for BitbucketCrateMeta {
+    fn new(
+        client: Client,
+        gh_api_client: GhApiClient,
+        data: Arc<Data>,
+        target_data: Arc<TargetDataErased>,
+        signature_policy: SignaturePolicy,
+    ) -> Arc<dyn super::Fetcher> {
+        Arc::new(Self(Arc::new(GhCrateMeta::create(
+            client,
+            gh_api_client,
+            data,
+            target_data,
+            signature_policy,
+            true,
+        ))))
+    }
+
+    async fn fetch_and_extract(
+        &self,
+        dst: &Path,
+        members: Option<&[PathBuf]>,
+    ) -> Result<ExtractedFiles, FetchError> {
+        self.0.fetch_and_extract(dst, members).await
+    }
+
+    fn find(self: Arc<Self>) -> JoinHandle<Result<bool, FetchError>> {
+        self.0.clone().find()
+    }
+
+    fn pkg_fmt(&self) -> PkgFmt {
+        self.0.pkg_fmt()
+    }
+
+    fn package_url(&self) -> Option<Url> {
+        self.0.package_url()
+    }
+
+    fn package_sha256(&self) -> Option<CompactString> {
+        self.0.package_sha256()
+    }
+
+    fn verifications(&self) -> Verifications {
+        self.0.verifications()
+    }
+
+    fn target_meta(&self) -> PkgMeta {
+        self.0.target_meta()
+    }
+
+    fn source_name(&self) -> CompactString {
+        self.0.source_name()
+    }
+
+    fn fetcher_name(&self) -> &'static str {
+        "BitbucketCrateMeta"
+    }
+
+    fn is_third_party(&self) -> bool {
+        false
+    }
+
+    fn target(&self) -> &str {
+        self.0.target()
+    }
+
+    fn target_data(&self) -> &Arc<TargetDataErased> {
+        self.0.target_data()
+    }
 }
 
 #[derive(Debug)]
@@ -31,9 +127,79 @@ struct Resolved {
     archive_suffix: Option<String>,
     repo: Option<String>,
     subcrate: Option<String>,
+    mirror: Option<String>,
+    target_alias: Option<String>,
+    tag: Option<String>,
+}
+
+impl Resolved {
+    /// Name of the target in the url of the package, `target` unless an
+    /// alias of it was used.
+    fn target<'a>(&'a self, target: &'a str) -> &'a str {
+        self.target_alias.as_deref().unwrap_or(target)
+    }
+}
+
+/// Where the packages are looked for, in addition to the templates.
+#[derive(Clone, Copy, Debug)]
+struct Location<'a> {
+    /// Base url of the mirror substituted for `{ mirror }`.
+    mirror: Option<&'a str>,
+    /// Name of the target substituted for `{ target }` instead of its triple.
+    target_alias: Option<&'a str>,
+    /// Release tag substituted for `{ tag }` instead of the version.
+    tag: Option<&'a str>,
 }
 
 impl GhCrateMeta {
+    fn create(
+        client: Client,
+        gh_api_client: GhApiClient,
+        data: Arc<Data>,
+        target_data: Arc<TargetDataErased>,
+        signature_policy: SignaturePolicy,
+        bitbucket: bool,
+    ) -> Self {
+        Self {
+            client,
+            gh_api_client,
+            data,
+            target_data,
+            signature_policy,
+            resolution: OnceCell::new(),
+            verifications: OnceCell::new(),
+            package_sha256: OnceCell::new(),
+            bitbucket,
+        }
+    }
+
+    /// Whether the package of the crate hosted in `info` is looked for.
+    ///
+    /// [`BitbucketCrateMeta`] only looks for the default urls of the crates
+    /// hosted on Bitbucket, which [`GhCrateMeta`] looks for as well, only
+    /// without authentication.
+    fn looks_for(&self, info: Option<&RepoInfo>) -> bool {
+        !self.bitbucket
+            || (self.target_data.meta.pkg_url.is_none()
+                && info.map_or(false, |info| {
+                    info.repository_host == RepositoryHost::BitBucket
+                }))
+    }
+
+    /// Value of the `Authorization` header of the requests to `url`, only
+    /// sent to Bitbucket itself.
+    fn auth_for(&self, url: &Url) -> Option<String> {
+        let token = self.data.bitbucket_token.as_deref()?;
+        (self.bitbucket && url.domain() == Some("bitbucket.org")).then(|| format!("Bearer {token}"))
+    }
+
+    fn download<'a>(&self, download: Download<'a>, url: &Url) -> Download<'a> {
+        match self.auth_for(url) {
+            Some(auth) => download.with_auth_token(&auth),
+            None => download,
+        }
+    }
+
     fn launch_baseline_find_tasks(
         &self,
         futures_resolver: &FuturesResolver<Resolved, FetchError>,
@@ -41,18 +207,27 @@ impl GhCrateMeta {
         pkg_url: &Template<'_>,
         repo: Option<&str>,
         subcrate: Option<&str>,
+        location: Location<'_>,
     ) {
         let render_url = |ext| {
-            let ctx = Context::from_data_with_repo(
+            let mut ctx = Context::from_data_with_repo(
                 &self.data,
-                &self.target_data.target,
+                location.target_alias.unwrap_or(&self.target_data.target),
                 &self.target_data.target_related_info,
                 ext,
                 repo,
                 subcrate,
             );
+            ctx.with_mirror(location.mirror).with_tag(location.tag);
             match ctx.render_url_with(pkg_url) {
                 Ok(url) => Some(url),
+                Err(err @ FetchError::MissingEnvVar(_)) => {
+                    error!(
+                        "Failed to render url for {}: {err}",
+                        self.target_data.target
+                    );
+                    None
+                }
                 Err(err) => {
                     warn!("Failed to render url for {ctx:#?}: {err}");
                     None
@@ -82,21 +257,107 @@ impl GhCrateMeta {
             let repo = repo.map(ToString::to_string);
             let subcrate = subcrate.map(ToString::to_string);
             let archive_suffix = ext.map(ToString::to_string);
+            let mirror = location.mirror.map(ToString::to_string);
+            let target_alias = location.target_alias.map(ToString::to_string);
+            let tag = location.tag.map(ToString::to_string);
+            let auth = self.auth_for(&url);
+            let wait_rate_limit = self.data.wait_rate_limit;
             async move {
-                Ok(does_url_exist(client, gh_api_client, &url)
-                    .await?
-                    .then_some(Resolved {
-                        url,
-                        pkg_fmt,
-                        repo,
-                        subcrate,
-                        archive_suffix,
-                    }))
+                let exists = if mirror.is_some() {
+                    does_mirror_url_exist(client, &url).await?
+                } else if let Some(auth) = auth {
+                    does_authed_url_exist(client, &url, &auth).await?
+                } else {
+                    does_url_exist(client, gh_api_client, &url, wait_rate_limit).await?
+                };
+
+                Ok(exists.then_some(Resolved {
+                    url,
+                    pkg_fmt,
+                    repo,
+                    subcrate,
+                    mirror,
+                    target_alias,
+                    tag,
+                    archive_suffix,
+                }))
             }
         }));
     }
 }
 
+impl GhCrateMeta {
+    /// Look for the packages at every url rendered from `pkg_urls` and
+    /// `pkg_fmts` for `location` at once.
+    /// Launch the tasks looking for the package at `location`, they run in
+    /// the background until resolved with [`GhCrateMeta::resolve_at`], or
+    /// cancelled once the resolver is dropped.
+    fn launch_find_at(
+        &self,
+        pkg_urls: &[Template<'_>],
+        pkg_fmts: impl Iterator<Item = PkgFmt> + Clone,
+        repo: Option<&str>,
+        subcrate: Option<&str>,
+        location: Location<'_>,
+    ) -> FuturesResolver<Resolved, FetchError> {
+        let resolver = FuturesResolver::default();
+
+        // Iterate over pkg_urls first to avoid String::clone.
+        for pkg_url in pkg_urls {
+            //             Clone iter pkg_fmts to ensure all pkg_fmts is
+            //             iterated over for each pkg_url, which is
+            //             basically cartesian product.
+            //             |
+            for pkg_fmt in pkg_fmts.clone() {
+                self.launch_baseline_find_tasks(
+                    &resolver, pkg_fmt, pkg_url, repo, subcrate, location,
+                );
+            }
+        }
+
+        resolver
+    }
+
+    async fn resolve_at(
+        &self,
+        resolver: FuturesResolver<Resolved, FetchError>,
+    ) -> Result<Option<Resolved>, FetchError> {
+        if self.data.prefer_smallest {
+            Ok(self.find_smallest(resolver.resolve_all().await?).await)
+        } else {
+            resolver.resolve().await
+        }
+    }
+
+    /// Pick the candidate with the smallest `Content-Length`, candidates
+    /// whose size cannot be retrieved are only picked if there's no other.
+    async fn find_smallest(&self, candidates: Vec<Resolved>) -> Option<Resolved> {
+        if candidates.len() <= 1 {
+            return candidates.into_iter().next();
+        }
+
+        let mut smallest = None;
+        for resolved in candidates {
+            let size = match self.client.get_content_length(resolved.url.clone()).await {
+                Ok(size) => size,
+                Err(err) => {
+                    warn!("Failed to get size of {}: {err}", resolved.url);
+                    None
+                }
+            };
+            debug!(url = %resolved.url, ?size, "Found candidate");
+
+            let size = size.unwrap_or(u64::MAX);
+            match &smallest {
+                Some((smallest_size, _)) if *smallest_size <= size => (),
+                _ => smallest = Some((size, resolved)),
+            }
+        }
+
+        smallest.map(|(_, resolved)| resolved)
+    }
+}
+
 #[async_trait::async_trait]
 impl super::Fetcher for GhCrateMeta {
     fn new(
@@ -106,20 +367,24 @@ impl super::Fetcher for GhCrateMeta {
         target_data: Arc<TargetDataErased>,
         signature_policy: SignaturePolicy,
     ) -> Arc<dyn super::Fetcher> {
-        Arc::new(Self {
+        Arc::new(Self::create(
             client,
             gh_api_client,
             data,
             target_data,
             signature_policy,
-            resolution: OnceCell::new(),
-        })
+            false,
+        ))
     }
 
     fn find(self: Arc<Self>) -> JoinHandle<Result<bool, FetchError>> {
         tokio::spawn(async move {
             let info = self.data.get_repo_info(&self.client).await?.as_ref();
 
+            if !self.looks_for(info) {
+                return Ok(false);
+            }
+
             let repo = info.map(|info| &info.repo);
             let subcrate = info.and_then(|info| info.subcrate.as_deref());
 
@@ -176,6 +441,7 @@ impl super::Fetcher for GhCrateMeta {
             {
                 if let Some(pkg_urls) = repository_host.get_default_pkg_url_template() {
                     let has_subcrate = subcrate.is_some();
+                    let has_tag = self.target_data.meta.tag.is_some();
 
                     Either::Right(
                         pkg_urls
@@ -183,7 +449,9 @@ impl super::Fetcher for GhCrateMeta {
                             // If subcrate is Some, then all templates will be included.
                             // Otherwise, only templates without key "subcrate" will be
                             // included.
-                            .filter(move |template| has_subcrate || !template.has_key("subcrate")),
+                            .filter(move |template| has_subcrate || !template.has_key("subcrate"))
+                            // Same for the tag, which is the version otherwise.
+                            .filter(move |template| has_tag || !template.has_key("tag")),
                     )
                 } else {
                     warn!(
@@ -211,6 +479,22 @@ impl super::Fetcher for GhCrateMeta {
             // Convert Option<Url> to Option<String> to reduce size of future.
             let repo = repo.map(|u| u.as_str().trim_end_matches('/'));
 
+            let tag = match self.target_data.meta.tag.as_deref() {
+                Some(tag) => Some(
+                    Context::from_data_with_repo(
+                        &self.data,
+                        &self.target_data.target,
+                        &self.target_data.target_related_info,
+                        None,
+                        repo,
+                        subcrate,
+                    )
+                    .render_with(&Template::parse(tag)?)?,
+                ),
+                None => None,
+            };
+            let tag = tag.as_deref();
+
             // Use reference to self to fix error of closure
             // launch_baseline_find_tasks which moves `this`
             let this = &self;
@@ -218,23 +502,78 @@ impl super::Fetcher for GhCrateMeta {
             let pkg_fmts = if let Some(pkg_fmt) = pkg_fmt {
                 Either::Left(iter::once(pkg_fmt))
             } else {
-                Either::Right(PkgFmt::iter())
+                Either::Right(default_pkg_fmts())
             };
 
-            let resolver = FuturesResolver::default();
+            let pkg_urls: Vec<_> = pkg_urls.collect();
 
-            // Iterate over pkg_urls first to avoid String::clone.
-            for pkg_url in pkg_urls {
-                //             Clone iter pkg_fmts to ensure all pkg_fmts is
-                //             iterated over for each pkg_url, which is
-                //             basically cartesian product.
-                //             |
-                for pkg_fmt in pkg_fmts.clone() {
-                    this.launch_baseline_find_tasks(&resolver, pkg_fmt, &pkg_url, repo, subcrate);
+            // Mirrors are tried in order, moving on to the next one only if
+            // the current one is unreachable, not if it lacks the package.
+            let mirrors = &self.target_data.meta.mirrors;
+            let mirrors: Vec<_> = if !mirrors.is_empty()
+                && pkg_urls.iter().any(|pkg_url| pkg_url.has_key("mirror"))
+            {
+                mirrors
+                    .iter()
+                    .map(|mirror| Some(mirror.as_str().trim_end_matches('/')))
+                    .collect()
+            } else {
+                vec![None]
+            };
+            let mut mirrors = mirrors.into_iter().peekable();
+
+            // Packages not named after the target triple are only looked for
+            // under the names commonly used for it if there's none that is.
+            let target = &self.target_data.target;
+            let target_aliases = if pkg_urls.iter().any(|pkg_url| pkg_url.has_key("target")) {
+                match self.target_data.meta.target_aliases.get(target) {
+                    Some(target_aliases) => Cow::Borrowed(target_aliases),
+                    None => Cow::Owned(target_aliases::default_target_aliases(target)),
+                }
+            } else {
+                Cow::Owned(Vec::new())
+            };
+
+            let mut resolved = None;
+            while let Some(mirror) = mirrors.next() {
+                // The names are all probed concurrently, but the package
+                // named after the triple is preferred, then the aliases in
+                // order.
+                let rounds: Vec<_> = iter::once(None)
+                    .chain(target_aliases.iter().map(|alias| Some(alias.as_str())))
+                    .map(|target_alias| {
+                        let location = Location {
+                            mirror,
+                            target_alias,
+                            tag,
+                        };
+                        this.launch_find_at(&pkg_urls, pkg_fmts.clone(), repo, subcrate, location)
+                    })
+                    .collect();
+
+                let mut res = Ok(None);
+                for resolver in rounds {
+                    res = this.resolve_at(resolver).await;
+                    if !matches!(res, Ok(None)) {
+                        break;
+                    }
+                }
+
+                match (res, mirror) {
+                    (
+                        Err(err @ (FetchError::MirrorUnavailable(_) | FetchError::Download(_))),
+                        Some(mirror),
+                    ) if mirrors.peek().is_some() => {
+                        warn!("Mirror {mirror} failed, trying the next one: {err}");
+                    }
+                    (res, _) => {
+                        resolved = res?;
+                        break;
+                    }
                 }
             }
 
-            if let Some(resolved) = resolver.resolve().await? {
+            if let Some(resolved) = resolved {
                 debug!(?resolved, "Winning URL found!");
                 self.resolution.set(resolved).unwrap(); // find() is called first
                 Ok(true)
@@ -244,7 +583,11 @@ impl super::Fetcher for GhCrateMeta {
         })
     }
 
-    async fn fetch_and_extract(&self, dst: &Path) -> Result<ExtractedFiles, FetchError> {
+    async fn fetch_and_extract(
+        &self,
+        dst: &Path,
+        members: Option<&[PathBuf]>,
+    ) -> Result<ExtractedFiles, FetchError> {
         let resolved = self.resolution.get().unwrap(); // find() is called first
         trace!(?resolved, "preparing to fetch");
 
@@ -264,18 +607,24 @@ impl super::Fetcher for GhCrateMeta {
 
                 let sign_url = Context::from_data_with_repo(
                     &self.data,
-                    &self.target_data.target,
+                    resolved.target(&self.target_data.target),
                     &self.target_data.target_related_info,
                     resolved.archive_suffix.as_deref(),
                     resolved.repo.as_deref(),
                     resolved.subcrate.as_deref(),
                 )
+                .with_mirror(resolved.mirror.as_deref())
+                .with_tag(resolved.tag.as_deref())
                 .with_url(&resolved.url)
                 .render_url_with(&template)?;
 
                 debug!(?sign_url, "Downloading signature");
-                let signature = Download::new(self.client.clone(), sign_url)
-                    .into_bytes()
+                let signature = self
+                    .download(
+                        Download::new(self.client.clone(), sign_url.clone()),
+                        &sign_url,
+                    )
+                    .into_bytes_bounded(SIDECAR_MAX_SIZE, SIDECAR_TIMEOUT)
                     .await?;
                 trace!(?signature, "got signature contents");
 
@@ -283,6 +632,50 @@ impl super::Fetcher for GhCrateMeta {
             }
         };
 
+        let checksum = match &self.target_data.meta.checksum {
+            None => None,
+            Some(config) => {
+                let default_file = format!("{{ url }}.{}", config.algorithm.as_str());
+                let template = Template::parse(config.file.as_deref().unwrap_or(&default_file))?;
+                trace!(?template, "parsed checksum file template");
+
+                let checksum_url = Context::from_data_with_repo(
+                    &self.data,
+                    resolved.target(&self.target_data.target),
+                    &self.target_data.target_related_info,
+                    resolved.archive_suffix.as_deref(),
+                    resolved.repo.as_deref(),
+                    resolved.subcrate.as_deref(),
+                )
+                .with_mirror(resolved.mirror.as_deref())
+                .with_tag(resolved.tag.as_deref())
+                .with_url(&resolved.url)
+                .render_url_with(&template)?;
+
+                debug!(?checksum_url, "Downloading checksum");
+                let checksum_file = self
+                    .download(
+                        Download::new(self.client.clone(), checksum_url.clone()),
+                        &checksum_url,
+                    )
+                    .into_bytes_bounded(SIDECAR_MAX_SIZE, SIDECAR_TIMEOUT)
+                    .await
+                    .map_err(|err| {
+                        error!("Failed to download checksum: {err}");
+                        FetchError::MissingChecksum
+                    })?;
+                trace!(?checksum_file, "got checksum contents");
+
+                let filename = resolved
+                    .url
+                    .path_segments()
+                    .and_then(|mut segments| segments.next_back())
+                    .unwrap_or_default();
+
+                Some(Checksum::parse(config.algorithm, &checksum_file, filename)?)
+            }
+        };
+
         debug!(
             url=%resolved.url,
             dst=%dst.display(),
@@ -290,21 +683,58 @@ impl super::Fetcher for GhCrateMeta {
             "Downloading package",
         );
         let mut data_verifier = verifier.data_verifier()?;
-        let files = Download::new_with_data_verifier(
-            self.client.clone(),
-            resolved.url.clone(),
-            data_verifier.as_mut(),
+        let checksum_algorithm = self
+            .target_data
+            .meta
+            .checksum
+            .as_ref()
+            .map(|config| config.algorithm);
+        let mut checksum_verifier = ChecksumDataVerifier::new(data_verifier.as_mut(), checksum)?;
+        let mut data_verifier = Sha256DataVerifier::new(&mut checksum_verifier);
+        let (download, copy) = keep_download(
+            self.download(
+                Download::new_with_data_verifier(
+                    self.client.clone(),
+                    resolved.url.clone(),
+                    &mut data_verifier,
+                ),
+                &resolved.url,
+            ),
+            &resolved.url,
+            self.data.keep_download.as_deref(),
+        );
+        let files = extract_download(
+            download,
+            resolved.pkg_fmt,
+            dst,
+            members,
+            self.data.extract_command.as_deref(),
         )
-        .and_extract(resolved.pkg_fmt, dst)
         .await?;
+
+        let sha256_digest = data_verifier.digest();
+        let valid_signature = data_verifier.validate();
+
+        trace!("verifying checksum (if any)");
+        checksum_verifier.verify()?;
+
         trace!("validating signature (if any)");
-        if data_verifier.validate() {
-            if let Some(info) = verifier.info() {
+        if valid_signature {
+            let signature = verifier.info();
+            if let Some(info) = &signature {
                 info!(
                     "Verified signature for package '{}': {info}",
                     self.data.name
                 );
             }
+            self.verifications
+                .set(Verifications {
+                    signature,
+                    checksum: checksum_algorithm,
+                })
+                .ok();
+            self.package_sha256.set(sha256_digest.into()).ok();
+            persist_download(copy).await?;
             Ok(files)
         } else {
             Err(FetchError::InvalidSignature)
@@ -315,6 +745,18 @@ impl super::Fetcher for GhCrateMeta {
         self.resolution.get().unwrap().pkg_fmt
     }
 
+    fn verifications(&self) -> Verifications {
+        self.verifications.get().cloned().unwrap_or_default()
+    }
+
+    fn package_url(&self) -> Option<Url> {
+        self.resolution.get().map(|resolved| resolved.url.clone())
+    }
+
+    fn package_sha256(&self) -> Option<CompactString> {
+        self.package_sha256.get().cloned()
+    }
+
     fn target_meta(&self) -> PkgMeta {
         let mut meta = self.target_data.meta.clone();
         meta.pkg_fmt = Some(self.pkg_fmt());
@@ -353,6 +795,12 @@ impl super::Fetcher for GhCrateMeta {
     }
 }
 
+/// Prefix of the keys interpolating environment variables, e.g. `{ env:BUILD_ID }`.
+///
+/// Crate metadata using them is rejected before the fetchers are launched,
+/// so they only come from templates given by the user.
+const ENV_KEY_PREFIX: &str = "env:";
+
 /// Template for constructing download paths
 #[derive(Clone)]
 struct Context<'c> {
@@ -372,10 +820,19 @@ struct Context<'c> {
     /// Workspace of the crate inside the repository.
     subcrate: Option<&'c str>,
 
+    /// Base url of the mirror the package is downloaded from.
+    mirror: Option<&'c str>,
+
+    /// Tag of the release, if it is not the version.
+    tag: Option<&'c str>,
+
     /// Url of the file being downloaded (only for signing.file)
     url: Option<&'c Url>,
 
     target_related_info: &'c dyn leon::Values,
+
+    /// Look up the environment variables of `env:` keys.
+    env: fn(&str) -> Option<String>,
 }
 
 impl fmt::Debug for Context<'_> {
@@ -388,6 +845,8 @@ impl fmt::Debug for Context<'_> {
             .field("archive_format", &self.archive_format)
             .field("binary_ext", &self.binary_ext)
             .field("subcrate", &self.subcrate)
+            .field("mirror", &self.mirror)
+            .field("tag", &self.tag)
             .field("url", &self.url)
             .finish_non_exhaustive()
     }
@@ -412,9 +871,16 @@ impl leon::Values for Context<'_> {
 
             "subcrate" => self.subcrate.map(Cow::Borrowed),
 
+            "mirror" => self.mirror.map(Cow::Borrowed),
+
+            "tag" => Some(Cow::Borrowed(self.tag.unwrap_or(self.version))),
+
             "url" => self.url.map(|url| Cow::Borrowed(url.as_str())),
 
-            key => self.target_related_info.get_value(key),
+            key => match key.strip_prefix(ENV_KEY_PREFIX) {
+                Some(var) => (self.env)(var).map(Cow::Owned),
+                None => self.target_related_info.get_value(key),
+            },
         }
     }
 }
@@ -453,20 +919,42 @@ impl<'c> Context<'c> {
                 ""
             },
             subcrate,
+            mirror: None,
+            tag: None,
             url: None,
 
             target_related_info,
+            env: |var| env::var(var).ok(),
         }
     }
 
+    fn with_mirror(&mut self, mirror: Option<&'c str>) -> &mut Self {
+        self.mirror = mirror;
+        self
+    }
+
+    fn with_tag(&mut self, tag: Option<&'c str>) -> &mut Self {
+        self.tag = tag;
+        self
+    }
+
     fn with_url(&mut self, url: &'c Url) -> &mut Self {
         self.url = Some(url);
         self
     }
 
+    fn render_with(&self, template: &Template<'_>) -> Result<String, FetchError> {
+        debug!(?template, context=?self, "render template");
+        template.render(self).map_err(|err| match err {
+            leon::RenderError::MissingKey(key) if key.starts_with(ENV_KEY_PREFIX) => {
+                FetchError::MissingEnvVar(key[ENV_KEY_PREFIX.len()..].into())
+            }
+            err => err.into(),
+        })
+    }
+
     fn render_url_with(&self, template: &Template<'_>) -> Result<Url, FetchError> {
-        debug!(?template, context=?self, "render url template");
-        Ok(Url::parse(&template.render(self)?)?)
+        Ok(Url::parse(&self.render_with(template)?)?)
     }
 
     #[cfg(test)]
@@ -475,9 +963,23 @@ impl<'c> Context<'c> {
     }
 }
 
+/// The formats probed for when the crate declares none.
+///
+/// lz4 packages are rare, so they are only looked for when declared or
+/// guessed from `pkg-url`, instead of adding probes to every resolution.
+fn default_pkg_fmts() -> impl Iterator<Item = PkgFmt> + Clone {
+    PkgFmt::iter().filter(|pkg_fmt| !matches!(pkg_fmt, PkgFmt::Tlz4 | PkgFmt::Lz4))
+}
+
 #[cfg(test)]
 mod test {
-    use super::{super::Data, Context};
+    use std::{num::NonZeroU16, sync::Arc};
+
+    use super::{
+        super::Data, default_pkg_fmts, BitbucketCrateMeta, Client, Context, FetchError,
+        GhApiClient, GhCrateMeta, PkgFmt, PkgMeta, RepoInfo, RepositoryHost, SignaturePolicy,
+    };
+    use crate::{Fetcher, TargetData};
     use compact_str::ToCompactString;
     use url::Url;
 
@@ -596,4 +1098,210 @@ mod test {
             "https://github.com/watchexec/cargo-watch/releases/download/v9.0.0/cargo-watch-v9.0.0-aarch64-pc-windows-msvc.exe"
         );
     }
+
+    #[test]
+    fn env_var() {
+        let data = Data::new(
+            "cargo-binstall".to_compact_string(),
+            "1.2.3".to_compact_string(),
+            None,
+        );
+        let target_info = leon::vals(|_| None);
+        let mut ctx = Context::from_data_with_repo(
+            &data,
+            "x86_64-unknown-linux-gnu",
+            &target_info,
+            Some(".tgz"),
+            None,
+            None,
+        );
+        ctx.env = |var| (var == "BUILD_ID").then(|| "42".to_owned());
+
+        assert_eq!(
+            ctx.render_url("https://example.com/{ env:BUILD_ID }/{ name }-{ target }.tgz")
+                .unwrap(),
+            Url::parse("https://example.com/42/cargo-binstall-x86_64-unknown-linux-gnu.tgz")
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn missing_env_var() {
+        let data = Data::new(
+            "cargo-binstall".to_compact_string(),
+            "1.2.3".to_compact_string(),
+            None,
+        );
+        let target_info = leon::vals(|_| None);
+        let mut ctx = Context::from_data_with_repo(
+            &data,
+            "x86_64-unknown-linux-gnu",
+            &target_info,
+            None,
+            None,
+            None,
+        );
+        ctx.env = |_| None;
+
+        assert!(matches!(
+            ctx.render_url("https://example.com/{ env:BUILD_ID }.tgz"),
+            Err(FetchError::MissingEnvVar(var)) if &*var == "BUILD_ID"
+        ));
+    }
+
+    #[test]
+    fn mirror() {
+        let data = Data::new(
+            "cargo-binstall".to_compact_string(),
+            "1.2.3".to_compact_string(),
+            None,
+        );
+        let target_info = leon::vals(|_| None);
+        let mut ctx = Context::from_data_with_repo(
+            &data,
+            "x86_64-unknown-linux-gnu",
+            &target_info,
+            Some(".tgz"),
+            None,
+            None,
+        );
+        ctx.with_mirror(Some("https://dl-2.example.com"));
+
+        assert_eq!(
+            ctx.render_url("{ mirror }/{ name }/{ version }/{ name }-{ target }{ archive-suffix }")
+                .unwrap(),
+            Url::parse(
+                "https://dl-2.example.com/cargo-binstall/1.2.3/cargo-binstall-x86_64-unknown-linux-gnu.tgz"
+            )
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn tag() {
+        let data = Data::new(
+            "cargo-binstall".to_compact_string(),
+            "1.2.3".to_compact_string(),
+            Some("https://github.com/cargo-bins/cargo-binstall".to_string()),
+        );
+        let target_info = leon::vals(|_| None);
+        let mut ctx = Context::from_data_with_repo(
+            &data,
+            "x86_64-unknown-linux-gnu",
+            &target_info,
+            Some(".tgz"),
+            data.repo.as_deref(),
+            None,
+        );
+        let template = "{ repo }/releases/download/{ tag }/{ name }-{ target }{ archive-suffix }";
+
+        assert_eq!(
+            ctx.render_url(template).unwrap(),
+            Url::parse(
+                "https://github.com/cargo-bins/cargo-binstall/releases/download/1.2.3/cargo-binstall-x86_64-unknown-linux-gnu.tgz"
+            )
+            .unwrap()
+        );
+
+        ctx.with_tag(Some("release-2024-01"));
+        assert_eq!(
+            ctx.render_url(template).unwrap(),
+            Url::parse(
+                "https://github.com/cargo-bins/cargo-binstall/releases/download/release-2024-01/cargo-binstall-x86_64-unknown-linux-gnu.tgz"
+            )
+            .unwrap()
+        );
+    }
+
+    fn gh_crate_meta(bitbucket: bool, pkg_url: Option<&str>) -> GhCrateMeta {
+        let client = Client::new(
+            concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION")),
+            None,
+            NonZeroU16::new(10).unwrap(),
+            1.try_into().unwrap(),
+            [],
+        )
+        .unwrap();
+
+        GhCrateMeta::create(
+            client.clone(),
+            GhApiClient::new(client, None),
+            Arc::new(
+                Data::new("crate".into(), "1.2.3".into(), None)
+                    .with_bitbucket_token(Some("token".into())),
+            ),
+            Arc::new(TargetData {
+                target: "x86_64-unknown-linux-gnu".into(),
+                meta: PkgMeta {
+                    pkg_url: pkg_url.map(String::from),
+                    ..Default::default()
+                },
+                target_related_info: Vec::<(String, String)>::new(),
+            }),
+            SignaturePolicy::IfPresent,
+            bitbucket,
+        )
+    }
+
+    fn repo_info(repo: &str) -> RepoInfo {
+        let repo = Url::parse(repo).unwrap();
+        RepoInfo {
+            repository_host: RepositoryHost::guess_git_hosting_services(&repo),
+            repo,
+            subcrate: None,
+        }
+    }
+
+    #[test]
+    fn bitbucket_crates_are_looked_for_by_both() {
+        let bitbucket = repo_info("https://bitbucket.org/someone/krate");
+        let github = repo_info("https://github.com/someone/krate");
+
+        assert!(gh_crate_meta(false, None).looks_for(Some(&bitbucket)));
+        assert!(gh_crate_meta(false, None).looks_for(Some(&github)));
+        assert!(gh_crate_meta(false, None).looks_for(None));
+
+        assert!(gh_crate_meta(true, None).looks_for(Some(&bitbucket)));
+        assert!(!gh_crate_meta(true, None).looks_for(Some(&github)));
+        assert!(!gh_crate_meta(true, None).looks_for(None));
+        // Custom urls are left to GhCrateMeta.
+        assert!(
+            !gh_crate_meta(true, Some("https://example.com/{ name }.tgz"))
+                .looks_for(Some(&bitbucket))
+        );
+    }
+
+    #[test]
+    fn bitbucket_token_only_sent_by_bitbucket_fetcher() {
+        let url = Url::parse("https://bitbucket.org/someone/krate/downloads/krate.tgz").unwrap();
+
+        assert_eq!(
+            gh_crate_meta(true, None).auth_for(&url).as_deref(),
+            Some("Bearer token")
+        );
+        assert_eq!(gh_crate_meta(false, None).auth_for(&url), None);
+        assert_eq!(
+            gh_crate_meta(true, None)
+                .auth_for(&Url::parse("https://example.com/krate.tgz").unwrap()),
+            None
+        );
+    }
+
+    #[test]
+    fn bitbucket_fetcher_name() {
+        let fetcher = BitbucketCrateMeta(Arc::new(gh_crate_meta(true, None)));
+
+        assert_eq!(fetcher.fetcher_name(), "BitbucketCrateMeta");
+        assert_eq!(gh_crate_meta(false, None).fetcher_name(), "GhCrateMeta");
+    }
+
+    #[test]
+    fn lz4_not_probed_by_default() {
+        let pkg_fmts: Vec<_> = default_pkg_fmts().collect();
+
+        assert!(pkg_fmts.contains(&PkgFmt::Tgz));
+        assert!(pkg_fmts.contains(&PkgFmt::Bin));
+        assert!(!pkg_fmts.contains(&PkgFmt::Tlz4));
+        assert!(!pkg_fmts.contains(&PkgFmt::Lz4));
+    }
 }
@@ -36,6 +36,7 @@ const GITHUB_RELEASE_PATHS: &[Template<'_>] = &[
     // %2F is escaped form of '/'
     template!("{ repo }/releases/download/{ subcrate }%2F{ version }"),
     template!("{ repo }/releases/download/{ subcrate }%2Fv{ version }"),
+    template!("{ repo }/releases/download/{ tag }"),
 ];
 
 const GITLAB_RELEASE_PATHS: &[Template<'_>] = &[
@@ -44,6 +45,16 @@ const GITLAB_RELEASE_PATHS: &[Template<'_>] = &[
     // %2F is escaped form of '/'
     template!("{ repo }/-/releases/{ subcrate }%2F{ version }/downloads/binaries"),
     template!("{ repo }/-/releases/{ subcrate }%2Fv{ version }/downloads/binaries"),
+    template!("{ repo }/-/releases/{ tag }/downloads/binaries"),
+];
+
+/// Downloads named after the tag of the release, for repositories whose tags
+/// are not the version. There is no per-release directory to put it in.
+pub const TAG_FILENAMES: &[Template<'_>] = &[
+    template!("/{ name }-{ tag }-{ target }{ archive-suffix }"),
+    template!("/{ name }-{ target }-{ tag }{ archive-suffix }"),
+    template!("/{ name }_{ tag }_{ target }{ archive-suffix }"),
+    template!("/{ name }_{ target }_{ tag }{ archive-suffix }"),
 ];
 
 const BITBUCKET_RELEASE_PATHS: &[Template<'_>] = &[template!("{ repo }/downloads")];
@@ -54,6 +65,7 @@ const SOURCEFORGE_RELEASE_PATHS: &[Template<'_>] = &[
     // %2F is escaped form of '/'
     template!("{ repo }/files/binaries/{ subcrate }%2F{  version }"),
     template!("{ repo }/files/binaries/{ subcrate }%2Fv{ version }"),
+    template!("{ repo }/files/binaries/{ tag }"),
 ];
 
 impl RepositoryHost {
@@ -87,7 +99,7 @@ impl RepositoryHost {
             )),
             BitBucket => Some(apply_filenames_to_paths(
                 BITBUCKET_RELEASE_PATHS,
-                &[FULL_FILENAMES],
+                &[FULL_FILENAMES, TAG_FILENAMES],
                 "",
             )),
             SourceForge => Some(apply_filenames_to_paths(
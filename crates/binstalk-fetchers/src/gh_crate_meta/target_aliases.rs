@@ -0,0 +1,128 @@
+/// Names commonly used by packages for the architecture of a target.
+fn arch_aliases(arch: &str) -> &'static [&'static str] {
+    match arch {
+        "x86_64" => &["x86_64", "amd64"],
+        "aarch64" => &["aarch64", "arm64"],
+        "i686" => &["i686", "x86"],
+        _ => &[],
+    }
+}
+
+/// Names commonly used by packages for the operating system of a target.
+///
+/// Packages only named after Linux are assumed to be linked against glibc,
+/// so musl targets only use the names mentioning musl, and Android, whose
+/// triples also contain `-linux`, none.
+fn os_aliases(target: &str) -> &'static [&'static str] {
+    if target.ends_with("-linux-gnu") {
+        &["linux", "linux-gnu"]
+    } else if target.ends_with("-linux-musl") {
+        &["linux-musl"]
+    } else if target.ends_with("-apple-darwin") {
+        &["darwin", "macos"]
+    } else if target.contains("-windows") {
+        &["windows"]
+    } else if target.ends_with("-freebsd") {
+        &["freebsd"]
+    } else {
+        &[]
+    }
+}
+
+/// Return the names packages commonly use for `target` instead of its
+/// triple, e.g. `amd64-linux` or `linux-x86_64` for
+/// `x86_64-unknown-linux-gnu`.
+pub(super) fn default_target_aliases(target: &str) -> Vec<String> {
+    let arch = target.split('-').next().unwrap_or_default();
+    let arches = arch_aliases(arch);
+    let oses = os_aliases(target);
+
+    let arch_first = oses
+        .iter()
+        .flat_map(|os| arches.iter().map(move |arch| format!("{arch}-{os}")));
+    let os_first = oses
+        .iter()
+        .flat_map(|os| arches.iter().map(move |arch| format!("{os}-{arch}")));
+
+    arch_first
+        .chain(os_first)
+        .filter(|alias| alias != target)
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_default_target_aliases() {
+        let aliases = default_target_aliases("x86_64-unknown-linux-gnu");
+        assert_eq!(
+            aliases,
+            [
+                "x86_64-linux",
+                "amd64-linux",
+                "x86_64-linux-gnu",
+                "amd64-linux-gnu",
+                "linux-x86_64",
+                "linux-amd64",
+                "linux-gnu-x86_64",
+                "linux-gnu-amd64"
+            ]
+        );
+
+        let aliases = default_target_aliases("aarch64-apple-darwin");
+        assert!(aliases.contains(&"arm64-macos".to_string()));
+        assert!(aliases.contains(&"darwin-arm64".to_string()));
+
+        assert_eq!(
+            default_target_aliases("i686-pc-windows-msvc"),
+            ["i686-windows", "x86-windows", "windows-i686", "windows-x86"]
+        );
+
+        assert!(default_target_aliases("riscv64gc-unknown-linux-gnu").is_empty());
+        assert!(default_target_aliases("x86_64-unknown-netbsd").is_empty());
+    }
+
+    #[test]
+    fn test_musl_target_aliases() {
+        let aliases = default_target_aliases("x86_64-unknown-linux-musl");
+        assert_eq!(
+            aliases,
+            [
+                "x86_64-linux-musl",
+                "amd64-linux-musl",
+                "linux-musl-x86_64",
+                "linux-musl-amd64"
+            ]
+        );
+
+        // Packages of glibc targets are not picked for musl ones, and
+        // conversely.
+        assert!(!aliases.contains(&"x86_64-linux".to_string()));
+        assert!(!default_target_aliases("x86_64-unknown-linux-gnu")
+            .iter()
+            .any(|alias| alias.contains("musl")));
+    }
+
+    #[test]
+    fn test_android_target_aliases() {
+        for target in [
+            "aarch64-linux-android",
+            "x86_64-linux-android",
+            "armv7-linux-androideabi",
+        ] {
+            assert!(default_target_aliases(target).is_empty(), "{target}");
+        }
+    }
+
+    #[test]
+    fn test_target_aliases_exclude_triple() {
+        // The triple itself is always tried first, not as an alias.
+        assert!(!default_target_aliases("x86_64-unknown-linux-gnu")
+            .contains(&"x86_64-unknown-linux-gnu".to_string()));
+        assert!(
+            !default_target_aliases("x86_64-linux-gnu").contains(&"x86_64-linux-gnu".to_string())
+        );
+    }
+}
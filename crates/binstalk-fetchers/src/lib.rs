@@ -1,11 +1,18 @@
 #![cfg_attr(docsrs, feature(doc_auto_cfg))]
 
-use std::{path::Path, sync::Arc};
+use std::{
+    path::{Path, PathBuf},
+    process::ExitStatus,
+    sync::Arc,
+    time::SystemTime,
+};
 
 use binstalk_downloader::{
-    download::DownloadError, gh_api_client::GhApiError, remote::Error as RemoteError,
+    download::DownloadError,
+    gh_api_client::GhApiError,
+    remote::{Error as RemoteError, StatusCode},
 };
-use binstalk_types::cargo_toml_binstall::SigningAlgorithm;
+use binstalk_types::cargo_toml_binstall::{ChecksumAlgorithm, SigningAlgorithm};
 use thiserror::Error as ThisError;
 use tokio::sync::OnceCell;
 pub use url::ParseError as UrlParseError;
@@ -18,12 +25,26 @@ mod quickinstall;
 #[cfg(feature = "quickinstall")]
 pub use quickinstall::*;
 
+#[cfg(feature = "external-fetcher")]
+mod external;
+#[cfg(feature = "external-fetcher")]
+pub use external::ExternalFetcher;
+
+mod gh_actions;
+pub use gh_actions::GhActionsArtifact;
+
+mod releases_index;
+use releases_index::ReleasesIndex;
+pub use releases_index::ReleasesIndexFetcher;
+
 mod common;
 use common::*;
 
 mod signing;
 use signing::*;
 
+mod checksum;
+
 mod futures_resolver;
 
 use gh_crate_meta::hosting::RepositoryHost;
@@ -53,6 +74,9 @@ pub enum FetchError {
     #[diagnostic(transparent)]
     TemplateRender(#[from] leon::RenderError),
 
+    #[error("Environment variable `{0}` used in the template is not set")]
+    MissingEnvVar(Box<str>),
+
     #[error("Failed to render template: {0}")]
     GhApi(#[from] GhApiError),
 
@@ -65,11 +89,66 @@ pub enum FetchError {
     #[error("Signing algorithm not supported: {0:?}")]
     UnsupportedSigningAlgorithm(SigningAlgorithm),
 
+    #[error("Checksum algorithm not supported: {0:?}")]
+    UnsupportedChecksumAlgorithm(ChecksumAlgorithm),
+
     #[error("No signature present")]
     MissingSignature,
 
     #[error("Failed to verify signature")]
     InvalidSignature,
+
+    #[error("No checksum present")]
+    MissingChecksum,
+
+    #[error("Failed to verify checksum")]
+    InvalidChecksum,
+
+    #[error("External fetcher failed: {0}")]
+    ExternalFetcher(Box<str>),
+
+    #[error("Mirror is unavailable, it responded with {0}")]
+    MirrorUnavailable(StatusCode),
+
+    #[error("Extraction command {} exited with {status}", command.display())]
+    ExtractCommand {
+        command: PathBuf,
+        status: ExitStatus,
+    },
+
+    #[error("GitHub Actions artifact: {0}")]
+    GhActionsArtifact(Box<str>),
+
+    #[error(
+        "Artifact {artifact} of the latest successful run of workflow {workflow} has expired, \
+         the workflow must be run again to install it"
+    )]
+    GhActionsArtifactExpired {
+        artifact: CompactString,
+        workflow: CompactString,
+    },
+
+    #[error("Releases index: {0}")]
+    ReleasesIndex(Box<str>),
+
+    #[error(
+        "GitHub rate limit exceeded while checking {url}, {}",
+        fmt_rate_limit_reset(*reset)
+    )]
+    GhRateLimit {
+        url: Box<str>,
+        reset: Option<SystemTime>,
+    },
+}
+
+fn fmt_rate_limit_reset(reset: Option<SystemTime>) -> String {
+    match reset.map(|reset| reset.duration_since(SystemTime::now()).unwrap_or_default()) {
+        Some(duration) => format!(
+            "it resets in {}s, pass --wait-rate-limit to wait for it or supply a GitHub token",
+            duration.as_secs()
+        ),
+        None => "pass --wait-rate-limit to wait for it or supply a GitHub token".to_owned(),
+    }
 }
 
 impl From<RemoteError> for FetchError {
@@ -99,7 +178,14 @@ pub trait Fetcher: Send + Sync {
         Self: Sized;
 
     /// Fetch a package and extract
-    async fn fetch_and_extract(&self, dst: &Path) -> Result<ExtractedFiles, FetchError>;
+    ///
+    ///  * `members` - if `Some`, then only the files at these paths
+    ///    (relative to the root of the archive) are extracted.
+    async fn fetch_and_extract(
+        &self,
+        dst: &Path,
+        members: Option<&[PathBuf]>,
+    ) -> Result<ExtractedFiles, FetchError>;
 
     /// Find the package, if it is available for download
     ///
@@ -118,6 +204,26 @@ pub trait Fetcher: Send + Sync {
     /// Return the package format
     fn pkg_fmt(&self) -> PkgFmt;
 
+    /// Return the url the package is downloaded from, once found.
+    ///
+    /// Return `None` if the package is not downloaded from a url.
+    fn package_url(&self) -> Option<Url> {
+        None
+    }
+
+    /// Return the sha256 digest of the package downloaded from
+    /// [`Fetcher::package_url`], once extracted by
+    /// [`Fetcher::fetch_and_extract`].
+    fn package_sha256(&self) -> Option<CompactString> {
+        None
+    }
+
+    /// Return what [`Fetcher::fetch_and_extract`] verified about the
+    /// package, once extracted.
+    fn verifications(&self) -> Verifications {
+        Verifications::default()
+    }
+
     /// Return finalized target meta.
     fn target_meta(&self) -> PkgMeta;
 
@@ -140,6 +246,22 @@ pub trait Fetcher: Send + Sync {
     fn target_data(&self) -> &Arc<TargetDataErased>;
 }
 
+/// What [`Fetcher::fetch_and_extract`] verified about a package.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Verifications {
+    /// Trusted comment of the signature verified, if any.
+    pub signature: Option<String>,
+    /// Algorithm of the checksum verified, if any.
+    pub checksum: Option<ChecksumAlgorithm>,
+}
+
+impl Verifications {
+    /// Return true if nothing was verified.
+    pub fn is_empty(&self) -> bool {
+        self.signature.is_none() && self.checksum.is_none()
+    }
+}
+
 #[derive(Clone, Debug)]
 struct RepoInfo {
     repo: Url,
@@ -167,6 +289,13 @@ pub struct Data {
     version: CompactString,
     repo: Option<String>,
     repo_info: OnceCell<Option<RepoInfo>>,
+    releases_index: OnceCell<ReleasesIndex>,
+    prefer_smallest: bool,
+    external_fetcher: Option<PathBuf>,
+    keep_download: Option<PathBuf>,
+    extract_command: Option<PathBuf>,
+    bitbucket_token: Option<CompactString>,
+    wait_rate_limit: bool,
 }
 
 impl Data {
@@ -176,9 +305,58 @@ impl Data {
             version,
             repo,
             repo_info: OnceCell::new(),
+            releases_index: OnceCell::new(),
+            prefer_smallest: false,
+            external_fetcher: None,
+            keep_download: None,
+            extract_command: None,
+            bitbucket_token: None,
+            wait_rate_limit: false,
         }
     }
 
+    /// If multiple packages are found for a target, pick the one with
+    /// the smallest `Content-Length` instead of the first one found.
+    pub fn with_prefer_smallest(mut self, prefer_smallest: bool) -> Self {
+        self.prefer_smallest = prefer_smallest;
+        self
+    }
+
+    /// Program run by [`ExternalFetcher`] to look for the package.
+    pub fn with_external_fetcher(mut self, external_fetcher: Option<PathBuf>) -> Self {
+        self.external_fetcher = external_fetcher;
+        self
+    }
+
+    /// Keep a copy of the downloaded package in `keep_download`, under the
+    /// file name it is downloaded as.
+    pub fn with_keep_download(mut self, keep_download: Option<PathBuf>) -> Self {
+        self.keep_download = keep_download;
+        self
+    }
+
+    /// Extract the `bin` packages, i.e. those in formats binstall does not
+    /// support, by running `extract_command` with the paths of the package
+    /// and of the directory to extract it to.
+    pub fn with_extract_command(mut self, extract_command: Option<PathBuf>) -> Self {
+        self.extract_command = extract_command;
+        self
+    }
+
+    /// Access token of the Bitbucket repository or workspace, used by
+    /// [`BitbucketCrateMeta`] to download from private repositories.
+    pub fn with_bitbucket_token(mut self, bitbucket_token: Option<CompactString>) -> Self {
+        self.bitbucket_token = bitbucket_token;
+        self
+    }
+
+    /// Wait for the GitHub rate limit to be reset when a download url
+    /// responds with 403 for it, instead of failing.
+    pub fn with_wait_rate_limit(mut self, wait_rate_limit: bool) -> Self {
+        self.wait_rate_limit = wait_rate_limit;
+        self
+    }
+
     #[instrument(level = "debug")]
     async fn get_repo_info(&self, client: &Client) -> Result<&Option<RepoInfo>, FetchError> {
         self.repo_info
@@ -1,14 +1,18 @@
-use std::{borrow::Cow, path::Path, sync::Arc};
+use std::{
+    borrow::Cow,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
-use binstalk_downloader::remote::Method;
+use binstalk_downloader::{download::DataVerifier, remote::Method};
 use binstalk_types::cargo_toml_binstall::{PkgFmt, PkgMeta, PkgSigning};
 use tokio::sync::OnceCell;
 use tracing::{error, info, trace};
 use url::Url;
 
 use crate::{
-    common::*, Data, FetchError, SignaturePolicy, SignatureVerifier, SigningAlgorithm,
-    TargetDataErased,
+    checksum::Sha256DataVerifier, common::*, Data, FetchError, SignaturePolicy, SignatureVerifier,
+    SigningAlgorithm, TargetDataErased, Verifications,
 };
 
 const BASE_URL: &str = "https://github.com/cargo-bins/cargo-quickinstall/releases/download";
@@ -59,6 +63,11 @@ pub struct QuickInstall {
     signature_url: Url,
     stats_url: Url,
     signature_policy: SignaturePolicy,
+    keep_download: Option<PathBuf>,
+    extract_command: Option<PathBuf>,
+    wait_rate_limit: bool,
+    package_sha256: once_cell::sync::OnceCell<CompactString>,
+    verifications: once_cell::sync::OnceCell<Verifications>,
 
     target_data: Arc<TargetDataErased>,
 }
@@ -107,6 +116,11 @@ impl super::Fetcher for QuickInstall {
                 .expect("stats_url is pre-generated and should never be invalid url"),
             package,
             signature_policy,
+            keep_download: data.keep_download.clone(),
+            extract_command: data.extract_command.clone(),
+            wait_rate_limit: data.wait_rate_limit,
+            package_sha256: Default::default(),
+            verifications: Default::default(),
 
             target_data,
         })
@@ -123,6 +137,7 @@ impl super::Fetcher for QuickInstall {
                     self.client.clone(),
                     self.gh_api_client.clone(),
                     &self.signature_url,
+                    self.wait_rate_limit,
                 )
                 .await
                 .map_err(|_| FetchError::MissingSignature)?;
@@ -132,6 +147,7 @@ impl super::Fetcher for QuickInstall {
                 self.client.clone(),
                 self.gh_api_client.clone(),
                 &self.package_url,
+                self.wait_rate_limit,
             )
             .await
         })
@@ -159,7 +175,11 @@ by rust officially."#,
         }
     }
 
-    async fn fetch_and_extract(&self, dst: &Path) -> Result<ExtractedFiles, FetchError> {
+    async fn fetch_and_extract(
+        &self,
+        dst: &Path,
+        members: Option<&[PathBuf]>,
+    ) -> Result<ExtractedFiles, FetchError> {
         let verifier = if self.signature_policy == SignaturePolicy::Ignore {
             SignatureVerifier::Noop
         } else {
@@ -190,19 +210,39 @@ by rust officially."#,
         };
 
         debug!(url=%self.package_url, "Downloading package");
-        let mut data_verifier = verifier.data_verifier()?;
-        let files = Download::new_with_data_verifier(
-            self.client.clone(),
-            self.package_url.clone(),
-            data_verifier.as_mut(),
+        let mut inner_verifier = verifier.data_verifier()?;
+        let mut data_verifier = Sha256DataVerifier::new(inner_verifier.as_mut());
+        let (download, copy) = keep_download(
+            Download::new_with_data_verifier(
+                self.client.clone(),
+                self.package_url.clone(),
+                &mut data_verifier,
+            ),
+            &self.package_url,
+            self.keep_download.as_deref(),
+        );
+        let files = extract_download(
+            download,
+            self.pkg_fmt(),
+            dst,
+            members,
+            self.extract_command.as_deref(),
         )
-        .and_extract(self.pkg_fmt(), dst)
         .await?;
         trace!("validating signature (if any)");
         if data_verifier.validate() {
-            if let Some(info) = verifier.info() {
+            let signature = verifier.info();
+            if let Some(info) = &signature {
                 info!("Verified signature for package '{}': {info}", self.package);
             }
+            self.verifications
+                .set(Verifications {
+                    signature,
+                    ..Default::default()
+                })
+                .ok();
+            self.package_sha256.set(data_verifier.digest().into()).ok();
+            persist_download(copy).await?;
             Ok(files)
         } else {
             Err(FetchError::InvalidSignature)
@@ -213,10 +253,24 @@ by rust officially."#,
         PkgFmt::Tgz
     }
 
+    fn package_url(&self) -> Option<Url> {
+        Some(self.package_url.clone())
+    }
+
+    fn package_sha256(&self) -> Option<CompactString> {
+        self.package_sha256.get().cloned()
+    }
+
+    fn verifications(&self) -> Verifications {
+        self.verifications.get().cloned().unwrap_or_default()
+    }
+
     fn target_meta(&self) -> PkgMeta {
         let mut meta = self.target_data.meta.clone();
         meta.pkg_fmt = Some(self.pkg_fmt());
         meta.bin_dir = Some("{ bin }{ binary-ext }".to_string());
+        // Packages are built from source, so binaries are named after their product
+        meta.bin_source_names = Default::default();
         meta
     }
 
@@ -0,0 +1,523 @@
+//! Fetcher selecting the package in a releases index, a JSON document
+//! published by the project which lists the assets of every version.
+//!
+//! It is configured in the crate's metadata:
+//!
+//! ```toml
+//! [package.metadata.binstall]
+//! releases-index = "https://example.com/{ name }/releases.json"
+//! ```
+//!
+//! The index is fetched once per crate and has to follow this schema:
+//!
+//! ```json
+//! {
+//!   "schema-version": 1,
+//!   "versions": {
+//!     "1.2.3": {
+//!       "targets": {
+//!         "x86_64-unknown-linux-gnu": {
+//!           "url": "https://example.com/tool-1.2.3-x86_64-unknown-linux-gnu.tgz",
+//!           "pkg-fmt": "tgz",
+//!           "checksum": { "algorithm": "sha256", "digest": "..." }
+//!         }
+//!       }
+//!     }
+//!   }
+//! }
+//! ```
+//!
+//! `url` may be relative to the index, `pkg-fmt` is optional and guessed
+//! from the url otherwise, and `checksum` is optional, `algorithm`
+//! defaulting to sha256. Indexes of another `schema-version` are rejected.
+//!
+//! The packages are verified against the `signing` of the crate like for
+//! [`GhCrateMeta`](crate::GhCrateMeta), `signing.file` being rendered with
+//! `name`, `version` and `url`, the url of the package.
+
+use std::{
+    borrow::Cow,
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use binstalk_downloader::download::DataVerifier;
+use binstalk_types::cargo_toml_binstall::ChecksumAlgorithm;
+use leon::Template;
+use serde::Deserialize;
+use tokio::sync::OnceCell;
+use tracing::info;
+
+use crate::{
+    checksum::{Checksum, ChecksumDataVerifier, Sha256DataVerifier},
+    common::*,
+    Data, FetchError, SignaturePolicy, SignatureVerifier, TargetDataErased, Verifications,
+};
+
+/// Version of the schema of the index supported.
+const SCHEMA_VERSION: u32 = 1;
+
+/// Maximum size of the index.
+const INDEX_MAX_SIZE: u64 = 16 * 1024 * 1024;
+const INDEX_TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct SchemaVersion {
+    schema_version: u32,
+}
+
+/// Content of a releases index.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) struct ReleasesIndex {
+    versions: BTreeMap<CompactString, IndexVersion>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct IndexVersion {
+    targets: BTreeMap<CompactString, IndexAsset>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct IndexAsset {
+    url: String,
+    #[serde(default)]
+    pkg_fmt: Option<PkgFmt>,
+    #[serde(default)]
+    checksum: Option<IndexChecksum>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct IndexChecksum {
+    #[serde(default)]
+    algorithm: ChecksumAlgorithm,
+    /// Digest encoded in base16.
+    digest: String,
+}
+
+impl ReleasesIndex {
+    fn parse(index: &[u8]) -> Result<Self, FetchError> {
+        let invalid = |err: serde_json::Error| {
+            FetchError::ReleasesIndex(format!("invalid releases index: {err}").into())
+        };
+
+        let SchemaVersion { schema_version } = serde_json::from_slice(index).map_err(invalid)?;
+        if schema_version != SCHEMA_VERSION {
+            return Err(FetchError::ReleasesIndex(
+                format!(
+                    "unsupported releases index schema-version {schema_version}, \
+                     only {SCHEMA_VERSION} is supported"
+                )
+                .into(),
+            ));
+        }
+
+        serde_json::from_slice(index).map_err(invalid)
+    }
+
+    async fn fetch(client: &Client, url: &Url) -> Result<Self, FetchError> {
+        debug!(%url, "Downloading releases index");
+        let index = Download::new(client.clone(), url.clone())
+            .into_bytes_bounded(INDEX_MAX_SIZE, INDEX_TIMEOUT)
+            .await?;
+        Self::parse(&index)
+    }
+
+    fn asset(&self, version: &str, target: &str) -> Option<&IndexAsset> {
+        self.versions.get(version)?.targets.get(target)
+    }
+}
+
+#[derive(Debug)]
+struct Resolved {
+    url: Url,
+    pkg_fmt: PkgFmt,
+    checksum: Option<IndexChecksum>,
+}
+
+pub struct ReleasesIndexFetcher {
+    client: Client,
+    data: Arc<Data>,
+    target_data: Arc<TargetDataErased>,
+    signature_policy: SignaturePolicy,
+    resolution: OnceCell<Option<Resolved>>,
+    package_sha256: OnceCell<CompactString>,
+    verifications: OnceCell<Verifications>,
+}
+
+struct Context<'a> {
+    name: &'a str,
+    version: &'a str,
+    /// Url of the package, only for `signing.file`
+    url: Option<&'a Url>,
+}
+
+impl leon::Values for Context<'_> {
+    fn get_value<'s>(&'s self, key: &str) -> Option<Cow<'s, str>> {
+        match key {
+            "name" => Some(Cow::Borrowed(self.name)),
+            "version" => Some(Cow::Borrowed(self.version)),
+            "url" => self.url.map(|url| Cow::Borrowed(url.as_str())),
+            _ => None,
+        }
+    }
+}
+
+impl ReleasesIndexFetcher {
+    async fn resolve(&self, index_url: &str) -> Result<Option<Resolved>, FetchError> {
+        let index_url = Url::parse(&Template::parse(index_url)?.render(&Context {
+            name: &self.data.name,
+            version: &self.data.version,
+            url: None,
+        })?)?;
+
+        let index = self
+            .data
+            .releases_index
+            .get_or_try_init(|| ReleasesIndex::fetch(&self.client, &index_url))
+            .await?;
+
+        let target = &self.target_data.target;
+        let Some(asset) = index.asset(&self.data.version, target) else {
+            debug!(
+                "Releases index has no asset for version {} on {target}",
+                self.data.version
+            );
+            return Ok(None);
+        };
+
+        let url = index_url.join(&asset.url)?;
+        let pkg_fmt = asset
+            .pkg_fmt
+            .or_else(|| PkgFmt::guess_pkg_format(url.path()))
+            .or(self.target_data.meta.pkg_fmt)
+            .ok_or_else(|| {
+                FetchError::ReleasesIndex(
+                    format!("cannot determine the format of the package at {url}").into(),
+                )
+            })?;
+
+        Ok(Some(Resolved {
+            url,
+            pkg_fmt,
+            checksum: asset.checksum.clone(),
+        }))
+    }
+
+    fn resolved(&self) -> &Resolved {
+        self.resolution
+            .get()
+            .and_then(Option::as_ref)
+            .expect("find() is called first")
+    }
+
+    /// Download the signature of the package if the crate declares
+    /// `signing`, following the same rules as `GhCrateMeta`.
+    async fn signature_verifier(&self, url: &Url) -> Result<SignatureVerifier, FetchError> {
+        match (self.signature_policy, &self.target_data.meta.signing) {
+            (SignaturePolicy::Ignore, _) | (SignaturePolicy::IfPresent, None) => {
+                Ok(SignatureVerifier::Noop)
+            }
+            (SignaturePolicy::Require, None) => Err(FetchError::MissingSignature),
+            (_, Some(config)) => {
+                let template = match config.file.as_deref() {
+                    Some(file) => Template::parse(file)?,
+                    None => leon_macros::template!("{ url }.sig"),
+                };
+                let sign_url = Url::parse(&template.render(&Context {
+                    name: &self.data.name,
+                    version: &self.data.version,
+                    url: Some(url),
+                })?)?;
+
+                debug!(%sign_url, "Downloading signature");
+                let signature = Download::new(self.client.clone(), sign_url)
+                    .into_bytes_bounded(SIDECAR_MAX_SIZE, SIDECAR_TIMEOUT)
+                    .await?;
+
+                SignatureVerifier::new(config, &signature)
+            }
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl super::Fetcher for ReleasesIndexFetcher {
+    fn new(
+        client: Client,
+        _gh_api_client: GhApiClient,
+        data: Arc<Data>,
+        target_data: Arc<TargetDataErased>,
+        signature_policy: SignaturePolicy,
+    ) -> Arc<dyn super::Fetcher> {
+        Arc::new(Self {
+            client,
+            data,
+            target_data,
+            signature_policy,
+            resolution: OnceCell::new(),
+            package_sha256: OnceCell::new(),
+            verifications: OnceCell::new(),
+        })
+    }
+
+    fn find(self: Arc<Self>) -> JoinHandle<Result<bool, FetchError>> {
+        tokio::spawn(async move {
+            let Some(index_url) = &self.target_data.meta.releases_index else {
+                return Ok(false);
+            };
+
+            let resolution = self
+                .resolution
+                .get_or_try_init(|| self.resolve(index_url))
+                .await?;
+            Ok(resolution.is_some())
+        })
+    }
+
+    async fn fetch_and_extract(
+        &self,
+        dst: &Path,
+        members: Option<&[PathBuf]>,
+    ) -> Result<ExtractedFiles, FetchError> {
+        let resolved = self.resolved();
+        let verifier = self.signature_verifier(&resolved.url).await?;
+        let checksum = resolved
+            .checksum
+            .as_ref()
+            .map(|checksum| Checksum::new(checksum.algorithm, &checksum.digest));
+
+        debug!(url = %resolved.url, "Downloading package");
+        let mut data_verifier = verifier.data_verifier()?;
+        let mut checksum_verifier = ChecksumDataVerifier::new(data_verifier.as_mut(), checksum)?;
+        let mut data_verifier = Sha256DataVerifier::new(&mut checksum_verifier);
+        let (download, copy) = keep_download(
+            Download::new_with_data_verifier(
+                self.client.clone(),
+                resolved.url.clone(),
+                &mut data_verifier,
+            ),
+            &resolved.url,
+            self.data.keep_download.as_deref(),
+        );
+        let files = extract_download(
+            download,
+            resolved.pkg_fmt,
+            dst,
+            members,
+            self.data.extract_command.as_deref(),
+        )
+        .await?;
+
+        let sha256_digest = data_verifier.digest();
+        let valid_signature = data_verifier.validate();
+
+        checksum_verifier.verify()?;
+        if !valid_signature {
+            return Err(FetchError::InvalidSignature);
+        }
+
+        let signature = verifier.info();
+        if let Some(info) = &signature {
+            info!(
+                "Verified signature for package '{}': {info}",
+                self.data.name
+            );
+        }
+        self.verifications
+            .set(Verifications {
+                signature,
+                checksum: resolved
+                    .checksum
+                    .as_ref()
+                    .map(|checksum| checksum.algorithm),
+            })
+            .ok();
+        self.package_sha256.set(sha256_digest.into()).ok();
+        persist_download(copy).await?;
+
+        Ok(files)
+    }
+
+    fn verifications(&self) -> Verifications {
+        self.verifications.get().cloned().unwrap_or_default()
+    }
+
+    fn pkg_fmt(&self) -> PkgFmt {
+        self.resolved().pkg_fmt
+    }
+
+    fn package_url(&self) -> Option<Url> {
+        Some(self.resolved().url.clone())
+    }
+
+    fn package_sha256(&self) -> Option<CompactString> {
+        self.package_sha256.get().cloned()
+    }
+
+    fn target_meta(&self) -> PkgMeta {
+        let mut meta = self.target_data.meta.clone();
+        meta.pkg_fmt = Some(self.pkg_fmt());
+        meta
+    }
+
+    fn source_name(&self) -> CompactString {
+        self.resolution
+            .get()
+            .and_then(Option::as_ref)
+            .and_then(|resolved| resolved.url.host_str())
+            .map(CompactString::from)
+            .unwrap_or_else(|| CompactString::from("releases index"))
+    }
+
+    fn fetcher_name(&self) -> &'static str {
+        "ReleasesIndexFetcher"
+    }
+
+    fn is_third_party(&self) -> bool {
+        false
+    }
+
+    fn target(&self) -> &str {
+        &self.target_data.target
+    }
+
+    fn target_data(&self) -> &Arc<TargetDataErased> {
+        &self.target_data
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::num::NonZeroU16;
+
+    use binstalk_types::cargo_toml_binstall::{PkgSigning, SigningAlgorithm};
+
+    use super::*;
+    use crate::TargetData;
+
+    fn fetcher(
+        signature_policy: SignaturePolicy,
+        signing: Option<PkgSigning>,
+    ) -> ReleasesIndexFetcher {
+        let client = Client::new(
+            concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION")),
+            None,
+            NonZeroU16::new(10).unwrap(),
+            1.try_into().unwrap(),
+            [],
+        )
+        .unwrap();
+
+        ReleasesIndexFetcher {
+            client,
+            data: Arc::new(Data::new("crate".into(), "1.2.3".into(), None)),
+            target_data: Arc::new(TargetData {
+                target: "x86_64-unknown-linux-gnu".into(),
+                meta: PkgMeta {
+                    signing,
+                    ..Default::default()
+                },
+                target_related_info: Vec::<(String, String)>::new(),
+            }),
+            signature_policy,
+            resolution: OnceCell::new(),
+            package_sha256: OnceCell::new(),
+            verifications: OnceCell::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_signature_policy() {
+        let url = Url::parse("https://example.com/crate-1.2.3.tgz").unwrap();
+        // Nothing listens on port 1, so the signature cannot be downloaded.
+        let signing = || {
+            Some(PkgSigning {
+                algorithm: SigningAlgorithm::Minisign,
+                pubkey: "RWRnmBcLmQbXVcEPWo2OOKMI36kki4GiI7gcBgIaPLwvxe14Wtxm9acX".into(),
+                file: Some("http://127.0.0.1:1/{ name }-{ version }.sig".into()),
+            })
+        };
+
+        for signature_policy in [SignaturePolicy::Ignore, SignaturePolicy::IfPresent] {
+            assert!(matches!(
+                fetcher(signature_policy, None)
+                    .signature_verifier(&url)
+                    .await,
+                Ok(SignatureVerifier::Noop)
+            ));
+        }
+        assert!(matches!(
+            fetcher(SignaturePolicy::Ignore, signing())
+                .signature_verifier(&url)
+                .await,
+            Ok(SignatureVerifier::Noop)
+        ));
+
+        assert!(matches!(
+            fetcher(SignaturePolicy::Require, None)
+                .signature_verifier(&url)
+                .await,
+            Err(FetchError::MissingSignature)
+        ));
+
+        // The signature declared by the crate is downloaded, and failing to
+        // do so is fatal.
+        for signature_policy in [SignaturePolicy::IfPresent, SignaturePolicy::Require] {
+            assert!(matches!(
+                fetcher(signature_policy, signing())
+                    .signature_verifier(&url)
+                    .await,
+                Err(FetchError::Download(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_parse_index() {
+        let index = ReleasesIndex::parse(
+            br#"{
+                "schema-version": 1,
+                "versions": {
+                    "1.2.3": {
+                        "targets": {
+                            "x86_64-unknown-linux-gnu": {
+                                "url": "tool-1.2.3.tgz",
+                                "checksum": { "digest": "abcd" }
+                            }
+                        }
+                    }
+                }
+            }"#,
+        )
+        .unwrap();
+
+        let asset = index.asset("1.2.3", "x86_64-unknown-linux-gnu").unwrap();
+        assert_eq!(asset.url, "tool-1.2.3.tgz");
+        assert_eq!(asset.pkg_fmt, None);
+        let checksum = asset.checksum.as_ref().unwrap();
+        assert_eq!(checksum.algorithm, ChecksumAlgorithm::Sha256);
+        assert_eq!(checksum.digest, "abcd");
+
+        assert!(index.asset("1.2.3", "aarch64-apple-darwin").is_none());
+        assert!(index.asset("1.2.4", "x86_64-unknown-linux-gnu").is_none());
+    }
+
+    #[test]
+    fn test_reject_schema_version() {
+        assert!(matches!(
+            ReleasesIndex::parse(br#"{ "schema-version": 2, "versions": {} }"#),
+            Err(FetchError::ReleasesIndex(_))
+        ));
+        assert!(matches!(
+            ReleasesIndex::parse(br#"{ "versions": {} }"#),
+            Err(FetchError::ReleasesIndex(_))
+        ));
+    }
+}
@@ -7,12 +7,12 @@
 //! NLJSON to the file will be understood fine.
 
 use std::{
-    borrow::Borrow,
     cmp,
     collections::{btree_set, BTreeSet},
     fs,
     io::{self, Seek, Write},
     iter::{IntoIterator, Iterator},
+    mem,
     path::{Path, PathBuf},
 };
 
@@ -20,6 +20,7 @@ use compact_str::CompactString;
 use fs_lock::FileLock;
 use home::cargo_home;
 use miette::Diagnostic;
+use semver::Version;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
@@ -108,25 +109,34 @@ impl From<Data> for CrateInfo {
     }
 }
 
-impl Borrow<str> for Data {
-    fn borrow(&self) -> &str {
-        &self.crate_info.name
+impl Data {
+    /// Records are unique per crate, or per version of the crate for the
+    /// versions installed with versioned binaries.
+    fn key(&self) -> (&str, Option<&Version>) {
+        record_key(&self.crate_info)
     }
 }
 
+fn record_key(crate_info: &CrateInfo) -> (&str, Option<&Version>) {
+    (
+        &crate_info.name,
+        crate_info.versioned.then(|| &crate_info.current_version),
+    )
+}
+
 impl PartialEq for Data {
     fn eq(&self, other: &Self) -> bool {
-        self.crate_info.name == other.crate_info.name
+        self.key() == other.key()
     }
 }
 impl PartialEq<CrateInfo> for Data {
     fn eq(&self, other: &CrateInfo) -> bool {
-        self.crate_info.name == other.name
+        self.key() == record_key(other)
     }
 }
 impl PartialEq<Data> for CrateInfo {
     fn eq(&self, other: &Data) -> bool {
-        self.name == other.crate_info.name
+        record_key(self) == other.key()
     }
 }
 impl Eq for Data {}
@@ -139,7 +149,7 @@ impl PartialOrd for Data {
 
 impl Ord for Data {
     fn cmp(&self, other: &Self) -> cmp::Ordering {
-        self.crate_info.name.cmp(&other.crate_info.name)
+        self.key().cmp(&other.key())
     }
 }
 
@@ -158,7 +168,7 @@ impl Records {
         for res in stream_deser {
             let item = res?;
 
-            self.data.replace(item);
+            replace_data(&mut self.data, item);
         }
 
         Ok(())
@@ -188,12 +198,22 @@ impl Records {
         Ok(())
     }
 
+    /// Return the record of the crate, or of its newest version if several
+    /// versions are installed with versioned binaries.
     pub fn get(&self, value: impl AsRef<str>) -> Option<&CrateInfo> {
-        self.data.get(value.as_ref()).map(|data| &data.crate_info)
+        let name = value.as_ref();
+
+        self.data
+            .iter()
+            .rev()
+            .find(|data| data.crate_info.name == name)
+            .map(|data| &data.crate_info)
     }
 
     pub fn contains(&self, value: impl AsRef<str>) -> bool {
-        self.data.contains(value.as_ref())
+        let name = value.as_ref();
+
+        self.data.iter().any(|data| data.crate_info.name == name)
     }
 
     /// Adds a value to the set.
@@ -205,16 +225,36 @@ impl Records {
     }
 
     /// Return the previous `CrateInfo` for the package if there is any.
+    ///
+    /// Installing a crate replaces all the records of it, except for the
+    /// versions installed with versioned binaries, which only replace the
+    /// same version and take over the binaries they share with the other
+    /// ones, e.g. the symlinks.
     pub fn replace(&mut self, value: CrateInfo) -> Option<CrateInfo> {
-        self.data.replace(Data::from(value)).map(CrateInfo::from)
+        replace_data(&mut self.data, Data::from(value)).map(CrateInfo::from)
     }
 
+    /// Remove all the records of the crate.
     pub fn remove(&mut self, value: impl AsRef<str>) -> bool {
-        self.data.remove(value.as_ref())
+        self.take(value).is_some()
     }
 
+    /// Remove all the records of the crate, returning the one [`Records::get`]
+    /// would return.
     pub fn take(&mut self, value: impl AsRef<str>) -> Option<CrateInfo> {
-        self.data.take(value.as_ref()).map(CrateInfo::from)
+        let name = value.as_ref();
+        let mut taken = None;
+
+        self.data.retain(|data| {
+            if data.crate_info.name == name {
+                taken = Some(data.crate_info.clone());
+                false
+            } else {
+                true
+            }
+        });
+
+        taken
     }
 
     pub fn len(&self) -> usize {
@@ -226,6 +266,31 @@ impl Records {
     }
 }
 
+fn replace_data(records: &mut BTreeSet<Data>, value: Data) -> Option<Data> {
+    let new = &value.crate_info;
+    let mut previous = None;
+
+    *records = mem::take(records)
+        .into_iter()
+        .filter_map(|mut data| {
+            if data.crate_info.name != new.name {
+                Some(data)
+            } else if data == value {
+                previous = Some(data);
+                None
+            } else if new.versioned {
+                data.crate_info.bins.retain(|bin| !new.bins.contains(bin));
+                (!data.crate_info.bins.is_empty()).then(|| data)
+            } else {
+                None
+            }
+        })
+        .collect();
+    records.insert(value);
+
+    previous
+}
+
 impl<'a> IntoIterator for &'a Records {
     type Item = &'a Data;
 
@@ -241,9 +306,10 @@ mod test {
     use super::*;
     use crate::crate_info::CrateSource;
 
+    use std::iter;
+
     use compact_str::CompactString;
     use detect_targets::TARGET;
-    use semver::Version;
     use tempfile::NamedTempFile;
 
     macro_rules! assert_records_eq {
@@ -270,6 +336,10 @@ mod test {
                 source: CrateSource::cratesio_registry(),
                 target: target.clone(),
                 bins: vec!["1".into(), "2".into()],
+                versioned: false,
+                package_sha256: None,
+                direct_url: None,
+                extra_files: Vec::new(),
             },
             CrateInfo {
                 name: "b".into(),
@@ -278,6 +348,10 @@ mod test {
                 source: CrateSource::cratesio_registry(),
                 target: target.clone(),
                 bins: vec!["1".into(), "2".into()],
+                versioned: false,
+                package_sha256: None,
+                direct_url: None,
+                extra_files: Vec::new(),
             },
             CrateInfo {
                 name: "a".into(),
@@ -286,6 +360,10 @@ mod test {
                 source: CrateSource::cratesio_registry(),
                 target: target.clone(),
                 bins: vec!["1".into()],
+                versioned: false,
+                package_sha256: None,
+                direct_url: None,
+                extra_files: Vec::new(),
             },
         ];
 
@@ -316,6 +394,10 @@ mod test {
             source: CrateSource::cratesio_registry(),
             target,
             bins: vec!["1".into(), "2".into()],
+            versioned: false,
+            package_sha256: None,
+            direct_url: None,
+            extra_files: Vec::new(),
         };
         append_to_path(path, [new_metadata.clone()]).unwrap();
         metadata_set.insert(new_metadata);
@@ -323,4 +405,86 @@ mod test {
         let records = Records::load_from_path(path).unwrap();
         assert_records_eq!(&records, &metadata_set);
     }
+
+    #[test]
+    fn extra_files_test() {
+        let named_tempfile = NamedTempFile::new().unwrap();
+        let path = named_tempfile.path();
+
+        let extra_files = vec![
+            PathBuf::from("/usr/share/doc/ripgrep/LICENSE-MIT"),
+            PathBuf::from("/usr/share/doc/ripgrep/COPYING"),
+        ];
+        let crate_info = CrateInfo {
+            name: "ripgrep".into(),
+            version_req: "*".into(),
+            current_version: Version::new(14, 0, 0),
+            source: CrateSource::cratesio_registry(),
+            target: TARGET.into(),
+            bins: vec!["rg".into()],
+            versioned: false,
+            package_sha256: None,
+            direct_url: None,
+            extra_files: extra_files.clone(),
+        };
+
+        append_to_path(path, [crate_info]).unwrap();
+
+        let records = Records::load_from_path(path).unwrap();
+        assert_eq!(records.get("ripgrep").unwrap().extra_files, extra_files);
+    }
+
+    #[test]
+    fn versioned_test() {
+        let named_tempfile = NamedTempFile::new().unwrap();
+        let path = named_tempfile.path();
+
+        let crate_info = |major, versioned, link: bool| CrateInfo {
+            name: "wasm-bindgen-cli".into(),
+            version_req: "*".into(),
+            current_version: Version::new(major, 0, 0),
+            source: CrateSource::cratesio_registry(),
+            target: TARGET.into(),
+            bins: iter::once(format!("wasm-bindgen-v{major}.0.0").into())
+                .chain(link.then(|| "wasm-bindgen".into()))
+                .collect(),
+            versioned,
+            package_sha256: None,
+            direct_url: None,
+            extra_files: Vec::new(),
+        };
+        let bins = |records: &Records| -> Vec<Vec<CompactString>> {
+            records
+                .into_iter()
+                .map(|data| data.crate_info.bins.clone())
+                .collect()
+        };
+
+        append_to_path(path, [crate_info(1, true, true), crate_info(2, true, true)]).unwrap();
+
+        let mut records = Records::load_from_path(path).unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(
+            bins(&records),
+            [
+                vec!["wasm-bindgen-v1.0.0"],
+                vec!["wasm-bindgen-v2.0.0", "wasm-bindgen"]
+            ]
+        );
+        assert_eq!(
+            records.get("wasm-bindgen-cli").unwrap().current_version,
+            Version::new(2, 0, 0)
+        );
+
+        // Reinstalling a version only replaces that version.
+        assert!(records.replace(crate_info(1, true, false)).is_some());
+        assert_eq!(records.len(), 2);
+
+        // A non-versioned install replaces all of them.
+        assert!(records.replace(crate_info(3, false, false)).is_none());
+        assert_eq!(bins(&records), [vec!["wasm-bindgen-v3.0.0"]]);
+
+        assert!(records.remove("wasm-bindgen-cli"));
+        assert!(records.is_empty());
+    }
 }
@@ -53,6 +53,33 @@ pub enum Env {
 #[derive(Debug, Deserialize)]
 pub struct Registry {
     pub index: Option<CompactString>,
+    /// Token to authenticate to the registry, usually set in
+    /// `credentials.toml` instead of `config.toml`.
+    pub token: Option<CompactString>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Build {
+    /// Default target triple(s) to build for
+    ///
+    /// env: CARGO_BUILD_TARGET
+    pub target: Option<BuildTarget>,
+}
+
+#[derive(Eq, PartialEq, Debug, Deserialize)]
+#[serde(untagged)]
+pub enum BuildTarget {
+    Single(CompactString),
+    Multiple(Vec<CompactString>),
+}
+
+impl BuildTarget {
+    pub fn into_vec(self) -> Vec<CompactString> {
+        match self {
+            Self::Single(target) => vec![target],
+            Self::Multiple(targets) => targets,
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -60,6 +87,14 @@ pub struct DefaultRegistry {
     pub default: Option<CompactString>,
 }
 
+/// Settings specific to binstall, read from the `[binstall]` table.
+#[derive(Debug, Deserialize)]
+pub struct Binstall {
+    /// Names that can be passed instead of the crate they map to,
+    /// e.g. `rg = "ripgrep"`.
+    pub aliases: Option<BTreeMap<CompactString, CompactString>>,
+}
+
 #[derive(Debug, Default, Deserialize)]
 pub struct Config {
     pub install: Option<Install>,
@@ -67,6 +102,8 @@ pub struct Config {
     pub env: Option<BTreeMap<CompactString, Env>>,
     pub registries: Option<BTreeMap<CompactString, Registry>>,
     pub registry: Option<DefaultRegistry>,
+    pub build: Option<Build>,
+    pub binstall: Option<Binstall>,
 }
 
 fn join_if_relative(path: Option<&mut PathBuf>, dir: &Path) {
@@ -135,22 +172,110 @@ impl Config {
     }
 
     pub fn load_from_path(path: impl AsRef<Path>) -> Result<Self, ConfigLoadError> {
-        fn inner(path: &Path) -> Result<Config, ConfigLoadError> {
-            match File::open(path) {
-                Ok(file) => {
-                    let file = FileLock::new_shared(file)?;
-                    // Any regular file must have a parent dir
-                    Config::load_from_reader(file, path.parent().unwrap())
-                }
-                Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Default::default()),
-                Err(err) => Err(err.into()),
+        let path = path.as_ref();
+        // Any regular file must have a parent dir
+        Self::load_file(path, path.parent().unwrap_or(path))
+    }
+
+    /// Load the config at `path` like cargo does, resolving the relative
+    /// paths in it from the parent of the directory containing it, e.g. the
+    /// project of `.cargo/config.toml`, instead of from that directory like
+    /// [`Config::load_from_path`] does.
+    pub fn load_from_path_like_cargo(path: impl AsRef<Path>) -> Result<Self, ConfigLoadError> {
+        let path = path.as_ref();
+        let dir = path.parent().unwrap_or(path);
+        Self::load_file(path, dir.parent().unwrap_or(dir))
+    }
+
+    /// Load and merge the configs cargo reads when run in `cwd`: the
+    /// `.cargo/config.toml` of `cwd` and of each of its ancestors, then the
+    /// `config.toml` of `cargo_home`, the ones closer to `cwd` taking
+    /// precedence.
+    ///
+    /// The relative paths are resolved like [`Config::load_from_path_like_cargo`].
+    pub fn load_hierarchy(cwd: &Path, cargo_home: &Path) -> Result<Self, ConfigLoadError> {
+        let mut paths: Vec<PathBuf> = cwd
+            .ancestors()
+            .map(|dir| config_path(&dir.join(".cargo")))
+            .collect();
+        let home_config = config_path(cargo_home);
+        if !paths.contains(&home_config) {
+            paths.push(home_config);
+        }
+
+        paths.iter().try_fold(Self::default(), |config, path| {
+            Ok(config.merge(Self::load_from_path_like_cargo(path)?))
+        })
+    }
+
+    fn load_file(path: &Path, dir: &Path) -> Result<Self, ConfigLoadError> {
+        match File::open(path) {
+            Ok(file) => {
+                let file = FileLock::new_shared(file)?;
+                Config::load_from_reader(file, dir)
             }
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Default::default()),
+            Err(err) => Err(err.into()),
         }
+    }
 
-        inner(path.as_ref())
+    /// Merge `other` into `self`, the values set in `self` taking precedence.
+    fn merge(self, other: Self) -> Self {
+        Self {
+            install: merge_with(self.install, other.install, |this, other| Install {
+                root: this.root.or(other.root),
+            }),
+            http: merge_with(self.http, other.http, |this, other| Http {
+                proxy: this.proxy.or(other.proxy),
+                timeout: this.timeout.or(other.timeout),
+                cainfo: this.cainfo.or(other.cainfo),
+            }),
+            env: merge_with(self.env, other.env, merge_maps),
+            registries: merge_with(self.registries, other.registries, merge_maps),
+            registry: merge_with(self.registry, other.registry, |this, other| {
+                DefaultRegistry {
+                    default: this.default.or(other.default),
+                }
+            }),
+            build: merge_with(self.build, other.build, |this, other| Build {
+                target: this.target.or(other.target),
+            }),
+            binstall: merge_with(self.binstall, other.binstall, |this, other| Binstall {
+                aliases: merge_with(this.aliases, other.aliases, merge_maps),
+            }),
+        }
     }
 }
 
+/// Return the path of the config in the directory `dir`, `config.toml` or
+/// the legacy `config` if only it exists.
+fn config_path(dir: &Path) -> PathBuf {
+    let path = dir.join("config.toml");
+    let legacy_path = dir.join("config");
+    if !path.exists() && legacy_path.is_file() {
+        legacy_path
+    } else {
+        path
+    }
+}
+
+fn merge_with<T>(this: Option<T>, other: Option<T>, f: impl FnOnce(T, T) -> T) -> Option<T> {
+    match (this, other) {
+        (Some(this), Some(other)) => Some(f(this, other)),
+        (this, other) => this.or(other),
+    }
+}
+
+fn merge_maps<V>(
+    mut this: BTreeMap<CompactString, V>,
+    other: BTreeMap<CompactString, V>,
+) -> BTreeMap<CompactString, V> {
+    for (key, value) in other {
+        this.entry(key).or_insert(value);
+    }
+    this
+}
+
 #[derive(Debug, Diagnostic, Error)]
 #[non_exhaustive]
 pub enum ConfigLoadError {
@@ -177,7 +302,7 @@ impl From<toml_edit::TomlError> for ConfigLoadError {
 mod tests {
     use super::*;
 
-    use std::{io::Cursor, path::MAIN_SEPARATOR};
+    use std::{fs, io::Cursor, path::MAIN_SEPARATOR};
 
     use compact_str::format_compact;
 
@@ -198,6 +323,12 @@ cainfo = "cert.pem"         # path to Certificate Authority (CA) bundle
 
 [install]
 root = "/some/path"         # `cargo install` destination directory
+
+[build]
+target = ["x86_64-unknown-linux-musl", "aarch64-unknown-linux-musl"]
+
+[binstall.aliases]
+rg = "ripgrep"
     "#;
 
     #[test]
@@ -236,5 +367,73 @@ root = "/some/path"         # `cargo install` destination directory
                 relative: Some(true),
             }
         );
+
+        assert_eq!(
+            config.build.unwrap().target.unwrap().into_vec(),
+            ["x86_64-unknown-linux-musl", "aarch64-unknown-linux-musl"]
+        );
+
+        let aliases = config.binstall.unwrap().aliases.unwrap();
+        assert_eq!(aliases.len(), 1);
+        assert_eq!(aliases["rg"], "ripgrep");
+    }
+
+    #[test]
+    fn test_relative_install_root() {
+        let home = tempfile::tempdir().unwrap();
+        let path = home.path().join(".cargo").join("config.toml");
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, "[install]\nroot = \"tools\"").unwrap();
+
+        // Like cargo, relative to the parent of the directory of the config
+        let config = Config::load_from_path_like_cargo(&path).unwrap();
+        assert_eq!(
+            config.install.unwrap().root.unwrap(),
+            home.path().join("tools")
+        );
+
+        let config = Config::load_from_path(&path).unwrap();
+        assert_eq!(
+            config.install.unwrap().root.unwrap(),
+            home.path().join(".cargo").join("tools")
+        );
+    }
+
+    #[test]
+    fn test_load_hierarchy() {
+        let dir = tempfile::tempdir().unwrap();
+        let cargo_home = dir.path().join("home");
+        let project = dir.path().join("project");
+        let cwd = project.join("src");
+        fs::create_dir_all(&cargo_home).unwrap();
+        fs::create_dir_all(project.join(".cargo")).unwrap();
+        fs::create_dir_all(&cwd).unwrap();
+
+        fs::write(
+            cargo_home.join("config.toml"),
+            "[install]\nroot = \"/home-root\"\n\
+             [http]\ntimeout = 30\n\
+             [binstall.aliases]\nrg = \"ripgrep\"\nfd = \"fd-find\"\n",
+        )
+        .unwrap();
+        // The legacy name is read too
+        fs::write(
+            project.join(".cargo").join("config"),
+            "[install]\nroot = \"tools\"\n\
+             [binstall.aliases]\nrg = \"ripgrep-fork\"\n",
+        )
+        .unwrap();
+
+        let config = Config::load_hierarchy(&cwd, &cargo_home).unwrap();
+
+        // The project config takes precedence...
+        assert_eq!(config.install.unwrap().root.unwrap(), project.join("tools"));
+        // ...but the values it does not set are read from cargo home.
+        assert_eq!(config.http.unwrap().timeout, Some(30));
+
+        let aliases = config.binstall.unwrap().aliases.unwrap();
+        assert_eq!(aliases.len(), 2);
+        assert_eq!(aliases["rg"], "ripgrep-fork");
+        assert_eq!(aliases["fd"], "fd-find");
     }
 }
@@ -8,7 +8,7 @@
 //! ecosystem.
 
 use std::{
-    collections::BTreeMap,
+    collections::{BTreeMap, BTreeSet},
     fs::File,
     io::{self, Seek},
     iter::IntoIterator,
@@ -80,6 +80,41 @@ impl CratesToml<'_> {
         });
     }
 
+    /// Only remove `version` of the crate, for crates installed
+    /// with versioned binaries.
+    pub fn remove_version(&mut self, name: &str, version: &Version) {
+        self.v1.retain(|(s, _bin)| {
+            let mut parts = s.splitn(3, ' ');
+            !(parts.next() == Some(name)
+                && parts
+                    .next()
+                    .and_then(|ver| ver.parse::<Version>().ok())
+                    .as_ref()
+                    == Some(version))
+        });
+    }
+
+    /// Stop tracking `bins` in the other entries of the crate, e.g. the
+    /// symlinks moved to a newer version installed with versioned binaries,
+    /// and remove the entries left without any binary.
+    pub fn remove_bins(&mut self, name: &str, bins: &[CompactString]) {
+        self.v1.retain_mut(|(s, entry_bins)| {
+            if s.split_once(' ').map(|(crate_name, _rest)| crate_name) != Some(name) {
+                return true;
+            }
+            if entry_bins.iter().any(|bin| bins.contains(bin)) {
+                *entry_bins = Cow::owned(
+                    entry_bins
+                        .iter()
+                        .filter(|bin| !bins.contains(bin))
+                        .cloned()
+                        .collect(),
+                );
+            }
+            !entry_bins.is_empty()
+        });
+    }
+
     pub fn write(&self) -> Result<(), CratesTomlParseError> {
         self.write_to_path(Self::default_path()?)
     }
@@ -125,13 +160,30 @@ impl CratesToml<'_> {
                 let version = &metadata.current_version;
                 let source = Source::from(&metadata.source);
 
-                c1.remove(name);
-                c1.v1.push((
-                    format!("{name} {version} ({source})"),
-                    Cow::borrowed(&metadata.bins),
-                ));
+                // Cargo has no source kind for the archives installed with
+                // `--url`, they are only recorded by binstall. The binaries
+                // they replaced are not installed by the crate anymore.
+                if metadata.direct_url.is_some() {
+                    c1.remove_bins(name, &metadata.bins);
+                    continue;
+                }
+
+                if metadata.versioned {
+                    c1.remove_version(name, version);
+                    c1.remove_bins(name, &metadata.bins);
+                } else {
+                    c1.remove(name);
+                }
+                let mut bins = metadata.bins.clone();
+                bins.sort_unstable();
+                c1.v1
+                    .push((format!("{name} {version} ({source})"), Cow::owned(bins)));
             }
 
+            // Sort the entries like cargo does, so that the manifest does
+            // not depend on the order the crates were installed in.
+            c1.v1.sort_unstable_by(|(x, _), (y, _)| x.cmp(y));
+
             file.rewind()?;
             c1.write_to_file(file)?;
 
@@ -161,6 +213,8 @@ impl CratesToml<'_> {
 
     /// Return BTreeMap with crate name as key and its corresponding version
     /// as value.
+    ///
+    /// If multiple versions of a crate are installed, the newest one is returned.
     pub fn collect_into_crates_versions(
         self,
     ) -> Result<BTreeMap<CompactString, Version>, CratesTomlParseError> {
@@ -171,9 +225,29 @@ impl CratesToml<'_> {
             }
         }
 
+        let mut crates_versions = BTreeMap::new();
+
+        for (s, _bins) in self.v1 {
+            let (name, version) = parse_name_ver(&s)?;
+
+            crates_versions
+                .entry(name)
+                .and_modify(|newest: &mut Version| {
+                    if *newest < version {
+                        *newest = version.clone();
+                    }
+                })
+                .or_insert(version);
+        }
+
+        Ok(crates_versions)
+    }
+
+    /// Return the names of the binaries installed by all the crates.
+    pub fn collect_into_bins(self) -> BTreeSet<CompactString> {
         self.v1
             .into_iter()
-            .map(|(s, _bins)| parse_name_ver(&s).map_err(CratesTomlParseError::from))
+            .flat_map(|(_, bins)| bins.into_owned())
             .collect()
     }
 }
@@ -214,6 +288,8 @@ impl From<toml_edit::de::Error> for CratesTomlParseError {
 
 #[cfg(test)]
 mod tests {
+    use std::iter;
+
     use super::*;
     use crate::crate_info::CrateSource;
 
@@ -235,6 +311,10 @@ mod tests {
                 source: CrateSource::cratesio_registry(),
                 target: TARGET.into(),
                 bins: vec!["cargo-binstall".into()],
+                versioned: false,
+                package_sha256: None,
+                direct_url: None,
+                extra_files: Vec::new(),
             }],
         )
         .unwrap();
@@ -261,6 +341,10 @@ mod tests {
                 source: CrateSource::cratesio_registry(),
                 target: TARGET.into(),
                 bins: vec!["cargo-binstall".into()],
+                versioned: false,
+                package_sha256: None,
+                direct_url: None,
+                extra_files: Vec::new(),
             }],
         )
         .unwrap();
@@ -312,6 +396,138 @@ mod tests {
 "xargo 0.3.26 (registry+https://github.com/rust-lang/crates.io-index)" = ["xargo", "xargo-check"]
         "#;
 
-        CratesToml::load_from_reader(raw_data.as_slice()).unwrap();
+        let bins = CratesToml::load_from_reader(raw_data.as_slice())
+            .unwrap()
+            .collect_into_bins();
+        assert!(bins.contains("cargo-add"));
+        assert!(bins.contains("xargo-check"));
+        assert!(!bins.contains("cargo-edit"));
+    }
+
+    #[test]
+    fn test_versioned() {
+        let tempdir = TempDir::new().unwrap();
+        let path = tempdir.path().join("crates-v1.toml");
+
+        let crate_info = |major, versioned, link: bool| CrateInfo {
+            name: "wasm-bindgen-cli".into(),
+            version_req: "*".into(),
+            current_version: Version::new(major, 0, 0),
+            source: CrateSource::cratesio_registry(),
+            target: TARGET.into(),
+            bins: iter::once(format!("wasm-bindgen-v{major}.0.0").into())
+                .chain(link.then(|| "wasm-bindgen".into()))
+                .collect(),
+            versioned,
+            package_sha256: None,
+            direct_url: None,
+            extra_files: Vec::new(),
+        };
+        let bins = |path: &Path| {
+            CratesToml::load_from_path(path)
+                .unwrap()
+                .collect_into_bins()
+        };
+
+        CratesToml::append_to_path(&path, &[crate_info(1, true, true)]).unwrap();
+        CratesToml::append_to_path(&path, &[crate_info(2, true, true)]).unwrap();
+
+        let crates_toml = CratesToml::load_from_path(&path).unwrap();
+        assert_eq!(crates_toml.v1.len(), 2);
+        assert!(crates_toml.v1[0].0.starts_with("wasm-bindgen-cli 1.0.0 "));
+
+        let crates = crates_toml.collect_into_crates_versions().unwrap();
+        assert_eq!(
+            crates.get("wasm-bindgen-cli").unwrap(),
+            &Version::new(2, 0, 0)
+        );
+
+        // The symlink is tracked with the newest version only
+        let crates_toml = CratesToml::load_from_path(&path).unwrap();
+        assert_eq!(&*crates_toml.v1[0].1, ["wasm-bindgen-v1.0.0"]);
+        assert_eq!(
+            &*crates_toml.v1[1].1,
+            ["wasm-bindgen", "wasm-bindgen-v2.0.0"]
+        );
+        assert_eq!(
+            bins(&path),
+            BTreeSet::from([
+                "wasm-bindgen".into(),
+                "wasm-bindgen-v1.0.0".into(),
+                "wasm-bindgen-v2.0.0".into()
+            ])
+        );
+
+        // Reinstalling a version only replaces that version
+        CratesToml::append_to_path(&path, &[crate_info(1, true, false)]).unwrap();
+        assert_eq!(CratesToml::load_from_path(&path).unwrap().v1.len(), 2);
+        assert!(bins(&path).contains("wasm-bindgen"));
+
+        // A non-versioned install replaces all of them
+        CratesToml::append_to_path(&path, &[crate_info(3, false, false)]).unwrap();
+        assert_eq!(CratesToml::load_from_path(&path).unwrap().v1.len(), 1);
+    }
+
+    #[test]
+    fn test_direct_url() {
+        let tempdir = TempDir::new().unwrap();
+        let path = tempdir.path().join("crates-v1.toml");
+
+        let crate_info = |direct_url: Option<&str>| CrateInfo {
+            name: "cargo-binstall".into(),
+            version_req: "*".into(),
+            current_version: Version::new(1, 0, 0),
+            source: CrateSource::cratesio_registry(),
+            target: TARGET.into(),
+            bins: vec!["cargo-binstall".into()],
+            versioned: false,
+            package_sha256: None,
+            direct_url: direct_url.map(|url| url.parse().unwrap()),
+            extra_files: Vec::new(),
+        };
+
+        CratesToml::append_to_path(&path, &[crate_info(None)]).unwrap();
+        assert_eq!(CratesToml::load_from_path(&path).unwrap().v1.len(), 1);
+
+        // Not recorded as installed from crates.io, and the binaries it
+        // replaced are not tracked anymore.
+        CratesToml::append_to_path(
+            &path,
+            &[crate_info(Some("https://example.com/cargo-binstall.tgz"))],
+        )
+        .unwrap();
+        assert!(CratesToml::load_from_path(&path).unwrap().v1.is_empty());
+    }
+
+    #[test]
+    fn test_deterministic() {
+        let tempdir = TempDir::new().unwrap();
+
+        let crate_info = |name: &str, bins: &[&str]| CrateInfo {
+            name: name.into(),
+            version_req: "*".into(),
+            current_version: Version::new(1, 0, 0),
+            source: CrateSource::cratesio_registry(),
+            target: TARGET.into(),
+            bins: bins.iter().copied().map(CompactString::from).collect(),
+            versioned: false,
+            package_sha256: None,
+            direct_url: None,
+            extra_files: Vec::new(),
+        };
+
+        let write = |file_name: &str, infos: &[CrateInfo]| {
+            let path = tempdir.path().join(file_name);
+            CratesToml::append_to_path(&path, infos).unwrap();
+            std::fs::read(path).unwrap()
+        };
+
+        let a = crate_info("a", &["a2", "a1"]);
+        let b = crate_info("b", &["b"]);
+
+        assert_eq!(
+            write("ab.toml", &[a.clone(), b.clone()]),
+            write("ba.toml", &[b, a])
+        );
     }
 }
@@ -1,8 +1,8 @@
 use std::{
-    collections::BTreeMap,
+    collections::{BTreeMap, BTreeSet},
     fs,
     io::{self, Seek},
-    path::Path,
+    path::{Path, PathBuf},
 };
 
 use fs_lock::FileLock;
@@ -34,18 +34,18 @@ pub enum ManifestsError {
 pub struct Manifests {
     binstall: BinstallCratesV1Records,
     cargo_crates_v1: FileLock,
+    cargo_roots: PathBuf,
 }
 
 impl Manifests {
     pub fn open_exclusive(cargo_roots: &Path) -> Result<Self, ManifestsError> {
         // Read cargo_binstall_metadata
-        let metadata_path = cargo_roots.join("binstall/crates-v1.json");
+        let [metadata_path, manifest_path] = Self::paths_in(cargo_roots);
         fs::create_dir_all(metadata_path.parent().unwrap())?;
 
         let binstall = BinstallCratesV1Records::load_from_path(&metadata_path)?;
 
         // Read cargo_install_v1_metadata
-        let manifest_path = cargo_roots.join(".crates.toml");
 
         let cargo_crates_v1 = fs::File::options()
             .read(true)
@@ -57,9 +57,22 @@ impl Manifests {
         Ok(Self {
             binstall,
             cargo_crates_v1,
+            cargo_roots: cargo_roots.to_owned(),
         })
     }
 
+    fn paths_in(cargo_roots: &Path) -> [PathBuf; 2] {
+        [
+            cargo_roots.join("binstall/crates-v1.json"),
+            cargo_roots.join(".crates.toml"),
+        ]
+    }
+
+    /// Paths of the binstall and cargo manifests.
+    pub fn paths(&self) -> [PathBuf; 2] {
+        Self::paths_in(&self.cargo_roots)
+    }
+
     fn rewind_cargo_crates_v1(&mut self) -> Result<(), ManifestsError> {
         self.cargo_crates_v1.rewind().map_err(ManifestsError::from)
     }
@@ -78,6 +91,30 @@ impl Manifests {
             .map_err(ManifestsError::from)
     }
 
+    /// Return the names of the binaries tracked in .crates.toml, whether
+    /// they were installed by binstall or `cargo-install`, and of the ones
+    /// installed with `--url`, only tracked by binstall.
+    pub fn load_tracked_bins(&mut self) -> Result<BTreeSet<CompactString>, ManifestsError> {
+        self.rewind_cargo_crates_v1()?;
+
+        let mut bins = CratesToml::load_from_reader(&mut self.cargo_crates_v1)
+            .map(CratesToml::collect_into_bins)?;
+        bins.extend(
+            self.binstall
+                .into_iter()
+                .map(|data| &data.crate_info)
+                .filter(|metadata| metadata.direct_url.is_some())
+                .flat_map(|metadata| metadata.bins.iter().cloned()),
+        );
+
+        Ok(bins)
+    }
+
+    /// Return the crate `name` as recorded by binstall when it was installed.
+    pub fn get_recorded(&self, name: &str) -> Option<&CrateInfo> {
+        self.binstall.get(name)
+    }
+
     pub fn update(mut self, metadata_vec: Vec<CrateInfo>) -> Result<(), ManifestsError> {
         self.rewind_cargo_crates_v1()?;
 
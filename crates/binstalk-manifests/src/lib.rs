@@ -15,6 +15,7 @@ pub mod cargo_config;
 pub mod cargo_crates_v1;
 /// Contains both [`binstall_crates_v1`] and [`cargo_crates_v1`].
 pub mod crates_manifests;
+pub mod target_config;
 
 pub use binstalk_types::{cargo_toml_binstall, crate_info};
 pub use compact_str::CompactString;
@@ -0,0 +1,78 @@
+//! Per-target configuration in the style of `Cross.toml`
+//!
+//! Teams can centralize how the binaries of each target are fetched, either
+//! in a dedicated file or alongside the settings of `cross`:
+//!
+//! ```toml
+//! [target.aarch64-unknown-linux-musl]
+//! image = "ghcr.io/cross-rs/aarch64-unknown-linux-musl:main" # ignored by binstall
+//! pkg-url = "https://mirror.example.com/{ name }/{ version }/{ name }-{ target }.tgz"
+//! ```
+//!
+//! Each `[target.TARGET]` table takes the same fields as
+//! `[package.metadata.binstall.overrides.TARGET]`, other fields are ignored.
+
+use std::{collections::BTreeMap, fs, path::Path};
+
+use compact_str::CompactString;
+use serde::Deserialize;
+
+use crate::{cargo_config::ConfigLoadError, cargo_toml_binstall::PkgOverride};
+
+#[derive(Debug, Default, Deserialize)]
+pub struct TargetConfig {
+    #[serde(default)]
+    pub target: BTreeMap<CompactString, PkgOverride>,
+}
+
+impl TargetConfig {
+    pub fn load_from_path(path: impl AsRef<Path>) -> Result<Self, ConfigLoadError> {
+        fn inner(path: &Path) -> Result<TargetConfig, ConfigLoadError> {
+            let content = fs::read(path)?;
+            Ok(toml_edit::de::from_slice(&content)?)
+        }
+
+        inner(path.as_ref())
+    }
+
+    /// Return the overrides for `target`, if any.
+    pub fn overrides(&self, target: &str) -> Option<&PkgOverride> {
+        self.target.get(target)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"
+[build]
+pre-build = ["apt-get update"]
+
+[target.aarch64-unknown-linux-musl]
+image = "ghcr.io/cross-rs/aarch64-unknown-linux-musl:main"
+pkg-url = "https://mirror.example.com/{ name }-{ target }.tgz"
+pkg-fmt = "tgz"
+
+[target.x86_64-unknown-linux-gnu]
+image = "ghcr.io/cross-rs/x86_64-unknown-linux-gnu:main"
+    "#;
+
+    #[test]
+    fn test_loading() {
+        let config: TargetConfig = toml_edit::de::from_str(CONFIG).unwrap();
+
+        let overrides = config.overrides("aarch64-unknown-linux-musl").unwrap();
+        assert_eq!(
+            overrides.pkg_url.as_deref(),
+            Some("https://mirror.example.com/{ name }-{ target }.tgz")
+        );
+        assert!(overrides.pkg_fmt.is_some());
+
+        assert_eq!(
+            config.overrides("x86_64-unknown-linux-gnu").unwrap(),
+            &PkgOverride::default()
+        );
+        assert!(config.overrides("x86_64-pc-windows-msvc").is_none());
+    }
+}
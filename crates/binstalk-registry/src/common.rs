@@ -10,7 +10,7 @@ use binstalk_types::cargo_toml_binstall::{Meta, TarBasedFmt};
 use cargo_toml_workspace::cargo_toml::Manifest;
 use compact_str::{format_compact, CompactString, ToCompactString};
 use leon::{Template, Values};
-use semver::{Version, VersionReq};
+use semver::{Comparator, Op, Version, VersionReq};
 use serde::Deserialize;
 use serde_json::Error as JsonError;
 use sha2::{Digest, Sha256};
@@ -18,9 +18,12 @@ use tracing::{debug, instrument};
 
 use crate::{visitor::ManifestVisitor, RegistryError};
 
-#[derive(Deserialize)]
+#[derive(Debug, Deserialize)]
 pub(super) struct RegistryConfig {
     pub(super) dl: CompactString,
+    /// Requests to the index and crate downloads must be authenticated.
+    #[serde(default, rename = "auth-required")]
+    pub(super) auth_required: bool,
 }
 
 struct Sha256Digest {
@@ -55,12 +58,13 @@ impl DataVerifier for Sha256Digest {
     }
 }
 
-#[instrument]
+#[instrument(skip(auth_token))]
 pub(super) async fn parse_manifest(
     client: Client,
     crate_name: &str,
     crate_url: Url,
     MatchedVersion { version, cksum }: MatchedVersion,
+    auth_token: Option<&str>,
 ) -> Result<Manifest<Meta>, RegistryError> {
     debug!("Fetching crate from: {crate_url} and extracting Cargo.toml from it");
 
@@ -69,7 +73,11 @@ pub(super) async fn parse_manifest(
     let checksum = decode_base16(cksum.as_bytes()).map_err(RegistryError::from)?;
     let mut digest = Sha256Digest::new(checksum);
 
-    Download::new_with_data_verifier(client, crate_url, &mut digest)
+    let mut download = Download::new_with_data_verifier(client, crate_url, &mut digest);
+    if let Some(auth_token) = auth_token {
+        download = download.with_auth_token(auth_token);
+    }
+    download
         .and_visit_tar(TarBasedFmt::Tgz, &mut manifest_visitor)
         .await?;
 
@@ -152,11 +160,12 @@ pub(super) fn render_dl_template(
 
 #[derive(Deserialize)]
 pub(super) struct RegistryIndexEntry {
-    vers: CompactString,
-    yanked: bool,
-    cksum: String,
+    pub(super) vers: CompactString,
+    pub(super) yanked: bool,
+    pub(super) cksum: String,
 }
 
+#[derive(Debug)]
 pub(super) struct MatchedVersion {
     pub(super) version: CompactString,
     /// sha256 checksum encoded in base16
@@ -167,8 +176,10 @@ impl MatchedVersion {
     pub(super) fn find(
         it: &mut dyn Iterator<Item = Result<RegistryIndexEntry, JsonError>>,
         version_req: &VersionReq,
+        allow_pre_releases: bool,
     ) -> Result<Self, RegistryError> {
         let mut ret = Option::<(Self, Version)>::None;
+        let mut latest_pre_release = Option::<Version>::None;
 
         for res in it {
             let entry = res.map_err(RegistryError::from)?;
@@ -185,7 +196,10 @@ impl MatchedVersion {
             };
 
             // Filter by version match
-            if !version_req.matches(&ver) {
+            if !is_version_matched(version_req, &ver, allow_pre_releases) {
+                if !ver.pre.is_empty() && latest_pre_release.as_ref() < Some(&ver) {
+                    latest_pre_release = Some(ver);
+                }
                 continue;
             }
 
@@ -203,9 +217,260 @@ impl MatchedVersion {
             }
         }
 
-        ret.map(|(num, _)| num)
-            .ok_or_else(|| RegistryError::VersionMismatch {
+        match (ret, latest_pre_release) {
+            (Some((matched, _)), _) => Ok(matched),
+            (None, Some(latest)) if *version_req == VersionReq::STAR => {
+                Err(RegistryError::OnlyPreReleases {
+                    latest: latest.to_compact_string(),
+                })
+            }
+            (None, _) => Err(RegistryError::VersionMismatch {
                 req: version_req.clone(),
+            }),
+        }
+    }
+}
+
+impl MatchedVersion {
+    /// Find the entry of the version picked by `select` among all the
+    /// versions of `entries`, including the yanked ones and pre-releases,
+    /// from the lowest to the highest.
+    pub(super) fn select(
+        entries: impl IntoIterator<Item = Result<RegistryIndexEntry, JsonError>>,
+        version_req: &VersionReq,
+        select: &(dyn Fn(&[CrateVersion]) -> Option<Version> + Sync),
+    ) -> Result<Self, RegistryError> {
+        let mut entries = entries
+            .into_iter()
+            .filter_map(|res| match res {
+                Ok(entry) => Version::parse(&entry.vers)
+                    .ok()
+                    .map(|version| Ok((version, entry))),
+                Err(err) => Some(Err(err)),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        entries.sort_unstable_by(|(x, _), (y, _)| x.cmp(y));
+
+        let versions: Vec<_> = entries
+            .iter()
+            .map(|(version, entry)| CrateVersion {
+                version: version.clone(),
+                yanked: entry.yanked,
             })
+            .collect();
+
+        let version_mismatch = || RegistryError::VersionMismatch {
+            req: version_req.clone(),
+        };
+
+        let Some(selected) = select(&versions) else {
+            let mut available = versions.iter().filter(|version| !version.yanked);
+            let only_pre_releases = available
+                .clone()
+                .all(|crate_version| !crate_version.version.pre.is_empty());
+
+            return match available.next_back() {
+                Some(latest) if only_pre_releases && *version_req == VersionReq::STAR => {
+                    Err(RegistryError::OnlyPreReleases {
+                        latest: latest.version.to_compact_string(),
+                    })
+                }
+                _ => Err(version_mismatch()),
+            };
+        };
+
+        let (_, entry) = entries
+            .into_iter()
+            .find(|(version, _)| *version == selected)
+            .ok_or_else(version_mismatch)?;
+
+        Ok(Self {
+            version: entry.vers,
+            cksum: entry.cksum,
+        })
+    }
+}
+
+/// A version of a crate published on the registry.
+#[derive(Clone, Debug)]
+pub struct CrateVersion {
+    pub version: Version,
+    pub yanked: bool,
+}
+
+/// Parse the versions out of the index entries of a crate, from the lowest
+/// to the highest, skipping the ones which are not valid semver.
+pub(super) fn parse_versions(
+    it: &mut dyn Iterator<Item = Result<RegistryIndexEntry, JsonError>>,
+) -> Result<Vec<CrateVersion>, RegistryError> {
+    let mut versions = Vec::new();
+
+    for res in it {
+        let entry = res.map_err(RegistryError::from)?;
+
+        if let Ok(version) = Version::parse(&entry.vers) {
+            versions.push(CrateVersion {
+                version,
+                yanked: entry.yanked,
+            });
+        }
+    }
+
+    versions.sort_unstable_by(|x, y| x.version.cmp(&y.version));
+
+    Ok(versions)
+}
+
+/// Return `true` if `version` matches `version_req`.
+///
+/// Pre-releases only match as specified by semver, i.e. if `version_req`
+/// names a pre-release of the same version, unless `allow_pre_releases`
+/// is set: pre-releases of the versions matching `version_req` then match
+/// too, except the ones of a version it requires at least, which are lower
+/// than it.
+pub fn is_version_matched(
+    version_req: &VersionReq,
+    version: &Version,
+    allow_pre_releases: bool,
+) -> bool {
+    if version_req.matches(version) {
+        return true;
+    }
+    if !allow_pre_releases || version.pre.is_empty() {
+        return false;
+    }
+
+    let stable = Version::new(version.major, version.minor, version.patch);
+
+    let is_lower_bound = |comparator: &Comparator| {
+        matches!(
+            comparator.op,
+            Op::Exact | Op::GreaterEq | Op::Tilde | Op::Caret | Op::Wildcard
+        ) && comparator.pre.is_empty()
+            && comparator.major == stable.major
+            && comparator.minor.unwrap_or(0) == stable.minor
+            && comparator.patch.unwrap_or(0) == stable.patch
+    };
+
+    version_req.matches(&stable) && !version_req.comparators.iter().any(is_lower_bound)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn find(versions: &[&str], version_req: &str, allow_pre_releases: bool) -> String {
+        let entries = versions.iter().map(|vers| {
+            Ok::<_, JsonError>(RegistryIndexEntry {
+                vers: (*vers).into(),
+                yanked: false,
+                cksum: String::new(),
+            })
+        });
+
+        MatchedVersion::find(
+            &mut entries.into_iter(),
+            &VersionReq::parse(version_req).unwrap(),
+            allow_pre_releases,
+        )
+        .unwrap()
+        .version
+        .to_string()
+    }
+
+    #[test]
+    fn test_find_stable_over_pre_release() {
+        let versions = ["1.0.0", "1.5.0-rc.1", "1.5.0", "2.0.0"];
+
+        assert_eq!(find(&versions, ">=1.0.0, <2.0.0", false), "1.5.0");
+        assert_eq!(find(&versions, ">=1.0.0, <2.0.0", true), "1.5.0");
+    }
+
+    #[test]
+    fn test_find_pre_release() {
+        let versions = ["1.0.0", "1.5.0", "1.6.0-rc.1", "2.0.0-rc.1"];
+
+        assert_eq!(find(&versions, ">=1.0.0, <2.0.0", false), "1.5.0");
+        assert_eq!(find(&versions, ">=1.0.0, <2.0.0", true), "1.6.0-rc.1");
+        assert_eq!(find(&versions, "*", true), "2.0.0-rc.1");
+
+        // A higher stable version is preferred
+        let versions = ["1.6.0-rc.1", "1.7.0"];
+        assert_eq!(find(&versions, "^1", true), "1.7.0");
+    }
+
+    #[test]
+    fn test_is_version_matched_lower_bound() {
+        let version_req = VersionReq::parse("^1.5.0").unwrap();
+
+        let matched = |version: &str| {
+            is_version_matched(&version_req, &Version::parse(version).unwrap(), true)
+        };
+
+        assert!(!matched("1.5.0-rc.1"));
+        assert!(matched("1.5.1-rc.1"));
+        assert!(!matched("2.0.0-rc.1"));
+
+        // Pre-releases named by the requirement always match
+        let version_req = VersionReq::parse(">=1.5.0-rc.1").unwrap();
+        assert!(is_version_matched(
+            &version_req,
+            &Version::parse("1.5.0-rc.2").unwrap(),
+            false
+        ));
+    }
+
+    fn select(
+        entries: &[(&str, bool)],
+        version_req: &str,
+        select: &(dyn Fn(&[CrateVersion]) -> Option<Version> + Sync),
+    ) -> Result<MatchedVersion, RegistryError> {
+        let entries = entries.iter().map(|(vers, yanked)| {
+            Ok::<_, JsonError>(RegistryIndexEntry {
+                vers: (*vers).into(),
+                yanked: *yanked,
+                cksum: format!("cksum-{vers}"),
+            })
+        });
+
+        MatchedVersion::select(entries, &VersionReq::parse(version_req).unwrap(), select)
+    }
+
+    #[test]
+    fn test_select_yanked() {
+        let entries = [("1.1.0", true), ("1.0.0", false)];
+
+        // The versions are sorted and the yanked ones are included
+        let matched = select(&entries, "*", &|versions| {
+            assert_eq!(versions.len(), 2);
+            assert_eq!(versions[0].version, Version::new(1, 0, 0));
+            assert!(versions[1].yanked);
+            Some(versions[1].version.clone())
+        })
+        .unwrap();
+        assert_eq!(matched.version, "1.1.0");
+        assert_eq!(matched.cksum, "cksum-1.1.0");
+    }
+
+    #[test]
+    fn test_select_none() {
+        let err = select(&[("1.0.0-rc.1", false)], "*", &|_| None).unwrap_err();
+        assert!(
+            matches!(&err, RegistryError::OnlyPreReleases { latest } if *latest == "1.0.0-rc.1"),
+            "{err:?}"
+        );
+
+        let err = select(&[("1.0.0", false)], "^2", &|_| None).unwrap_err();
+        assert!(
+            matches!(err, RegistryError::VersionMismatch { .. }),
+            "{err:?}"
+        );
+
+        // A version which is not published cannot be selected
+        let err = select(&[("1.0.0", false)], "*", &|_| Some(Version::new(2, 0, 0))).unwrap_err();
+        assert!(
+            matches!(err, RegistryError::VersionMismatch { .. }),
+            "{err:?}"
+        );
     }
 }
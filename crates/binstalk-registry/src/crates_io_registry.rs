@@ -6,7 +6,10 @@ use semver::{Comparator, Op as ComparatorOp, Version as SemVersion, VersionReq};
 use serde::Deserialize;
 use tracing::{debug, instrument};
 
-use crate::{parse_manifest, MatchedVersion, RegistryError};
+use crate::{
+    is_version_matched, parse_manifest, CrateVersion, MatchedVersion, RegistryError,
+    RegistryIndexEntry,
+};
 
 /// Return `Some(checksum)` if the version is not yanked, otherwise `None`.
 async fn is_crate_yanked(client: &Client, url: Url) -> Result<Option<String>, RemoteError> {
@@ -34,7 +37,8 @@ async fn fetch_crate_cratesio_version_matched(
     client: &Client,
     url: Url,
     version_req: &VersionReq,
-) -> Result<Option<(CompactString, String)>, RemoteError> {
+    allow_pre_releases: bool,
+) -> Result<Option<(CompactString, String)>, RegistryError> {
     #[derive(Deserialize)]
     struct CrateInfo {
         #[serde(rename = "crate")]
@@ -45,7 +49,7 @@ async fn fetch_crate_cratesio_version_matched(
 
     #[derive(Deserialize)]
     struct CrateInfoInner {
-        max_stable_version: CompactString,
+        max_stable_version: Option<CompactString>,
     }
 
     #[derive(Deserialize)]
@@ -60,8 +64,25 @@ async fn fetch_crate_cratesio_version_matched(
 
     let crate_info: CrateInfo = client.get(url).send(true).await?.json().await?;
 
-    let version_with_checksum = if version_req == &VersionReq::STAR {
-        let version = crate_info.inner.max_stable_version;
+    let version_with_checksum = if version_req == &VersionReq::STAR && !allow_pre_releases {
+        let Some(version) = crate_info.inner.max_stable_version else {
+            // Only pre-releases are published, `VersionReq::STAR` would never
+            // match any of them.
+            let latest = crate_info
+                .versions
+                .iter()
+                .filter(|item| !item.yanked)
+                .filter_map(|item| semver::Version::parse(&item.num).ok())
+                .max();
+
+            return match latest {
+                Some(latest) => Err(RegistryError::OnlyPreReleases {
+                    latest: latest.to_compact_string(),
+                }),
+                None => Ok(None),
+            };
+        };
+
         crate_info
             .versions
             .into_iter()
@@ -84,9 +105,11 @@ async fn fetch_crate_cratesio_version_matched(
                     let ver = semver::Version::parse(&num).ok()?;
 
                     // Filter by version match
-                    version_req
-                        .matches(&ver)
-                        .then_some((num, ver, item.checksum))
+                    is_version_matched(version_req, &ver, allow_pre_releases).then_some((
+                        num,
+                        ver,
+                        item.checksum,
+                    ))
                 } else {
                     None
                 }
@@ -110,6 +133,7 @@ pub async fn fetch_crate_cratesio_api(
     client: Client,
     name: &str,
     version_req: &VersionReq,
+    allow_pre_releases: bool,
 ) -> Result<Manifest<Meta>, RegistryError> {
     let url = Url::parse(&format!("https://crates.io/api/v1/crates/{name}"))?;
 
@@ -136,12 +160,23 @@ pub async fn fetch_crate_cratesio_api(
             is_crate_yanked(&client, url)
                 .await
                 .map(|ret| ret.map(|checksum| (version, checksum)))
+                .map_err(RegistryError::from)
+        }
+        _ => {
+            fetch_crate_cratesio_version_matched(
+                &client,
+                url.clone(),
+                version_req,
+                allow_pre_releases,
+            )
+            .await
         }
-        _ => fetch_crate_cratesio_version_matched(&client, url.clone(), version_req).await,
     }
     .map_err(|e| match e {
-        RemoteError::Http(e) if e.is_status() => RegistryError::NotFound(name.into()),
-        e => e.into(),
+        RegistryError::Remote(RemoteError::Http(e)) if e.is_status() => {
+            RegistryError::NotFound(name.into())
+        }
+        e => e,
     })?
     .ok_or_else(|| RegistryError::VersionMismatch {
         req: version_req.clone(),
@@ -157,5 +192,76 @@ pub async fn fetch_crate_cratesio_api(
         .push(&version)
         .push("download");
 
-    parse_manifest(client, name, crate_url, MatchedVersion { version, cksum }).await
+    parse_manifest(
+        client,
+        name,
+        crate_url,
+        MatchedVersion { version, cksum },
+        None,
+    )
+    .await
+}
+
+/// Like [`fetch_crate_cratesio_api`], for the version picked by `select`
+/// among all the versions published, see
+/// [`crate::Registry::fetch_crate_selected`].
+#[instrument(skip(select))]
+pub async fn fetch_crate_cratesio_api_selected(
+    client: Client,
+    name: &str,
+    version_req: &VersionReq,
+    select: &(dyn Fn(&[CrateVersion]) -> Option<SemVersion> + Sync),
+) -> Result<Manifest<Meta>, RegistryError> {
+    #[derive(Deserialize)]
+    struct CrateInfo {
+        versions: Vec<Version>,
+    }
+
+    #[derive(Deserialize)]
+    struct Version {
+        num: CompactString,
+        yanked: bool,
+        checksum: String,
+    }
+
+    let url = Url::parse(&format!("https://crates.io/api/v1/crates/{name}"))?;
+
+    debug!("Looking up crate information");
+
+    let crate_info: CrateInfo = client
+        .get(url.clone())
+        .send(true)
+        .await
+        .map_err(|e| match e {
+            RemoteError::Http(e) if e.is_status() => RegistryError::NotFound(name.into()),
+            e => e.into(),
+        })?
+        .json()
+        .await?;
+
+    let matched_version = MatchedVersion::select(
+        crate_info.versions.into_iter().map(|version| {
+            Ok(RegistryIndexEntry {
+                vers: version.num,
+                yanked: version.yanked,
+                cksum: version.checksum,
+            })
+        }),
+        version_req,
+        select,
+    )?;
+
+    debug!(
+        "Found information for crate version: '{}'",
+        matched_version.version
+    );
+
+    let mut crate_url = url;
+    crate_url
+        .path_segments_mut()
+        .unwrap()
+        .push(&matched_version.version)
+        .push("download");
+
+    parse_manifest(client, name, crate_url, matched_version, None).await
 }
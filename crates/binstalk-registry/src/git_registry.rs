@@ -5,7 +5,7 @@ use binstalk_types::cargo_toml_binstall::Meta;
 use cargo_toml_workspace::cargo_toml::Manifest;
 use compact_str::{CompactString, ToCompactString};
 use once_cell::sync::OnceCell;
-use semver::VersionReq;
+use semver::{Version, VersionReq};
 use serde_json::{from_slice as json_from_slice, Deserializer as JsonDeserializer};
 use simple_git::{GitCancellationToken, GitUrl, Repository};
 use tempfile::TempDir;
@@ -14,8 +14,8 @@ use tracing::instrument;
 use url::Url;
 
 use crate::{
-    crate_prefix_components, parse_manifest, render_dl_template, MatchedVersion, RegistryConfig,
-    RegistryError,
+    crate_prefix_components, parse_manifest, parse_versions, render_dl_template, CrateVersion,
+    MatchedVersion, RegistryConfig, RegistryError,
 };
 
 #[derive(Debug)]
@@ -74,12 +74,11 @@ impl GitRegistry {
     }
 
     /// WARNING: This is a blocking operation.
-    fn find_crate_matched_ver(
+    fn get_index_entries(
         repo: &Repository,
         crate_name: &str,
         (c1, c2): &(CompactString, Option<CompactString>),
-        version_req: &VersionReq,
-    ) -> Result<MatchedVersion, RegistryError> {
+    ) -> Result<Vec<u8>, RegistryError> {
         let mut path = PathBuf::with_capacity(128);
         path.push(&**c1);
         if let Some(c2) = c2 {
@@ -87,13 +86,24 @@ impl GitRegistry {
         }
 
         path.push(&*crate_name.to_lowercase());
-        let crate_versions = repo
-            .get_head_commit_entry_data_by_path(path)?
-            .ok_or_else(|| RegistryError::NotFound(crate_name.into()))?;
+        repo.get_head_commit_entry_data_by_path(path)?
+            .ok_or_else(|| RegistryError::NotFound(crate_name.into()))
+    }
+
+    /// WARNING: This is a blocking operation.
+    fn find_crate_matched_ver(
+        repo: &Repository,
+        crate_name: &str,
+        crate_prefix: &(CompactString, Option<CompactString>),
+        version_req: &VersionReq,
+        allow_pre_releases: bool,
+    ) -> Result<MatchedVersion, RegistryError> {
+        let crate_versions = Self::get_index_entries(repo, crate_name, crate_prefix)?;
 
         MatchedVersion::find(
             &mut JsonDeserializer::from_slice(&crate_versions).into_iter(),
             version_req,
+            allow_pre_releases,
         )
     }
 
@@ -103,6 +113,7 @@ impl GitRegistry {
         client: Client,
         name: &str,
         version_req: &VersionReq,
+        allow_pre_releases: bool,
     ) -> Result<Manifest<Meta>, RegistryError> {
         let crate_prefix = crate_prefix_components(name)?;
         let crate_name = name.to_compact_string();
@@ -123,8 +134,13 @@ impl GitRegistry {
                 .git_index
                 .get_or_try_init(|| GitIndex::new(this.0.url.clone(), cancellation_token))?;
 
-            let matched_version =
-                Self::find_crate_matched_ver(repo, &crate_name, &crate_prefix, &version_req)?;
+            let matched_version = Self::find_crate_matched_ver(
+                repo,
+                &crate_name,
+                &crate_prefix,
+                &version_req,
+                allow_pre_releases,
+            )?;
 
             let url = Url::parse(&render_dl_template(
                 dl_template,
@@ -140,6 +156,88 @@ impl GitRegistry {
         // Git operation done, disarm it
         cancel_on_drop.disarm();
 
-        parse_manifest(client, name, dl_url, matched_version).await
+        parse_manifest(client, name, dl_url, matched_version, None).await
+    }
+
+    /// See [`crate::Registry::fetch_crate_selected`].
+    #[instrument(skip(select))]
+    pub async fn fetch_crate_selected(
+        &self,
+        client: Client,
+        name: &str,
+        version_req: &VersionReq,
+        select: &(dyn Fn(&[CrateVersion]) -> Option<Version> + Sync),
+    ) -> Result<Manifest<Meta>, RegistryError> {
+        let crate_prefix = crate_prefix_components(name)?;
+        let crate_name = name.to_compact_string();
+        let this = self.clone();
+
+        let cancellation_token = GitCancellationToken::default();
+        // Cancel git operation if the future is cancelled (dropped).
+        let cancel_on_drop = cancellation_token.clone().cancel_on_drop();
+
+        let (crate_versions, dl_template) = spawn_blocking({
+            let crate_prefix = crate_prefix.clone();
+            move || {
+                let GitIndex {
+                    _tempdir: _,
+                    repo,
+                    dl_template,
+                } = this
+                    .0
+                    .git_index
+                    .get_or_try_init(|| GitIndex::new(this.0.url.clone(), cancellation_token))?;
+
+                let crate_versions = Self::get_index_entries(repo, &crate_name, &crate_prefix)?;
+
+                Ok::<_, RegistryError>((crate_versions, dl_template.clone()))
+            }
+        })
+        .await??;
+
+        // Git operation done, disarm it
+        cancel_on_drop.disarm();
+
+        let matched_version = MatchedVersion::select(
+            JsonDeserializer::from_slice(&crate_versions).into_iter(),
+            version_req,
+            select,
+        )?;
+        let dl_url = Url::parse(&render_dl_template(
+            &dl_template,
+            name,
+            &crate_prefix,
+            &matched_version,
+        )?)?;
+
+        parse_manifest(client, name, dl_url, matched_version, None).await
+    }
+
+    #[instrument]
+    pub async fn fetch_versions(&self, name: &str) -> Result<Vec<CrateVersion>, RegistryError> {
+        let crate_prefix = crate_prefix_components(name)?;
+        let crate_name = name.to_compact_string();
+        let this = self.clone();
+
+        let cancellation_token = GitCancellationToken::default();
+        // Cancel git operation if the future is cancelled (dropped).
+        let cancel_on_drop = cancellation_token.clone().cancel_on_drop();
+
+        let versions = spawn_blocking(move || {
+            let GitIndex { repo, .. } = this
+                .0
+                .git_index
+                .get_or_try_init(|| GitIndex::new(this.0.url.clone(), cancellation_token))?;
+
+            let crate_versions = Self::get_index_entries(repo, &crate_name, &crate_prefix)?;
+
+            parse_versions(&mut JsonDeserializer::from_slice(&crate_versions).into_iter())
+        })
+        .await??;
+
+        // Git operation done, disarm it
+        cancel_on_drop.disarm();
+
+        Ok(versions)
     }
 }
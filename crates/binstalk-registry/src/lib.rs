@@ -12,7 +12,7 @@ use cargo_toml_workspace::cargo_toml::{Error as CargoTomlError, Manifest};
 use compact_str::CompactString;
 use leon::{ParseError, RenderError};
 use miette::Diagnostic;
-use semver::VersionReq;
+use semver::{Version, VersionReq};
 use serde_json::Error as JsonError;
 use thiserror::Error as ThisError;
 use tokio::task;
@@ -27,6 +27,7 @@ mod visitor;
 
 mod common;
 use common::*;
+pub use common::{is_version_matched, CrateVersion};
 
 #[cfg(feature = "git")]
 mod git_registry;
@@ -36,7 +37,7 @@ pub use git_registry::GitRegistry;
 #[cfg(any(feature = "crates_io_api", test))]
 mod crates_io_registry;
 #[cfg(any(feature = "crates_io_api", test))]
-pub use crates_io_registry::fetch_crate_cratesio_api;
+pub use crates_io_registry::{fetch_crate_cratesio_api, fetch_crate_cratesio_api_selected};
 
 mod sparse_registry;
 pub use sparse_registry::SparseRegistry;
@@ -75,6 +76,12 @@ pub enum RegistryError {
     #[error("no version matching requirement '{req}'")]
     VersionMismatch { req: semver::VersionReq },
 
+    #[error("only pre-release versions are available, the latest one is '{latest}'")]
+    #[diagnostic(help(
+        "Pass --pre to install the latest pre-release, or --version to pick a specific one."
+    ))]
+    OnlyPreReleases { latest: CompactString },
+
     #[error("Failed to parse cargo manifest: {0}")]
     #[diagnostic(help("If you used --manifest-path, check the Cargo.toml syntax."))]
     CargoManifest(#[from] Box<CargoTomlError>),
@@ -102,6 +109,8 @@ impl From<CargoTomlError> for RegistryError {
     }
 }
 
+const CRATES_IO_SPARSE_INDEX: &str = "https://index.crates.io/";
+
 #[derive(Clone, Debug)]
 #[non_exhaustive]
 pub enum Registry {
@@ -146,7 +155,7 @@ impl Registry {
     /// Return a crates.io sparse registry
     pub fn crates_io_sparse_registry() -> Self {
         Self::Sparse(Arc::new(SparseRegistry::new(
-            Url::parse("https://index.crates.io/").unwrap(),
+            Url::parse(CRATES_IO_SPARSE_INDEX).unwrap(),
         )))
     }
 
@@ -173,28 +182,145 @@ impl Registry {
         }
     }
 
+    /// Authenticate to the registry with `token`, if it requires it.
+    ///
+    /// Only supported by sparse registries, git registries ignore it.
+    pub fn with_auth_token(self, token: CompactString) -> Self {
+        match self {
+            Self::Sparse(sparse_registry) => Self::Sparse(Arc::new(
+                SparseRegistry::new(sparse_registry.url().clone()).with_auth_token(token),
+            )),
+            #[cfg(feature = "git")]
+            Self::Git(git_registry) => {
+                tracing::warn!(
+                    "Authentication to git registries is not supported, ignoring the token"
+                );
+                Self::Git(git_registry)
+            }
+        }
+    }
+
     /// Fetch the latest crate with `crate_name` and with version matching
     /// `version_req`.
+    ///
+    /// The highest version matched is returned, a stable version being higher
+    /// than its pre-releases. If `allow_pre_releases` is set, pre-releases of
+    /// the versions matched by `version_req` are also considered, except the
+    /// ones of its lower bound, e.g. `1.6.0-rc.1` is considered for
+    /// `>=1.0.0, <2.0.0` but `1.0.0-rc.1` is not.
+    ///
+    /// With feature `crates_io_api`, the crates.io API is used instead of the
+    /// crates.io sparse index if the index cannot be reached.
     pub async fn fetch_crate_matched(
         &self,
         client: Client,
         crate_name: &str,
         version_req: &VersionReq,
+        allow_pre_releases: bool,
     ) -> Result<Manifest<Meta>, RegistryError> {
         match self {
             Self::Sparse(sparse_registry) => {
-                sparse_registry
-                    .fetch_crate_matched(client, crate_name, version_req)
+                let res = sparse_registry
+                    .fetch_crate_matched(
+                        client.clone(),
+                        crate_name,
+                        version_req,
+                        allow_pre_releases,
+                    )
+                    .await;
+
+                #[cfg(feature = "crates_io_api")]
+                if let Err(RegistryError::Remote(err)) = &res {
+                    if sparse_registry.is_crates_io() {
+                        tracing::warn!(
+                            "Failed to reach the crates.io sparse index: {err}, falling back to the crates.io API"
+                        );
+                        return fetch_crate_cratesio_api(
+                            client,
+                            crate_name,
+                            version_req,
+                            allow_pre_releases,
+                        )
+                        .await;
+                    }
+                }
+
+                res
+            }
+            #[cfg(feature = "git")]
+            Self::Git(git_registry) => {
+                git_registry
+                    .fetch_crate_matched(client, crate_name, version_req, allow_pre_releases)
                     .await
             }
+        }
+    }
+
+    /// Fetch the version of crate `crate_name` picked by `select` among all
+    /// the versions published, including the yanked ones and pre-releases,
+    /// from the lowest to the highest.
+    ///
+    /// Unlike [`Registry::fetch_crate_matched`], the version picked is
+    /// fetched even if it is yanked. `version_req` is only used to report
+    /// that `select` did not pick any version.
+    ///
+    /// With feature `crates_io_api`, the crates.io API is used instead of the
+    /// crates.io sparse index if the index cannot be reached.
+    pub async fn fetch_crate_selected(
+        &self,
+        client: Client,
+        crate_name: &str,
+        version_req: &VersionReq,
+        select: &(dyn Fn(&[CrateVersion]) -> Option<Version> + Sync),
+    ) -> Result<Manifest<Meta>, RegistryError> {
+        match self {
+            Self::Sparse(sparse_registry) => {
+                let res = sparse_registry
+                    .fetch_crate_selected(client.clone(), crate_name, version_req, select)
+                    .await;
+
+                #[cfg(feature = "crates_io_api")]
+                if let Err(RegistryError::Remote(err)) = &res {
+                    if sparse_registry.is_crates_io() {
+                        tracing::warn!(
+                            "Failed to reach the crates.io sparse index: {err}, falling back to the crates.io API"
+                        );
+                        return fetch_crate_cratesio_api_selected(
+                            client,
+                            crate_name,
+                            version_req,
+                            select,
+                        )
+                        .await;
+                    }
+                }
+
+                res
+            }
             #[cfg(feature = "git")]
             Self::Git(git_registry) => {
                 git_registry
-                    .fetch_crate_matched(client, crate_name, version_req)
+                    .fetch_crate_selected(client, crate_name, version_req, select)
                     .await
             }
         }
     }
+
+    /// Fetch all the versions of crate `crate_name` published, including
+    /// the yanked ones and pre-releases, from the lowest to the highest.
+    pub async fn fetch_versions(
+        &self,
+        client: Client,
+        crate_name: &str,
+    ) -> Result<Vec<CrateVersion>, RegistryError> {
+        match self {
+            Self::Sparse(sparse_registry) => {
+                sparse_registry.fetch_versions(client, crate_name).await
+            }
+            #[cfg(feature = "git")]
+            Self::Git(git_registry) => git_registry.fetch_versions(crate_name).await,
+        }
+    }
 }
 
 impl FromStr for Registry {
@@ -243,13 +369,14 @@ mod test {
         let crate_name = "cargo-binstall";
         let version_req = &VersionReq::parse("=1.0.0").unwrap();
         let manifest_from_sparse = sparse_registry
-            .fetch_crate_matched(client.clone(), crate_name, version_req)
+            .fetch_crate_matched(client.clone(), crate_name, version_req, false)
             .await
             .unwrap();
 
-        let manifest_from_cratesio_api = fetch_crate_cratesio_api(client, crate_name, version_req)
-            .await
-            .unwrap();
+        let manifest_from_cratesio_api =
+            fetch_crate_cratesio_api(client, crate_name, version_req, false)
+                .await
+                .unwrap();
 
         let serialized_manifest_from_sparse = to_string(&manifest_from_sparse).unwrap();
         let serialized_manifest_from_cratesio_api = to_string(&manifest_from_cratesio_api).unwrap();
@@ -277,12 +404,12 @@ mod test {
         let crate_name = "cargo-binstall";
         let version_req = &VersionReq::parse("=1.0.0").unwrap();
         let manifest_from_git = git_registry
-            .fetch_crate_matched(client.clone(), crate_name, version_req)
+            .fetch_crate_matched(client.clone(), crate_name, version_req, false)
             .await
             .unwrap();
 
         let manifest_from_cratesio_api = Registry::default()
-            .fetch_crate_matched(client, crate_name, version_req)
+            .fetch_crate_matched(client, crate_name, version_req, false)
             .await
             .unwrap();
 
@@ -1,22 +1,37 @@
-use binstalk_downloader::remote::{Client, Error as RemoteError};
+use std::fmt;
+
+use binstalk_downloader::{
+    bytes::Bytes,
+    remote::{Client, Error as RemoteError, RequestBuilder},
+};
 use binstalk_types::cargo_toml_binstall::Meta;
 use cargo_toml_workspace::cargo_toml::Manifest;
 use compact_str::CompactString;
-use semver::VersionReq;
+use semver::{Version, VersionReq};
 use serde_json::Deserializer as JsonDeserializer;
 use tokio::sync::OnceCell;
 use tracing::instrument;
 use url::Url;
 
 use crate::{
-    crate_prefix_components, parse_manifest, render_dl_template, MatchedVersion, RegistryConfig,
-    RegistryError,
+    crate_prefix_components, parse_manifest, parse_versions, render_dl_template, CrateVersion,
+    MatchedVersion, RegistryConfig, RegistryError, CRATES_IO_SPARSE_INDEX,
 };
 
+/// Token to authenticate to the registry, kept out of `Debug` output.
+struct AuthToken(CompactString);
+
+impl fmt::Debug for AuthToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("AuthToken(..)")
+    }
+}
+
 #[derive(Debug)]
 pub struct SparseRegistry {
     url: Url,
-    dl_template: OnceCell<CompactString>,
+    config: OnceCell<RegistryConfig>,
+    auth_token: Option<AuthToken>,
 }
 
 impl SparseRegistry {
@@ -24,32 +39,72 @@ impl SparseRegistry {
     pub fn new(url: Url) -> Self {
         Self {
             url,
-            dl_template: Default::default(),
+            config: Default::default(),
+            auth_token: None,
         }
     }
 
-    async fn get_dl_template(&self, client: &Client) -> Result<&str, RegistryError> {
-        self.dl_template
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    /// Return `true` if this is the sparse index of crates.io.
+    pub fn is_crates_io(&self) -> bool {
+        self.url.as_str() == CRATES_IO_SPARSE_INDEX
+    }
+
+    /// Authenticate to the registry with `token`, if it requires it.
+    pub fn with_auth_token(self, token: CompactString) -> Self {
+        Self {
+            auth_token: Some(AuthToken(token)),
+            ..self
+        }
+    }
+
+    fn request(&self, client: &Client, url: Url, authenticate: bool) -> RequestBuilder {
+        let request = client.get(url);
+        match &self.auth_token {
+            Some(AuthToken(token)) if authenticate => request.header("Authorization", token),
+            _ => request,
+        }
+    }
+
+    async fn get_config(&self, client: &Client) -> Result<&RegistryConfig, RegistryError> {
+        self.config
             .get_or_try_init(|| {
                 Box::pin(async {
                     let mut url = self.url.clone();
                     url.path_segments_mut().unwrap().push("config.json");
-                    let config: RegistryConfig = client.get(url).send(true).await?.json().await?;
-                    Ok(config.dl)
+                    // The config of registries requiring authentication
+                    // can only be fetched with the token.
+                    Ok(self
+                        .request(client, url, true)
+                        .send(true)
+                        .await?
+                        .json()
+                        .await?)
                 })
             })
             .await
-            .map(AsRef::as_ref)
     }
 
-    /// `url` must be a valid http(s) url.
-    async fn find_crate_matched_ver(
+    /// Return the token to send, if the registry requires authentication.
+    fn auth_token(&self, config: &RegistryConfig) -> Option<&str> {
+        match &self.auth_token {
+            Some(AuthToken(token)) if config.auth_required => Some(token),
+            _ => None,
+        }
+    }
+
+    /// Fetch the index entries of the crate.
+    async fn fetch_index_entries(
+        &self,
         client: &Client,
-        mut url: Url,
+        config: &RegistryConfig,
         crate_name: &str,
         (c1, c2): &(CompactString, Option<CompactString>),
-        version_req: &VersionReq,
-    ) -> Result<MatchedVersion, RegistryError> {
+    ) -> Result<Bytes, RegistryError> {
+        let mut url = self.url.clone();
         {
             let mut path = url.path_segments_mut().unwrap();
 
@@ -61,8 +116,7 @@ impl SparseRegistry {
             path.push(&crate_name.to_lowercase());
         }
 
-        let body = client
-            .get(url)
+        self.request(client, url, config.auth_required)
             .send(true)
             .await
             .map_err(|e| match e {
@@ -71,10 +125,26 @@ impl SparseRegistry {
             })?
             .bytes()
             .await
-            .map_err(RegistryError::from)?;
+            .map_err(RegistryError::from)
+    }
+
+    /// `url` must be a valid http(s) url.
+    async fn find_crate_matched_ver(
+        &self,
+        client: &Client,
+        config: &RegistryConfig,
+        crate_name: &str,
+        crate_prefix: &(CompactString, Option<CompactString>),
+        version_req: &VersionReq,
+        allow_pre_releases: bool,
+    ) -> Result<MatchedVersion, RegistryError> {
+        let body = self
+            .fetch_index_entries(client, config, crate_name, crate_prefix)
+            .await?;
         MatchedVersion::find(
             &mut JsonDeserializer::from_slice(&body).into_iter(),
             version_req,
+            allow_pre_releases,
         )
     }
 
@@ -84,24 +154,84 @@ impl SparseRegistry {
         client: Client,
         crate_name: &str,
         version_req: &VersionReq,
+        allow_pre_releases: bool,
     ) -> Result<Manifest<Meta>, RegistryError> {
         let crate_prefix = crate_prefix_components(crate_name)?;
-        let dl_template = self.get_dl_template(&client).await?;
-        let matched_version = Self::find_crate_matched_ver(
-            &client,
-            self.url.clone(),
+        let config = self.get_config(&client).await?;
+        let matched_version = self
+            .find_crate_matched_ver(
+                &client,
+                config,
+                crate_name,
+                &crate_prefix,
+                version_req,
+                allow_pre_releases,
+            )
+            .await?;
+        let dl_url = Url::parse(&render_dl_template(
+            &config.dl,
             crate_name,
             &crate_prefix,
-            version_req,
+            &matched_version,
+        )?)?;
+
+        parse_manifest(
+            client,
+            crate_name,
+            dl_url,
+            matched_version,
+            self.auth_token(config),
         )
-        .await?;
+        .await
+    }
+
+    /// See [`crate::Registry::fetch_crate_selected`].
+    #[instrument(skip(select))]
+    pub async fn fetch_crate_selected(
+        &self,
+        client: Client,
+        crate_name: &str,
+        version_req: &VersionReq,
+        select: &(dyn Fn(&[CrateVersion]) -> Option<Version> + Sync),
+    ) -> Result<Manifest<Meta>, RegistryError> {
+        let crate_prefix = crate_prefix_components(crate_name)?;
+        let config = self.get_config(&client).await?;
+        let body = self
+            .fetch_index_entries(&client, config, crate_name, &crate_prefix)
+            .await?;
+        let matched_version = MatchedVersion::select(
+            JsonDeserializer::from_slice(&body).into_iter(),
+            version_req,
+            select,
+        )?;
         let dl_url = Url::parse(&render_dl_template(
-            dl_template,
+            &config.dl,
             crate_name,
             &crate_prefix,
             &matched_version,
         )?)?;
 
-        parse_manifest(client, crate_name, dl_url, matched_version).await
+        parse_manifest(
+            client,
+            crate_name,
+            dl_url,
+            matched_version,
+            self.auth_token(config),
+        )
+        .await
+    }
+
+    pub async fn fetch_versions(
+        &self,
+        client: Client,
+        crate_name: &str,
+    ) -> Result<Vec<CrateVersion>, RegistryError> {
+        let crate_prefix = crate_prefix_components(crate_name)?;
+        let config = self.get_config(&client).await?;
+        let body = self
+            .fetch_index_entries(&client, config, crate_name, &crate_prefix)
+            .await?;
+
+        parse_versions(&mut JsonDeserializer::from_slice(&body).into_iter())
     }
 }
@@ -31,14 +31,67 @@ pub struct PkgMeta {
     /// Format for package downloads
     pub pkg_fmt: Option<PkgFmt>,
 
+    /// Template of the tag of the release, substituted for `{ tag }` in
+    /// `pkg_url`, for projects whose tags are not the crate version.
+    ///
+    /// `{ tag }` is the version if unset.
+    pub tag: Option<String>,
+
+    /// Base urls of the hosts mirroring the packages, substituted for
+    /// `{ mirror }` in `pkg_url` and tried in order until one is reachable
+    pub mirrors: Vec<String>,
+
     /// Path template for binary files in packages
     pub bin_dir: Option<String>,
 
     /// Package signing configuration
     pub signing: Option<PkgSigning>,
 
+    /// Package checksum configuration
+    pub checksum: Option<PkgChecksum>,
+
+    /// Name of the binaries in packages, keyed by the name of their
+    /// `[[bin]]` product, for packages where they differ.
+    ///
+    /// The binaries are still installed under the product name.
+    pub bin_source_names: BTreeMap<String, String>,
+
+    /// Names the packages use for targets instead of their triple, keyed by
+    /// the triple, tried in order if no package is found for the triple.
+    ///
+    /// They replace the aliases binstall derives from the triple, such as
+    /// `amd64-linux` for `x86_64-unknown-linux-gnu`, an empty list disables
+    /// them.
+    pub target_aliases: BTreeMap<String, Vec<String>>,
+
+    /// Path templates of files in packages to install alongside the
+    /// binaries, such as license texts, which are not made executable.
+    pub extra_files: Vec<String>,
+
     /// Target specific overrides
     pub overrides: BTreeMap<String, PkgOverride>,
+
+    /// Variants of the packages built for CPU features, from the most to
+    /// the least demanding, the first one supported by the CPU is used
+    /// over the baseline package.
+    pub cpu_variants: Vec<CpuVariant>,
+
+    /// Tools the project wants installed for development, in
+    /// `crate[@version]` syntax, installed by `cargo binstall --dev-tools`.
+    pub tools: Vec<String>,
+
+    /// Artifact uploaded by a GitHub Actions workflow, installed for
+    /// projects publishing builds of their workflows rather than releases.
+    pub github_actions: Option<PkgGhActions>,
+
+    /// URL template of the releases index, a JSON document listing the
+    /// packages of every version and target along with their checksums.
+    pub releases_index: Option<String>,
+
+    /// Whether the author asks for the crate to be built from source
+    /// rather than installed from pre-built binaries, e.g. because they
+    /// cannot be redistributed.
+    pub disabled: bool,
 }
 
 impl PkgMeta {
@@ -50,14 +103,22 @@ impl PkgMeta {
         if let Some(o) = &pkg_override.pkg_fmt {
             self.pkg_fmt = Some(*o);
         }
+        if let Some(o) = &pkg_override.mirrors {
+            self.mirrors = o.clone();
+        }
         if let Some(o) = &pkg_override.bin_dir {
             self.bin_dir = Some(o.clone());
         }
+        if let Some(o) = &pkg_override.checksum {
+            self.checksum = Some(o.clone());
+        }
     }
 
     /// Merge configuration overrides into object
     ///
-    ///  * `pkg_overrides` - ordered in preference
+    ///  * `pkg_overrides` - ordered in preference, each field is taken from
+    ///    the first override setting it (e.g. a target specific `bin_dir`),
+    ///    falling back to the one in `self`.
     pub fn merge_overrides<'a, It>(&self, pkg_overrides: It) -> Self
     where
         It: IntoIterator<Item = &'a PkgOverride> + Clone,
@@ -75,6 +136,14 @@ impl PkgMeta {
                 .find_map(|pkg_override| pkg_override.pkg_fmt)
                 .or(self.pkg_fmt),
 
+            tag: self.tag.clone(),
+
+            mirrors: pkg_overrides
+                .clone()
+                .into_iter()
+                .find_map(|pkg_override| pkg_override.mirrors.clone())
+                .unwrap_or_else(|| self.mirrors.clone()),
+
             bin_dir: pkg_overrides
                 .clone()
                 .into_iter()
@@ -82,18 +151,34 @@ impl PkgMeta {
                 .or_else(|| self.bin_dir.clone()),
 
             signing: pkg_overrides
+                .clone()
                 .into_iter()
                 .find_map(|pkg_override| pkg_override.signing.clone())
                 .or_else(|| self.signing.clone()),
 
+            checksum: pkg_overrides
+                .into_iter()
+                .find_map(|pkg_override| pkg_override.checksum.clone())
+                .or_else(|| self.checksum.clone()),
+
+            bin_source_names: self.bin_source_names.clone(),
+            target_aliases: self.target_aliases.clone(),
+            extra_files: self.extra_files.clone(),
             overrides: Default::default(),
+            cpu_variants: Default::default(),
+            tools: Default::default(),
+            github_actions: self.github_actions.clone(),
+            releases_index: self.releases_index.clone(),
+            disabled: self.disabled,
         }
     }
 }
 
 /// Target specific overrides for binary installation
 ///
-/// Exposed via `[package.metadata.TARGET]` in `Cargo.toml`
+/// Exposed via `[package.metadata.binstall.overrides.TARGET]` in `Cargo.toml`,
+/// any field set here takes precedence over the one in [`PkgMeta`] for
+/// that target.
 #[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "kebab-case", default)]
 pub struct PkgOverride {
@@ -103,11 +188,33 @@ pub struct PkgOverride {
     /// Format override for package downloads
     pub pkg_fmt: Option<PkgFmt>,
 
+    /// Mirrors override for package downloads
+    pub mirrors: Option<Vec<String>>,
+
     /// Path template override for binary files in packages
     pub bin_dir: Option<String>,
 
     /// Package signing configuration
     pub signing: Option<PkgSigning>,
+
+    /// Package checksum configuration
+    pub checksum: Option<PkgChecksum>,
+}
+
+/// Variant of the package built for CPU features
+///
+/// Exposed via `[[package.metadata.binstall.cpu-variants]]` in `Cargo.toml`,
+/// the fields set here take precedence over the ones in [`PkgMeta`] when
+/// the CPU supports all of `features`.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct CpuVariant {
+    /// Names of the `target_feature`s required, such as `avx2`, or of an
+    /// x86-64 microarchitecture level such as `x86-64-v3`
+    pub features: Vec<String>,
+
+    #[serde(flatten)]
+    pub overrides: PkgOverride,
 }
 
 #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
@@ -141,3 +248,124 @@ pub enum SigningAlgorithm {
     /// [minisign](https://jedisct1.github.io/minisign/)
     Minisign,
 }
+
+/// Artifact of a GitHub Actions workflow
+///
+/// Exposed via `[package.metadata.binstall.github-actions]` in `Cargo.toml`,
+/// the artifact uploaded by the latest successful run of the workflow is
+/// installed.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct PkgGhActions {
+    /// Repository running the workflow, as `owner/repo`, defaults to the
+    /// repository of the crate.
+    #[serde(default)]
+    pub repo: Option<String>,
+
+    /// File name or id of the workflow, e.g. `nightly.yml`.
+    pub workflow: String,
+
+    /// Name template of the artifact, e.g. `{ name }-{ target }`.
+    pub artifact: String,
+
+    /// Template of the branch or tag whose pushes trigger the workflow, e.g.
+    /// `v{ version }` for a workflow run on the tags of the releases,
+    /// defaults to the default branch of the repository.
+    #[serde(default)]
+    pub branch: Option<String>,
+
+    /// Template of the tag of the version, defaults to `v{ version }`.
+    ///
+    /// Only the runs on the commit of this tag are considered.
+    #[serde(default)]
+    pub tag: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct PkgChecksum {
+    /// Checksum algorithm, defaults to sha256.
+    #[serde(default)]
+    pub algorithm: ChecksumAlgorithm,
+
+    /// Checksum file override template (url to download),
+    /// defaults to `{ url }.{ algorithm }`.
+    #[serde(default)]
+    pub file: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+#[non_exhaustive]
+pub enum ChecksumAlgorithm {
+    #[default]
+    Sha256,
+    Sha512,
+    /// [BLAKE3](https://github.com/BLAKE3-team/BLAKE3)
+    Blake3,
+}
+
+impl ChecksumAlgorithm {
+    /// Name of the algorithm, as used in the extension of checksum files.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ChecksumAlgorithm::Sha256 => "sha256",
+            ChecksumAlgorithm::Sha512 => "sha512",
+            ChecksumAlgorithm::Blake3 => "blake3",
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_merge_overrides_target_bin_dir() {
+        let meta = PkgMeta {
+            bin_dir: Some("{ name }-{ target }/{ bin }{ binary-ext }".to_string()),
+            ..Default::default()
+        };
+        let target_override = PkgOverride {
+            bin_dir: Some("{ bin }{ binary-ext }".to_string()),
+            ..Default::default()
+        };
+        let cli_override = PkgOverride::default();
+
+        let merged = meta.merge_overrides([&cli_override, &target_override]);
+        assert_eq!(merged.bin_dir.as_deref(), Some("{ bin }{ binary-ext }"));
+
+        // Targets without an override keep using the default one
+        let merged = meta.merge_overrides([&cli_override]);
+        assert_eq!(merged.bin_dir, meta.bin_dir);
+
+        // Explicit cli override wins over the target specific one
+        let cli_override = PkgOverride {
+            bin_dir: Some("bin/{ bin }{ binary-ext }".to_string()),
+            ..Default::default()
+        };
+        let merged = meta.merge_overrides([&cli_override, &target_override]);
+        assert_eq!(merged.bin_dir.as_deref(), Some("bin/{ bin }{ binary-ext }"));
+    }
+
+    #[test]
+    fn test_merge_overrides_mirrors() {
+        let meta = PkgMeta {
+            mirrors: vec!["https://a.example.com".to_string()],
+            ..Default::default()
+        };
+        let target_override = PkgOverride {
+            mirrors: Some(vec![
+                "https://b.example.com".to_string(),
+                "https://c.example.com".to_string(),
+            ]),
+            ..Default::default()
+        };
+
+        let merged = meta.merge_overrides([&PkgOverride::default()]);
+        assert_eq!(merged.mirrors, meta.mirrors);
+
+        let merged = meta.merge_overrides([&PkgOverride::default(), &target_override]);
+        assert_eq!(Some(merged.mirrors), target_override.mirrors);
+    }
+}
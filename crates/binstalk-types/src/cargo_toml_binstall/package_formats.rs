@@ -18,10 +18,14 @@ pub enum PkgFmt {
     Txz,
     /// Download format is TAR + Zstd
     Tzstd,
+    /// Download format is TAR + LZ4
+    Tlz4,
     /// Download format is Zip
     Zip,
     /// Download format is raw / binary
     Bin,
+    /// Download format is raw / binary compressed with LZ4
+    Lz4,
 }
 
 impl Default for PkgFmt {
@@ -39,11 +43,19 @@ impl PkgFmt {
             PkgFmt::Tgz => PkgFmtDecomposed::Tar(TarBasedFmt::Tgz),
             PkgFmt::Txz => PkgFmtDecomposed::Tar(TarBasedFmt::Txz),
             PkgFmt::Tzstd => PkgFmtDecomposed::Tar(TarBasedFmt::Tzstd),
+            PkgFmt::Tlz4 => PkgFmtDecomposed::Tar(TarBasedFmt::Tlz4),
             PkgFmt::Bin => PkgFmtDecomposed::Bin,
+            PkgFmt::Lz4 => PkgFmtDecomposed::Lz4Bin,
             PkgFmt::Zip => PkgFmtDecomposed::Zip,
         }
     }
 
+    /// Return `true` if the package is the binary itself, possibly
+    /// compressed, instead of an archive containing it.
+    pub fn is_bare_binary(self) -> bool {
+        matches!(self, PkgFmt::Bin | PkgFmt::Lz4)
+    }
+
     /// List of possible file extensions for the format
     /// (with prefix `.`).
     ///
@@ -56,6 +68,7 @@ impl PkgFmt {
             PkgFmt::Tgz => &[".tgz", ".tar.gz"],
             PkgFmt::Txz => &[".txz", ".tar.xz"],
             PkgFmt::Tzstd => &[".tzstd", ".tzst", ".tar.zst"],
+            PkgFmt::Tlz4 => &[".tlz4", ".tar.lz4"],
             PkgFmt::Bin => {
                 if is_windows {
                     &[".bin", "", ".exe"]
@@ -64,11 +77,19 @@ impl PkgFmt {
                 }
             }
             PkgFmt::Zip => &[".zip"],
+            PkgFmt::Lz4 => &[".lz4"],
         }
     }
 
     /// Given the pkg-url template, guess the possible pkg-fmt.
+    ///
+    /// A file name without any extension is assumed to be the bare binary.
     pub fn guess_pkg_format(pkg_url: &str) -> Option<Self> {
+        let file_name = pkg_url.rsplit('/').next()?;
+        if !file_name.is_empty() && !file_name.contains('.') {
+            return Some(PkgFmt::Bin);
+        }
+
         let mut it = pkg_url.rsplitn(3, '.');
 
         let guess = match it.next()? {
@@ -86,6 +107,11 @@ impl PkgFmt {
             "tzstd" | "tzst" => Some(PkgFmt::Tzstd),
             "zst" if it.next() == Some("tar") => Some(PkgFmt::Tzstd),
 
+            "tlz4" => Some(PkgFmt::Tlz4),
+            "lz4" if it.next() == Some("tar") => Some(PkgFmt::Tlz4),
+            // The rest of the url was consumed by the guard above
+            "lz4" => return Some(PkgFmt::Lz4),
+
             "exe" | "bin" => Some(PkgFmt::Bin),
             "zip" => Some(PkgFmt::Zip),
 
@@ -104,6 +130,8 @@ impl PkgFmt {
 pub enum PkgFmtDecomposed {
     Tar(TarBasedFmt),
     Bin,
+    /// Raw / binary compressed with LZ4
+    Lz4Bin,
     Zip,
 }
 
@@ -119,6 +147,8 @@ pub enum TarBasedFmt {
     Txz,
     /// Download format is TAR + Zstd
     Tzstd,
+    /// Download format is TAR + LZ4
+    Tlz4,
 }
 
 impl From<TarBasedFmt> for PkgFmt {
@@ -129,6 +159,57 @@ impl From<TarBasedFmt> for PkgFmt {
             TarBasedFmt::Tgz => PkgFmt::Tgz,
             TarBasedFmt::Txz => PkgFmt::Txz,
             TarBasedFmt::Tzstd => PkgFmt::Tzstd,
+            TarBasedFmt::Tlz4 => PkgFmt::Tlz4,
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_guess_pkg_format() {
+        let guess = |pkg_url| PkgFmt::guess_pkg_format(pkg_url);
+
+        assert_eq!(
+            guess("{ repo }/v{ version }/{ name }.tar.gz"),
+            Some(PkgFmt::Tgz)
+        );
+        assert_eq!(
+            guess("{ repo }/v{ version }/{ name }.zip"),
+            Some(PkgFmt::Zip)
+        );
+        assert_eq!(
+            guess("{ repo }/v{ version }/{ name }.exe"),
+            Some(PkgFmt::Bin)
+        );
+
+        // Bare binaries
+        assert_eq!(
+            guess("{ repo }/v{ version }/{ name }-{ target }{ binary-ext }"),
+            Some(PkgFmt::Bin)
+        );
+        assert_eq!(
+            guess("/releases/tool-x86_64-unknown-linux-gnu"),
+            Some(PkgFmt::Bin)
+        );
+
+        // LZ4
+        assert_eq!(
+            guess("{ repo }/v{ version }/{ name }.tar.lz4"),
+            Some(PkgFmt::Tlz4)
+        );
+        assert_eq!(
+            guess("{ repo }/v{ version }/{ name }.tlz4"),
+            Some(PkgFmt::Tlz4)
+        );
+        assert_eq!(
+            guess("{ repo }/v{ version }/{ name }-{ target }.lz4"),
+            Some(PkgFmt::Lz4)
+        );
+
+        assert_eq!(guess("{ repo }/v{ version }/{ name }.{ target }"), None);
+        assert_eq!(guess("{ repo }/v{ version }/"), None);
+    }
+}
@@ -1,6 +1,6 @@
 //! Common structure for crate information for post-install manifests.
 
-use std::{borrow, cmp, hash};
+use std::{borrow, cmp, hash, path::PathBuf};
 
 use compact_str::CompactString;
 use maybe_owned::MaybeOwned;
@@ -24,6 +24,25 @@ pub struct CrateInfo {
     pub source: CrateSource,
     pub target: CompactString,
     pub bins: Vec<CompactString>,
+    /// Installed with versioned binary names, alongside the other
+    /// versions of the crate instead of replacing them.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub versioned: bool,
+    /// sha256 digest of the package downloaded, recorded for the packages
+    /// rebuilt by third parties (QuickInstall) to detect when they change.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub package_sha256: Option<CompactString>,
+    /// Url of the archive installed with `--url`.
+    ///
+    /// Such crates are only recorded by binstall, cargo has no source kind
+    /// for them. `source` is then the crates.io registry and
+    /// `current_version` the version requested, 0.0.0 if none.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub direct_url: Option<Url>,
+    /// Paths of the extra files installed alongside the binaries, such as
+    /// licenses.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extra_files: Vec<PathBuf>,
 }
 
 impl borrow::Borrow<str> for CrateInfo {
@@ -1,6 +1,6 @@
 use std::{
-    io,
-    path::PathBuf,
+    io::{self, Write},
+    path::{Path, PathBuf},
     process::{ExitCode, ExitStatus, Termination},
 };
 
@@ -20,6 +20,7 @@ use crate::{
     helpers::{
         cargo_toml::Error as CargoTomlError, cargo_toml_workspace::Error as LoadManifestFromWSError,
     },
+    manifests::cargo_toml_binstall::PkgFmt,
     registry::{InvalidRegistryError, RegistryError},
 };
 
@@ -131,7 +132,44 @@ pub enum BinstallError {
     /// - Exit: 68
     #[error(transparent)]
     #[diagnostic(severity(error), code(binstall::download))]
-    Download(#[from] DownloadError),
+    Download(DownloadError),
+
+    /// The download exceeds the size set by `--max-download-size`.
+    ///
+    /// - Code: `binstall::download::too_large`
+    /// - Exit: 69
+    #[error("download exceeds the maximum size of {max_size} bytes")]
+    #[diagnostic(
+        severity(error),
+        code(binstall::download::too_large),
+        help("Check the url is correct, or raise the limit with --max-download-size.")
+    )]
+    DownloadTooLarge { max_size: u64 },
+
+    /// The package downloaded is truncated or corrupted.
+    ///
+    /// - Code: `binstall::download::corrupt`
+    /// - Exit: 93
+    #[error("the {fmt} package is truncated or corrupted: {source}")]
+    #[diagnostic(
+        severity(error),
+        code(binstall::download::corrupt),
+        help("The download may have been interrupted, retry or use another fetcher with --strategies.")
+    )]
+    CorruptArchive { fmt: PkgFmt, source: io::Error },
+
+    /// The temporary directory is on a filesystem binaries cannot be
+    /// downloaded to, e.g. it is full or read-only.
+    ///
+    /// - Code: `binstall::temp_dir`
+    /// - Exit: 66
+    #[error("temporary directory {} cannot be used: {reason}", path.display())]
+    #[diagnostic(
+        severity(error),
+        code(binstall::temp_dir),
+        help("Use --temp-dir to put the temporary directory on another filesystem.")
+    )]
+    UnusableTempDir { path: PathBuf, reason: &'static str },
 
     /// A subprocess failed.
     ///
@@ -229,6 +267,32 @@ pub enum BinstallError {
     )]
     SuperfluousVersionOption,
 
+    /// `--exact` was used with a version requirement not naming a complete version.
+    ///
+    /// - Code: `binstall::version::inexact`
+    /// - Exit: 81
+    #[error("version requirement '{0}' is not an exact version")]
+    #[diagnostic(
+        severity(error),
+        code(binstall::version::inexact),
+        help("--exact requires a complete version, e.g. crate@1.2.3 or --version 1.2.3")
+    )]
+    InexactVersion(CompactString),
+
+    /// The crate does not have any binary target at all.
+    ///
+    /// This usually means that it is a library crate, which cannot be installed.
+    ///
+    /// - Code: `binstall::resolve::no_binaries`
+    /// - Exit: 85
+    #[error("crate {0} has no binaries to install")]
+    #[diagnostic(
+        severity(error),
+        code(binstall::resolve::no_binaries),
+        help("This crate does not have any [[bin]] targets, it is probably a library crate.\nDid you mean to install a different crate, e.g. one with a `-cli` suffix?")
+    )]
+    NoBinaries(CompactString),
+
     /// No binaries were found for the crate.
     ///
     /// When installing, either the binaries are specified in the crate's Cargo.toml, or they're
@@ -241,7 +305,7 @@ pub enum BinstallError {
     #[diagnostic(
         severity(error),
         code(binstall::resolve::binaries),
-        help("This crate doesn't specify any binaries, so there's nothing to install.")
+        help("This crate declares binary targets, but none of them are named, so there's nothing to install.")
     )]
     UnspecifiedBinaries,
 
@@ -283,6 +347,95 @@ pub enum BinstallError {
     #[diagnostic(severity(error), code(binstall::cargo_manifest))]
     CargoTomlMissingPackage(CompactString),
 
+    /// The manifest downloaded from `--manifest-url` is of another crate.
+    ///
+    /// - Code: `binstall::cargo_manifest`
+    /// - Exit: 100
+    #[error("Cargo.toml is of crate {found} rather than {name}")]
+    #[diagnostic(severity(error), code(binstall::cargo_manifest))]
+    ManifestNameMismatch {
+        name: CompactString,
+        found: CompactString,
+    },
+
+    /// The package of the crate is not downloaded from the host of its
+    /// repository, with `--strict-origin`.
+    ///
+    /// - Code: `binstall::strict_origin`
+    /// - Exit: 101
+    #[error(
+        "package of {name} is downloaded from {}, which is not on the host of its repository {}",
+        .url.as_deref().unwrap_or("an unknown origin"),
+        .repo.as_deref().unwrap_or("(none declared)")
+    )]
+    #[diagnostic(
+        severity(error),
+        code(binstall::strict_origin),
+        help("Check the `pkg-url` of the crate, or install without --strict-origin")
+    )]
+    OriginMismatch {
+        name: CompactString,
+        url: Option<Box<str>>,
+        repo: Option<Box<str>>,
+    },
+
+    /// The `binstall.toml` next to the manifest of the crate is invalid.
+    ///
+    /// - Code: `binstall::cargo_manifest`
+    /// - Exit: 102
+    #[error("Invalid binstall.toml at {path}: {reason}")]
+    #[diagnostic(severity(error), code(binstall::cargo_manifest))]
+    InvalidSidecar { path: Box<str>, reason: Box<str> },
+
+    /// The author of the crate disabled pre-built binaries, and the
+    /// fallback to `cargo-install` is disabled.
+    ///
+    /// - Code: `binstall::prebuilt_disabled`
+    /// - Exit: 103
+    #[error(
+        "Pre-built binaries of {0} are disabled by its author and cannot be built from source"
+    )]
+    #[diagnostic(
+        severity(error),
+        code(binstall::prebuilt_disabled),
+        help("Enable the fallback to cargo-install, or try them anyway with --force-prebuilt")
+    )]
+    PrebuiltDisabled(CompactString),
+
+    /// The binstall metadata of the crate interpolates environment variables,
+    /// which only templates given by the user may do.
+    ///
+    /// - Code: `binstall::cargo_manifest`
+    /// - Exit: 104
+    #[error("binstall metadata of {name} uses environment variables in {fields}")]
+    #[diagnostic(
+        severity(error),
+        code(binstall::cargo_manifest),
+        help("`{{ env:VAR }}` is only available in --pkg-url and --target-config")
+    )]
+    EnvVarInCrateMetadata {
+        name: CompactString,
+        fields: Box<str>,
+    },
+
+    /// The `--after` constraints form a cycle among the crates to install,
+    /// including those added by `--from-file` or `--dev-tools`.
+    ///
+    /// - Code: `binstall::install_after`
+    /// - Exit: 105
+    #[error("--after constraints form a cycle involving {0}")]
+    #[diagnostic(severity(error), code(binstall::install_after))]
+    InstallAfterCycle(CompactString),
+
+    /// The [`FetcherSelector`](crate::ops::FetcherSelector) returned the
+    /// index of a fetcher which is not among the candidates.
+    ///
+    /// - Code: `binstall::fetcher_selector`
+    /// - Exit: 106
+    #[error("Fetcher #{index} was selected, but only {count} were found")]
+    #[diagnostic(severity(error), code(binstall::fetcher_selector))]
+    InvalidFetcherSelection { index: usize, count: usize },
+
     /// bin-dir configuration provided generates duplicate source path.
     ///
     /// - Code: `binstall::cargo_manifest`
@@ -291,6 +444,18 @@ pub enum BinstallError {
     #[diagnostic(severity(error), code(binstall::SourceFilePath))]
     DuplicateSourceFilePath { path: PathBuf },
 
+    /// `--frozen` forbids an operation the installation needs.
+    ///
+    /// - Code: `binstall::frozen`
+    /// - Exit: 91
+    #[error("--frozen is specified, but {0}")]
+    #[diagnostic(
+        severity(error),
+        code(binstall::frozen),
+        help("Use --manifest-path with a local checkout and enable the compile strategy, or remove --frozen.")
+    )]
+    Frozen(&'static str),
+
     /// Fallback to `cargo-install` is disabled.
     ///
     /// - Code: `binstall::no_fallback_to_cargo_install`
@@ -299,6 +464,19 @@ pub enum BinstallError {
     #[diagnostic(severity(error), code(binstall::no_fallback_to_cargo_install))]
     NoFallbackToCargoInstall,
 
+    /// None of the fetchers has a pre-built binary for the targets probed,
+    /// and fallback to `cargo-install` is disabled.
+    ///
+    /// - Code: `binstall::no_prebuilt_binaries`
+    /// - Exit: 92
+    #[error("no pre-built binary was found for targets {targets}")]
+    #[diagnostic(
+        severity(error),
+        code(binstall::no_prebuilt_binaries),
+        help("Use --targets to try a compatible target (e.g. a musl one), or enable the compile strategy to build from source.")
+    )]
+    NoPrebuiltBinaries { targets: CompactString },
+
     /// Fallback to `cargo-install` is disabled.
     ///
     /// - Code: `binstall::invalid_pkg_fmt`
@@ -355,9 +533,11 @@ impl BinstallError {
             InvalidSignature { .. } => 40,
             MissingSignature(_) => 41,
             UrlParse(_) => 65,
+            UnusableTempDir { .. } => 66,
             TemplateParseError(..) => 67,
             FetchError(..) => 68,
             Download(_) => 68,
+            DownloadTooLarge { .. } => 69,
             SubProcess { .. } => 70,
             Io(_) => 74,
             UnknownRegistryName(_) => 75,
@@ -366,12 +546,17 @@ impl BinstallError {
             CargoManifest { .. } => 78,
             RegistryParseError(..) => 79,
             VersionParse { .. } => 80,
+            InexactVersion(_) => 81,
             SuperfluousVersionOption => 84,
+            NoBinaries(_) => 85,
             UnspecifiedBinaries => 86,
             NoViableTargets => 87,
             BinFile(_) => 88,
             CargoTomlMissingPackage(_) => 89,
             DuplicateSourceFilePath { .. } => 90,
+            Frozen(_) => 91,
+            NoPrebuiltBinaries { .. } => 92,
+            CorruptArchive { .. } => 93,
             NoFallbackToCargoInstall => 94,
             InvalidPkgFmt(..) => 95,
             GhApiErr(..) => 96,
@@ -379,6 +564,13 @@ impl BinstallError {
             #[cfg(feature = "git")]
             GitError(_) => 98,
             LoadManifestFromWSError(_) => 99,
+            ManifestNameMismatch { .. } => 100,
+            OriginMismatch { .. } => 101,
+            InvalidSidecar { .. } => 102,
+            PrebuiltDisabled(_) => 103,
+            EnvVarInCrateMetadata { .. } => 104,
+            InstallAfterCycle(_) => 105,
+            InvalidFetcherSelection { .. } => 106,
             CrateContext(context) => context.err.exit_number(),
         };
 
@@ -399,6 +591,35 @@ impl BinstallError {
         self.exit_number().into()
     }
 
+    /// Return the I/O error `self` is caused by, if any.
+    fn io_error(&self) -> Option<&io::Error> {
+        match self {
+            BinstallError::Io(err)
+            | BinstallError::Download(DownloadError::Io(err))
+            | BinstallError::BinFile(bins::Error::Io(err)) => Some(err),
+            BinstallError::FetchError(err) => match &**err {
+                FetchError::Download(DownloadError::Io(err)) => Some(err),
+                _ => None,
+            },
+            BinstallError::CrateContext(context) => context.err.io_error(),
+            _ => None,
+        }
+    }
+
+    /// Return the reason the temporary directory `temp_dir` cannot be used,
+    /// if `self` is caused by the filesystem it is on (e.g. it is full).
+    ///
+    /// I/O errors do not tell which path they are about, so this only holds
+    /// if writing a file in `temp_dir` fails in the same way.
+    ///
+    /// This is a blocking function.
+    pub(crate) fn temp_dir_failure(&self, temp_dir: &Path) -> Option<&'static str> {
+        let reason = filesystem_failure(self.io_error()?)?;
+        let probe_err = probe_temp_dir(temp_dir).err()?;
+
+        (filesystem_failure(&probe_err) == Some(reason)).then_some(reason)
+    }
+
     /// Add crate context to the error
     pub fn crate_context(self, crate_name: impl Into<CompactString>) -> Self {
         Self::CrateContext(Box::new(CrateContextError {
@@ -408,6 +629,40 @@ impl BinstallError {
     }
 }
 
+/// Return the reason `err` is caused by the filesystem, if it is.
+fn filesystem_failure(err: &io::Error) -> Option<&'static str> {
+    #[cfg(unix)]
+    const FILESYSTEM_FULL: &[i32] = &[28 /* ENOSPC */];
+    #[cfg(windows)]
+    const FILESYSTEM_FULL: &[i32] = &[
+        39,  // ERROR_HANDLE_DISK_FULL
+        112, // ERROR_DISK_FULL
+    ];
+    #[cfg(not(any(unix, windows)))]
+    const FILESYSTEM_FULL: &[i32] = &[];
+
+    // EROFS
+    const READ_ONLY_FILESYSTEM: i32 = 30;
+
+    match err.raw_os_error() {
+        Some(code) if FILESYSTEM_FULL.contains(&code) => Some("no space left on the filesystem"),
+        Some(READ_ONLY_FILESYSTEM) if cfg!(unix) => Some("the filesystem is read-only"),
+        _ if err.kind() == io::ErrorKind::PermissionDenied => {
+            Some("permission denied, the filesystem may be read-only")
+        }
+        _ => None,
+    }
+}
+
+/// Write a file in `temp_dir`, like the packages downloaded to it are.
+fn probe_temp_dir(temp_dir: &Path) -> io::Result<()> {
+    let mut file = tempfile::Builder::new()
+        .prefix("probe")
+        .tempfile_in(temp_dir)?;
+    file.write_all(b"probe")?;
+    file.as_file().sync_all()
+}
+
 impl Termination for BinstallError {
     fn report(self) -> ExitCode {
         let code = self.exit_code();
@@ -449,6 +704,18 @@ impl From<BinstallError> for io::Error {
     }
 }
 
+impl From<DownloadError> for BinstallError {
+    fn from(e: DownloadError) -> Self {
+        match e {
+            DownloadError::TooLarge { max_size } => BinstallError::DownloadTooLarge { max_size },
+            DownloadError::CorruptArchive { fmt, source } => {
+                BinstallError::CorruptArchive { fmt, source }
+            }
+            e => BinstallError::Download(e),
+        }
+    }
+}
+
 impl From<RemoteError> for BinstallError {
     fn from(e: RemoteError) -> Self {
         DownloadError::from(e).into()
@@ -499,6 +766,32 @@ impl From<LoadManifestFromWSError> for BinstallError {
 
 impl From<FetchError> for BinstallError {
     fn from(e: FetchError) -> Self {
-        BinstallError::FetchError(Box::new(e))
+        match e {
+            FetchError::Download(DownloadError::TooLarge { max_size }) => {
+                BinstallError::DownloadTooLarge { max_size }
+            }
+            FetchError::Download(DownloadError::CorruptArchive { fmt, source }) => {
+                BinstallError::CorruptArchive { fmt, source }
+            }
+            FetchError::ExtractCommand { command, status } => BinstallError::SubProcess {
+                command: command.display().to_string().into(),
+                status,
+            },
+            e => BinstallError::FetchError(Box::new(e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn temp_dir_failure_on_other_path() {
+        let temp_dir = tempfile::tempdir().unwrap();
+
+        // e.g. failing to write to `--keep-download`
+        let err = BinstallError::Io(io::ErrorKind::PermissionDenied.into());
+        assert_eq!(err.temp_dir_failure(temp_dir.path()), None);
     }
 }
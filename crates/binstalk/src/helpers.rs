@@ -1,3 +1,8 @@
+use std::{io, path::Path, time::SystemTime};
+
+use filetime::FileTime;
+
+pub(crate) mod cpu_features;
 pub mod jobserver_client;
 pub mod remote;
 pub(crate) mod target_triple;
@@ -10,6 +15,13 @@ pub(crate) use cargo_toml_workspace::{self, cargo_toml};
 #[cfg(feature = "git")]
 pub(crate) use simple_git as git;
 
+/// Set the access and modification times of `path` to `mtime`, without
+/// following symlinks.
+pub fn pin_mtime(path: &Path, mtime: SystemTime) -> io::Result<()> {
+    let mtime = FileTime::from_system_time(mtime);
+    filetime::set_symlink_file_times(path, mtime, mtime)
+}
+
 pub(crate) fn is_universal_macos(target: &str) -> bool {
     ["universal-apple-darwin", "universal2-apple-darwin"].contains(&target)
 }
@@ -0,0 +1,86 @@
+use std::env::consts::ARCH;
+
+use crate::manifests::cargo_toml_binstall::CpuVariant;
+
+/// Return the first of `variants` supported by the CPU binstall runs on,
+/// or `None` to use the baseline package.
+///
+/// Only packages for the architecture of the host are considered, since
+/// the features of other CPUs cannot be detected.
+pub(crate) fn select_variant<'a>(
+    variants: &'a [CpuVariant],
+    target_arch: &str,
+) -> Option<&'a CpuVariant> {
+    if target_arch != ARCH {
+        return None;
+    }
+
+    variants
+        .iter()
+        .find(|variant| variant.features.iter().all(|feature| is_detected(feature)))
+}
+
+/// Return `true` if the CPU supports `feature`, either a `target_feature`
+/// name or an x86-64 microarchitecture level.
+///
+/// Features unknown to binstall are assumed to be unsupported.
+#[cfg(target_arch = "x86_64")]
+fn is_detected(feature: &str) -> bool {
+    macro_rules! detected {
+        ($($feature:tt),*) => { true $(&& is_x86_feature_detected!($feature))* };
+    }
+
+    match feature {
+        "x86-64-v2" => detected!("cmpxchg16b", "popcnt", "sse3", "sse4.1", "sse4.2", "ssse3"),
+        "x86-64-v3" => {
+            is_detected("x86-64-v2")
+                && detected!("avx", "avx2", "bmi1", "bmi2", "f16c", "fma", "lzcnt", "movbe")
+        }
+        "x86-64-v4" => {
+            is_detected("x86-64-v3")
+                && detected!("avx512f", "avx512bw", "avx512cd", "avx512dq", "avx512vl")
+        }
+        "aes" => detected!("aes"),
+        "avx" => detected!("avx"),
+        "avx2" => detected!("avx2"),
+        "avx512f" => detected!("avx512f"),
+        "bmi1" => detected!("bmi1"),
+        "bmi2" => detected!("bmi2"),
+        "fma" => detected!("fma"),
+        "lzcnt" => detected!("lzcnt"),
+        "pclmulqdq" => detected!("pclmulqdq"),
+        "popcnt" => detected!("popcnt"),
+        "sha" => detected!("sha"),
+        "sse3" => detected!("sse3"),
+        "sse4.1" => detected!("sse4.1"),
+        "sse4.2" => detected!("sse4.2"),
+        "ssse3" => detected!("ssse3"),
+        _ => false,
+    }
+}
+
+/// Return `true` if the CPU supports `feature`, a `target_feature` name.
+///
+/// Features unknown to binstall are assumed to be unsupported.
+#[cfg(target_arch = "aarch64")]
+fn is_detected(feature: &str) -> bool {
+    use std::arch::is_aarch64_feature_detected;
+
+    match feature {
+        "aes" => is_aarch64_feature_detected!("aes"),
+        "crc" => is_aarch64_feature_detected!("crc"),
+        "dotprod" => is_aarch64_feature_detected!("dotprod"),
+        "lse" => is_aarch64_feature_detected!("lse"),
+        "neon" => is_aarch64_feature_detected!("neon"),
+        "sha2" => is_aarch64_feature_detected!("sha2"),
+        "sha3" => is_aarch64_feature_detected!("sha3"),
+        "sve" => is_aarch64_feature_detected!("sve"),
+        "sve2" => is_aarch64_feature_detected!("sve2"),
+        _ => false,
+    }
+}
+
+#[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+fn is_detected(_feature: &str) -> bool {
+    false
+}
@@ -8,4 +8,4 @@ use binstalk_bins as bins;
 pub use binstalk_fetchers as fetchers;
 pub use binstalk_registry as registry;
 pub use binstalk_types as manifests;
-pub use detect_targets::{get_desired_targets, DesiredTargets, TARGET};
+pub use detect_targets::{get_desired_targets, get_host_target, DesiredTargets, TARGET};
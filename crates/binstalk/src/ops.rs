@@ -1,30 +1,158 @@
 //! Concrete Binstall operations.
 
-use std::{path::PathBuf, sync::Arc};
+use std::{collections::BTreeMap, fmt, path::PathBuf, str::FromStr, sync::Arc, time::SystemTime};
 
-use semver::VersionReq;
+use compact_str::CompactString;
+use semver::{Version, VersionReq};
+use url::Url;
 
 use crate::{
+    errors::BinstallError,
     fetchers::{Data, Fetcher, SignaturePolicy, TargetDataErased},
     helpers::{
         self, gh_api_client::GhApiClient, jobserver_client::LazyJobserverClient, remote::Client,
     },
     manifests::cargo_toml_binstall::PkgOverride,
-    registry::Registry,
+    registry::{self, CrateVersion, Registry},
     DesiredTargets,
 };
 
+pub mod check_manifest;
+pub mod report;
 pub mod resolve;
 
 pub type Resolver =
     fn(Client, GhApiClient, Arc<Data>, Arc<TargetDataErased>, SignaturePolicy) -> Arc<dyn Fetcher>;
 
+type SelectFn = dyn Fn(&str, &[resolve::Candidate]) -> Result<usize, BinstallError> + Send + Sync;
+
+/// Picks the fetcher to install a crate from, see [`FetcherSelector::new`].
+#[derive(Clone)]
+pub struct FetcherSelector(Arc<SelectFn>);
+
+impl FetcherSelector {
+    /// `select` is called with the name of a crate and the fetchers which
+    /// found a package of it, in the order of preference, and returns the
+    /// index of the one to install from, or [`BinstallError::UserAbort`].
+    /// The others are tried in order if the selected one cannot be used.
+    ///
+    /// It is run on a blocking thread, and only if there is more than one.
+    pub fn new(
+        select: impl Fn(&str, &[resolve::Candidate]) -> Result<usize, BinstallError>
+            + Send
+            + Sync
+            + 'static,
+    ) -> Self {
+        Self(Arc::new(select))
+    }
+
+    pub(crate) fn select(
+        &self,
+        name: &str,
+        candidates: &[resolve::Candidate],
+    ) -> Result<usize, BinstallError> {
+        (self.0)(name, candidates)
+    }
+}
+
+impl fmt::Debug for FetcherSelector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("FetcherSelector")
+    }
+}
+
+/// Policy choosing the version of a crate to install among the ones
+/// published on the registry, see [`Options::version_selector`].
+pub trait VersionSelector: fmt::Debug + Send + Sync {
+    /// Return the version to install among `versions`, from the lowest to
+    /// the highest and including the yanked ones, or `None` if none of
+    /// them is acceptable for `version_req`.
+    ///
+    /// `allow_pre_releases` is [`Options::allow_pre_releases`].
+    fn select(
+        &self,
+        versions: &[CrateVersion],
+        version_req: &VersionReq,
+        allow_pre_releases: bool,
+    ) -> Option<Version>;
+}
+
+/// The highest version matching the version requirement which is not
+/// yanked, as picked if [`Options::version_selector`] is `None`.
+///
+/// Custom [`VersionSelector`]s can delegate to it once they have filtered
+/// out the versions they reject.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LatestVersionSelector;
+
+impl VersionSelector for LatestVersionSelector {
+    fn select(
+        &self,
+        versions: &[CrateVersion],
+        version_req: &VersionReq,
+        allow_pre_releases: bool,
+    ) -> Option<Version> {
+        versions
+            .iter()
+            .filter(|crate_version| {
+                !crate_version.yanked
+                    && registry::is_version_matched(
+                        version_req,
+                        &crate_version.version,
+                        allow_pre_releases,
+                    )
+            })
+            .map(|crate_version| &crate_version.version)
+            .max()
+            .cloned()
+    }
+}
+
 #[derive(Debug)]
 #[non_exhaustive]
 pub enum CargoTomlFetchOverride {
     #[cfg(feature = "git")]
     Git(helpers::git::GitUrl),
     Path(PathBuf),
+    /// Download the `Cargo.toml` at this url instead of the crate from the
+    /// registry, e.g. the raw file in the repository of the crate.
+    ManifestUrl(Url),
+    /// Skip the registry and fetchers entirely, download the archive at
+    /// `url` and install `bins` from it.
+    Url {
+        url: Url,
+        bins: Vec<CompactString>,
+    },
+}
+
+/// Rewrite rule for the `repository` of crates, in `pattern=replacement` syntax.
+#[derive(Clone, Debug)]
+pub struct RepoRewrite {
+    pub pattern: CompactString,
+    pub replacement: CompactString,
+}
+
+impl RepoRewrite {
+    /// Apply `rewrites` in order to `repo`.
+    pub fn apply_all(rewrites: &[Self], repo: String) -> String {
+        rewrites.iter().fold(repo, |repo, rewrite| {
+            repo.replace(rewrite.pattern.as_str(), &rewrite.replacement)
+        })
+    }
+}
+
+impl FromStr for RepoRewrite {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once('=') {
+            Some((pattern, replacement)) if !pattern.is_empty() => Ok(Self {
+                pattern: pattern.into(),
+                replacement: replacement.into(),
+            }),
+            _ => Err("expected `pattern=replacement` with a non-empty pattern"),
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -34,24 +162,101 @@ pub struct Options {
     pub force: bool,
     pub quiet: bool,
     pub locked: bool,
+    /// Forbid network access, in addition to `locked`.
+    pub frozen: bool,
     pub no_track: bool,
+    /// Install binaries under versioned names, alongside other versions
+    /// of the crate, with symlinks only pointing to the newest one.
+    pub versioned_bin: bool,
 
     pub version_req: Option<VersionReq>,
+    /// Only accept the exact version named by the version requirement.
+    pub exact: bool,
+    /// Also consider the pre-releases of the versions matching the version
+    /// requirement, installing one if no higher stable version matches.
+    pub allow_pre_releases: bool,
+    /// Choose the version of the crates fetched from the registry, defaults
+    /// to [`LatestVersionSelector`].
+    pub version_selector: Option<Arc<dyn VersionSelector>>,
+    /// Do not check whether `version_req` resolves to an older version than
+    /// the latest one in the registry.
+    pub quiet_version_check: bool,
     pub cargo_toml_fetch_override: Option<CargoTomlFetchOverride>,
+    /// Workspace whose crates are installed from their local manifest
+    /// instead of the one in the registry, if their version matches.
+    pub local_manifest_dir: Option<PathBuf>,
     pub cli_overrides: PkgOverride,
+    /// Overrides for each target, taking precedence over the ones of the crate.
+    pub target_overrides: BTreeMap<CompactString, PkgOverride>,
 
     pub desired_targets: DesiredTargets,
     pub resolvers: Vec<Resolver>,
+    /// Pick the fetcher to install each crate from among all of those which
+    /// found a package, instead of taking the first one.
+    pub select_fetcher: Option<FetcherSelector>,
+    /// Program run by the external fetcher, if it is among `resolvers`.
+    pub external_fetcher: Option<PathBuf>,
+    /// Program extracting the `bin` packages, run with the paths of the
+    /// package and of the directory to extract it to.
+    pub extract_command: Option<PathBuf>,
     pub cargo_install_fallback: bool,
+    /// Try the pre-built binaries of crates even if their author disabled
+    /// them with `disabled` in their binstall metadata.
+    pub force_prebuilt: bool,
+    /// Features enabled when building from source, only used by the
+    /// `cargo install` fallback.
+    pub features: Vec<CompactString>,
+    pub no_default_features: bool,
+    pub all_features: bool,
 
     pub temp_dir: PathBuf,
+    /// Directory to keep a copy of the downloaded packages in.
+    pub keep_download: Option<PathBuf>,
     pub install_path: PathBuf,
     pub cargo_root: Option<PathBuf>,
+    /// Directory the `extra-files` of the packages are installed into, in a
+    /// subdirectory named after the crate, `None` to not install them.
+    pub extra_files_dir: Option<PathBuf>,
+    /// Shell command run before installing the binaries of each crate,
+    /// aborting its installation if it fails.
+    pub pre_install_cmd: Option<String>,
+    /// Shell command run after successfully installing the binaries of
+    /// each crate, only warning if it fails.
+    ///
+    /// The install commands are run one at a time, with their stdout
+    /// redirected to stderr.
+    pub post_install_cmd: Option<String>,
+    /// Modification time set on the installed files, so that installing
+    /// the same crates produces the same files.
+    pub pinned_mtime: Option<SystemTime>,
+    /// Warn if the installed binaries report another version than the one
+    /// of the crate when run with `--version`.
+    pub check_version: bool,
+    /// Launch `find()` of the fetchers one at a time, in the order of
+    /// preference, instead of all at once.
+    pub sequential_discovery: bool,
 
+    /// Client used by the fetchers and the registry, which can share an
+    /// existing [`ReqwestClient`] via [`Client::from_reqwest`].
+    ///
+    /// [`ReqwestClient`]: crate::helpers::remote::ReqwestClient
     pub client: Client,
     pub gh_api_client: GhApiClient,
+    /// Access token used to download from private Bitbucket repositories.
+    pub bitbucket_token: Option<CompactString>,
+    /// Wait for the GitHub rate limit to be reset instead of failing.
+    pub wait_rate_limit: bool,
     pub jobserver_client: LazyJobserverClient,
     pub registry: Registry,
 
     pub signature_policy: SignaturePolicy,
+    pub prefer_smallest: bool,
+    /// Third-party sources allowed to be used, all of them if `None`.
+    pub trusted_hosts: Option<Vec<CompactString>>,
+    /// Abort if a package is not downloaded from the host of the crate's
+    /// repository.
+    pub strict_origin: bool,
+    /// Rewrites applied in order to the `repository` of crates, before
+    /// the fetchers use it.
+    pub repo_rewrites: Vec<RepoRewrite>,
 }
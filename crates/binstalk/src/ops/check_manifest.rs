@@ -0,0 +1,502 @@
+//! Offline validation of `[package.metadata.binstall]`.
+
+use std::{
+    fmt,
+    path::{Component, Path},
+    str::FromStr,
+};
+
+use leon::Template;
+
+use crate::{
+    errors::BinstallError,
+    helpers::{cargo_toml::Manifest, target_triple::TargetTriple},
+    manifests::cargo_toml_binstall::{Meta, PkgFmt, PkgMeta, PkgOverride},
+    ops::resolve::{load_manifest_path, CrateName},
+};
+
+/// Keys available when rendering `pkg-url`.
+const PKG_URL_KEYS: &[&str] = &[
+    "name",
+    "repo",
+    "target",
+    "version",
+    "archive-format",
+    "format",
+    "archive-suffix",
+    "binary-ext",
+    "subcrate",
+    "mirror",
+    "tag",
+];
+
+/// Keys available when rendering `bin-dir`.
+const BIN_DIR_KEYS: &[&str] = &[
+    "name",
+    "repo",
+    "target",
+    "version",
+    "bin",
+    "binary-ext",
+    "format",
+];
+
+/// Keys available when rendering the files downloaded along the package.
+const SIDECAR_KEYS: &[&str] = &[
+    "name",
+    "repo",
+    "target",
+    "version",
+    "archive-format",
+    "format",
+    "archive-suffix",
+    "binary-ext",
+    "subcrate",
+    "mirror",
+    "tag",
+    "url",
+];
+
+/// Keys available when rendering `tag`.
+const TAG_KEYS: &[&str] = &["name", "repo", "target", "version", "subcrate"];
+
+/// Keys available when rendering `github-actions.artifact`.
+const GH_ACTIONS_ARTIFACT_KEYS: &[&str] = &["name", "version", "target"];
+
+/// Keys available when rendering `releases-index`.
+const RELEASES_INDEX_KEYS: &[&str] = &["name", "version"];
+
+/// Keys derived from the target, available in every template.
+const TARGET_KEYS: &[&str] = &[
+    "target-family",
+    "target-arch",
+    "target-libc",
+    "target-vendor",
+];
+
+/// Prefix of the keys interpolating environment variables, which only
+/// templates given by the user may use.
+const ENV_KEY_PREFIX: &str = "env:";
+
+/// A mistake found in the binstall metadata of a crate.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ManifestIssue {
+    /// Path of the offending field in `[package.metadata.binstall]`,
+    /// e.g. `overrides.x86_64-pc-windows-msvc.bin-dir`.
+    pub field: String,
+    pub message: String,
+}
+
+impl fmt::Display for ManifestIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.field, self.message)
+    }
+}
+
+/// Load the manifest at `manifest_path`, which may be either the Cargo.toml
+/// itself or the folder containing it, and check its binstall metadata
+/// without any network access.
+///
+/// If `name` is `None`, the package defined by the manifest is checked,
+/// otherwise the crate is looked up in the workspace.
+///
+/// This is a blocking function.
+pub fn check_manifest(
+    manifest_path: &Path,
+    name: Option<&str>,
+) -> Result<Vec<ManifestIssue>, BinstallError> {
+    let manifest = match name {
+        Some(name) => load_manifest_path(manifest_path, name)?,
+        None => {
+            let manifest_path = if manifest_path.is_dir() {
+                manifest_path.join("Cargo.toml")
+            } else {
+                manifest_path.to_owned()
+            };
+
+            let manifest = Manifest::<Meta>::from_path_with_metadata(&manifest_path)?;
+            let name = manifest
+                .package
+                .as_ref()
+                .map(|package| package.name.clone())
+                .ok_or(BinstallError::CargoTomlMissingPackage("<root>".into()))?;
+
+            load_manifest_path(manifest_path, name)?
+        }
+    };
+
+    let bins_count = manifest.bin.len();
+
+    Ok(manifest
+        .package
+        .and_then(|package| package.metadata)
+        .and_then(|metadata| metadata.binstall)
+        .map(|meta| check_meta(&meta, bins_count))
+        .unwrap_or_default())
+}
+
+/// Check the binstall metadata of a crate providing `bins_count` binaries.
+pub fn check_meta(meta: &PkgMeta, bins_count: usize) -> Vec<ManifestIssue> {
+    check(meta, bins_count).issues
+}
+
+/// Return the fields of the binstall metadata of a crate interpolating
+/// environment variables, e.g. `pkg-url` for `{ env:BUILD_ID }`.
+pub(crate) fn env_key_fields(meta: &PkgMeta) -> Vec<String> {
+    check(meta, 0).env_fields
+}
+
+fn check(meta: &PkgMeta, bins_count: usize) -> Checker {
+    let mut checker = Checker::default();
+
+    checker.check_pkg(meta, None, &meta.mirrors, bins_count);
+
+    if let Some(tag) = &meta.tag {
+        checker.check_template("tag", tag, TAG_KEYS);
+    }
+
+    if let Some(github_actions) = &meta.github_actions {
+        checker.check_template(
+            "github-actions.artifact",
+            &github_actions.artifact,
+            GH_ACTIONS_ARTIFACT_KEYS,
+        );
+    }
+
+    if let Some(releases_index) = &meta.releases_index {
+        checker.check_template_strict("releases-index", releases_index, RELEASES_INDEX_KEYS);
+    }
+
+    for (target, pkg_override) in &meta.overrides {
+        let field = format!("overrides.{target}");
+
+        if TargetTriple::from_str(target).is_err() {
+            checker.push(&field, "is not a known target triple");
+        }
+
+        checker.check_override(&field, meta, pkg_override, bins_count);
+    }
+
+    for (i, variant) in meta.cpu_variants.iter().enumerate() {
+        let field = format!("cpu-variants[{i}]");
+
+        if variant.features.is_empty() {
+            checker.push(&format!("{field}.features"), "is empty");
+        }
+
+        checker.check_override(&field, meta, &variant.overrides, bins_count);
+    }
+
+    for target in meta.target_aliases.keys() {
+        if TargetTriple::from_str(target).is_err() {
+            checker.push(
+                &format!("target-aliases.{target}"),
+                "is not a known target triple",
+            );
+        }
+    }
+
+    for tool in &meta.tools {
+        if CrateName::from_str(tool).is_err() {
+            checker.push(
+                "tools",
+                format!("`{tool}` is not a valid `crate[@version]`"),
+            );
+        }
+    }
+
+    checker
+}
+
+#[derive(Default)]
+struct Checker {
+    issues: Vec<ManifestIssue>,
+    /// Fields using `env:` keys.
+    env_fields: Vec<String>,
+}
+
+impl Checker {
+    fn push(&mut self, field: &str, message: impl Into<String>) {
+        self.issues.push(ManifestIssue {
+            field: field.to_owned(),
+            message: message.into(),
+        });
+    }
+
+    fn check_override(
+        &mut self,
+        field: &str,
+        meta: &PkgMeta,
+        pkg_override: &PkgOverride,
+        bins_count: usize,
+    ) {
+        let PkgOverride {
+            pkg_url,
+            pkg_fmt,
+            mirrors,
+            bin_dir,
+            signing,
+            checksum,
+        } = pkg_override;
+
+        let mirrors = mirrors.as_deref().unwrap_or(&meta.mirrors);
+        let meta = PkgMeta {
+            pkg_url: pkg_url.clone(),
+            pkg_fmt: pkg_fmt.or(meta.pkg_fmt),
+            bin_dir: bin_dir.clone(),
+            signing: signing.clone(),
+            checksum: checksum.clone(),
+            ..Default::default()
+        };
+
+        self.check_pkg(&meta, Some(field), mirrors, bins_count);
+    }
+
+    /// Check the templates of `meta`, `prefix` is set for overrides, which
+    /// only apply to some targets.
+    fn check_pkg(
+        &mut self,
+        meta: &PkgMeta,
+        prefix: Option<&str>,
+        mirrors: &[String],
+        bins_count: usize,
+    ) {
+        let field = |name: &str| match prefix {
+            Some(prefix) => format!("{prefix}.{name}"),
+            None => name.to_owned(),
+        };
+
+        if let Some(pkg_url) = &meta.pkg_url {
+            let field = field("pkg-url");
+
+            if let Some(tt) = self.check_template(&field, pkg_url, PKG_URL_KEYS) {
+                if prefix.is_none() && !tt.has_key("target") && !tt.has_any_of_keys(TARGET_KEYS) {
+                    self.push(
+                        &field,
+                        "does not depend on the target, the same package would be \
+                         downloaded for every target",
+                    );
+                }
+
+                if meta.pkg_fmt.is_none()
+                    && !tt.has_any_of_keys(&["format", "archive-format", "archive-suffix"])
+                    && PkgFmt::guess_pkg_format(pkg_url).is_none()
+                {
+                    self.push(
+                        &field,
+                        "the package format cannot be deduced from it, set `pkg-fmt`",
+                    );
+                }
+
+                if tt.has_key("mirror") && mirrors.is_empty() {
+                    self.push(&field, "uses `{ mirror }` but no `mirrors` are listed");
+                }
+            }
+        }
+
+        if let Some(bin_dir) = &meta.bin_dir {
+            let field = field("bin-dir");
+
+            if let Some(tt) = self.check_template(&field, bin_dir, BIN_DIR_KEYS) {
+                let path = Path::new(bin_dir);
+                if path.is_absolute() || path.components().any(|c| c == Component::ParentDir) {
+                    self.push(&field, "must be a relative path inside the package");
+                }
+
+                if bins_count > 1 && !tt.has_key("bin") {
+                    self.push(
+                        &field,
+                        "does not contain `{ bin }`, every binary would be read from \
+                         the same file",
+                    );
+                }
+            }
+        }
+
+        let signing_file = meta
+            .signing
+            .as_ref()
+            .and_then(|signing| signing.file.as_ref());
+        if let Some(file) = signing_file {
+            self.check_template(&field("signing.file"), file, SIDECAR_KEYS);
+        }
+
+        let checksum_file = meta
+            .checksum
+            .as_ref()
+            .and_then(|checksum| checksum.file.as_ref());
+        if let Some(file) = checksum_file {
+            self.check_template(&field("checksum.file"), file, SIDECAR_KEYS);
+        }
+    }
+
+    fn parse_template<'s>(&mut self, field: &str, template: &'s str) -> Option<Template<'s>> {
+        match Template::parse(template) {
+            Ok(tt) => Some(tt),
+            Err(err) => {
+                self.push(field, format!("cannot be parsed: {err}"));
+                None
+            }
+        }
+    }
+
+    /// Parse `template` and check that it only uses `keys`, for the templates
+    /// rendered without the target keys nor environment variables.
+    fn check_template_strict<'s>(
+        &mut self,
+        field: &str,
+        template: &'s str,
+        keys: &[&str],
+    ) -> Option<Template<'s>> {
+        let tt = self.parse_template(field, template)?;
+
+        let unknown_keys: Vec<&str> = tt
+            .keys()
+            .filter(|key| !keys.contains(key))
+            .copied()
+            .collect();
+        if !unknown_keys.is_empty() {
+            self.push(field, format!("unknown keys {}", list_keys(unknown_keys)));
+        }
+
+        Some(tt)
+    }
+
+    /// Parse `template` and check that it only uses `keys` and the target keys.
+    fn check_template<'s>(
+        &mut self,
+        field: &str,
+        template: &'s str,
+        keys: &[&str],
+    ) -> Option<Template<'s>> {
+        let tt = self.parse_template(field, template)?;
+
+        let (env_keys, unknown_keys): (Vec<&str>, Vec<&str>) = tt
+            .keys()
+            .filter(|key| !keys.contains(key) && !TARGET_KEYS.contains(key))
+            .partition(|key| key.starts_with(ENV_KEY_PREFIX));
+
+        if !unknown_keys.is_empty() {
+            self.push(field, format!("unknown keys {}", list_keys(unknown_keys)));
+        }
+
+        if !env_keys.is_empty() {
+            self.push(
+                field,
+                format!(
+                    "uses environment variables {}, which are only available in \
+                     templates given with `--pkg-url` or `--target-config`",
+                    list_keys(env_keys)
+                ),
+            );
+            self.env_fields.push(field.to_owned());
+        }
+
+        Some(tt)
+    }
+}
+
+fn list_keys(keys: Vec<&str>) -> String {
+    keys.iter()
+        .map(|key| format!("`{key}`"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn fields(issues: Vec<ManifestIssue>) -> Vec<String> {
+        issues.into_iter().map(|issue| issue.field).collect()
+    }
+
+    #[test]
+    fn valid_meta() {
+        let meta = PkgMeta {
+            pkg_url: Some("{ repo }/releases/download/v{ version }/{ name }-{ target }.tgz".into()),
+            bin_dir: Some("{ name }-{ target }/{ bin }{ binary-ext }".into()),
+            ..Default::default()
+        };
+
+        assert_eq!(check_meta(&meta, 2), []);
+    }
+
+    #[test]
+    fn invalid_meta() {
+        let mut meta = PkgMeta {
+            pkg_url: Some("{ repo }/releases/download/{ versio }/{ name }.pkg".into()),
+            bin_dir: Some("../{ name }{ binary-ext }".into()),
+            tools: vec!["cargo-nextest@abc".into()],
+            ..Default::default()
+        };
+        meta.overrides.insert(
+            "not-a-target".into(),
+            PkgOverride {
+                pkg_url: Some("{ repo }/{ mirror }.zip".into()),
+                ..Default::default()
+            },
+        );
+
+        assert_eq!(
+            fields(check_meta(&meta, 2)),
+            [
+                "pkg-url",
+                "pkg-url",
+                "pkg-url",
+                "bin-dir",
+                "bin-dir",
+                "overrides.not-a-target",
+                "overrides.not-a-target.pkg-url",
+                "tools",
+            ]
+        );
+    }
+
+    #[test]
+    fn releases_index_keys() {
+        let meta = PkgMeta {
+            releases_index: Some("https://example.com/{ name }/{ version }.json".into()),
+            ..Default::default()
+        };
+        assert_eq!(check_meta(&meta, 1), []);
+
+        // The index is fetched once for all the targets
+        for releases_index in [
+            "https://example.com/{ name }-{ target }.json",
+            "https://example.com/{ name }-{ target-arch }.json",
+            "https://example.com/{ env:HOME }.json",
+        ] {
+            let meta = PkgMeta {
+                releases_index: Some(releases_index.into()),
+                ..Default::default()
+            };
+            assert_eq!(fields(check_meta(&meta, 1)), ["releases-index"]);
+            assert!(env_key_fields(&meta).is_empty());
+        }
+    }
+
+    #[test]
+    fn env_keys() {
+        let mut meta = PkgMeta {
+            pkg_url: Some("{ repo }/{ env:BUILD_ID }/{ name }-{ target }.tgz".into()),
+            ..Default::default()
+        };
+        meta.overrides.insert(
+            "x86_64-pc-windows-msvc".into(),
+            PkgOverride {
+                bin_dir: Some("{ env:HOME }/{ bin }{ binary-ext }".into()),
+                ..Default::default()
+            },
+        );
+
+        assert_eq!(
+            fields(check_meta(&meta, 1)),
+            ["pkg-url", "overrides.x86_64-pc-windows-msvc.bin-dir"]
+        );
+        assert_eq!(
+            env_key_fields(&meta),
+            ["pkg-url", "overrides.x86_64-pc-windows-msvc.bin-dir"]
+        );
+    }
+}
@@ -0,0 +1,214 @@
+//! Structured summary of an installation, for library consumers that want
+//! to report on it instead of relying on the logs.
+
+use std::{path::PathBuf, time::Duration};
+
+use compact_str::{CompactString, ToCompactString};
+use semver::Version;
+
+use crate::{fetchers::Verifications, ops::resolve::Resolution};
+
+/// Summary of an installation.
+#[derive(Debug, Default)]
+#[non_exhaustive]
+pub struct InstallReport {
+    /// One entry per crate which is up-to-date or has been installed, or
+    /// would be in a dry-run.
+    pub crates: Vec<CrateReport>,
+    /// If `true`, nothing has been installed.
+    pub dry_run: bool,
+    /// Crates resolved, but not installed yet.
+    pending: Vec<CrateReport>,
+}
+
+impl InstallReport {
+    pub fn new(dry_run: bool) -> Self {
+        Self {
+            dry_run,
+            ..Default::default()
+        }
+    }
+
+    /// Record the resolution of crate `name`.
+    ///
+    /// Unless in a dry-run, the crate is only added to [`InstallReport::crates`]
+    /// once [`InstallReport::set_installed`] is called for it.
+    pub fn add_resolution(
+        &mut self,
+        name: CompactString,
+        resolution: &Resolution,
+        phase_times: PhaseTimes,
+    ) {
+        let krate = CrateReport::new(name, resolution, phase_times);
+
+        if self.dry_run || matches!(resolution, Resolution::AlreadyUpToDate) {
+            self.crates.push(krate);
+        } else {
+            self.pending.push(krate);
+        }
+    }
+
+    /// Record that crate `name` has been installed in `install_time`.
+    pub fn set_installed(&mut self, name: &str, install_time: Duration) {
+        if let Some(index) = self.pending.iter().position(|krate| krate.name == name) {
+            let mut krate = self.pending.remove(index);
+            krate.phase_times.install = Some(install_time);
+            self.crates.push(krate);
+        } else if let Some(krate) = self.crates.iter_mut().find(|krate| krate.name == name) {
+            krate.phase_times.install = Some(install_time);
+        }
+    }
+
+    /// Turn the report into the one of a dry-run, for the crates resolved
+    /// but which are not going to be installed.
+    pub fn set_dry_run(&mut self) {
+        self.dry_run = true;
+        self.crates.append(&mut self.pending);
+    }
+}
+
+/// Outcome of the installation of one crate.
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct CrateReport {
+    pub name: CompactString,
+    pub outcome: CrateOutcome,
+    /// Time spent in each phase of the installation.
+    pub phase_times: PhaseTimes,
+}
+
+/// Time spent in each phase of the installation of a crate.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct PhaseTimes {
+    /// Resolving the version to install, including the registry lookup.
+    pub resolution: Duration,
+    /// Waiting for the fetchers to find a package for the targets.
+    pub discovery: Duration,
+    /// Downloading the package, the extraction is included since it runs
+    /// while the package is being downloaded.
+    pub download: Duration,
+    /// Installing the binaries, or building the crate from source.
+    ///
+    /// `None` if the crate is not installed.
+    pub install: Option<Duration>,
+}
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum CrateOutcome {
+    /// The crate is already installed and up-to-date.
+    AlreadyUpToDate,
+
+    /// Pre-built binaries are installed.
+    Fetched {
+        version: Version,
+        target: CompactString,
+        /// Name of the source the binaries are downloaded from.
+        source_name: CompactString,
+        is_third_party: bool,
+        /// Paths of the binaries installed.
+        bins: Vec<PathBuf>,
+        /// Paths of the symlinks created (or updated).
+        links: Vec<PathBuf>,
+        /// What was verified about the package downloaded.
+        verifications: Verifications,
+    },
+
+    /// The crate is installed from source using `cargo-install`.
+    FromSource { version: CompactString },
+}
+
+impl CrateReport {
+    /// Create report of crate `name` from its resolution.
+    fn new(name: CompactString, resolution: &Resolution, phase_times: PhaseTimes) -> Self {
+        let outcome = match resolution {
+            Resolution::AlreadyUpToDate => CrateOutcome::AlreadyUpToDate,
+            Resolution::Fetch(fetch) => CrateOutcome::Fetched {
+                version: fetch.new_version.clone(),
+                target: fetch.fetcher.target().to_compact_string(),
+                source_name: fetch.fetcher.source_name(),
+                is_third_party: fetch.fetcher.is_third_party(),
+                bins: fetch
+                    .bin_files
+                    .iter()
+                    .map(|bin_file| bin_file.dest.clone())
+                    .collect(),
+                links: fetch
+                    .bin_files
+                    .iter()
+                    .filter_map(|bin_file| bin_file.link.clone())
+                    .collect(),
+                verifications: fetch.fetcher.verifications(),
+            },
+            Resolution::InstallFromSource(source) => CrateOutcome::FromSource {
+                version: source.version.clone(),
+            },
+        };
+
+        Self {
+            name,
+            outcome,
+            phase_times,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::ops::resolve::ResolutionSource;
+
+    fn from_source(name: &str) -> Resolution {
+        Resolution::InstallFromSource(ResolutionSource {
+            name: name.into(),
+            version: "1.0.0".into(),
+            git_tag: None,
+        })
+    }
+
+    fn names(report: &InstallReport) -> Vec<&str> {
+        report
+            .crates
+            .iter()
+            .map(|krate| krate.name.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_crates_recorded_once_installed() {
+        let mut report = InstallReport::new(false);
+        let install_time = Duration::from_secs(2);
+
+        report.add_resolution("a".into(), &from_source("a"), PhaseTimes::default());
+        report.add_resolution(
+            "b".into(),
+            &Resolution::AlreadyUpToDate,
+            PhaseTimes::default(),
+        );
+        report.add_resolution("c".into(), &from_source("c"), PhaseTimes::default());
+        assert_eq!(names(&report), ["b"]);
+
+        // `a` is never installed, e.g. because its build failed.
+        report.set_installed("c", install_time);
+        assert_eq!(names(&report), ["b", "c"]);
+        assert_eq!(report.crates[1].phase_times.install, Some(install_time));
+        assert!(matches!(
+            report.crates[1].outcome,
+            CrateOutcome::FromSource { .. }
+        ));
+    }
+
+    #[test]
+    fn test_dry_run() {
+        let mut report = InstallReport::new(false);
+        report.add_resolution("a".into(), &from_source("a"), PhaseTimes::default());
+        report.set_dry_run();
+        assert!(report.dry_run);
+        assert_eq!(names(&report), ["a"]);
+
+        let mut report = InstallReport::new(true);
+        report.add_resolution("a".into(), &from_source("a"), PhaseTimes::default());
+        assert_eq!(names(&report), ["a"]);
+    }
+}
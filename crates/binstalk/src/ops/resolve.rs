@@ -1,17 +1,18 @@
 use std::{
     borrow::Cow,
-    collections::{BTreeMap, BTreeSet},
-    iter, mem,
-    path::Path,
+    collections::{BTreeMap, BTreeSet, HashSet},
+    fmt, fs, io, iter, mem,
+    path::{Path, PathBuf},
     str::FromStr,
-    sync::Arc,
+    sync::{Arc, Mutex, PoisonError},
+    time::{Duration, Instant},
 };
 
 use compact_str::{CompactString, ToCompactString};
 use itertools::Itertools;
 use leon::Template;
 use maybe_owned::MaybeOwned;
-use semver::{Version, VersionReq};
+use semver::{Comparator, Op, Version, VersionReq};
 use tempfile::TempDir;
 use tokio::task::spawn_blocking;
 use tracing::{debug, error, info, instrument, warn};
@@ -19,14 +20,25 @@ use tracing::{debug, error, info, instrument, warn};
 use crate::{
     bins,
     errors::{BinstallError, VersionParseError},
-    fetchers::{Data, Fetcher, TargetData},
+    fetchers::{Data, FetchError, Fetcher, TargetData},
     helpers::{
-        self, cargo_toml::Manifest, cargo_toml_workspace::load_manifest_from_workspace,
-        download::ExtractedFiles, remote::Client, target_triple::TargetTriple,
+        self,
+        cargo_toml::{AbstractFilesystem, Manifest, Value},
+        cargo_toml_workspace::{
+            load_manifest_and_path_from_workspace, load_manifests_from_workspace,
+        },
+        cpu_features,
+        download::{Download, DownloadError, ExtractedFiles},
+        remote::{Client, Url},
+        target_triple::TargetTriple,
         tasks::AutoAbortJoinHandle,
     },
-    manifests::cargo_toml_binstall::{Meta, PkgMeta, PkgOverride},
-    ops::{CargoTomlFetchOverride, Options},
+    manifests::cargo_toml_binstall::{CpuVariant, Meta, PkgFmt, PkgMeta, PkgOverride},
+    ops::{
+        check_manifest, report::PhaseTimes, CargoTomlFetchOverride, FetcherSelector,
+        LatestVersionSelector, Options, RepoRewrite,
+    },
+    registry::CrateVersion,
 };
 
 mod crate_name;
@@ -37,6 +49,13 @@ mod version_ext;
 #[doc(inline)]
 pub use version_ext::VersionReqExt;
 
+mod version_check;
+
+mod sidecar;
+
+#[cfg(feature = "git")]
+mod git_release;
+
 mod resolution;
 #[doc(inline)]
 pub use resolution::{Resolution, ResolutionFetch, ResolutionSource};
@@ -47,139 +66,151 @@ pub async fn resolve(
     crate_name: CrateName,
     curr_version: Option<Version>,
 ) -> Result<Resolution, BinstallError> {
+    resolve_timed(opts, crate_name, curr_version)
+        .await
+        .map(|(resolution, _)| resolution)
+}
+
+/// Same as [`resolve`], also returning the time spent in each phase of the
+/// resolution.
+#[instrument(skip_all)]
+pub async fn resolve_timed(
+    opts: Arc<Options>,
+    crate_name: CrateName,
+    curr_version: Option<Version>,
+) -> Result<(Resolution, PhaseTimes), BinstallError> {
     let crate_name_name = crate_name.name.clone();
-    let resolution = resolve_inner(opts, crate_name, curr_version)
+    let mut times = PhaseTimes::default();
+    let resolution = resolve_inner(opts, crate_name, curr_version, &mut times)
         .await
         .map_err(|err| err.crate_context(crate_name_name))?;
 
-    Ok(resolution)
+    Ok((resolution, times))
 }
 
 async fn resolve_inner(
     opts: Arc<Options>,
     crate_name: CrateName,
     curr_version: Option<Version>,
+    times: &mut PhaseTimes,
 ) -> Result<Resolution, BinstallError> {
     info!("Resolving package: '{}'", crate_name);
 
-    let version_req = match (&crate_name.version_req, &opts.version_req) {
-        (Some(version), None) => MaybeOwned::Borrowed(version),
-        (None, Some(version)) => MaybeOwned::Borrowed(version),
-        (Some(_), Some(_)) => Err(BinstallError::SuperfluousVersionOption)?,
-        (None, None) => MaybeOwned::Owned(VersionReq::STAR),
-    };
+    let start = Instant::now();
+
+    let version_req = resolve_version_req(&opts, crate_name.version_req.as_ref())?;
 
     let version_req_str = version_req.to_compact_string();
 
+    let newest_installed = curr_version.clone();
+
     let Some(package_info) = PackageInfo::resolve(
         &opts,
         crate_name.name,
         curr_version,
         &version_req,
+        allow_pre_releases(&opts, crate_name.stable_only),
         opts.client.clone(),
+        true,
     )
     .await?
     else {
+        times.resolution = start.elapsed();
         return Ok(Resolution::AlreadyUpToDate);
     };
 
-    let desired_targets = opts
-        .desired_targets
-        .get()
-        .await
-        .iter()
-        .map(|target| TargetTriple::from_str(target).map(|triple| (triple, target)))
-        .collect::<Result<Vec<_>, _>>()?;
-    let resolvers = &opts.resolvers;
+    times.resolution = start.elapsed();
 
-    let mut handles: Vec<(Arc<dyn Fetcher>, _)> =
-        Vec::with_capacity(desired_targets.len() * resolvers.len());
+    // Shared with the candidates to select from
+    let package_info = Arc::new(package_info);
 
-    let data = Arc::new(Data::new(
-        package_info.name.clone(),
-        package_info.version_str.clone(),
-        package_info.repo.clone(),
-    ));
+    resolve_package(opts, package_info, version_req_str, newest_installed, times).await
+}
 
-    handles.extend(
-        desired_targets
-            .into_iter()
-            .map(|(triple, target)| {
-                debug!("Building metadata for target: {target}");
+/// Resolve how to install the package described by `package_info`, from
+/// its pre-built binaries or from source.
+async fn resolve_package(
+    opts: Arc<Options>,
+    package_info: Arc<PackageInfo>,
+    version_req_str: CompactString,
+    newest_installed: Option<Version>,
+    times: &mut PhaseTimes,
+) -> Result<Resolution, BinstallError> {
+    package_info.check_cargo_subcommand_binaries();
 
-                let target_meta = package_info.meta.merge_overrides(
-                    iter::once(&opts.cli_overrides).chain(package_info.overrides.get(target)),
-                );
+    if package_info.meta.disabled && !opts.force_prebuilt {
+        info!(
+            "The author of {} disabled pre-built binaries, skipping them \
+            (use --force-prebuilt to try them anyway)",
+            package_info.name
+        );
 
-                debug!("Found metadata: {target_meta:?}");
+        return if opts.cargo_install_fallback {
+            Ok(Resolution::InstallFromSource(ResolutionSource {
+                name: package_info.name.clone(),
+                version: package_info.version_str.clone(),
+                git_tag: package_info.git_tag.clone(),
+            }))
+        } else {
+            Err(BinstallError::PrebuiltDisabled(package_info.name.clone()))
+        };
+    }
 
-                Arc::new(TargetData {
-                    target: target.clone(),
-                    meta: target_meta,
-                    target_related_info: triple,
-                })
-            })
-            .cartesian_product(resolvers)
-            .map(|(target_data, f)| {
-                let fetcher = f(
-                    opts.client.clone(),
-                    opts.gh_api_client.clone(),
-                    data.clone(),
-                    target_data,
-                    opts.signature_policy,
-                );
-                (fetcher.clone(), AutoAbortJoinHandle::new(fetcher.find()))
-            }),
-    );
+    // With versioned binaries, the symlinks are only updated when
+    // installing the newest version.
+    let update_links = !opts.versioned_bin
+        || newest_installed.map_or(true, |newest| newest < package_info.version);
+
+    let start = Instant::now();
+    let handles = launch_fetchers(&opts, &package_info).await?;
+    times.discovery = start.elapsed();
 
+    let mut probed_targets = Vec::new();
+    let mut probed_fetchers = BTreeSet::new();
+    let mut any_available = false;
+    // Fetchers which found a package, only collected to select one of them.
+    let mut candidates = Vec::new();
+
+    // Every resolver of every target is tried, in the order of preference,
+    // before falling back to building from source: a target having no
+    // usable package, e.g. the native one on a host which can also run
+    // binaries of another, does not stop the others from being tried.
     for (fetcher, handle) in handles {
         fetcher.clone().report_to_upstream();
-        match handle.flattened_join().await {
+
+        if !probed_targets
+            .iter()
+            .any(|target| *target == fetcher.target())
+        {
+            probed_targets.push(fetcher.target().to_compact_string());
+        }
+        probed_fetchers.insert(fetcher.fetcher_name());
+
+        let start = Instant::now();
+        let found = handle.flattened_join().await;
+        times.discovery += start.elapsed();
+
+        match found {
             Ok(true) => {
-                // Generate temporary binary path
-                let bin_path = opts.temp_dir.join(format!(
-                    "bin-{}-{}-{}",
-                    package_info.name,
-                    fetcher.target(),
-                    fetcher.fetcher_name()
-                ));
+                any_available = true;
 
-                match download_extract_and_verify(
-                    fetcher.as_ref(),
-                    &bin_path,
-                    &package_info,
-                    &opts.install_path,
-                    opts.no_symlinks,
-                )
-                .await
-                {
-                    Ok(bin_files) => {
-                        if !bin_files.is_empty() {
-                            return Ok(Resolution::Fetch(Box::new(ResolutionFetch {
-                                fetcher,
-                                new_version: package_info.version,
-                                name: package_info.name,
-                                version_req: version_req_str,
-                                bin_files,
-                            })));
-                        } else {
-                            warn!(
-                                "Error when checking binaries provided by fetcher {}: \
-                                The fetcher does not provide any optional binary",
-                                fetcher.source_name(),
-                            );
-                        }
-                    }
-                    Err(err) => {
-                        if let BinstallError::UserAbort = err {
-                            return Err(err);
-                        }
-                        warn!(
-                            "Error while downloading and extracting from fetcher {}: {}",
-                            fetcher.source_name(),
-                            err
-                        );
-                    }
+                if opts.select_fetcher.is_some() {
+                    candidates.push(Candidate::new(&package_info, &version_req_str, fetcher));
+                    continue;
+                }
+
+                let start = Instant::now();
+                let downloaded =
+                    try_fetcher(fetcher.as_ref(), &package_info, &opts, update_links).await;
+                times.download += start.elapsed();
+
+                if let Some(files) = downloaded? {
+                    return Ok(resolution_fetch(
+                        fetcher,
+                        &package_info,
+                        version_req_str,
+                        files,
+                    ));
                 }
             }
             Ok(false) => (),
@@ -193,201 +224,1306 @@ async fn resolve_inner(
         }
     }
 
+    if let Some(select_fetcher) = &opts.select_fetcher {
+        for Candidate { fetcher, .. } in
+            select_candidate(select_fetcher, &package_info.name, candidates).await?
+        {
+            let start = Instant::now();
+            let downloaded =
+                try_fetcher(fetcher.as_ref(), &package_info, &opts, update_links).await;
+            times.download += start.elapsed();
+
+            if let Some(files) = downloaded? {
+                return Ok(resolution_fetch(
+                    fetcher,
+                    &package_info,
+                    version_req_str,
+                    files,
+                ));
+            }
+        }
+    }
+
+    // Explain why the installation falls back or fails, if no fetcher has
+    // anything for the targets probed.
+    let no_prebuilt = !any_available && !probed_fetchers.is_empty();
+    let probed_targets = probed_targets.iter().join(", ");
+    if no_prebuilt {
+        warn!(
+            "No pre-built binary of {} v{} was found for targets {probed_targets} by {}",
+            package_info.name,
+            package_info.version,
+            probed_fetchers.into_iter().join(", "),
+        );
+    }
+
     if opts.cargo_install_fallback {
+        if no_prebuilt {
+            warn!("Falling back to building {} from source", package_info.name);
+        }
         Ok(Resolution::InstallFromSource(ResolutionSource {
-            name: package_info.name,
-            version: package_info.version_str,
+            name: package_info.name.clone(),
+            version: package_info.version_str.clone(),
+            git_tag: package_info.git_tag.clone(),
         }))
+    } else if opts.frozen {
+        Err(BinstallError::Frozen(
+            "downloading pre-built binaries requires network access",
+        ))
+    } else if no_prebuilt {
+        Err(BinstallError::NoPrebuiltBinaries {
+            targets: probed_targets.into(),
+        })
     } else {
         Err(BinstallError::NoFallbackToCargoInstall)
     }
 }
 
-///  * `fetcher` - `fetcher.find()` must have returned `Ok(true)`.
-///
-/// Can return empty Vec if all `BinFile` is optional and does not exist
-/// in the archive downloaded.
-async fn download_extract_and_verify(
-    fetcher: &dyn Fetcher,
-    bin_path: &Path,
+fn resolution_fetch(
+    fetcher: Arc<dyn Fetcher>,
     package_info: &PackageInfo,
-    install_path: &Path,
-    no_symlinks: bool,
-) -> Result<Vec<bins::BinFile>, BinstallError> {
-    // Download and extract it.
-    // If that fails, then ignore this fetcher.
-    let extracted_files = fetcher.fetch_and_extract(bin_path).await?;
-    debug!("extracted_files = {extracted_files:#?}");
-
-    // Build final metadata
-    let meta = fetcher.target_meta();
-
-    // Verify that all non-optional bin_files exist
-    let bin_files = collect_bin_files(
+    version_req: CompactString,
+    (bin_files, extra_files): (Vec<bins::BinFile>, Vec<bins::ExtraFile>),
+) -> Resolution {
+    Resolution::Fetch(Box::new(ResolutionFetch {
         fetcher,
-        package_info,
-        meta,
-        bin_path,
-        install_path,
-        no_symlinks,
-        &extracted_files,
-    )?;
+        new_version: package_info.version.clone(),
+        name: package_info.name.clone(),
+        version_req,
+        bin_files,
+        extra_files,
+    }))
+}
 
-    let name = &package_info.name;
+/// Download and extract the package found by `fetcher`, returning `None`
+/// if it cannot be used and the next fetcher should be tried instead.
+async fn try_fetcher(
+    fetcher: &dyn Fetcher,
+    package_info: &PackageInfo,
+    opts: &Options,
+    update_links: bool,
+) -> Result<Option<(Vec<bins::BinFile>, Vec<bins::ExtraFile>)>, BinstallError> {
+    if opts.strict_origin {
+        check_origin(fetcher, package_info, opts)?;
+    }
 
-    package_info
-        .binaries
-        .iter()
-        .zip(bin_files)
-        .filter_map(|(bin, bin_file)| {
-            match bin_file.check_source_exists(&mut |p| extracted_files.has_file(p)) {
-                Ok(()) => Some(Ok(bin_file)),
+    // Generate temporary binary path
+    let bin_path = opts.temp_dir.join(format!(
+        "bin-{}-{}-{}",
+        package_info.name,
+        fetcher.target(),
+        fetcher.fetcher_name()
+    ));
 
-                // This binary is optional
-                Err(err) => {
-                    let required_features = &bin.required_features;
-                    let bin_name = bin.name.as_str();
+    match download_extract_and_verify(fetcher, &bin_path, package_info, opts, update_links).await {
+        Ok((bin_files, _)) if bin_files.is_empty() => {
+            warn!(
+                "Error when checking binaries provided by fetcher {}: \
+                The fetcher does not provide any optional binary",
+                fetcher.source_name(),
+            );
+            Ok(None)
+        }
+        Ok(files) => Ok(Some(files)),
+        Err(
+            err @ (BinstallError::UserAbort
+            | BinstallError::OriginMismatch { .. }
+            | BinstallError::DownloadTooLarge { .. }
+            | BinstallError::SubProcess { .. }),
+        ) => Err(err),
+        Err(err) => {
+            let temp_dir = opts.temp_dir.clone();
+            let (err, reason) = spawn_blocking(move || {
+                let reason = err.temp_dir_failure(&temp_dir);
+                (err, reason)
+            })
+            .await?;
 
-                    if required_features.is_empty() {
-                        error!(
-                            "When resolving {name} bin {bin_name} is not found.\
-This binary is not optional so it must be included in the archive, please contact with\
-upstream to fix this issue."
-                        );
-                        // This bin is not optional, error
-                        Some(Err(err))
-                    } else {
-                        // Optional, print a warning and continue.
-                        let features = required_features.iter().format(",");
-                        warn!(
-                            "When resolving {name} bin {bin_name} is not found. \
-But since it requies features {features}, this bin is ignored."
-                        );
-                        None
-                    }
-                }
+            // Other fetchers would fail in the same way.
+            if let Some(reason) = reason {
+                error!("Failed to download to the temporary directory: {err}");
+                return Err(BinstallError::UnusableTempDir {
+                    path: opts.temp_dir.clone(),
+                    reason,
+                });
             }
-        })
-        .collect::<Result<Vec<bins::BinFile>, bins::Error>>()
-        .map_err(BinstallError::from)
+            warn!(
+                "Error while downloading and extracting from fetcher {}: {}",
+                fetcher.source_name(),
+                err
+            );
+            Ok(None)
+        }
+    }
 }
 
-fn collect_bin_files(
+/// Check that `fetcher` downloads from the host of the `repository` of the
+/// crate, or one of its subdomains, failing closed if either is unknown.
+///
+/// Third-party sources are not checked, they are restricted by
+/// [`Options::trusted_hosts`] instead.
+fn check_origin(
     fetcher: &dyn Fetcher,
     package_info: &PackageInfo,
-    meta: PkgMeta,
-    bin_path: &Path,
-    install_path: &Path,
-    no_symlinks: bool,
-    extracted_files: &ExtractedFiles,
-) -> Result<Vec<bins::BinFile>, BinstallError> {
-    // List files to be installed
-    // based on those found via Cargo.toml
-    let bin_data = bins::Data {
-        name: &package_info.name,
-        target: fetcher.target(),
-        version: &package_info.version_str,
-        repo: package_info.repo.as_deref(),
-        meta,
-        bin_path,
-        install_path,
-        target_related_info: &fetcher.target_data().target_related_info,
-    };
+    opts: &Options,
+) -> Result<(), BinstallError> {
+    if fetcher.is_third_party() {
+        return Ok(());
+    }
+
+    let repo = package_info
+        .repo
+        .clone()
+        .map(|repo| RepoRewrite::apply_all(&opts.repo_rewrites, repo));
+
+    check_url_origin(&package_info.name, fetcher.package_url(), repo)
+}
 
-    let bin_dir = bin_data
-        .meta
-        .bin_dir
+/// Check that `url` is on the host of `repo` or one of its subdomains,
+/// failing if either of them is unknown.
+fn check_url_origin(
+    name: &CompactString,
+    url: Option<Url>,
+    repo: Option<String>,
+) -> Result<(), BinstallError> {
+    let repo_host = repo
         .as_deref()
-        .map(Cow::Borrowed)
-        .unwrap_or_else(|| {
-            bins::infer_bin_dir_template(&bin_data, &mut |p| extracted_files.get_dir(p).is_some())
-        });
+        .and_then(|repo| Url::parse(repo).ok())
+        .and_then(|repo| repo.host_str().map(str::to_ascii_lowercase));
+    let host = url
+        .as_ref()
+        .and_then(Url::host_str)
+        .map(str::to_ascii_lowercase);
 
-    let template = Template::parse(&bin_dir)?;
+    match (host, repo_host) {
+        (Some(host), Some(repo_host))
+            if host == repo_host
+                || host
+                    .strip_suffix(&*repo_host)
+                    .map_or(false, |subdomain| subdomain.ends_with('.')) =>
+        {
+            Ok(())
+        }
+        _ => Err(BinstallError::OriginMismatch {
+            name: name.clone(),
+            url: url.map(|url| url.as_str().into()),
+            repo: repo.map(Into::into),
+        }),
+    }
+}
 
-    // Create bin_files
-    let bin_files = package_info
-        .binaries
-        .iter()
-        .map(|bin| bins::BinFile::new(&bin_data, bin.name.as_str(), &template, no_symlinks))
-        .collect::<Result<Vec<_>, bins::Error>>()?;
+/// Let `select_fetcher` pick one of the fetchers which found a package,
+/// it is only asked if there is more than one.
+///
+/// Return the candidates in the order they should be tried: the selected
+/// one first, then the others in the order of preference.
+async fn select_candidate(
+    select_fetcher: &FetcherSelector,
+    name: &CompactString,
+    candidates: Vec<Candidate>,
+) -> Result<Vec<Candidate>, BinstallError> {
+    if candidates.len() <= 1 {
+        return Ok(candidates);
+    }
 
-    let mut source_set = BTreeSet::new();
+    let select_fetcher = select_fetcher.clone();
+    let name = name.clone();
+    let (index, mut candidates) = spawn_blocking(move || {
+        let index = select_fetcher.select(&name, &candidates);
+        (index, candidates)
+    })
+    .await?;
+    let index = index?;
 
-    for bin in &bin_files {
-        if !source_set.insert(&bin.source) {
-            return Err(BinstallError::DuplicateSourceFilePath {
-                path: bin.source.clone(),
-            });
-        }
+    if index >= candidates.len() {
+        return Err(BinstallError::InvalidFetcherSelection {
+            index,
+            count: candidates.len(),
+        });
     }
+    candidates[..=index].rotate_right(1);
 
-    Ok(bin_files)
+    Ok(candidates)
 }
 
-struct PackageInfo {
-    meta: PkgMeta,
-    binaries: Vec<Bin>,
-    name: CompactString,
-    version_str: CompactString,
-    version: Version,
-    repo: Option<String>,
-    overrides: BTreeMap<String, PkgOverride>,
+/// Pre-built packages available for a target, as found by [`list_targets`].
+#[derive(Debug)]
+pub struct TargetAvailability {
+    pub target: String,
+    /// Name of the sources providing a pre-built package for the target,
+    /// empty if there is none.
+    pub sources: Vec<CompactString>,
 }
 
-struct Bin {
-    name: String,
-    required_features: Vec<String>,
+/// Check which of the desired targets have pre-built packages of
+/// `crate_name` available, without downloading or installing anything.
+pub async fn list_targets(
+    opts: Arc<Options>,
+    crate_name: CrateName,
+) -> Result<Vec<TargetAvailability>, BinstallError> {
+    let crate_name_name = crate_name.name.clone();
+
+    list_targets_inner(&opts, crate_name)
+        .await
+        .map_err(|err| err.crate_context(crate_name_name))
 }
 
-impl PackageInfo {
-    /// Return `None` if already up-to-date.
-    async fn resolve(
-        opts: &Options,
-        name: CompactString,
-        curr_version: Option<Version>,
-        version_req: &VersionReq,
-        client: Client,
-    ) -> Result<Option<Self>, BinstallError> {
-        use CargoTomlFetchOverride::*;
+async fn list_targets_inner(
+    opts: &Options,
+    crate_name: CrateName,
+) -> Result<Vec<TargetAvailability>, BinstallError> {
+    let version_req = resolve_version_req(opts, crate_name.version_req.as_ref())?;
 
-        // Fetch crate via crates.io, git, or use a local manifest path
-        let manifest = match opts.cargo_toml_fetch_override.as_ref() {
-            Some(Path(manifest_path)) => {
-                let manifest_path = manifest_path.clone();
-                let name = name.clone();
+    let package_info = PackageInfo::resolve(
+        opts,
+        crate_name.name,
+        None,
+        &version_req,
+        allow_pre_releases(opts, crate_name.stable_only),
+        opts.client.clone(),
+        true,
+    )
+    .await?
+    .expect("PackageInfo::resolve only returns None if curr_version is Some");
 
-                spawn_blocking(move || load_manifest_path(manifest_path, &name)).await??
-            }
-            #[cfg(feature = "git")]
-            Some(Git(git_url)) => {
-                use helpers::git::{GitCancellationToken, Repository as GitRepository};
+    let mut availabilities: Vec<TargetAvailability> = opts
+        .desired_targets
+        .get()
+        .await
+        .iter()
+        .map(|target| TargetAvailability {
+            target: target.clone(),
+            sources: Vec::new(),
+        })
+        .collect();
 
-                let git_url = git_url.clone();
-                let name = name.clone();
-                let cancellation_token = GitCancellationToken::default();
-                // Cancel git operation if the future is cancelled (dropped).
-                let cancel_on_drop = cancellation_token.clone().cancel_on_drop();
+    for (fetcher, handle) in launch_fetchers(opts, &package_info).await? {
+        match handle.flattened_join().await {
+            Ok(true) => {
+                if let Some(availability) = availabilities
+                    .iter_mut()
+                    .find(|availability| availability.target == fetcher.target())
+                {
+                    availability.sources.push(fetcher.source_name());
+                }
+            }
+            Ok(false) => (),
+            Err(err) => warn!(
+                "Error while checking fetcher {} for target {}: {err}",
+                fetcher.source_name(),
+                fetcher.target(),
+            ),
+        }
+    }
 
-                let ret = spawn_blocking(move || {
-                    let dir = TempDir::new()?;
-                    GitRepository::shallow_clone(git_url, dir.as_ref(), Some(cancellation_token))?;
+    Ok(availabilities)
+}
 
-                    load_manifest_from_workspace(dir.as_ref(), &name).map_err(BinstallError::from)
-                })
-                .await??;
+/// Maximum number of versions [`list_versions`] checks for pre-built
+/// packages, starting from the newest one.
+pub const MAX_CHECKED_VERSIONS: usize = 10;
 
-                // Git operation done, disarm it
-                cancel_on_drop.disarm();
+/// A version of a crate published on the registry, as found by [`list_versions`].
+#[derive(Debug)]
+pub struct VersionAvailability {
+    pub version: Version,
+    pub yanked: bool,
+    /// Whether a pre-built package is available for one of the desired
+    /// targets, `None` if it was not checked: yanked versions and versions
+    /// older than the newest [`MAX_CHECKED_VERSIONS`] ones are skipped.
+    pub has_prebuilt: Option<bool>,
+    /// The error checking for a pre-built package failed with, in which
+    /// case `has_prebuilt` is `None`.
+    pub error: Option<BinstallError>,
+}
 
-                ret
+/// List the versions of `crate_name` published on the registry, matching
+/// its version requirement if any, from the lowest to the highest, and
+/// check which of the newest [`MAX_CHECKED_VERSIONS`] have pre-built
+/// packages for the desired targets without downloading or installing
+/// anything.
+pub async fn list_versions(
+    opts: Arc<Options>,
+    crate_name: CrateName,
+) -> Result<Vec<VersionAvailability>, BinstallError> {
+    let crate_name_name = crate_name.name.clone();
+
+    list_versions_inner(opts, crate_name)
+        .await
+        .map_err(|err| err.crate_context(crate_name_name))
+}
+
+async fn list_versions_inner(
+    opts: Arc<Options>,
+    crate_name: CrateName,
+) -> Result<Vec<VersionAvailability>, BinstallError> {
+    let version_req = resolve_version_req(&opts, crate_name.version_req.as_ref())?;
+
+    let versions = Box::pin(
+        opts.registry
+            .fetch_versions(opts.client.clone(), &crate_name.name),
+    )
+    .await?;
+
+    let versions: Vec<_> = versions
+        .into_iter()
+        .filter(|crate_version| {
+            *version_req == VersionReq::STAR || version_req.matches(&crate_version.version)
+        })
+        .collect();
+
+    // Only the yanked status is known from the registry, the other checks
+    // need the manifest of each version, so only probe the newest ones and
+    // do it concurrently.
+    let checks: Vec<_> = versions
+        .iter()
+        .rev()
+        .filter(|crate_version| !crate_version.yanked)
+        .take(MAX_CHECKED_VERSIONS)
+        .map(|crate_version| {
+            let version = crate_version.version.clone();
+            let handle = AutoAbortJoinHandle::spawn(has_prebuilt(
+                opts.clone(),
+                crate_name.name.clone(),
+                version.clone(),
+            ));
+
+            (version, handle)
+        })
+        .collect();
+
+    // A version failing to be checked, e.g. because its manifest is
+    // invalid, is reported along with it rather than failing the listing.
+    let mut checked = BTreeMap::new();
+    for (version, handle) in checks {
+        checked.insert(version, handle.flattened_join().await);
+    }
+
+    Ok(versions
+        .into_iter()
+        .map(|crate_version| {
+            let (has_prebuilt, error) = match checked.remove(&crate_version.version) {
+                Some(Ok(has_prebuilt)) => (Some(has_prebuilt), None),
+                Some(Err(err)) => (None, Some(err)),
+                None => (None, None),
+            };
+
+            VersionAvailability {
+                version: crate_version.version,
+                yanked: crate_version.yanked,
+                has_prebuilt,
+                error,
             }
-            None => {
-                Box::pin(
-                    opts.registry
-                        .fetch_crate_matched(client, &name, version_req),
+        })
+        .collect())
+}
+
+/// Return `true` if any fetcher finds a pre-built package of `version` of
+/// crate `name` for the desired targets.
+async fn has_prebuilt(
+    opts: Arc<Options>,
+    name: CompactString,
+    version: Version,
+) -> Result<bool, BinstallError> {
+    let version_req = exact_version_req(&version);
+
+    // The exact version is picked by the caller, not requested by the user,
+    // so do not tell them about newer versions.
+    let package_info = PackageInfo::resolve(
+        &opts,
+        name,
+        None,
+        &version_req,
+        opts.allow_pre_releases,
+        opts.client.clone(),
+        false,
+    )
+    .await?
+    .expect("PackageInfo::resolve only returns None if curr_version is Some");
+
+    for (fetcher, handle) in launch_fetchers(&opts, &package_info).await? {
+        match handle.flattened_join().await {
+            Ok(true) => return Ok(true),
+            Ok(false) => (),
+            Err(err) => warn!(
+                "Error while checking fetcher {} for target {}: {err}",
+                fetcher.source_name(),
+                fetcher.target(),
+            ),
+        }
+    }
+
+    Ok(false)
+}
+
+/// A source providing a pre-built package, as found by [`resolve_candidates`]
+/// or given to the [`FetcherSelector`].
+#[derive(Clone)]
+pub struct Candidate {
+    /// Version of the crate resolved, the same for all candidates.
+    pub version: Version,
+    pub target: String,
+    pub source_name: CompactString,
+    pub fetcher_name: &'static str,
+    pub is_third_party: bool,
+    /// Url the package is downloaded from, if it is downloaded from one.
+    pub url: Option<url::Url>,
+    /// The fetcher which found the package, to download and extract it
+    /// once picked.
+    pub fetcher: Arc<dyn Fetcher>,
+    package_info: Arc<PackageInfo>,
+    version_req: CompactString,
+}
+
+impl Candidate {
+    fn new(
+        package_info: &Arc<PackageInfo>,
+        version_req: &CompactString,
+        fetcher: Arc<dyn Fetcher>,
+    ) -> Self {
+        Self {
+            version: package_info.version.clone(),
+            target: fetcher.target().to_string(),
+            source_name: fetcher.source_name(),
+            fetcher_name: fetcher.fetcher_name(),
+            is_third_party: fetcher.is_third_party(),
+            url: fetcher.package_url(),
+            fetcher,
+            package_info: package_info.clone(),
+            version_req: version_req.clone(),
+        }
+    }
+}
+
+impl fmt::Debug for Candidate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Candidate")
+            .field("version", &self.version)
+            .field("target", &self.target)
+            .field("source_name", &self.source_name)
+            .field("fetcher_name", &self.fetcher_name)
+            .field("is_third_party", &self.is_third_party)
+            .field("url", &self.url)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Find every source providing a pre-built package of `crate_name` for the
+/// desired targets, instead of only the first one like [`resolve`] does, so
+/// that the user can pick one.
+///
+/// The candidates are ordered by target, then by the order of the resolvers.
+/// Nothing is downloaded or installed.
+pub async fn resolve_candidates(
+    opts: Arc<Options>,
+    crate_name: CrateName,
+) -> Result<Vec<Candidate>, BinstallError> {
+    let crate_name_name = crate_name.name.clone();
+
+    resolve_candidates_inner(&opts, crate_name)
+        .await
+        .map_err(|err| err.crate_context(crate_name_name))
+}
+
+async fn resolve_candidates_inner(
+    opts: &Options,
+    crate_name: CrateName,
+) -> Result<Vec<Candidate>, BinstallError> {
+    let version_req = resolve_version_req(opts, crate_name.version_req.as_ref())?;
+    let version_req_str = version_req.to_compact_string();
+
+    let package_info = PackageInfo::resolve(
+        opts,
+        crate_name.name,
+        None,
+        &version_req,
+        allow_pre_releases(opts, crate_name.stable_only),
+        opts.client.clone(),
+        true,
+    )
+    .await?
+    .expect("PackageInfo::resolve only returns None if curr_version is Some");
+    let package_info = Arc::new(package_info);
+
+    let mut candidates = Vec::new();
+
+    for (fetcher, handle) in launch_fetchers(opts, &package_info).await? {
+        match handle.flattened_join().await {
+            Ok(true) => candidates.push(Candidate::new(&package_info, &version_req_str, fetcher)),
+            Ok(false) => (),
+            Err(err) => warn!(
+                "Error while checking fetcher {} for target {}: {err}",
+                fetcher.source_name(),
+                fetcher.target(),
+            ),
+        }
+    }
+
+    Ok(candidates)
+}
+
+/// Download and extract the package of `candidate`, picked among the ones
+/// returned by [`resolve_candidates`], returning the [`Resolution`] to
+/// install like [`resolve`] does.
+pub async fn resolve_candidate(
+    opts: Arc<Options>,
+    candidate: Candidate,
+) -> Result<Resolution, BinstallError> {
+    let Candidate {
+        fetcher,
+        package_info,
+        version_req,
+        ..
+    } = candidate;
+
+    let res = match try_fetcher(fetcher.as_ref(), &package_info, &opts, true).await {
+        Ok(Some(files)) => Ok(resolution_fetch(fetcher, &package_info, version_req, files)),
+        Ok(None) => Err(BinstallError::NoPrebuiltBinaries {
+            targets: fetcher.target().into(),
+        }),
+        Err(err) => Err(err),
+    };
+
+    res.map_err(|err| err.crate_context(package_info.name.clone()))
+}
+
+/// What [`report_unsupported`] found about the pre-built binaries of a crate.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Finding {
+    /// The crate has no `[package.metadata.binstall]`, so only the default
+    /// templates are tried.
+    NoBinstallMetadata,
+    /// The crate has neither a `repository` nor a `pkg-url`, including in
+    /// the overrides, so there is nowhere to look for its releases.
+    NoRepository,
+    /// A package for `target` is available from `source` and provides all
+    /// the binaries.
+    Supported {
+        target: String,
+        source: CompactString,
+    },
+    /// A package for `target` was found in `source`, but cannot be used.
+    Unusable {
+        target: String,
+        source: CompactString,
+        reason: String,
+    },
+    /// No source has a package for `target`, e.g. because no release asset
+    /// matches the templates.
+    NoPackage { target: String },
+    /// The crate could not be checked at all, e.g. because it does not exist.
+    Failed { reason: String },
+}
+
+/// Check what `crate_name` is missing for pre-built binaries to be installed
+/// for each of the desired targets, without installing anything.
+///
+/// Unlike [`list_targets`], the packages found are downloaded and extracted
+/// to the temporary directory, to check their format and their binaries.
+///
+/// Errors are reported as [`Finding::Failed`], so that one crate failing does
+/// not prevent the others from being reported, unless the user aborted.
+pub async fn report_unsupported(
+    opts: Arc<Options>,
+    crate_name: CrateName,
+) -> Result<Vec<Finding>, BinstallError> {
+    match report_unsupported_inner(&opts, crate_name).await {
+        Err(err @ BinstallError::UserAbort) => Err(err),
+        Err(err) => Ok(vec![Finding::Failed {
+            reason: err.to_string(),
+        }]),
+        Ok(findings) => Ok(findings),
+    }
+}
+
+/// Return true if a `pkg-url` is set for any of the desired targets, by the
+/// crate or by the overrides.
+async fn has_pkg_url(opts: &Options, package_info: &PackageInfo) -> bool {
+    package_info.meta.pkg_url.is_some()
+        || opts.cli_overrides.pkg_url.is_some()
+        || opts.desired_targets.get().await.iter().any(|target| {
+            opts.target_overrides
+                .get(target.as_str())
+                .into_iter()
+                .chain(package_info.overrides.get(target))
+                .any(|pkg_override| pkg_override.pkg_url.is_some())
+        })
+}
+
+async fn report_unsupported_inner(
+    opts: &Options,
+    crate_name: CrateName,
+) -> Result<Vec<Finding>, BinstallError> {
+    let version_req = resolve_version_req(opts, crate_name.version_req.as_ref())?;
+
+    let package_info = PackageInfo::resolve(
+        opts,
+        crate_name.name,
+        None,
+        &version_req,
+        allow_pre_releases(opts, crate_name.stable_only),
+        opts.client.clone(),
+        true,
+    )
+    .await?
+    .expect("PackageInfo::resolve only returns None if curr_version is Some");
+
+    let mut findings = Vec::new();
+
+    if !package_info.has_binstall_metadata {
+        findings.push(Finding::NoBinstallMetadata);
+    }
+    if package_info.repo.is_none() && !has_pkg_url(opts, &package_info).await {
+        findings.push(Finding::NoRepository);
+    }
+
+    // Targets for which a package has been found, usable or not.
+    let mut found_targets: Vec<String> = Vec::new();
+    let mut supported_targets: Vec<String> = Vec::new();
+
+    for (fetcher, handle) in launch_fetchers(opts, &package_info).await? {
+        let target = fetcher.target().to_string();
+        if supported_targets.contains(&target) {
+            continue;
+        }
+
+        let unusable = |reason: String| Finding::Unusable {
+            target: target.clone(),
+            source: fetcher.source_name(),
+            reason,
+        };
+
+        match handle.flattened_join().await {
+            Ok(true) => {
+                found_targets.push(target.clone());
+
+                let bin_path = opts.temp_dir.join(format!(
+                    "report-{}-{}-{}",
+                    package_info.name,
+                    fetcher.target(),
+                    fetcher.fetcher_name()
+                ));
+
+                match download_extract_and_verify(
+                    fetcher.as_ref(),
+                    &bin_path,
+                    &package_info,
+                    opts,
+                    false,
                 )
+                .await
+                {
+                    Ok((bin_files, _)) if !bin_files.is_empty() => {
+                        findings.push(Finding::Supported {
+                            target: target.clone(),
+                            source: fetcher.source_name(),
+                        });
+                        supported_targets.push(target.clone());
+                    }
+                    Ok(_) => {
+                        findings.push(unusable("the package does not provide any binary".into()))
+                    }
+                    Err(err @ BinstallError::UserAbort) => return Err(err),
+                    Err(err) => findings.push(unusable(err.to_string())),
+                }
+            }
+            Ok(false) => (),
+            Err(err) => {
+                found_targets.push(target.clone());
+                findings.push(unusable(err.to_string()));
+            }
+        }
+    }
+
+    findings.extend(
+        opts.desired_targets
+            .get()
+            .await
+            .iter()
+            .filter(|target| !found_targets.contains(target))
+            .map(|target| Finding::NoPackage {
+                target: target.clone(),
+            }),
+    );
+
+    Ok(findings)
+}
+
+type FetcherHandle = (Arc<dyn Fetcher>, FindHandle);
+
+/// `find()` of a fetcher, launched along with the others unless
+/// [`Options::sequential_discovery`] is set, in which case it is only
+/// launched once joined.
+enum FindHandle {
+    Launched(AutoAbortJoinHandle<Result<bool, FetchError>>),
+    Deferred(Arc<dyn Fetcher>),
+}
+
+impl FindHandle {
+    async fn flattened_join(self) -> Result<bool, BinstallError> {
+        match self {
+            Self::Launched(handle) => handle.flattened_join().await,
+            Self::Deferred(fetcher) => {
+                AutoAbortJoinHandle::new(fetcher.find())
+                    .flattened_join()
+                    .await
+            }
+        }
+    }
+}
+
+/// Return `false` if `fetcher` is a third-party source not in
+/// [`Options::trusted_hosts`].
+fn is_trusted(opts: &Options, fetcher: &dyn Fetcher) -> bool {
+    match &opts.trusted_hosts {
+        Some(trusted_hosts) if fetcher.is_third_party() => {
+            let source_name = fetcher.source_name();
+            trusted_hosts
+                .iter()
+                .any(|host| host.eq_ignore_ascii_case(&source_name))
+        }
+        _ => true,
+    }
+}
+
+/// Launch `find()` of every resolver for every desired target, in the order
+/// of preference.
+async fn launch_fetchers(
+    opts: &Options,
+    package_info: &PackageInfo,
+) -> Result<Vec<FetcherHandle>, BinstallError> {
+    let desired_targets = opts
+        .desired_targets
+        .get()
+        .await
+        .iter()
+        .map(|target| TargetTriple::from_str(target).map(|triple| (triple, target)))
+        .collect::<Result<Vec<_>, _>>()?;
+    // Pre-built binaries cannot be downloaded without network access.
+    let resolvers = if opts.frozen {
+        &[][..]
+    } else {
+        &opts.resolvers[..]
+    };
+
+    let mut handles = Vec::with_capacity(desired_targets.len() * resolvers.len());
+    let mut untrusted_sources = BTreeSet::new();
+
+    let data = Arc::new(
+        Data::new(
+            package_info.name.clone(),
+            package_info.version_str.clone(),
+            package_info
+                .repo
+                .clone()
+                .map(|repo| RepoRewrite::apply_all(&opts.repo_rewrites, repo)),
+        )
+        .with_prefer_smallest(opts.prefer_smallest)
+        .with_external_fetcher(opts.external_fetcher.clone())
+        .with_keep_download(opts.keep_download.clone())
+        .with_extract_command(opts.extract_command.clone())
+        .with_bitbucket_token(opts.bitbucket_token.clone())
+        .with_wait_rate_limit(opts.wait_rate_limit),
+    );
+
+    handles.extend(
+        desired_targets
+            .into_iter()
+            .map(|(triple, target)| {
+                debug!("Building metadata for target: {target}");
+
+                let cpu_variant = cpu_features::select_variant(
+                    &package_info.meta.cpu_variants,
+                    &triple.target_arch,
+                );
+                if let Some(cpu_variant) = cpu_variant {
+                    debug!(
+                        "Using the variant for CPU features {:?} on {target}",
+                        cpu_variant.features
+                    );
+                }
+
+                let target_meta = merge_target_overrides(opts, package_info, target, cpu_variant);
+
+                debug!("Found metadata: {target_meta:?}");
+
+                Arc::new(TargetData {
+                    target: target.clone(),
+                    meta: target_meta,
+                    target_related_info: triple,
+                })
+            })
+            .cartesian_product(resolvers)
+            .map(|(target_data, f)| {
+                f(
+                    opts.client.clone(),
+                    opts.gh_api_client.clone(),
+                    data.clone(),
+                    target_data,
+                    opts.signature_policy,
+                )
+            })
+            .filter(|fetcher| {
+                let trusted = is_trusted(opts, &**fetcher);
+                if !trusted {
+                    untrusted_sources.insert(fetcher.source_name());
+                }
+                trusted
+            })
+            .map(|fetcher| {
+                let handle = if opts.sequential_discovery {
+                    FindHandle::Deferred(fetcher.clone())
+                } else {
+                    FindHandle::Launched(AutoAbortJoinHandle::new(fetcher.clone().find()))
+                };
+                (fetcher, handle)
+            }),
+    );
+
+    for source in untrusted_sources {
+        warn!("Skipping third-party source {source}: it is not in --trusted-host");
+    }
+
+    Ok(handles)
+}
+
+///  * `fetcher` - `fetcher.find()` must have returned `Ok(true)`.
+///
+/// Can return empty Vec if all `BinFile` is optional and does not exist
+/// in the archive downloaded.
+///  * `update_links` - if `false`, symlinks to the binaries are not created.
+///
+/// Return the binaries along with the extra files found in the package.
+async fn download_extract_and_verify(
+    fetcher: &dyn Fetcher,
+    bin_path: &Path,
+    package_info: &PackageInfo,
+    opts: &Options,
+    update_links: bool,
+) -> Result<(Vec<bins::BinFile>, Vec<bins::ExtraFile>), BinstallError> {
+    // Build final metadata
+    let meta = fetcher.target_meta();
+
+    let extra_files = collect_extra_files(fetcher, package_info, &meta, bin_path, opts)?;
+
+    // If bin-dir is specified, then paths to the binaries are known before
+    // downloading, so only they need to be extracted from the archive.
+    let bin_files = if meta.bin_dir.is_some() {
+        Some(collect_bin_files(
+            fetcher,
+            package_info,
+            meta.clone(),
+            bin_path,
+            opts,
+            update_links,
+            None,
+        )?)
+    } else {
+        None
+    };
+    let members: Option<Vec<PathBuf>> = bin_files.as_ref().map(|bin_files| {
+        bin_files
+            .iter()
+            .flat_map(|bin_file| {
+                // The `.exe` suffix may be missing from `bin-dir`.
+                iter::once(bin_file.archive_source_path.clone())
+                    .chain(bin_file.exe_archive_source_path())
+            })
+            .chain(
+                extra_files
+                    .iter()
+                    .map(|extra_file| extra_file.archive_source_path.clone()),
+            )
+            .collect()
+    });
+
+    // Download and extract it.
+    // If that fails, then ignore this fetcher.
+    let extracted_files = match fetcher
+        .fetch_and_extract(bin_path, members.as_deref())
+        .await
+    {
+        // Truncated downloads are usually caused by a flaky connection,
+        // so try once more.
+        Err(FetchError::Download(DownloadError::CorruptArchive { fmt, source })) => {
+            warn!(
+                "The {fmt} package from {} is truncated or corrupted ({source}), retrying",
+                fetcher.source_name()
+            );
+
+            // Do not mix the files of both attempts.
+            let path = bin_path.to_path_buf();
+            spawn_blocking(move || remove_path(&path)).await??;
+
+            fetcher
+                .fetch_and_extract(bin_path, members.as_deref())
                 .await?
+        }
+        res => res?,
+    };
+    debug!("extracted_files = {extracted_files:#?}");
+
+    // Verify that all non-optional bin_files exist
+    let bin_files = match bin_files {
+        Some(bin_files) => bin_files,
+        None => collect_bin_files(
+            fetcher,
+            package_info,
+            meta,
+            bin_path,
+            opts,
+            update_links,
+            Some(&extracted_files),
+        )?,
+    };
+
+    let name = &package_info.name;
+
+    // Extra files are only informative, so missing ones are skipped.
+    let extra_files = extra_files
+        .into_iter()
+        .filter(|extra_file| {
+            let exists = extra_file
+                .check_source_exists(&mut |p| extracted_files.has_file(p))
+                .is_ok();
+            if !exists {
+                warn!(
+                    "When resolving {name} extra file {} is not found, it is skipped",
+                    extra_file.archive_source_path.display()
+                );
+            }
+            exists
+        })
+        .collect();
+
+    let bin_files = package_info
+        .binaries
+        .iter()
+        .zip(bin_files)
+        .filter_map(|(bin, mut bin_file)| {
+            match bin_file.check_source_exists(&mut |p| extracted_files.has_file(p)) {
+                Ok(()) => Some(Ok(bin_file)),
+
+                // This binary is optional
+                Err(err) => {
+                    let required_features = &bin.required_features;
+                    let bin_name = bin.name.as_str();
+
+                    if required_features.is_empty() {
+                        error!(
+                            "When resolving {name} bin {bin_name} is not found.\
+This binary is not optional so it must be included in the archive, please contact with\
+upstream to fix this issue."
+                        );
+                        // This bin is not optional, error
+                        Some(Err(err))
+                    } else {
+                        // Optional, print a warning and continue.
+                        let features = required_features.iter().format(",");
+                        warn!(
+                            "When resolving {name} bin {bin_name} is not found. \
+But since it requies features {features}, this bin is ignored."
+                        );
+                        None
+                    }
+                }
+            }
+        })
+        .collect::<Result<Vec<bins::BinFile>, bins::Error>>()?;
+
+    Ok((bin_files, extra_files))
+}
+
+/// Return the `extra-files` of the package to install in
+/// `opts.extra_files_dir`, if set.
+fn collect_extra_files(
+    fetcher: &dyn Fetcher,
+    package_info: &PackageInfo,
+    meta: &PkgMeta,
+    bin_path: &Path,
+    opts: &Options,
+) -> Result<Vec<bins::ExtraFile>, BinstallError> {
+    let Some(extra_files_dir) = &opts.extra_files_dir else {
+        return Ok(Vec::new());
+    };
+    if meta.pkg_fmt.map_or(false, PkgFmt::is_bare_binary) {
+        // The package is the binary itself
+        return Ok(Vec::new());
+    }
+
+    let bin_data = bins::Data {
+        name: &package_info.name,
+        target: fetcher.target(),
+        version: &package_info.version_str,
+        repo: package_info.repo.as_deref(),
+        meta: PkgMeta::default(),
+        bin_path,
+        install_path: &opts.install_path,
+        versioned: opts.versioned_bin,
+        target_related_info: &fetcher.target_data().target_related_info,
+    };
+
+    let extra_files = meta
+        .extra_files
+        .iter()
+        .map(|extra_file| -> Result<_, BinstallError> {
+            let template = Template::parse(extra_file)?;
+            Ok(bins::ExtraFile::new(&bin_data, &template, extra_files_dir)?)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    bins::ExtraFile::check_collisions(&extra_files)?;
+
+    Ok(extra_files)
+}
+
+fn collect_bin_files(
+    fetcher: &dyn Fetcher,
+    package_info: &PackageInfo,
+    meta: PkgMeta,
+    bin_path: &Path,
+    opts: &Options,
+    update_links: bool,
+    extracted_files: Option<&ExtractedFiles>,
+) -> Result<Vec<bins::BinFile>, BinstallError> {
+    // List files to be installed
+    // based on those found via Cargo.toml
+    let bin_data = bins::Data {
+        name: &package_info.name,
+        target: fetcher.target(),
+        version: &package_info.version_str,
+        repo: package_info.repo.as_deref(),
+        meta,
+        bin_path,
+        install_path: &opts.install_path,
+        versioned: opts.versioned_bin,
+        target_related_info: &fetcher.target_data().target_related_info,
+    };
+
+    let is_bare_binary = bin_data.meta.pkg_fmt.map_or(false, PkgFmt::is_bare_binary);
+    let is_bin_dir_inferred = !is_bare_binary && bin_data.meta.bin_dir.is_none();
+
+    let bin_dir = if is_bare_binary {
+        // The package is the binary itself, there is no dir to look into.
+        Cow::Borrowed("{ bin }{ binary-ext }")
+    } else {
+        bin_data
+            .meta
+            .bin_dir
+            .as_deref()
+            .map(Cow::Borrowed)
+            .unwrap_or_else(|| {
+                bins::infer_bin_dir_template(&bin_data, &mut |p| {
+                    extracted_files
+                        .and_then(|extracted_files| extracted_files.get_dir(p))
+                        .is_some()
+                })
+            })
+    };
+
+    let template = Template::parse(&bin_dir)?;
+    let no_symlinks = opts.no_symlinks || !update_links;
+
+    // Create bin_files
+    let mut bin_files = package_info
+        .binaries
+        .iter()
+        .map(|bin| bins::BinFile::new(&bin_data, bin.name.as_str(), &template, no_symlinks))
+        .collect::<Result<Vec<_>, bins::Error>>()?;
+
+    // Archives bundling the binaries of several crates, e.g. the release of
+    // a workspace, may put them anywhere: look the binaries up by name.
+    if let (true, Some(extracted_files)) = (is_bin_dir_inferred, extracted_files) {
+        for bin_file in &mut bin_files {
+            locate_bundled_bin(bin_file, bin_path, extracted_files);
+        }
+    }
+
+    let mut source_set = BTreeSet::new();
+
+    for bin in &bin_files {
+        if !source_set.insert(&bin.source) {
+            return Err(BinstallError::DuplicateSourceFilePath {
+                path: bin.source.clone(),
+            });
+        }
+    }
+
+    Ok(bin_files)
+}
+
+/// Point `bin_file` to the only file of the package with the name of its
+/// source, if it is not at the path inferred.
+///
+/// Files of other crates in the package are ignored, but the binary is left
+/// as is if several files have its name, as picking one would be a guess.
+fn locate_bundled_bin(
+    bin_file: &mut bins::BinFile,
+    bin_path: &Path,
+    extracted_files: &ExtractedFiles,
+) {
+    if extracted_files.has_file(&bin_file.archive_source_path) {
+        return;
+    }
+    let Some(file_name) = bin_file.archive_source_path.file_name() else {
+        return;
+    };
+
+    let Ok(path) = extracted_files.find_files(file_name).exactly_one() else {
+        return;
+    };
+
+    debug!(
+        "Binary {} found at {} in the package",
+        bin_file.base_name,
+        path.display()
+    );
+    bin_file.source = bin_path.join(path);
+    bin_file.archive_source_path = path.to_path_buf();
+}
+
+struct PackageInfo {
+    meta: PkgMeta,
+    binaries: Vec<Bin>,
+    name: CompactString,
+    version_str: CompactString,
+    version: Version,
+    repo: Option<String>,
+    overrides: BTreeMap<String, PkgOverride>,
+    /// Whether the crate has a `[package.metadata.binstall]` section.
+    has_binstall_metadata: bool,
+    /// Tag of the release the manifest is from, for `--git` installs.
+    git_tag: Option<CompactString>,
+}
+
+struct Bin {
+    name: String,
+    required_features: Vec<String>,
+}
+
+impl PackageInfo {
+    /// Return `None` if already up-to-date.
+    ///
+    /// If `check_newer_version` is set, the user is told when `version_req`
+    /// keeps them behind the latest version, see [`notify_newer_version`].
+    async fn resolve(
+        opts: &Options,
+        name: CompactString,
+        curr_version: Option<Version>,
+        version_req: &VersionReq,
+        allow_pre_releases: bool,
+        client: Client,
+        check_newer_version: bool,
+    ) -> Result<Option<Self>, BinstallError> {
+        use CargoTomlFetchOverride::*;
+
+        if opts.frozen && !matches!(opts.cargo_toml_fetch_override, Some(Path(_))) {
+            return Err(BinstallError::Frozen(
+                "fetching the crate manifest requires network access",
+            ));
+        }
+
+        // Latest version of the crate on the registry, if fetched from it
+        let mut latest_version = None;
+        // Tag of the release checked out for `--git`
+        #[cfg_attr(not(feature = "git"), allow(unused_mut))]
+        let mut git_tag = None;
+
+        // Fetch crate via crates.io, git, or use a local manifest path
+        let manifest = match opts.cargo_toml_fetch_override.as_ref() {
+            Some(Path(manifest_path)) => {
+                let manifest_path = manifest_path.clone();
+                let name = name.clone();
+
+                spawn_blocking(move || load_manifest_path(manifest_path, &name)).await??
+            }
+            #[cfg(feature = "git")]
+            Some(Git(git_url)) => {
+                use helpers::git::{GitCancellationToken, Repository as GitRepository};
+
+                // Install the latest release rather than the default branch,
+                // which may have unreleased changes.
+                let tag =
+                    git_release::fetch_latest_release_tag(&client, &opts.gh_api_client, git_url)
+                        .await
+                        .unwrap_or_else(|err| {
+                            warn!("Failed to look up the latest release of {git_url}: {err}");
+                            None
+                        });
+                match &tag {
+                    Some(tag) => info!("Installing {name} from release {tag} of {git_url}"),
+                    None => info!("No release of {git_url} found, installing {name} from HEAD"),
+                }
+                git_tag = tag.clone();
+
+                let name = name.clone();
+
+                if let Some(tag) = tag {
+                    let dir = TempDir::new()?;
+                    let root =
+                        git_release::download_release_source(&client, git_url, &tag, dir.as_ref())
+                            .await?;
+
+                    spawn_blocking(move || {
+                        let manifest = load_manifest_path(root, &name);
+                        drop(dir);
+                        manifest
+                    })
+                    .await??
+                } else {
+                    let git_url = git_url.clone();
+                    let cancellation_token = GitCancellationToken::default();
+                    // Cancel git operation if the future is cancelled (dropped).
+                    let cancel_on_drop = cancellation_token.clone().cancel_on_drop();
+
+                    let ret = spawn_blocking(move || {
+                        let dir = TempDir::new()?;
+                        GitRepository::shallow_clone(
+                            git_url,
+                            dir.as_ref(),
+                            Some(cancellation_token),
+                        )?;
+
+                        load_manifest_path(dir.as_ref(), &name)
+                    })
+                    .await??;
+
+                    // Git operation done, disarm it
+                    cancel_on_drop.disarm();
+
+                    ret
+                }
+            }
+            Some(ManifestUrl(url)) => {
+                let mut manifest = load_manifest_url(&client, url, &name).await?;
+                sidecar::apply_sidecar_url(&client, &mut manifest, url).await?;
+                manifest
+            }
+            Some(Url { url, bins }) => {
+                return Ok(Some(Self::from_direct_url(name, url, bins, version_req)));
+            }
+            None => {
+                let local_manifest = match &opts.local_manifest_dir {
+                    Some(dir) => load_local_manifest(dir, &name, version_req).await?,
+                    None => None,
+                };
+
+                match local_manifest {
+                    Some(manifest) => manifest,
+                    None => {
+                        let (manifest, latest) = fetch_crate_selected(
+                            opts,
+                            client.clone(),
+                            &name,
+                            version_req,
+                            allow_pre_releases,
+                        )
+                        .await?;
+                        latest_version = latest;
+                        manifest
+                    }
+                }
             }
         };
 
@@ -396,16 +1532,14 @@ impl PackageInfo {
         };
 
         let new_version_str = package.version().to_compact_string();
-        let new_version = match Version::parse(&new_version_str) {
-            Ok(new_version) => new_version,
-            Err(err) => {
-                return Err(Box::new(VersionParseError {
-                    v: new_version_str,
-                    err,
-                })
-                .into())
+        let new_version = parse_package_version(&new_version_str)?;
+
+        if let Some(latest_version) = latest_version {
+            if check_newer_version && !opts.quiet_version_check && *version_req != VersionReq::STAR
+            {
+                notify_newer_version(&name, version_req, &new_version, &latest_version);
             }
-        };
+        }
 
         if let Some(curr_version) = curr_version {
             if new_version == curr_version {
@@ -417,23 +1551,47 @@ impl PackageInfo {
             }
         }
 
-        let (mut meta, binaries): (_, Vec<Bin>) = (
-            package
-                .metadata
-                .take()
-                .and_then(|m| m.binstall)
-                .unwrap_or_default(),
+        let binaries: Vec<Bin> = if !manifest.bin.is_empty() {
             manifest
                 .bin
                 .into_iter()
                 .filter_map(|p| {
-                    p.name.map(|name| Bin {
+                    let name = p
+                        .name
+                        .or_else(|| infer_bin_name(&package.name, p.path.as_deref()?))?;
+
+                    Some(Bin {
                         name,
                         required_features: p.required_features,
                     })
                 })
-                .collect(),
-        );
+                .collect()
+        } else if package.autobins && manifest.lib.is_none() {
+            // Without the sources (e.g. a bare Cargo.toml), no target can be
+            // discovered, so assume `src/main.rs` exists as cargo's autobins would.
+            debug!("No target found in the manifest of {name}, assuming a binary named after it");
+
+            vec![Bin {
+                name: package.name.clone(),
+                required_features: Vec::new(),
+            }]
+        } else {
+            return Err(BinstallError::NoBinaries(name));
+        };
+
+        let meta = package.metadata.take().and_then(|m| m.binstall);
+        let has_binstall_metadata = meta.is_some();
+        let mut meta = meta.unwrap_or_default();
+
+        // The metadata is written by the author of the crate, who must not
+        // be able to read the environment of the user.
+        let env_fields = check_manifest::env_key_fields(&meta);
+        if !env_fields.is_empty() {
+            return Err(BinstallError::EnvVarInCrateMetadata {
+                name,
+                fields: env_fields.join(", ").into(),
+            });
+        }
 
         // Check binaries
         if binaries.is_empty() {
@@ -447,12 +1605,328 @@ impl PackageInfo {
                 version_str: new_version_str,
                 version: new_version,
                 repo: package.repository().map(ToString::to_string),
+                has_binstall_metadata,
+                git_tag,
             }))
         }
     }
+
+    /// Synthesize the package info for installing `bins` directly from the
+    /// archive at `url`, without consulting any registry.
+    ///
+    /// The version is the exact one of `version_req` if any, 0.0.0 otherwise.
+    fn from_direct_url(
+        name: CompactString,
+        url: &url::Url,
+        bins: &[CompactString],
+        version_req: &VersionReq,
+    ) -> Self {
+        let version = exact_version(version_req).unwrap_or_else(|| Version::new(0, 0, 0));
+
+        // Escape the url so that it would not be interpreted as a template.
+        let mut pkg_url = String::with_capacity(url.as_str().len());
+        for c in url.as_str().chars() {
+            if matches!(c, '\\' | '{' | '}') {
+                pkg_url.push('\\');
+            }
+            pkg_url.push(c);
+        }
+
+        Self {
+            meta: PkgMeta {
+                pkg_url: Some(pkg_url),
+                pkg_fmt: PkgFmt::guess_pkg_format(url.path()),
+                ..Default::default()
+            },
+            binaries: bins
+                .iter()
+                .map(|bin| Bin {
+                    name: bin.to_string(),
+                    required_features: Vec::new(),
+                })
+                .collect(),
+            name,
+            version_str: version.to_compact_string(),
+            version,
+            repo: None,
+            overrides: BTreeMap::new(),
+            has_binstall_metadata: true,
+            git_tag: None,
+        }
+    }
+
+    /// Crates named `cargo-xyz` are expected to provide a `cargo-xyz` binary
+    /// so that `cargo xyz` works after installation.
+    ///
+    /// Warn if none of the binaries follow that convention.
+    fn check_cargo_subcommand_binaries(&self) {
+        let name = &self.name;
+
+        if !name.starts_with("cargo-") {
+            return;
+        }
+
+        if self.binaries.iter().any(|bin| bin.name == name.as_str()) {
+            return;
+        }
+
+        if self
+            .binaries
+            .iter()
+            .any(|bin| bin.name.starts_with("cargo-"))
+        {
+            debug!(
+                "Crate {name} does not provide binary {name}, but provides other cargo subcommands"
+            );
+        } else {
+            warn!(
+                "Crate {name} looks like a cargo subcommand, but none of its binaries ({}) \
+                are named `cargo-*`, so `cargo {}` will not work after installation",
+                self.binaries
+                    .iter()
+                    .map(|bin| bin.name.as_str())
+                    .format(", "),
+                &name["cargo-".len()..],
+            );
+        }
+    }
+}
+
+/// Fetch the manifest of the version of crate `name` picked by
+/// [`Options::version_selector`] among the ones published on the registry,
+/// along with the latest stable version published.
+async fn fetch_crate_selected(
+    opts: &Options,
+    client: Client,
+    name: &str,
+    version_req: &VersionReq,
+    allow_pre_releases: bool,
+) -> Result<(Manifest<Meta>, Option<Version>), BinstallError> {
+    let version_selector = opts
+        .version_selector
+        .as_deref()
+        .unwrap_or(&LatestVersionSelector);
+    let latest_version = Mutex::new(None);
+
+    let select = |versions: &[CrateVersion]| {
+        *latest_version
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = versions
+            .iter()
+            .filter(|v| !v.yanked && v.version.pre.is_empty())
+            .map(|v| &v.version)
+            .max()
+            .cloned();
+
+        let version = version_selector.select(versions, version_req, allow_pre_releases);
+        if let Some(version) = &version {
+            debug!("Version {version} of {name} selected by {version_selector:?}");
+        }
+        version
+    };
+
+    let manifest = Box::pin(
+        opts.registry
+            .fetch_crate_selected(client, name, version_req, &select),
+    )
+    .await?;
+
+    Ok((
+        manifest,
+        latest_version
+            .into_inner()
+            .unwrap_or_else(PoisonError::into_inner),
+    ))
+}
+
+/// Inform the user if `version_req` keeps them behind the latest version
+/// of the crate in the registry.
+fn notify_newer_version(
+    name: &str,
+    version_req: &VersionReq,
+    new_version: &Version,
+    latest_version: &Version,
+) {
+    if latest_version > new_version {
+        info!(
+            "{name} v{latest_version} is available, but the version requirement '{version_req}' \
+             resolves to v{new_version}, pass --quiet-version-check to silence this notice"
+        );
+    }
+}
+
+/// Return whether pre-releases may be selected, which they are not for
+/// `crate@latest` even if [`Options::allow_pre_releases`] is set.
+fn allow_pre_releases(opts: &Options, stable_only: bool) -> bool {
+    opts.allow_pre_releases && !stable_only
+}
+
+/// Return the version requirement of the crate, which must be an exact
+/// version if `opts.exact` is set.
+fn resolve_version_req<'a>(
+    opts: &'a Options,
+    crate_version_req: Option<&'a VersionReq>,
+) -> Result<MaybeOwned<'a, VersionReq>, BinstallError> {
+    let version_req = match (crate_version_req, &opts.version_req) {
+        (Some(version), None) => MaybeOwned::Borrowed(version),
+        (None, Some(version)) => MaybeOwned::Borrowed(version),
+        (Some(_), Some(_)) => Err(BinstallError::SuperfluousVersionOption)?,
+        (None, None) => MaybeOwned::Owned(VersionReq::STAR),
+    };
+
+    if opts.exact {
+        match version_req.to_exact() {
+            Some(version_req) => Ok(MaybeOwned::Owned(version_req)),
+            None => Err(BinstallError::InexactVersion(
+                version_req.to_compact_string(),
+            )),
+        }
+    } else {
+        Ok(version_req)
+    }
+}
+
+/// Apply the overrides for `target` to the metadata of the crate, the ones
+/// of the user first, then the ones of `cpu_variant` and at last the target
+/// specific ones of the crate.
+fn merge_target_overrides(
+    opts: &Options,
+    package_info: &PackageInfo,
+    target: &str,
+    cpu_variant: Option<&CpuVariant>,
+) -> PkgMeta {
+    package_info.meta.merge_overrides(
+        iter::once(&opts.cli_overrides)
+            .chain(opts.target_overrides.get(target))
+            .chain(cpu_variant.map(|cpu_variant| &cpu_variant.overrides))
+            .chain(package_info.overrides.get(target)),
+    )
+}
+
+/// Remove the file or directory at `path`, if any.
+fn remove_path(path: &Path) -> io::Result<()> {
+    match fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.is_dir() => fs::remove_dir_all(path),
+        Ok(_) => fs::remove_file(path),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(err) => Err(err),
+    }
 }
 
-/// Load binstall metadata from the crate `Cargo.toml` at the provided path
+/// Infer the name of a `[[bin]]` target without one from its `path`, the same
+/// way cargo does for targets in its default locations.
+fn infer_bin_name(package_name: &str, path: &str) -> Option<String> {
+    let path = Path::new(path);
+
+    if path == Path::new("src/main.rs") {
+        return Some(package_name.to_string());
+    }
+
+    let path = path.strip_prefix("src/bin").ok()?;
+    let dir = path.parent()?;
+
+    let name = if dir == Path::new("") {
+        // src/bin/<name>.rs
+        path.file_stem()?
+    } else if path.file_name()? == "main.rs" && dir.parent() == Some(Path::new("")) {
+        // src/bin/<name>/main.rs
+        dir.file_name()?
+    } else {
+        return None;
+    };
+
+    name.to_str().map(ToString::to_string)
+}
+
+/// Return the version requirement only accepting `version`.
+fn exact_version_req(version: &Version) -> VersionReq {
+    VersionReq {
+        comparators: vec![Comparator {
+            op: Op::Exact,
+            major: version.major,
+            minor: Some(version.minor),
+            patch: Some(version.patch),
+            pre: version.pre.clone(),
+        }],
+    }
+}
+
+/// Return the version if `version_req` only accepts exactly one version.
+fn exact_version(version_req: &VersionReq) -> Option<Version> {
+    match version_req.comparators.as_slice() {
+        [Comparator {
+            op: Op::Exact,
+            major,
+            minor: Some(minor),
+            patch: Some(patch),
+            pre,
+        }] => Some(Version {
+            major: *major,
+            minor: *minor,
+            patch: *patch,
+            pre: pre.clone(),
+            build: Default::default(),
+        }),
+        _ => None,
+    }
+}
+
+/// Load the manifest of crate `name` from the workspace at `dir`, if it
+/// contains the crate and its version matches `version_req`.
+async fn load_local_manifest(
+    dir: &Path,
+    name: &CompactString,
+    version_req: &VersionReq,
+) -> Result<Option<Manifest<Meta>>, BinstallError> {
+    let dir = dir.to_owned();
+    let crate_name = name.clone();
+
+    let manifest = spawn_blocking(move || -> Result<_, BinstallError> {
+        let (mut manifest, manifest_path) =
+            match load_manifest_and_path_from_workspace(dir, crate_name) {
+                Ok(loaded) => loaded,
+                Err(err) if err.is_not_found() => return Ok(None),
+                Err(err) => return Err(err.into()),
+            };
+        sidecar::apply_sidecar_file(&mut manifest, &manifest_path)?;
+        Ok(Some(manifest))
+    })
+    .await??;
+    let Some(manifest) = manifest else {
+        debug!("{name} is not in the local workspace, fetching it from the registry");
+        return Ok(None);
+    };
+
+    let Some(package) = &manifest.package else {
+        return Err(BinstallError::CargoTomlMissingPackage(name.clone()));
+    };
+
+    // A malformed local version fails right away, instead of silently
+    // falling back to the registry.
+    let version = parse_package_version(package.version())?;
+
+    if version_req.matches(&version) {
+        debug!("Using the local manifest of {name} v{version}");
+        Ok(Some(manifest))
+    } else {
+        debug!("The local version of {name} does not match {version_req}, ignoring it");
+        Ok(None)
+    }
+}
+
+/// Parse the `version` of the package of a manifest, which must be valid
+/// semver, before it is used in any way.
+fn parse_package_version(version: &str) -> Result<Version, BinstallError> {
+    Version::parse(version).map_err(|err| {
+        BinstallError::from(Box::new(VersionParseError {
+            v: version.into(),
+            err,
+        }))
+    })
+}
+
+/// Load binstall metadata from the crate `Cargo.toml` at the provided path,
+/// overridden by the `binstall.toml` next to it if any.
 ///
 /// This is a blocking function.
 pub fn load_manifest_path<P: AsRef<Path>, N: AsRef<str>>(
@@ -466,7 +1940,9 @@ pub fn load_manifest_path<P: AsRef<Path>, N: AsRef<str>>(
         );
 
         // Load and parse manifest (this checks file system for binary output names)
-        let manifest = load_manifest_from_workspace(manifest_path, crate_name)?;
+        let (mut manifest, manifest_path) =
+            load_manifest_and_path_from_workspace(manifest_path, crate_name)?;
+        sidecar::apply_sidecar_file(&mut manifest, &manifest_path)?;
 
         // Return metadata
         Ok(manifest)
@@ -474,3 +1950,628 @@ pub fn load_manifest_path<P: AsRef<Path>, N: AsRef<str>>(
 
     inner(manifest_path.as_ref(), name.as_ref())
 }
+
+/// Maximum size of a manifest downloaded by [`load_manifest_url`], which is
+/// far larger than any real one.
+const MANIFEST_MAX_SIZE: u64 = 1024 * 1024;
+
+/// Maximum time allowed to download a manifest by [`load_manifest_url`].
+const MANIFEST_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Download the `Cargo.toml` of the crate `name` at `url` and load it like a
+/// local manifest, except that it has no sources to discover binaries from.
+async fn load_manifest_url(
+    client: &Client,
+    url: &url::Url,
+    name: &str,
+) -> Result<Manifest<Meta>, BinstallError> {
+    /// Filesystem of a manifest downloaded without its sources.
+    struct NoSources;
+
+    impl AbstractFilesystem for NoSources {
+        fn file_names_in(&self, _rel_path: &str) -> io::Result<HashSet<Box<str>>> {
+            Ok(HashSet::new())
+        }
+    }
+
+    debug!("Downloading crate {name} manifest from {url}");
+    let content = Download::new(client.clone(), url.clone())
+        .into_bytes_bounded(MANIFEST_MAX_SIZE, MANIFEST_TIMEOUT)
+        .await?;
+
+    let mut manifest = Manifest::<Meta>::from_slice_with_metadata(&content)?;
+    // Fails on the fields inherited from a workspace, which is not available.
+    manifest.complete_from_abstract_filesystem::<Value, _>(&NoSources, None)?;
+
+    match &manifest.package {
+        Some(package) if package.name != name => Err(BinstallError::ManifestNameMismatch {
+            name: name.into(),
+            found: package.name.as_str().into(),
+        }),
+        _ => Ok(manifest),
+    }
+}
+
+/// Load the tools declared in `[package.metadata.binstall] tools` of the
+/// manifest at `manifest_path`, which may be either the Cargo.toml itself
+/// or the folder containing it, and of the members of its workspace.
+pub fn load_dev_tools(manifest_path: &Path) -> Result<Vec<CrateName>, BinstallError> {
+    debug!("Reading dev tools from {}", manifest_path.display());
+
+    load_manifests_from_workspace::<Meta>(manifest_path)?
+        .into_iter()
+        .filter_map(|(manifest, _)| manifest.package?.metadata?.binstall)
+        .flat_map(|meta| meta.tools)
+        .map(|tool| {
+            CrateName::from_str(&tool).map_err(|err| {
+                BinstallError::from(Box::new(VersionParseError {
+                    v: tool.into(),
+                    err,
+                }))
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::{
+        fs,
+        num::NonZeroU16,
+        sync::atomic::{AtomicBool, Ordering::Relaxed},
+    };
+
+    use crate::{
+        fetchers::{SignaturePolicy, TargetDataErased},
+        get_desired_targets,
+        helpers::{gh_api_client::GhApiClient, jobserver_client::LazyJobserverClient},
+        ops::VersionSelector,
+        registry::Registry,
+    };
+
+    /// Fetcher whose package only contains the binary `krate`.
+    ///
+    /// If the flag is set, the next download is truncated after extracting
+    /// the file `partial`.
+    struct FakeFetcher(&'static str, Arc<TargetDataErased>, AtomicBool);
+
+    impl FakeFetcher {
+        fn with_name(source_name: &'static str) -> Arc<Self> {
+            Arc::new(Self(
+                source_name,
+                Arc::new(TargetData {
+                    target: "x86_64-unknown-linux-gnu".into(),
+                    meta: PkgMeta::default(),
+                    target_related_info: Vec::<(String, String)>::new(),
+                }),
+                AtomicBool::new(false),
+            ))
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl Fetcher for FakeFetcher {
+        fn new(
+            _client: Client,
+            _gh_api_client: GhApiClient,
+            _data: Arc<Data>,
+            _target_data: Arc<TargetDataErased>,
+            _signature_policy: SignaturePolicy,
+        ) -> Arc<dyn Fetcher> {
+            FakeFetcher::with_name("fake")
+        }
+
+        async fn fetch_and_extract(
+            &self,
+            dst: &Path,
+            _members: Option<&[PathBuf]>,
+        ) -> Result<ExtractedFiles, FetchError> {
+            fs::create_dir_all(dst).unwrap();
+            if self.2.swap(false, Relaxed) {
+                fs::write(dst.join("partial"), "").unwrap();
+                return Err(DownloadError::CorruptArchive {
+                    fmt: PkgFmt::Tgz,
+                    source: io::ErrorKind::UnexpectedEof.into(),
+                }
+                .into());
+            }
+            fs::write(dst.join("krate"), "binary").unwrap();
+            Ok(ExtractedFiles::from_dir(dst).unwrap())
+        }
+
+        fn find(self: Arc<Self>) -> tokio::task::JoinHandle<Result<bool, FetchError>> {
+            tokio::spawn(async { Ok(true) })
+        }
+
+        fn pkg_fmt(&self) -> PkgFmt {
+            PkgFmt::Tgz
+        }
+
+        fn target_meta(&self) -> PkgMeta {
+            PkgMeta::default()
+        }
+
+        fn source_name(&self) -> CompactString {
+            self.0.into()
+        }
+
+        fn fetcher_name(&self) -> &'static str {
+            "FakeFetcher"
+        }
+
+        fn is_third_party(&self) -> bool {
+            false
+        }
+
+        fn target(&self) -> &str {
+            "x86_64-unknown-linux-gnu"
+        }
+
+        fn target_data(&self) -> &Arc<TargetDataErased> {
+            &self.1
+        }
+    }
+
+    fn package_info() -> Arc<PackageInfo> {
+        Arc::new(PackageInfo {
+            meta: PkgMeta::default(),
+            binaries: vec![Bin {
+                name: "krate".into(),
+                required_features: Vec::new(),
+            }],
+            name: "krate".into(),
+            version_str: "1.0.0".into(),
+            version: Version::new(1, 0, 0),
+            repo: None,
+            overrides: BTreeMap::new(),
+            has_binstall_metadata: true,
+            git_tag: None,
+        })
+    }
+
+    fn options(temp_dir: &Path) -> Options {
+        let client = Client::new(
+            concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION")),
+            None,
+            NonZeroU16::new(10).unwrap(),
+            1.try_into().unwrap(),
+            [],
+        )
+        .unwrap();
+
+        Options {
+            no_symlinks: false,
+            dry_run: false,
+            force: false,
+            quiet: false,
+            locked: false,
+            frozen: false,
+            no_track: false,
+            versioned_bin: false,
+            version_req: None,
+            exact: false,
+            allow_pre_releases: false,
+            version_selector: None,
+            quiet_version_check: true,
+            cargo_toml_fetch_override: None,
+            local_manifest_dir: None,
+            cli_overrides: PkgOverride::default(),
+            target_overrides: BTreeMap::new(),
+            desired_targets: get_desired_targets(Some(vec!["x86_64-unknown-linux-gnu".into()])),
+            resolvers: Vec::new(),
+            select_fetcher: None,
+            external_fetcher: None,
+            extract_command: None,
+            cargo_install_fallback: false,
+            force_prebuilt: false,
+            features: Vec::new(),
+            no_default_features: false,
+            all_features: false,
+            temp_dir: temp_dir.join("tmp"),
+            keep_download: None,
+            install_path: temp_dir.join("bin"),
+            cargo_root: None,
+            extra_files_dir: None,
+            pre_install_cmd: None,
+            post_install_cmd: None,
+            pinned_mtime: None,
+            check_version: false,
+            sequential_discovery: false,
+            gh_api_client: GhApiClient::new(client.clone(), None),
+            client,
+            bitbucket_token: None,
+            wait_rate_limit: false,
+            jobserver_client: LazyJobserverClient::new(),
+            registry: Registry::default(),
+            signature_policy: SignaturePolicy::IfPresent,
+            prefer_smallest: false,
+            trusted_hosts: None,
+            strict_origin: false,
+            repo_rewrites: Vec::new(),
+        }
+    }
+
+    fn candidates(names: &[&'static str]) -> Vec<Candidate> {
+        let package_info = package_info();
+        names
+            .iter()
+            .map(|name| Candidate::new(&package_info, &"*".into(), FakeFetcher::with_name(name)))
+            .collect()
+    }
+
+    fn source_names(candidates: &[Candidate]) -> Vec<&str> {
+        candidates
+            .iter()
+            .map(|candidate| candidate.source_name.as_str())
+            .collect()
+    }
+
+    async fn select(index: usize, names: &[&'static str]) -> Result<Vec<Candidate>, BinstallError> {
+        let selector = FetcherSelector::new(move |_, _| Ok(index));
+        select_candidate(&selector, &"krate".into(), candidates(names)).await
+    }
+
+    #[tokio::test]
+    async fn selected_candidate_is_tried_first() {
+        let candidates = select(2, &["a", "b", "c", "d"]).await.unwrap();
+        assert_eq!(source_names(&candidates), ["c", "a", "b", "d"]);
+
+        let candidates = select(0, &["a", "b"]).await.unwrap();
+        assert_eq!(source_names(&candidates), ["a", "b"]);
+    }
+
+    #[tokio::test]
+    async fn single_candidate_is_not_selected() {
+        let candidates = select(5, &["a"]).await.unwrap();
+        assert_eq!(source_names(&candidates), ["a"]);
+    }
+
+    #[tokio::test]
+    async fn out_of_range_selection() {
+        let err = select(2, &["a", "b"]).await.unwrap_err();
+        assert!(
+            matches!(
+                err,
+                BinstallError::InvalidFetcherSelection { index: 2, count: 2 }
+            ),
+            "{err:?}"
+        );
+    }
+
+    fn check(url: Option<&str>, repo: Option<&str>) -> Result<(), BinstallError> {
+        check_url_origin(
+            &"krate".into(),
+            url.map(|url| Url::parse(url).unwrap()),
+            repo.map(str::to_string),
+        )
+    }
+
+    #[test]
+    fn origin_on_repo_host() {
+        let repo = Some("https://github.com/cargo-bins/cargo-binstall");
+
+        check(Some("https://github.com/cargo-bins/a.tgz"), repo).unwrap();
+        check(Some("https://objects.GitHub.com/a.tgz"), repo).unwrap();
+    }
+
+    #[test]
+    fn origin_mismatch() {
+        let repo = Some("https://github.com/cargo-bins/cargo-binstall");
+
+        for (url, repo) in [
+            (Some("https://example.com/a.tgz"), repo),
+            (Some("https://evilgithub.com/a.tgz"), repo),
+            (Some("https://github.com/cargo-bins/a.tgz"), None),
+            (
+                Some("https://github.com/cargo-bins/a.tgz"),
+                Some("not a url"),
+            ),
+            // The origin of packages not downloaded from a known url
+            // cannot be checked.
+            (None, repo),
+            (None, None),
+        ] {
+            let err = check(url, repo).unwrap_err();
+            assert!(
+                matches!(err, BinstallError::OriginMismatch { .. }),
+                "{url:?}, {repo:?}: {err:?}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn resolve_picked_candidate() {
+        let dir = tempfile::tempdir().unwrap();
+        let opts = Arc::new(options(dir.path()));
+
+        let candidate = candidates(&["a", "b"]).pop().unwrap();
+        let Resolution::Fetch(fetch) = resolve_candidate(opts, candidate).await.unwrap() else {
+            panic!("the candidate should be fetched");
+        };
+
+        assert_eq!(fetch.name, "krate");
+        assert_eq!(fetch.new_version, Version::new(1, 0, 0));
+        assert_eq!(fetch.version_req, "*");
+        assert_eq!(fetch.fetcher.source_name(), "b");
+        assert_eq!(fetch.bin_files.len(), 1);
+        assert_eq!(
+            fs::read_to_string(&fetch.bin_files[0].source).unwrap(),
+            "binary"
+        );
+        assert_eq!(fetch.bin_files[0].dest, dir.path().join("bin/krate-v1.0.0"));
+    }
+
+    #[tokio::test]
+    async fn retry_corrupt_archive() {
+        let dir = tempfile::tempdir().unwrap();
+        let opts = options(dir.path());
+        let bin_path = dir.path().join("package");
+
+        let fetcher = FakeFetcher::with_name("a");
+        fetcher.2.store(true, Relaxed);
+
+        let (bin_files, _) =
+            download_extract_and_verify(fetcher.as_ref(), &bin_path, &package_info(), &opts, false)
+                .await
+                .unwrap();
+
+        assert_eq!(bin_files.len(), 1);
+        assert!(bin_path.join("krate").exists());
+        // The files extracted by the failed attempt are removed.
+        assert!(!bin_path.join("partial").exists());
+    }
+
+    #[test]
+    fn cpu_variant_precedence() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = "x86_64-unknown-linux-gnu";
+        let pkg_override = |pkg_url: &str| PkgOverride {
+            pkg_url: Some(pkg_url.into()),
+            ..Default::default()
+        };
+        let cpu_variant = CpuVariant {
+            features: vec!["avx2".into()],
+            overrides: pkg_override("variant"),
+        };
+
+        let mut package_info = package_info();
+        let package_info_mut = Arc::get_mut(&mut package_info).unwrap();
+        package_info_mut.meta.pkg_url = Some("baseline".into());
+        package_info_mut
+            .overrides
+            .insert(target.into(), pkg_override("target"));
+
+        let pkg_url = |opts: &Options, cpu_variant| {
+            merge_target_overrides(opts, &package_info, target, cpu_variant).pkg_url
+        };
+
+        let opts = options(dir.path());
+        assert_eq!(pkg_url(&opts, None).as_deref(), Some("target"));
+        // The variant takes precedence over the target specific overrides of the crate...
+        assert_eq!(
+            pkg_url(&opts, Some(&cpu_variant)).as_deref(),
+            Some("variant")
+        );
+
+        // ...but not over the ones of the user.
+        let opts = Options {
+            target_overrides: BTreeMap::from([(target.into(), pkg_override("user"))]),
+            ..options(dir.path())
+        };
+        assert_eq!(pkg_url(&opts, Some(&cpu_variant)).as_deref(), Some("user"));
+
+        let opts = Options {
+            cli_overrides: pkg_override("cli"),
+            ..options(dir.path())
+        };
+        assert_eq!(pkg_url(&opts, Some(&cpu_variant)).as_deref(), Some("cli"));
+    }
+
+    #[tokio::test]
+    async fn selector_error() {
+        let selector = FetcherSelector::new(|_, _| Err(BinstallError::UserAbort));
+        let err = select_candidate(&selector, &"krate".into(), candidates(&["a", "b"]))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, BinstallError::UserAbort), "{err:?}");
+    }
+
+    #[test]
+    fn latest_version_selector() {
+        let versions: Vec<_> = [("1.0.0", false), ("1.1.0-rc.1", false), ("1.2.0", true)]
+            .into_iter()
+            .map(|(version, yanked)| CrateVersion {
+                version: Version::parse(version).unwrap(),
+                yanked,
+            })
+            .collect();
+        let select = |version_req: &str, allow_pre_releases| {
+            LatestVersionSelector
+                .select(
+                    &versions,
+                    &VersionReq::parse(version_req).unwrap(),
+                    allow_pre_releases,
+                )
+                .map(|version| version.to_string())
+        };
+
+        assert_eq!(select("^1", false).as_deref(), Some("1.0.0"));
+        assert_eq!(select("^1", true).as_deref(), Some("1.1.0-rc.1"));
+        assert_eq!(select("^2", true), None);
+    }
+
+    #[tokio::test]
+    async fn report_failed_crate() {
+        let dir = tempfile::tempdir().unwrap();
+        let opts = Options {
+            frozen: true,
+            ..options(dir.path())
+        };
+
+        let findings = report_unsupported(Arc::new(opts), "krate".parse().unwrap())
+            .await
+            .unwrap();
+        assert!(
+            matches!(&findings[..], [Finding::Failed { reason }] if reason.contains("frozen")),
+            "{findings:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn pkg_url_from_overrides() {
+        let dir = tempfile::tempdir().unwrap();
+        let pkg_override = PkgOverride {
+            pkg_url: Some("https://example.com/{ name }.tgz".into()),
+            ..Default::default()
+        };
+
+        let opts = options(dir.path());
+        assert!(!has_pkg_url(&opts, &package_info()).await);
+
+        let mut with_override = package_info();
+        Arc::get_mut(&mut with_override)
+            .unwrap()
+            .overrides
+            .insert("x86_64-unknown-linux-gnu".into(), pkg_override.clone());
+        assert!(has_pkg_url(&opts, &with_override).await);
+
+        let opts = Options {
+            target_overrides: BTreeMap::from([(
+                "x86_64-unknown-linux-gnu".into(),
+                pkg_override.clone(),
+            )]),
+            ..options(dir.path())
+        };
+        assert!(has_pkg_url(&opts, &package_info()).await);
+
+        let opts = Options {
+            target_overrides: BTreeMap::from([("aarch64-apple-darwin".into(), pkg_override)]),
+            ..options(dir.path())
+        };
+        assert!(!has_pkg_url(&opts, &package_info()).await);
+    }
+
+    /// Resolve a crate whose author disabled pre-built binaries.
+    async fn resolve_disabled(opts: Options) -> Result<Resolution, BinstallError> {
+        let mut package_info = package_info();
+        Arc::get_mut(&mut package_info).unwrap().meta.disabled = true;
+
+        resolve_package(
+            Arc::new(opts),
+            package_info,
+            "*".into(),
+            None,
+            &mut PhaseTimes::default(),
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn disabled_falls_back_to_source() {
+        let dir = tempfile::tempdir().unwrap();
+        let opts = Options {
+            cargo_install_fallback: true,
+            resolvers: vec![FakeFetcher::new],
+            ..options(dir.path())
+        };
+
+        let Resolution::InstallFromSource(source) = resolve_disabled(opts).await.unwrap() else {
+            panic!("the crate should be built from source");
+        };
+        assert_eq!(source.name, "krate");
+        assert_eq!(source.version, "1.0.0");
+    }
+
+    #[tokio::test]
+    async fn disabled_without_fallback() {
+        let dir = tempfile::tempdir().unwrap();
+        let opts = Options {
+            resolvers: vec![FakeFetcher::new],
+            ..options(dir.path())
+        };
+
+        let Err(err) = resolve_disabled(opts).await else {
+            panic!("the crate should not be installed");
+        };
+        assert!(
+            matches!(&err, BinstallError::PrebuiltDisabled(name) if *name == "krate"),
+            "{err:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn disabled_force_prebuilt() {
+        let dir = tempfile::tempdir().unwrap();
+        let opts = Options {
+            cargo_install_fallback: true,
+            force_prebuilt: true,
+            resolvers: vec![FakeFetcher::new],
+            ..options(dir.path())
+        };
+
+        let Resolution::Fetch(fetch) = resolve_disabled(opts).await.unwrap() else {
+            panic!("the pre-built binaries should be fetched");
+        };
+        assert_eq!(fetch.fetcher.source_name(), "fake");
+        assert_eq!(fetch.bin_files.len(), 1);
+    }
+
+    #[test]
+    fn bin_names_inferred_from_paths() {
+        assert_eq!(
+            infer_bin_name("krate", "src/main.rs").as_deref(),
+            Some("krate")
+        );
+        assert_eq!(
+            infer_bin_name("krate", "src/bin/tool.rs").as_deref(),
+            Some("tool")
+        );
+        assert_eq!(
+            infer_bin_name("krate", "src/bin/tool/main.rs").as_deref(),
+            Some("tool")
+        );
+
+        // Paths cargo does not infer names from
+        assert_eq!(infer_bin_name("krate", "src/lib.rs"), None);
+        assert_eq!(infer_bin_name("krate", "src/tool.rs"), None);
+        assert_eq!(infer_bin_name("krate", "src/bin"), None);
+        assert_eq!(infer_bin_name("krate", "src/bin/tool/other.rs"), None);
+        assert_eq!(infer_bin_name("krate", "src/bin/a/b/main.rs"), None);
+        assert_eq!(infer_bin_name("krate", "bin/main.rs"), None);
+    }
+
+    #[test]
+    fn dev_tools_of_virtual_workspace() {
+        let dir = tempfile::tempdir().unwrap();
+        let write = |path: &str, contents: &str| {
+            let path = dir.path().join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        };
+        let member = |name: &str, tools: &str| {
+            format!(
+                "[package]\nname = \"{name}\"\nversion = \"0.1.0\"\n\n\
+                 [package.metadata.binstall]\ntools = {tools}\n"
+            )
+        };
+        write("Cargo.toml", "[workspace]\nmembers = [\"crates/*\"]\n");
+        write("crates/a/Cargo.toml", &member("a", r#"["cargo-nextest"]"#));
+        write("crates/a/src/lib.rs", "");
+        write(
+            "crates/b/Cargo.toml",
+            &member("b", r#"["cargo-deny@0.14"]"#),
+        );
+        write("crates/b/src/lib.rs", "");
+
+        let mut tools: Vec<_> = load_dev_tools(dir.path())
+            .unwrap()
+            .into_iter()
+            .map(|tool| tool.to_string())
+            .collect();
+        tools.sort_unstable();
+
+        assert_eq!(tools, ["cargo-deny@=0.14", "cargo-nextest"]);
+    }
+}
@@ -6,17 +6,22 @@ use semver::{Error, VersionReq};
 
 use super::version_ext::VersionReqExt;
 
-#[derive(Debug, Clone, Eq, PartialEq)]
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
 pub struct CrateName {
     pub name: CompactString,
     pub version_req: Option<VersionReq>,
+    /// Set for `crate@latest`, which selects the newest stable version even
+    /// if pre-releases are allowed.
+    pub stable_only: bool,
 }
 
 impl fmt::Display for CrateName {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}", self.name)?;
 
-        if let Some(version) = &self.version_req {
+        if self.stable_only {
+            write!(f, "@latest")?;
+        } else if let Some(version) = &self.version_req {
             write!(f, "@{version}")?;
         }
 
@@ -28,16 +33,22 @@ impl FromStr for CrateName {
     type Err = Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        Ok(if let Some((name, version)) = s.split_once('@') {
-            CrateName {
+        Ok(match s.split_once('@') {
+            Some((name, "latest")) => CrateName {
+                name: name.into(),
+                version_req: Some(VersionReq::STAR),
+                stable_only: true,
+            },
+            Some((name, version)) => CrateName {
                 name: name.into(),
                 version_req: Some(VersionReq::parse_from_cli(version)?),
-            }
-        } else {
-            CrateName {
+                stable_only: false,
+            },
+            None => CrateName {
                 name: s.into(),
                 version_req: None,
-            }
+                stable_only: false,
+            },
         })
     }
 }
@@ -63,11 +74,13 @@ mod tests {
         ([ $( ( $input_name:expr, $input_version:expr ) ),*  ], [ $( ( $output_name:expr, $output_version:expr ) ),*  ]) => {
             let input_crate_names = vec![$( CrateName {
                 name: $input_name.into(),
-                version_req: Some($input_version.parse().unwrap())
+                version_req: Some($input_version.parse().unwrap()),
+                stable_only: false,
             }, )*];
 
             let mut output_crate_names: Vec<CrateName> = vec![$( CrateName {
-                name: $output_name.into(), version_req: Some($output_version.parse().unwrap())
+                name: $output_name.into(), version_req: Some($output_version.parse().unwrap()),
+                stable_only: false,
             }, )*];
             output_crate_names.sort_by(|x, y| x.name.cmp(&y.name));
 
@@ -76,6 +89,22 @@ mod tests {
         };
     }
 
+    #[test]
+    fn test_parse_latest() {
+        let crate_name: CrateName = "cargo-nextest@latest".parse().unwrap();
+        assert_eq!(crate_name.name, "cargo-nextest");
+        assert_eq!(crate_name.version_req, Some(VersionReq::STAR));
+        assert!(crate_name.stable_only);
+        assert_eq!(crate_name.to_string(), "cargo-nextest@latest");
+
+        let crate_name: CrateName = "cargo-nextest@*".parse().unwrap();
+        assert!(!crate_name.stable_only);
+
+        let crate_name: CrateName = "cargo-nextest".parse().unwrap();
+        assert_eq!(crate_name.version_req, None);
+        assert!(!crate_name.stable_only);
+    }
+
     #[test]
     fn test_dedup() {
         // Base case 0: Empty input
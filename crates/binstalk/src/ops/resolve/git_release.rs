@@ -0,0 +1,113 @@
+//! Latest release of the repository of a `--git` install.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use compact_str::CompactString;
+use serde::Deserialize;
+use tracing::debug;
+
+use crate::{
+    errors::BinstallError,
+    helpers::{
+        download::Download,
+        gh_api_client::GhApiClient,
+        git::GitUrl,
+        remote::{Client, StatusCode, Url},
+    },
+    manifests::cargo_toml_binstall::PkgFmt,
+};
+
+#[derive(Deserialize)]
+struct Release {
+    tag_name: CompactString,
+}
+
+/// Return the tag of the latest release of the repository at `git_url`,
+/// using the API of its host.
+///
+/// Return `None` if the repository has no release, or if its host is
+/// neither GitHub nor GitLab.
+pub(super) async fn fetch_latest_release_tag(
+    client: &Client,
+    gh_api_client: &GhApiClient,
+    git_url: &GitUrl,
+) -> Result<Option<CompactString>, BinstallError> {
+    let repo_path = git_url.repo_path();
+
+    let request = match git_url.host() {
+        Some("github.com") => {
+            let request = client
+                .get(Url::parse(&format!(
+                    "https://api.github.com/repos/{repo_path}/releases/latest"
+                ))?)
+                .header("Accept", "application/vnd.github+json")
+                .header("X-GitHub-Api-Version", "2022-11-28");
+
+            match gh_api_client.auth_token() {
+                Some(auth_token) => request.bearer_auth(&auth_token),
+                None => request,
+            }
+        }
+        Some("gitlab.com") => client.get(Url::parse(&format!(
+            "https://gitlab.com/api/v4/projects/{}/releases/permalink/latest",
+            repo_path.replace('/', "%2F"),
+        ))?),
+        host => {
+            debug!("Cannot look up the releases of repositories on {host:?}");
+            return Ok(None);
+        }
+    };
+
+    let response = request.send(false).await?;
+    if response.status() == StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+
+    let release: Release = response.error_for_status()?.json().await?;
+    Ok(Some(release.tag_name))
+}
+
+/// Download and extract the source archive of release `tag` of the
+/// repository at `git_url` into `dir`, returning the root of the sources.
+///
+/// The release tag may not be checked out by a shallow clone, the archive
+/// of the tag served by GitHub and GitLab is used instead.
+pub(super) async fn download_release_source(
+    client: &Client,
+    git_url: &GitUrl,
+    tag: &str,
+    dir: &Path,
+) -> Result<PathBuf, BinstallError> {
+    let repo_path = git_url.repo_path();
+
+    let url = match git_url.host() {
+        Some("github.com") => {
+            format!("https://github.com/{repo_path}/archive/refs/tags/{tag}.tar.gz")
+        }
+        Some("gitlab.com") => {
+            let project = repo_path.rsplit('/').next().unwrap_or(&repo_path);
+            format!("https://gitlab.com/{repo_path}/-/archive/{tag}/{project}-{tag}.tar.gz")
+        }
+        host => unreachable!("{host:?} has no release, see fetch_latest_release_tag"),
+    };
+    debug!("Downloading the sources of {tag} from {url}");
+
+    Download::new(client.clone(), Url::parse(&url)?)
+        .and_extract(PkgFmt::Tgz, dir)
+        .await?;
+
+    // The archives have a single directory at their root, named after the
+    // repository and the tag.
+    let mut entries = fs::read_dir(dir)?;
+    match (entries.next().transpose()?, entries.next()) {
+        (Some(entry), None) if entry.file_type()?.is_dir() => Ok(entry.path()),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("unexpected layout of the source archive {url}"),
+        )
+        .into()),
+    }
+}
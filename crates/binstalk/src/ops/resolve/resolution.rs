@@ -1,4 +1,19 @@
-use std::{borrow::Cow, env, ffi::OsStr, fmt, iter, path::Path, sync::Arc};
+use std::{
+    borrow::Cow,
+    env,
+    ffi::OsStr,
+    fmt, io, iter,
+    num::NonZeroUsize,
+    panic,
+    path::Path,
+    process,
+    sync::{
+        atomic::{AtomicUsize, Ordering::Relaxed},
+        Arc, Mutex, PoisonError,
+    },
+    thread,
+    time::SystemTime,
+};
 
 use command_group::AsyncCommandGroup;
 use compact_str::{CompactString, ToCompactString};
@@ -12,8 +27,9 @@ use crate::{
     bins,
     errors::BinstallError,
     fetchers::Fetcher,
+    helpers,
     manifests::crate_info::{CrateInfo, CrateSource},
-    ops::Options,
+    ops::{resolve::version_check, CargoTomlFetchOverride, Options},
 };
 
 pub struct ResolutionFetch {
@@ -22,11 +38,15 @@ pub struct ResolutionFetch {
     pub name: CompactString,
     pub version_req: CompactString,
     pub bin_files: Vec<bins::BinFile>,
+    /// Files installed alongside the binaries, such as licenses.
+    pub extra_files: Vec<bins::ExtraFile>,
 }
 
 pub struct ResolutionSource {
     pub name: CompactString,
     pub version: CompactString,
+    /// Tag to build from with `--git`, the default branch if `None`.
+    pub git_tag: Option<CompactString>,
 }
 
 pub enum Resolution {
@@ -49,10 +69,178 @@ impl Resolution {
     }
 }
 
+type InstallFp = fn(&bins::BinFile) -> Result<(), bins::Error>;
+
+/// Maximum number of threads used to install the binaries of one crate.
+const MAX_INSTALL_THREADS: usize = 8;
+
+/// Call `install` on each of `bin_files`, spreading them over a bounded
+/// number of threads for crates shipping many binaries.
+///
+/// Stops on the first error, though files already being installed by the
+/// other threads are still finished.
+fn install_concurrently(
+    bin_files: &[bins::BinFile],
+    install: InstallFp,
+) -> Result<(), bins::Error> {
+    let threads = thread::available_parallelism()
+        .map(NonZeroUsize::get)
+        .unwrap_or(1)
+        .min(MAX_INSTALL_THREADS);
+
+    run_concurrently(bin_files, threads, install)
+}
+
+/// Call `f` on each of `items` using at most `threads` threads, stopping on
+/// the first error and resuming any panic on the calling thread.
+fn run_concurrently<T: Sync, E: Send>(
+    items: &[T],
+    threads: usize,
+    f: impl Fn(&T) -> Result<(), E> + Sync,
+) -> Result<(), E> {
+    let threads = threads.min(items.len());
+
+    if threads <= 1 {
+        return items.iter().try_for_each(f);
+    }
+
+    let next = AtomicUsize::new(0);
+
+    thread::scope(|s| {
+        let handles: Vec<_> = (0..threads)
+            .map(|_| {
+                s.spawn(|| loop {
+                    let Some(item) = items.get(next.fetch_add(1, Relaxed)) else {
+                        break Ok(());
+                    };
+                    if let Err(err) = f(item) {
+                        // Prevent other threads from picking up more items.
+                        next.store(items.len(), Relaxed);
+                        break Err(err);
+                    }
+                })
+            })
+            .collect();
+
+        handles.into_iter().try_for_each(|handle| {
+            handle
+                .join()
+                .unwrap_or_else(|err| panic::resume_unwind(err))
+        })
+    })
+}
+
+/// Set the modification time of the installed files to `mtime`.
+fn pin_mtimes(
+    bin_files: &[bins::BinFile],
+    extra_files: &[bins::ExtraFile],
+    mtime: SystemTime,
+    links: bool,
+) -> io::Result<()> {
+    let bin_paths = bin_files
+        .iter()
+        .flat_map(|bin| iter::once(&bin.dest).chain(bin.link.as_ref().filter(|_| links)));
+    let extra_paths = extra_files.iter().map(|extra_file| &extra_file.dest);
+
+    bin_paths
+        .chain(extra_paths)
+        .try_for_each(|path| helpers::pin_mtime(path, mtime))
+}
+
+/// Serialises the install hooks, so that the ones of crates installed
+/// concurrently neither interleave their output nor race with each other.
+static INSTALL_HOOK_LOCK: Mutex<()> = Mutex::new(());
+
+/// Run `install` between the pre-install command and the post-install
+/// command, if any.
+///
+/// The pre-install command failing aborts the installation. The post-install
+/// command is only run once `install` succeeded, and it failing is only
+/// warned about, since the binaries are installed already.
+///
+/// This is a blocking function.
+fn with_install_hooks(
+    name: &str,
+    version: &Version,
+    bin_files: &[bins::BinFile],
+    pre_install_cmd: Option<&str>,
+    post_install_cmd: Option<&str>,
+    install: impl FnOnce() -> Result<(), BinstallError>,
+) -> Result<(), BinstallError> {
+    if let Some(cmd) = pre_install_cmd {
+        run_install_hook(cmd, name, version, bin_files)?;
+    }
+
+    install()?;
+
+    if let Some(cmd) = post_install_cmd {
+        if let Err(err) = run_install_hook(cmd, name, version, bin_files) {
+            warn!("Post-install command of {name} failed: {err}");
+        }
+    }
+
+    Ok(())
+}
+
+/// Run `cmd` with the shell, passing the crate and the paths of its
+/// binaries in the environment.
+///
+/// Its stdout is redirected to stderr, which is left for the output of
+/// binstall itself, e.g. with `--json`.
+///
+/// This is a blocking function.
+fn run_install_hook(
+    cmd: &str,
+    name: &str,
+    version: &Version,
+    bin_files: &[bins::BinFile],
+) -> Result<(), BinstallError> {
+    let mut command = if cfg!(windows) {
+        let mut command = process::Command::new("cmd");
+        command.arg("/C");
+        command
+    } else {
+        let mut command = process::Command::new("sh");
+        command.arg("-c");
+        command
+    };
+
+    let installed_paths = env::join_paths(bin_files.iter().map(|bin| &bin.dest))
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+
+    let _guard = INSTALL_HOOK_LOCK
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+
+    debug!("Running install command `{cmd}` for {name}");
+
+    let mut child = command
+        .arg(cmd)
+        .env("BINSTALL_CRATE_NAME", name)
+        .env("BINSTALL_CRATE_VERSION", version.to_string())
+        .env("BINSTALL_INSTALLED_PATHS", installed_paths)
+        .stdout(process::Stdio::piped())
+        .spawn()?;
+
+    let copied = match child.stdout.take() {
+        Some(mut stdout) => io::copy(&mut stdout, &mut io::stderr()).map(drop),
+        None => Ok(()),
+    };
+    let status = child.wait()?;
+    copied?;
+
+    if status.success() {
+        Ok(())
+    } else {
+        Err(BinstallError::SubProcess {
+            command: cmd.into(),
+            status,
+        })
+    }
+}
+
 impl ResolutionFetch {
     pub fn install(self, opts: &Options) -> Result<CrateInfo, BinstallError> {
-        type InstallFp = fn(&bins::BinFile) -> Result<(), bins::Error>;
-
         let (install_bin, install_link): (InstallFp, InstallFp) = match (opts.no_track, opts.force)
         {
             (true, true) | (false, _) => (bins::BinFile::install_bin, bins::BinFile::install_link),
@@ -62,30 +250,177 @@ impl ResolutionFetch {
             ),
         };
 
+        self.install_files(opts, &self.bin_files, install_bin, install_link, true)?;
+
+        Ok(self.crate_info(opts, &self.bin_files, &self.extra_files))
+    }
+
+    /// Install the binaries into `install_path` rather than the install
+    /// path of the resolution, which is left untouched.
+    ///
+    /// Unlike [`ResolutionFetch::install`], the binaries are copied, so that
+    /// one resolution can be installed into several places, and the extra
+    /// files are not installed.
+    ///
+    /// The returned [`CrateInfo`] is to be recorded in the manifests of the
+    /// cargo root of `install_path`, if any.
+    pub fn install_to(
+        &self,
+        opts: &Options,
+        install_path: &Path,
+    ) -> Result<CrateInfo, BinstallError> {
+        let (install_bin, install_link): (InstallFp, InstallFp) = match (opts.no_track, opts.force)
+        {
+            (true, true) | (false, _) => {
+                (bins::BinFile::install_bin_copy, bins::BinFile::install_link)
+            }
+            (true, false) => (
+                bins::BinFile::install_bin_noclobber,
+                bins::BinFile::install_link_noclobber,
+            ),
+        };
+
+        let bin_files: Vec<_> = self
+            .bin_files
+            .iter()
+            .map(|bin| bin.with_install_path(install_path))
+            .collect();
+
+        self.install_files(opts, &bin_files, install_bin, install_link, false)?;
+
+        Ok(self.crate_info(opts, &bin_files, &[]))
+    }
+
+    /// Install `bin_files` and their symlinks, along with the extra files
+    /// if `extra_files` is `true`, running the install hooks around it.
+    fn install_files(
+        &self,
+        opts: &Options,
+        bin_files: &[bins::BinFile],
+        install_bin: InstallFp,
+        install_link: InstallFp,
+        extra_files: bool,
+    ) -> Result<(), BinstallError> {
+        with_install_hooks(
+            &self.name,
+            &self.new_version,
+            bin_files,
+            opts.pre_install_cmd.as_deref(),
+            opts.post_install_cmd.as_deref(),
+            || self.install_files_inner(opts, bin_files, install_bin, install_link, extra_files),
+        )
+    }
+
+    fn install_files_inner(
+        &self,
+        opts: &Options,
+        bin_files: &[bins::BinFile],
+        install_bin: InstallFp,
+        install_link: InstallFp,
+        extra_files: bool,
+    ) -> Result<(), BinstallError> {
         info!("Installing binaries...");
-        for file in &self.bin_files {
-            install_bin(file)?;
-        }
+        install_concurrently(bin_files, install_bin)?;
 
         // Generate symlinks
         if !opts.no_symlinks {
-            for file in &self.bin_files {
-                install_link(file)?;
-            }
+            install_concurrently(bin_files, install_link)?;
         }
 
-        Ok(CrateInfo {
-            name: self.name,
-            version_req: self.version_req,
-            current_version: self.new_version,
+        let extra_files = if extra_files { &*self.extra_files } else { &[] };
+        for extra_file in extra_files {
+            extra_file.install()?;
+        }
+
+        if let Some(mtime) = opts.pinned_mtime {
+            pin_mtimes(bin_files, extra_files, mtime, !opts.no_symlinks)?;
+        }
+
+        if opts.check_version {
+            self.check_version(bin_files);
+        }
+
+        Ok(())
+    }
+
+    fn crate_info(
+        &self,
+        opts: &Options,
+        bin_files: &[bins::BinFile],
+        extra_files: &[bins::ExtraFile],
+    ) -> CrateInfo {
+        CrateInfo {
+            name: self.name.clone(),
+            version_req: self.version_req.clone(),
+            current_version: self.new_version.clone(),
             source: CrateSource::cratesio_registry(),
             target: self.fetcher.target().to_compact_string(),
-            bins: self
-                .bin_files
-                .into_iter()
-                .map(|bin| bin.base_name)
+            bins: if opts.versioned_bin {
+                // Record the versioned binaries, so that uninstalling this
+                // version does not remove the other ones, and the symlinks
+                // if they now point to this version.
+                bin_files
+                    .iter()
+                    .flat_map(|bin| iter::once(&bin.dest).chain(&bin.link))
+                    .map(|path| path.file_name().unwrap().to_string_lossy().into())
+                    .collect()
+            } else {
+                bin_files.iter().map(|bin| bin.base_name.clone()).collect()
+            },
+            versioned: opts.versioned_bin,
+            // Only the packages rebuilt by third parties could change over
+            // time for the same version.
+            package_sha256: self
+                .fetcher
+                .is_third_party()
+                .then(|| self.fetcher.package_sha256())
+                .flatten(),
+            direct_url: match &opts.cargo_toml_fetch_override {
+                Some(CargoTomlFetchOverride::Url { url, .. }) => Some(url.clone()),
+                _ => None,
+            },
+            extra_files: extra_files
+                .iter()
+                .map(|extra_file| extra_file.dest.clone())
                 .collect(),
-        })
+        }
+    }
+
+    /// Warn if a binary reports another version than the one of the crate
+    /// when run with `--version`.
+    ///
+    /// This is a blocking function.
+    fn check_version(&self, bin_files: &[bins::BinFile]) {
+        for bin in bin_files {
+            let output = match version_check::run_version(&bin.dest) {
+                Ok(Some(output)) => output,
+                Ok(None) => {
+                    debug!("`{} --version` failed or timed out", bin.dest.display());
+                    continue;
+                }
+                Err(err) => {
+                    debug!("Failed to run `{} --version`: {err}", bin.dest.display());
+                    continue;
+                }
+            };
+
+            let mut reported = version_check::reported_versions(&output).peekable();
+            if reported.peek().is_none() {
+                debug!(
+                    "No version in the output of `{} --version`",
+                    bin.dest.display()
+                );
+            } else if !reported.any(|version| version.matches(&self.new_version)) {
+                warn!(
+                    "{} reports `{}`, which does not match the version {} of {}: \
+                    the package may contain another version",
+                    bin.base_name,
+                    output.lines().next().unwrap_or_default().trim(),
+                    self.new_version,
+                    self.name,
+                );
+            }
+        }
     }
 
     pub fn print(&self, opts: &Options) {
@@ -121,6 +456,13 @@ impl ResolutionFetch {
                 info!("  - {}", file.preview_link());
             }
         }
+
+        if !self.extra_files.is_empty() {
+            info!("And the following extra files:");
+            for file in &self.extra_files {
+                info!("  - {}", file.preview());
+            }
+        }
     }
 }
 
@@ -147,10 +489,32 @@ impl ResolutionSource {
             .arg(version)
             .kill_on_drop(true);
 
+        #[cfg(feature = "git")]
+        if let Some(crate::ops::CargoTomlFetchOverride::Git(git_url)) =
+            &opts.cargo_toml_fetch_override
+        {
+            cmd.arg("--git").arg(git_url.to_string());
+            if let Some(tag) = &self.git_tag {
+                cmd.arg("--tag").arg(tag.as_str());
+            }
+        }
+
         if let Some(target) = target {
             cmd.arg("--target").arg(target);
         }
 
+        if !opts.features.is_empty() {
+            cmd.arg("--features").arg(opts.features.join(","));
+        }
+
+        if opts.no_default_features {
+            cmd.arg("--no-default-features");
+        }
+
+        if opts.all_features {
+            cmd.arg("--all-features");
+        }
+
         if opts.quiet {
             cmd.arg("--quiet");
         }
@@ -159,7 +523,9 @@ impl ResolutionSource {
             cmd.arg("--force");
         }
 
-        if opts.locked {
+        if opts.frozen {
+            cmd.arg("--frozen");
+        } else if opts.locked {
             cmd.arg("--locked");
         }
 
@@ -219,3 +585,184 @@ fn format_cmd(cmd: &Command) -> impl fmt::Display + '_ {
 
     iter::once(program).chain(program_args).format(" ")
 }
+
+#[cfg(all(test, unix))]
+mod test {
+    use super::*;
+
+    use std::fs;
+
+    fn hook(log: &Path, name: &str, succeed: bool) -> String {
+        format!(
+            "echo {name} >> '{}'; {}",
+            log.display(),
+            if succeed { "true" } else { "false" }
+        )
+    }
+
+    fn run(
+        log: &Path,
+        pre_succeeds: bool,
+        install_succeeds: bool,
+        post_succeeds: bool,
+    ) -> (Result<(), BinstallError>, String) {
+        let version = Version::new(1, 0, 0);
+        let res = with_install_hooks(
+            "crate",
+            &version,
+            &[],
+            Some(&hook(log, "pre", pre_succeeds)),
+            Some(&hook(log, "post", post_succeeds)),
+            || {
+                fs::write(log, fs::read_to_string(log)? + "install\n")?;
+                if install_succeeds {
+                    Ok(())
+                } else {
+                    Err(io::Error::new(io::ErrorKind::Other, "install failed").into())
+                }
+            },
+        );
+
+        (res, fs::read_to_string(log).unwrap())
+    }
+
+    #[test]
+    fn test_install_hooks() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("log");
+        fs::write(&log, "").unwrap();
+
+        let (res, log) = run(&log, true, true, true);
+        res.unwrap();
+        assert_eq!(log, "pre\ninstall\npost\n");
+    }
+
+    #[test]
+    fn test_failed_pre_install_hook() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("log");
+        fs::write(&log, "").unwrap();
+
+        let (res, log) = run(&log, false, true, true);
+        assert!(matches!(res, Err(BinstallError::SubProcess { .. })));
+        assert_eq!(log, "pre\n");
+    }
+
+    #[test]
+    fn test_failed_install() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("log");
+        fs::write(&log, "").unwrap();
+
+        let (res, log) = run(&log, true, false, true);
+        res.unwrap_err();
+        assert_eq!(log, "pre\ninstall\n");
+    }
+
+    #[test]
+    fn test_failed_post_install_hook() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("log");
+        fs::write(&log, "").unwrap();
+
+        let (res, log) = run(&log, true, true, false);
+        res.unwrap();
+        assert_eq!(log, "pre\ninstall\npost\n");
+    }
+}
+
+#[cfg(test)]
+mod concurrency_test {
+    use super::*;
+
+    use std::{
+        sync::{atomic::AtomicBool, Barrier},
+        time::Duration,
+    };
+
+    #[test]
+    fn test_run_concurrently() {
+        let items: Vec<usize> = (0..100).collect();
+        let calls: Vec<AtomicUsize> = items.iter().map(|_| AtomicUsize::new(0)).collect();
+        let in_flight = AtomicUsize::new(0);
+        let max_in_flight = AtomicUsize::new(0);
+        // Each thread blocks on its first item until all of them have
+        // picked one, which only completes if they really run concurrently.
+        let barrier = Barrier::new(MAX_INSTALL_THREADS);
+
+        run_concurrently(&items, MAX_INSTALL_THREADS, |&i| {
+            let n = in_flight.fetch_add(1, Relaxed) + 1;
+            max_in_flight.fetch_max(n, Relaxed);
+
+            if i < MAX_INSTALL_THREADS {
+                barrier.wait();
+            }
+            calls[i].fetch_add(1, Relaxed);
+
+            in_flight.fetch_sub(1, Relaxed);
+            Ok::<_, ()>(())
+        })
+        .unwrap();
+
+        assert!(calls.iter().all(|calls| calls.load(Relaxed) == 1));
+        assert_eq!(max_in_flight.load(Relaxed), MAX_INSTALL_THREADS);
+    }
+
+    #[test]
+    fn test_run_concurrently_error() {
+        let items: Vec<usize> = (0..1000).collect();
+        let calls = AtomicUsize::new(0);
+
+        let res = run_concurrently(&items, MAX_INSTALL_THREADS, |&i| {
+            calls.fetch_add(1, Relaxed);
+            thread::sleep(Duration::from_millis(1));
+            if i == 3 {
+                Err(i)
+            } else {
+                Ok(())
+            }
+        });
+
+        assert_eq!(res, Err(3));
+        // The other threads stop picking up items after the failure.
+        assert!(calls.load(Relaxed) < items.len());
+    }
+
+    #[test]
+    fn test_run_sequentially_error() {
+        let items: Vec<usize> = (0..10).collect();
+        let calls = AtomicUsize::new(0);
+
+        let res = run_concurrently(&items, 1, |&i| {
+            calls.fetch_add(1, Relaxed);
+            if i == 3 {
+                Err(i)
+            } else {
+                Ok(())
+            }
+        });
+
+        assert_eq!(res, Err(3));
+        assert_eq!(calls.load(Relaxed), 4);
+    }
+
+    #[test]
+    fn test_run_concurrently_panic() {
+        let items: Vec<usize> = (0..100).collect();
+        let finished = AtomicBool::new(false);
+
+        let res = panic::catch_unwind(|| {
+            run_concurrently(&items, MAX_INSTALL_THREADS, |&i| {
+                if i == 3 {
+                    panic!("install failed");
+                }
+                Ok::<_, ()>(())
+            })
+            .unwrap();
+            finished.store(true, Relaxed);
+        });
+
+        assert!(res.is_err());
+        assert!(!finished.load(Relaxed));
+    }
+}
@@ -0,0 +1,182 @@
+//! `binstall.toml`, a file next to `Cargo.toml` overriding the binstall
+//! metadata of the crate.
+//!
+//! It has the content of the `[package.metadata.binstall]` section, and is
+//! merged into it: tables are merged key by key, while any other value of
+//! the sidecar replaces the one in `Cargo.toml`.
+
+use std::{fmt, fs, io, path::Path, str};
+
+use tracing::debug;
+
+use crate::{
+    errors::BinstallError,
+    helpers::{
+        cargo_toml::{Manifest, Value},
+        remote::{Client, StatusCode, Url},
+    },
+    manifests::cargo_toml_binstall::{Meta, PkgMeta},
+};
+
+const SIDECAR_FILE_NAME: &str = "binstall.toml";
+
+/// Apply the sidecar next to the `Cargo.toml` at `manifest_path` to
+/// `manifest`, if there is one.
+///
+/// This is a blocking function.
+pub(super) fn apply_sidecar_file(
+    manifest: &mut Manifest<Meta>,
+    manifest_path: &Path,
+) -> Result<(), BinstallError> {
+    let path = manifest_path.with_file_name(SIDECAR_FILE_NAME);
+
+    let sidecar = match fs::read_to_string(&path) {
+        Ok(sidecar) => sidecar,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(err) => return Err(err.into()),
+    };
+
+    apply_sidecar(manifest, &sidecar, &path.display())
+}
+
+/// Apply the sidecar next to the `Cargo.toml` at `manifest_url` to
+/// `manifest`, if there is one.
+pub(super) async fn apply_sidecar_url(
+    client: &Client,
+    manifest: &mut Manifest<Meta>,
+    manifest_url: &Url,
+) -> Result<(), BinstallError> {
+    let url = manifest_url.join(SIDECAR_FILE_NAME)?;
+
+    let response = client.get(url.clone()).send(false).await?;
+    if response.status() == StatusCode::NOT_FOUND {
+        return Ok(());
+    }
+
+    let sidecar = response.error_for_status()?.bytes().await?;
+    let sidecar = str::from_utf8(&sidecar).map_err(|err| BinstallError::InvalidSidecar {
+        path: url.as_str().into(),
+        reason: err.to_string().into(),
+    })?;
+
+    apply_sidecar(manifest, sidecar, &url)
+}
+
+fn apply_sidecar(
+    manifest: &mut Manifest<Meta>,
+    sidecar: &str,
+    origin: &dyn fmt::Display,
+) -> Result<(), BinstallError> {
+    let invalid = |reason: String| BinstallError::InvalidSidecar {
+        path: origin.to_string().into(),
+        reason: reason.into(),
+    };
+
+    let sidecar: Value = sidecar.parse().map_err(|err| invalid(format!("{err}")))?;
+
+    let Some(package) = manifest.package.as_mut() else {
+        return Ok(());
+    };
+
+    debug!(
+        "Overriding the binstall metadata of {} by {origin}",
+        package.name
+    );
+
+    let meta = package
+        .metadata
+        .take()
+        .and_then(|meta| meta.binstall)
+        .unwrap_or_default();
+    let mut merged = Value::try_from(meta).map_err(|err| invalid(format!("{err}")))?;
+    merge(&mut merged, sidecar);
+    let meta: PkgMeta = merged.try_into().map_err(|err| invalid(format!("{err}")))?;
+
+    package.metadata = Some(Meta {
+        binstall: Some(meta),
+    });
+
+    Ok(())
+}
+
+/// Merge `overlay` into `base`, `overlay` taking precedence.
+fn merge(base: &mut Value, overlay: Value) {
+    match (base, overlay) {
+        (Value::Table(base), Value::Table(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    Some(base_value) => merge(base_value, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_apply_sidecar() {
+        let mut manifest = Manifest::<Meta>::from_slice_with_metadata(
+            br#"
+[package]
+name = "cargo-binstall"
+version = "1.0.0"
+
+[package.metadata.binstall]
+pkg-url = "{ repo }/releases/download/v{ version }/{ name }.tgz"
+bin-dir = "{ bin }{ binary-ext }"
+
+[package.metadata.binstall.overrides.x86_64-pc-windows-msvc]
+pkg-fmt = "zip"
+"#,
+        )
+        .unwrap();
+
+        apply_sidecar(
+            &mut manifest,
+            r#"
+pkg-url = "{ repo }/releases/download/v{ version }/{ name }-{ target }.tgz"
+
+[overrides.x86_64-pc-windows-msvc]
+bin-dir = "{ name }/{ bin }{ binary-ext }"
+"#,
+            &"binstall.toml",
+        )
+        .unwrap();
+
+        let meta = manifest
+            .package
+            .unwrap()
+            .metadata
+            .unwrap()
+            .binstall
+            .unwrap();
+        assert_eq!(
+            meta.pkg_url.as_deref(),
+            Some("{ repo }/releases/download/v{ version }/{ name }-{ target }.tgz")
+        );
+        assert_eq!(meta.bin_dir.as_deref(), Some("{ bin }{ binary-ext }"));
+
+        let windows = &meta.overrides["x86_64-pc-windows-msvc"];
+        assert!(windows.pkg_fmt.is_some());
+        assert_eq!(
+            windows.bin_dir.as_deref(),
+            Some("{ name }/{ bin }{ binary-ext }")
+        );
+
+        let mut manifest = Manifest::<Meta>::from_slice_with_metadata(
+            b"[package]\nname = \"cargo-binstall\"\nversion = \"1.0.0\"\n",
+        )
+        .unwrap();
+        assert!(matches!(
+            apply_sidecar(&mut manifest, "pkg-url = ", &"binstall.toml"),
+            Err(BinstallError::InvalidSidecar { .. })
+        ));
+    }
+}
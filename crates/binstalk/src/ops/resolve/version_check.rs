@@ -0,0 +1,139 @@
+//! Check the version reported by the installed binaries, to catch packages
+//! containing another version than the one they are released as.
+
+use std::{
+    io::{self, Read},
+    path::Path,
+    process::{Command, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+use semver::Version;
+
+/// Time given to a binary to print its version before it is killed.
+const VERSION_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Run `path --version`, returning its output if it succeeds in time.
+///
+/// This is a blocking function.
+pub(super) fn run_version(path: &Path) -> io::Result<Option<String>> {
+    let mut child = Command::new(path)
+        .arg("--version")
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()?;
+
+    let start = Instant::now();
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if start.elapsed() > VERSION_TIMEOUT {
+            child.kill().ok();
+            child.wait()?;
+            return Ok(None);
+        }
+        thread::sleep(Duration::from_millis(20));
+    };
+
+    if !status.success() {
+        return Ok(None);
+    }
+
+    let mut output = String::new();
+    if let Some(mut stdout) = child.stdout.take() {
+        stdout.read_to_string(&mut output)?;
+    }
+    Ok(Some(output))
+}
+
+/// Version reported by a binary.
+#[derive(Debug)]
+pub(super) struct ReportedVersion {
+    version: Version,
+    /// Whether the patch component is reported, it is 0 in `version` if not.
+    has_patch: bool,
+}
+
+impl ReportedVersion {
+    /// Whether it is `expected`, ignoring the build metadata.
+    pub(super) fn matches(&self, expected: &Version) -> bool {
+        let version = &self.version;
+
+        version.major == expected.major
+            && version.minor == expected.minor
+            && version.pre == expected.pre
+            && (!self.has_patch || version.patch == expected.patch)
+    }
+}
+
+/// Versions found in the output of `--version`, which is formatted
+/// differently by each tool, e.g. `tool 1.2.3`, `tool v1.2.3 (abcdef 2024-01-01)`
+/// or `tool version 1.2`.
+pub(super) fn reported_versions(output: &str) -> impl Iterator<Item = ReportedVersion> + '_ {
+    output
+        .split(|c: char| c.is_whitespace() || matches!(c, ',' | ';' | '(' | ')' | '"' | '\''))
+        .filter_map(|word| {
+            let word = word.trim_start_matches(['v', 'V']).trim_end_matches('.');
+            match Version::parse(word) {
+                Ok(version) => Some(ReportedVersion {
+                    version,
+                    has_patch: true,
+                }),
+                Err(_) => {
+                    let (major, minor) = word.split_once('.')?;
+                    Some(ReportedVersion {
+                        version: Version::new(major.parse().ok()?, minor.parse().ok()?, 0),
+                        has_patch: false,
+                    })
+                }
+            }
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn versions(output: &str) -> Vec<(Version, bool)> {
+        reported_versions(output)
+            .map(|reported| (reported.version, reported.has_patch))
+            .collect()
+    }
+
+    #[test]
+    fn parse_reported_versions() {
+        assert_eq!(
+            versions("ripgrep 14.1.0\n"),
+            [(Version::new(14, 1, 0), true)]
+        );
+        assert_eq!(
+            versions("cargo-nextest v0.9.67 (abcdef 2024-01-01)"),
+            [(Version::new(0, 9, 67), true)]
+        );
+        assert_eq!(
+            versions("tool version 1.2."),
+            [(Version::new(1, 2, 0), false)]
+        );
+        assert_eq!(
+            versions("tool 1.0.0-beta.1+build"),
+            [(Version::parse("1.0.0-beta.1+build").unwrap(), true)]
+        );
+        assert!(versions("no version here").is_empty());
+    }
+
+    #[test]
+    fn match_versions() {
+        let expected = Version::new(1, 2, 3);
+        let reported = |version: &str| reported_versions(version).next().unwrap();
+
+        assert!(reported("1.2.3").matches(&expected));
+        assert!(reported("1.2.3+abc").matches(&expected));
+        assert!(reported("1.2").matches(&expected));
+        assert!(!reported("1.2.0").matches(&expected));
+        assert!(!reported("1.1").matches(&expected));
+        assert!(!reported("1.2.3-rc.1").matches(&expected));
+    }
+}
@@ -1,5 +1,5 @@
 use compact_str::format_compact;
-use semver::{Prerelease, Version, VersionReq};
+use semver::{Comparator, Op, Prerelease, Version, VersionReq};
 
 /// Extension trait for [`VersionReq`].
 pub trait VersionReqExt {
@@ -14,6 +14,14 @@ pub trait VersionReqExt {
     fn parse_from_cli(str: &str) -> Result<Self, semver::Error>
     where
         Self: Sized;
+
+    /// Turn a requirement on a complete version, such as `1.2.3`, `^1.2.3` or
+    /// `~1.2.3`, into one only accepting this exact version.
+    ///
+    /// Return `None` if `self` does not name a complete version.
+    fn to_exact(&self) -> Option<Self>
+    where
+        Self: Sized;
 }
 
 impl VersionReqExt for VersionReq {
@@ -63,6 +71,23 @@ impl VersionReqExt for VersionReq {
             version.parse()
         }
     }
+
+    fn to_exact(&self) -> Option<Self> {
+        match self.comparators.as_slice() {
+            [comparator @ Comparator {
+                op: Op::Exact | Op::Caret | Op::Tilde,
+                minor: Some(_),
+                patch: Some(_),
+                ..
+            }] => Some(VersionReq {
+                comparators: vec![Comparator {
+                    op: Op::Exact,
+                    ..comparator.clone()
+                }],
+            }),
+            _ => None,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -96,4 +121,23 @@ mod tests {
             .unwrap()
             .is_latest_compatible(&Version::parse("0.1.0-alpha").unwrap()));
     }
+
+    #[test]
+    fn test_to_exact() {
+        let exact = |req: &str| {
+            VersionReq::parse_from_cli(req)
+                .unwrap()
+                .to_exact()
+                .map(|req| req.to_string())
+        };
+
+        assert_eq!(exact("1.2.3").as_deref(), Some("=1.2.3"));
+        assert_eq!(exact("^1.2.3").as_deref(), Some("=1.2.3"));
+        assert_eq!(exact("~1.2.3-alpha").as_deref(), Some("=1.2.3-alpha"));
+
+        assert_eq!(exact("1.2"), None);
+        assert_eq!(exact(">=1.2.3"), None);
+        assert_eq!(exact(">=1.2.3, <2"), None);
+        assert_eq!(exact("*"), None);
+    }
 }
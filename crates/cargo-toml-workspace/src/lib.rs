@@ -23,13 +23,24 @@ pub fn load_manifest_from_workspace<Metadata: DeserializeOwned>(
     workspace_path: impl AsRef<Path>,
     crate_name: impl AsRef<str>,
 ) -> Result<Manifest<Metadata>, Error> {
+    load_manifest_and_path_from_workspace(workspace_path, crate_name).map(|(manifest, _)| manifest)
+}
+
+/// Same as [`load_manifest_from_workspace`], but also return the path to
+/// the `Cargo.toml` of the crate.
+///
+/// WARNING: This is a blocking operation.
+pub fn load_manifest_and_path_from_workspace<Metadata: DeserializeOwned>(
+    workspace_path: impl AsRef<Path>,
+    crate_name: impl AsRef<str>,
+) -> Result<(Manifest<Metadata>, PathBuf), Error> {
     fn inner<Metadata: DeserializeOwned>(
         workspace_path: &Path,
         crate_name: &str,
-    ) -> Result<Manifest<Metadata>, Error> {
+    ) -> Result<(Manifest<Metadata>, PathBuf), Error> {
         load_manifest_from_workspace_inner(workspace_path, crate_name).map_err(|inner| Error {
             workspace_path: workspace_path.into(),
-            crate_name: crate_name.into(),
+            crate_name: Some(crate_name.into()),
             inner,
         })
     }
@@ -37,15 +48,57 @@ pub fn load_manifest_from_workspace<Metadata: DeserializeOwned>(
     inner(workspace_path.as_ref(), crate_name.as_ref())
 }
 
+/// Load the `Cargo.toml` at the provided path and those of all the
+/// members of its workspace, along with their paths.
+///
+/// WARNING: This is a blocking operation.
+///
+///  * `workspace_path` - can be a directory (path to workspace) or
+///    a file (path to `Cargo.toml`).
+pub fn load_manifests_from_workspace<Metadata: DeserializeOwned>(
+    workspace_path: impl AsRef<Path>,
+) -> Result<Vec<(Manifest<Metadata>, PathBuf)>, Error> {
+    fn inner<Metadata: DeserializeOwned>(
+        workspace_path: &Path,
+    ) -> Result<Vec<(Manifest<Metadata>, PathBuf)>, Error> {
+        let mut manifests = Vec::new();
+
+        visit_workspace(workspace_path, |manifest, manifest_path| {
+            manifests.push((manifest, manifest_path));
+            None::<()>
+        })
+        .map_err(|inner| Error {
+            workspace_path: workspace_path.into(),
+            crate_name: None,
+            inner,
+        })?;
+
+        Ok(manifests)
+    }
+
+    inner(workspace_path.as_ref())
+}
+
 #[derive(Debug, ThisError)]
-#[error("Failed to load {crate_name} from {}: {inner}", workspace_path.display())]
+#[error(
+    "Failed to load {} from {}: {inner}",
+    crate_name.as_deref().unwrap_or("the manifests"),
+    workspace_path.display()
+)]
 pub struct Error {
     workspace_path: Box<Path>,
-    crate_name: CompactString,
+    crate_name: Option<CompactString>,
     #[source]
     inner: ErrorInner,
 }
 
+impl Error {
+    /// Return true if the workspace does not contain the crate.
+    pub fn is_not_found(&self) -> bool {
+        matches!(self.inner, ErrorInner::NotFound)
+    }
+}
+
 #[derive(Debug, ThisError)]
 enum ErrorInner {
     #[error("Invalid pattern in workspace.members or workspace.exclude: {0}")]
@@ -68,12 +121,34 @@ enum ErrorInner {
 fn load_manifest_from_workspace_inner<Metadata: DeserializeOwned>(
     workspace_path: &Path,
     crate_name: &str,
-) -> Result<Manifest<Metadata>, ErrorInner> {
+) -> Result<(Manifest<Metadata>, PathBuf), ErrorInner> {
     debug!(
         "Loading manifest of crate {crate_name} from workspace: {}",
         workspace_path.display()
     );
 
+    visit_workspace(
+        workspace_path,
+        |manifest: Manifest<Metadata>, manifest_path| {
+            let name = manifest.package.as_ref().map(|p| &*p.name);
+            debug!(
+                "Loading from {}, manifest.package.name = {:#?}",
+                manifest_path.display(),
+                name
+            );
+
+            (name == Some(crate_name)).then_some((manifest, manifest_path))
+        },
+    )?
+    .ok_or(ErrorInner::NotFound)
+}
+
+/// Call `visit` with the manifest at `workspace_path` and those of the
+/// members of its workspace, until it returns `Some`.
+fn visit_workspace<Metadata: DeserializeOwned, T>(
+    workspace_path: &Path,
+    mut visit: impl FnMut(Manifest<Metadata>, PathBuf) -> Option<T>,
+) -> Result<Option<T>, ErrorInner> {
     let manifest_path = if workspace_path.is_file() {
         workspace_path.to_owned()
     } else {
@@ -85,46 +160,37 @@ fn load_manifest_from_workspace_inner<Metadata: DeserializeOwned>(
     while let Some(manifest_path) = manifest_paths.pop() {
         let manifest = Manifest::<Metadata>::from_path_with_metadata(&manifest_path)?;
 
-        let name = manifest.package.as_ref().map(|p| &*p.name);
-        debug!(
-            "Loading from {}, manifest.package.name = {:#?}",
-            manifest_path.display(),
-            name
-        );
-
-        if name == Some(crate_name) {
-            return Ok(manifest);
-        }
-
-        if let Some(ws) = manifest.workspace {
-            let excludes = ws.exclude;
-            let members = ws.members;
-
-            if members.is_empty() {
-                continue;
-            }
-
-            let exclude_patterns = excludes
-                .into_iter()
-                .map(|pat| Pattern::new(&pat))
-                .collect::<Result<Vec<_>, _>>()?;
-
-            let workspace_path = manifest_path.parent().unwrap();
-
-            for member in members {
-                for path in Pattern::new(&member)?.glob_dirs(workspace_path)? {
-                    if !exclude_patterns
-                        .iter()
-                        .any(|exclude| exclude.matches_with_trailing(&path))
-                    {
-                        manifest_paths.push(workspace_path.join(path).join("Cargo.toml"));
+        if let Some(ws) = &manifest.workspace {
+            let excludes = &ws.exclude;
+            let members = &ws.members;
+
+            if !members.is_empty() {
+                let exclude_patterns = excludes
+                    .iter()
+                    .map(|pat| Pattern::new(pat))
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                let workspace_path = manifest_path.parent().unwrap();
+
+                for member in members {
+                    for path in Pattern::new(member)?.glob_dirs(workspace_path)? {
+                        if !exclude_patterns
+                            .iter()
+                            .any(|exclude| exclude.matches_with_trailing(&path))
+                        {
+                            manifest_paths.push(workspace_path.join(path).join("Cargo.toml"));
+                        }
                     }
                 }
             }
         }
+
+        if let Some(found) = visit(manifest, manifest_path) {
+            return Ok(Some(found));
+        }
     }
 
-    Err(ErrorInner::NotFound)
+    Ok(None)
 }
 
 struct Pattern(Vec<glob::Pattern>);
@@ -260,12 +326,40 @@ mod test {
             .unwrap_err();
         assert!(matches!(err, ErrorInner::NotFound), "{:#?}", err);
 
-        let manifest =
-            load_manifest_from_workspace::<cargo_toml::Value>(&p, "cargo-watch").unwrap();
+        let (manifest, manifest_path) =
+            load_manifest_and_path_from_workspace::<cargo_toml::Value>(&p, "cargo-watch").unwrap();
+        assert_eq!(
+            manifest_path,
+            p.join("crates/a/b/c/d/e/cargo-watch/Cargo.toml")
+        );
         let package = manifest.package.unwrap();
         assert_eq!(package.name, "cargo-watch");
         assert_eq!(package.version.as_ref().unwrap(), "8.4.0");
         assert_eq!(manifest.bin.len(), 1);
         assert_eq!(manifest.bin[0].name.as_deref().unwrap(), "cargo-watch");
     }
+
+    #[test]
+    fn test_load_all() {
+        let p = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .parent()
+            .unwrap()
+            .parent()
+            .unwrap()
+            .join("e2e-tests/manifests/workspace");
+
+        let manifests = load_manifests_from_workspace::<cargo_toml::Value>(&p).unwrap();
+
+        let mut names: Vec<_> = manifests
+            .iter()
+            .map(|(manifest, _)| manifest.package.as_ref().map(|package| &*package.name))
+            .collect();
+        names.sort_unstable();
+        // The virtual manifest of the workspace has no package, and the
+        // excluded member is skipped.
+        assert_eq!(names, [None, Some("cargo-binstall"), Some("cargo-watch")]);
+        assert!(manifests
+            .iter()
+            .any(|(manifest, path)| manifest.workspace.is_some() && *path == p.join("Cargo.toml")));
+    }
 }
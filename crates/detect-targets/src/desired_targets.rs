@@ -6,10 +6,23 @@ use tokio::sync::OnceCell;
 
 #[derive(Debug)]
 enum DesiredTargetsInner {
-    AutoDetect(Arc<OnceCell<Vec<String>>>),
+    AutoDetect {
+        targets: Arc<OnceCell<Vec<String>>>,
+        host_only: bool,
+    },
     Initialized(Vec<String>),
 }
 
+/// Detect the targets, only keeping the host target if `host_only`.
+async fn detect(host_only: bool) -> Vec<String> {
+    let mut targets = detect_targets().await;
+    if host_only {
+        // The host target always comes first
+        targets.truncate(1);
+    }
+    targets
+}
+
 #[derive(Debug)]
 pub struct DesiredTargets(DesiredTargetsInner);
 
@@ -18,15 +31,18 @@ impl DesiredTargets {
         Self(DesiredTargetsInner::Initialized(targets))
     }
 
-    fn auto_detect() -> Self {
+    fn auto_detect(host_only: bool) -> Self {
         let arc = Arc::new(OnceCell::new());
 
         let once_cell = arc.clone();
         tokio::spawn(async move {
-            once_cell.get_or_init(detect_targets).await;
+            once_cell.get_or_init(|| detect(host_only)).await;
         });
 
-        Self(DesiredTargetsInner::AutoDetect(arc))
+        Self(DesiredTargetsInner::AutoDetect {
+            targets: arc,
+            host_only,
+        })
     }
 
     pub async fn get(&self) -> &[String] {
@@ -38,7 +54,7 @@ impl DesiredTargets {
             // This will mostly just wait for the spawned task,
             // on rare occausion though, it will poll the future
             // returned by `detect_targets`.
-            AutoDetect(once_cell) => once_cell.get_or_init(detect_targets).await,
+            AutoDetect { targets, host_only } => targets.get_or_init(|| detect(*host_only)).await,
         }
     }
 
@@ -49,7 +65,7 @@ impl DesiredTargets {
 
         match &self.0 {
             Initialized(targets) => Some(targets),
-            AutoDetect(..) => None,
+            AutoDetect { .. } => None,
         }
     }
 }
@@ -64,6 +80,14 @@ pub fn get_desired_targets(opts_targets: Option<Vec<String>>) -> DesiredTargets
     if let Some(targets) = opts_targets {
         DesiredTargets::initialized(targets)
     } else {
-        DesiredTargets::auto_detect()
+        DesiredTargets::auto_detect(false)
     }
 }
+
+/// Like [`get_desired_targets`] without user override, but only the host
+/// target is kept, without the other targets it can run, e.g. the musl
+/// target on a glibc Linux or `x86_64-apple-darwin` on an
+/// `aarch64-apple-darwin` through emulation.
+pub fn get_host_target() -> DesiredTargets {
+    DesiredTargets::auto_detect(true)
+}
@@ -68,7 +68,7 @@ mod detect;
 pub use detect::detect_targets;
 
 mod desired_targets;
-pub use desired_targets::{get_desired_targets, DesiredTargets};
+pub use desired_targets::{get_desired_targets, get_host_target, DesiredTargets};
 
 /// Compiled target triple, used as default for binary fetching
 pub const TARGET: &str = env!("TARGET");
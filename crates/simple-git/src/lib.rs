@@ -82,6 +82,21 @@ impl fmt::Display for GitUrl {
     }
 }
 
+impl GitUrl {
+    /// Return the host of the repository, `None` for a local one.
+    pub fn host(&self) -> Option<&str> {
+        self.0.host()
+    }
+
+    /// Return the path of the repository on its host, without the leading
+    /// `/` and the `.git` suffix, e.g. `owner/repo` for a GitHub repository.
+    pub fn repo_path(&self) -> String {
+        let path = String::from_utf8_lossy(&self.0.path);
+        let path = path.trim_start_matches('/');
+        path.strip_suffix(".git").unwrap_or(path).to_owned()
+    }
+}
+
 impl FromStr for GitUrl {
     type Err = GitUrlParseError;
 